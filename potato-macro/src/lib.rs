@@ -168,6 +168,247 @@ fn parse_header_attr(tokens: &proc_macro2::TokenStream) -> Result<(String, Strin
     parser.parse2(tokens.clone())
 }
 
+/// `security_headers` 标注可覆盖的字段
+const SECURITY_HEADER_FIELDS: [&str; 10] = [
+    "hsts",
+    "content_type_options",
+    "frame_options",
+    "referrer_policy",
+    "permissions_policy",
+    "cross_origin_opener_policy",
+    "cross_origin_embedder_policy",
+    "cross_origin_resource_policy",
+    "csp",
+    "csp_report_only",
+];
+
+/// security_headers 标注解析结果：(预设名, [(字段名, 值)])
+type SecurityHeadersAttr = (Option<String>, Vec<(String, String)>);
+
+/// 解析 security_headers 标注
+///
+/// 支持 `#[security_headers(off)]`、`#[security_headers(strict, csp = "...")]` 等形式
+fn parse_security_headers_attr(attr: &syn::Attribute) -> SecurityHeadersAttr {
+    enum Item {
+        Preset(Ident),
+        Field(Ident, String),
+    }
+    let parser = |input: syn::parse::ParseStream| {
+        let mut items = vec![];
+        while !input.is_empty() {
+            let ident: Ident = input.parse()?;
+            if input.peek(Token![=]) {
+                input.parse::<Token![=]>()?;
+                let value = if input.peek(syn::LitBool) {
+                    input.parse::<syn::LitBool>()?.value.to_string()
+                } else {
+                    input.parse::<syn::LitStr>()?.value()
+                };
+                items.push(Item::Field(ident, value));
+            } else {
+                items.push(Item::Preset(ident));
+            }
+            if !input.is_empty() {
+                input.parse::<Token![,]>()?;
+            }
+        }
+        Ok(items)
+    };
+    let items = attr.parse_args_with(parser).unwrap_or_else(|err| {
+        panic!("invalid `security_headers` annotation: {err}");
+    });
+    if items.is_empty() {
+        panic!("`security_headers` annotation requires a preset or at least one field");
+    }
+    let (mut preset, mut fields) = (None, vec![]);
+    for item in items {
+        match item {
+            Item::Preset(ident) => {
+                let name = ident.to_string();
+                if !["strict", "basic", "off"].contains(&name.as_str()) {
+                    panic!(
+                        "unknown security headers preset `{name}`, expected strict, basic or off"
+                    );
+                }
+                if preset.replace(name).is_some() {
+                    panic!("`security_headers` annotation accepts only one preset");
+                }
+            }
+            Item::Field(ident, value) => {
+                let name = ident.to_string();
+                if !SECURITY_HEADER_FIELDS.contains(&name.as_str()) {
+                    panic!(
+                        "unknown security headers field `{name}`, expected one of: {}",
+                        SECURITY_HEADER_FIELDS.join(", ")
+                    );
+                }
+                fields.push((name, value));
+            }
+        }
+    }
+    (preset, fields)
+}
+
+/// 解析 require_role / require_scope / auth_scheme 标注，如 `#[require_role("admin", "ops")]`
+fn parse_requirement_attr(attr: &syn::Attribute, attr_name: &str) -> Vec<String> {
+    let parser = syn::punctuated::Punctuated::<syn::LitStr, syn::Token![,]>::parse_terminated;
+    let names = attr.parse_args_with(parser).unwrap_or_else(|err| {
+        panic!("invalid `{attr_name}` annotation: {err}");
+    });
+    let names: Vec<String> = names.iter().map(|name| name.value()).collect();
+    if names.is_empty() || names.iter().any(|name| name.is_empty()) {
+        panic!("`{attr_name}` annotation requires at least one non-empty name");
+    }
+    names
+}
+
+/// rate_limit 标注解析结果
+struct RateLimitAttr {
+    limit: u32,
+    per_secs: u64,
+    key: proc_macro2::TokenStream, // 设置限流键的构造方法调用
+}
+
+/// 解析 `"30s"`、`"1m"`、`"1h"`、`"1d"` 形式的时长，返回秒数
+fn parse_duration_secs(value: &str) -> Option<u64> {
+    let unit_pos = value
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(value.len());
+    let count: u64 = value[..unit_pos].parse().ok()?;
+    let unit = match &value[unit_pos..] {
+        "" | "s" => 1,
+        "m" => 60,
+        "h" => 3600,
+        "d" => 86400,
+        _ => return None,
+    };
+    Some(count * unit).filter(|secs| *secs > 0)
+}
+
+/// 解析 rate_limit 标注，如 `#[rate_limit(100, per = "1m", key = "ip")]`
+///
+/// `key` 可为 `"ip"`（默认）、`"subject"` 或签名为 `fn(&HttpRequest) -> Option<String>` 的函数路径
+fn parse_rate_limit_attr(attr: &syn::Attribute) -> RateLimitAttr {
+    let parser = |input: syn::parse::ParseStream| {
+        let limit: syn::LitInt = input.parse()?;
+        let (mut per, mut key) = (None, None);
+        while !input.is_empty() {
+            input.parse::<Token![,]>()?;
+            if input.is_empty() {
+                break;
+            }
+            let name: Ident = input.parse()?;
+            input.parse::<Token![=]>()?;
+            match name.to_string().as_str() {
+                "per" => per = Some(input.parse::<syn::LitStr>()?.value()),
+                "key" if input.peek(syn::LitStr) => {
+                    let lit = input.parse::<syn::LitStr>()?;
+                    key = Some(match lit.value().as_str() {
+                        "ip" => quote! { .per_ip() },
+                        "subject" => quote! { .per_subject() },
+                        other => {
+                            return Err(syn::Error::new(
+                                lit.span(),
+                                format!("unknown rate limit key `{other}`, expected \"ip\", \"subject\" or a function path"),
+                            ))
+                        }
+                    });
+                }
+                "key" => {
+                    let path: syn::Path = input.parse()?;
+                    key = Some(quote! { .with_key(#path) });
+                }
+                other => {
+                    return Err(syn::Error::new(
+                        name.span(),
+                        format!("unknown rate_limit option `{other}`, expected per or key"),
+                    ))
+                }
+            }
+        }
+        Ok((limit.base10_parse::<u32>()?, per, key))
+    };
+    let (limit, per, key) = attr.parse_args_with(parser).unwrap_or_else(|err| {
+        panic!("invalid `rate_limit` annotation: {err}");
+    });
+    if limit == 0 {
+        panic!("rate_limit must be greater than 0");
+    }
+    let per = per.unwrap_or_else(|| "1s".to_string());
+    let per_secs = parse_duration_secs(&per).unwrap_or_else(|| {
+        panic!("invalid rate_limit period `{per}`, expected e.g. \"30s\", \"1m\", \"1h\" or \"1d\"")
+    });
+    RateLimitAttr {
+        limit,
+        per_secs,
+        key: key.unwrap_or_default(),
+    }
+}
+
+/// 校验 ip_filter 标注中的网段，如 `10.0.0.0/8`、`::1`
+fn is_valid_cidr(value: &str) -> bool {
+    let (addr, prefix) = match value.trim().split_once('/') {
+        Some((addr, prefix)) => (addr, Some(prefix)),
+        None => (value.trim(), None),
+    };
+    let max_prefix = match addr.parse::<std::net::IpAddr>() {
+        Ok(std::net::IpAddr::V4(_)) => 32,
+        Ok(std::net::IpAddr::V6(_)) => 128,
+        Err(_) => return false,
+    };
+    prefix.is_none_or(|prefix| {
+        prefix
+            .parse::<u8>()
+            .is_ok_and(|prefix| prefix <= max_prefix)
+    })
+}
+
+/// 解析 ip_filter 标注，如 `#[ip_filter(allow = ["10.0.0.0/8", "::1"], deny = ["10.0.0.9"])]`，
+/// 返回 (允许列表, 拒绝列表)
+fn parse_ip_filter_attr(attr: &syn::Attribute) -> (Vec<String>, Vec<String>) {
+    let parser = |input: syn::parse::ParseStream| {
+        let (mut allow, mut deny) = (vec![], vec![]);
+        while !input.is_empty() {
+            let name: Ident = input.parse()?;
+            input.parse::<Token![=]>()?;
+            let content;
+            syn::bracketed!(content in input);
+            let cidrs =
+                syn::punctuated::Punctuated::<syn::LitStr, Token![,]>::parse_terminated(&content)?;
+            for cidr in cidrs.iter() {
+                if !is_valid_cidr(&cidr.value()) {
+                    return Err(syn::Error::new(
+                        cidr.span(),
+                        format!("invalid cidr `{}`", cidr.value()),
+                    ));
+                }
+            }
+            let cidrs = cidrs.iter().map(|cidr| cidr.value());
+            match name.to_string().as_str() {
+                "allow" => allow.extend(cidrs),
+                "deny" => deny.extend(cidrs),
+                other => {
+                    return Err(syn::Error::new(
+                        name.span(),
+                        format!("unknown ip_filter option `{other}`, expected allow or deny"),
+                    ))
+                }
+            }
+            if !input.is_empty() {
+                input.parse::<Token![,]>()?;
+            }
+        }
+        Ok((allow, deny))
+    };
+    let (allow, deny) = attr.parse_args_with(parser).unwrap_or_else(|err| {
+        panic!("invalid `ip_filter` annotation: {err}");
+    });
+    if allow.is_empty() && deny.is_empty() {
+        panic!("`ip_filter` annotation requires allow or deny list");
+    }
+    (allow, deny)
+}
+
 fn random_ident() -> Ident {
     let mut rng = rand::thread_rng();
     let value = format!("__potato_id_{}", rng.r#gen::<u64>());
@@ -789,6 +1030,12 @@ fn http_handler_macro(attr: TokenStream, input: TokenStream, req_name: &str) ->
     let mut fn_headers: Vec<(String, String)> = Vec::new();
     let mut cors_config: Option<CorsAttrConfig> = None;
     let mut max_concurrency: Option<usize> = None;
+    let mut security_headers: Option<SecurityHeadersAttr> = None;
+    let mut csrf_exempt = false;
+    let mut auth_schemes: Vec<String> = Vec::new();
+    let mut rate_limit: Option<RateLimitAttr> = None;
+    let mut ip_filter: Option<(Vec<String>, Vec<String>)> = None;
+    let mut requirements: Vec<(bool, Vec<String>)> = Vec::new(); // (是否为角色, 名称)
     let mut remaining_attrs = Vec::new();
 
     for attr in root_fn.attrs.iter() {
@@ -914,6 +1161,39 @@ fn http_handler_macro(attr: TokenStream, input: TokenStream, req_name: &str) ->
             continue;
         }
 
+        if attr_last_ident(attr).as_deref() == Some("security_headers") {
+            security_headers = Some(parse_security_headers_attr(attr));
+            continue;
+        }
+
+        if attr_last_ident(attr).as_deref() == Some("csrf_exempt") {
+            if !matches!(attr.meta, syn::Meta::Path(_)) {
+                panic!("`csrf_exempt` annotation takes no arguments");
+            }
+            csrf_exempt = true;
+            continue;
+        }
+
+        match attr_last_ident(attr).as_deref() {
+            Some(name @ ("require_role" | "require_scope")) => {
+                requirements.push((name == "require_role", parse_requirement_attr(attr, name)));
+                continue;
+            }
+            Some("auth_scheme") => {
+                auth_schemes.extend(parse_requirement_attr(attr, "auth_scheme"));
+                continue;
+            }
+            Some("rate_limit") => {
+                rate_limit = Some(parse_rate_limit_attr(attr));
+                continue;
+            }
+            Some("ip_filter") => {
+                ip_filter = Some(parse_ip_filter_attr(attr));
+                continue;
+            }
+            _ => {}
+        }
+
         remaining_attrs.push(attr.clone());
     }
 
@@ -943,7 +1223,10 @@ fn http_handler_macro(attr: TokenStream, input: TokenStream, req_name: &str) ->
     // 但如果 handler 不需要缓存，我们不应该强制要求 Authorization header
     // 这样可以避免给不需要认证的 handler 添加不必要的认证要求
     let need_once_cache = handler_has_once_cache;
-    let need_session_cache = handler_has_session_cache;
+    // 标注了 require_role / require_scope 的 handler 同样需要鉴权
+    let need_session_cache = handler_has_session_cache || !requirements.is_empty();
+    // 未声明 SessionCache 参数时，访问要求也可由 use_oidc 附加的声明满足
+    let oidc_fallback = !handler_has_session_cache && !requirements.is_empty();
 
     let preprocess_adapters: Vec<Ident> = preprocess_fns
         .iter()
@@ -1362,6 +1645,137 @@ fn http_handler_macro(attr: TokenStream, input: TokenStream, req_name: &str) ->
         quote! {}
     };
 
+    // 路由级安全头覆盖，应答时与 use_security_headers 的全局策略合并
+    let security_headers_code = if let Some((preset, fields)) = &security_headers {
+        let preset = match preset.as_deref() {
+            Some("off") => quote! { Some(potato::SecurityHeaders::none()) },
+            Some(name) => {
+                let name = Ident::new(name, Span::call_site());
+                quote! { Some(potato::SecurityHeaders::#name()) }
+            }
+            None => quote! { None },
+        };
+        let fields = fields
+            .iter()
+            .map(|(name, value)| quote! { (#name, #value) });
+        quote! {
+            potato::SecurityHeadersOverride {
+                preset: #preset,
+                fields: &[#(#fields),*],
+            }
+            .attach(req);
+        }
+    } else {
+        quote! {}
+    };
+
+    // 标注了 csrf_exempt 的路由跳过 use_csrf 校验
+    let csrf_exempt_flag = if csrf_exempt {
+        quote! {
+            potato::inventory::submit!{potato::CsrfExemptFlag::new(
+                potato::HttpMethod::#req_name,
+                #final_path_expr,
+            )}
+        }
+    } else {
+        quote! {}
+    };
+
+    // 访问要求在鉴权通过后、预处理之前校验，同时写入文档
+    let requirements_expr = {
+        let items = requirements.iter().map(|(is_role, names)| match is_role {
+            true => quote! { potato::AuthRequirement::Role(&[#(#names),*]) },
+            false => quote! { potato::AuthRequirement::Scope(&[#(#names),*]) },
+        });
+        quote! { &[#(#items),*] }
+    };
+    let requirements_check_code = if requirements.is_empty() {
+        quote! {}
+    } else {
+        quote! {
+            let __potato_access = match (&__potato_oidc_claims, __potato_session_cache.as_ref()) {
+                (Some(claims), _) => claims.check_access(#requirements_expr),
+                (None, Some(session_cache)) => session_cache.check_access(#requirements_expr),
+                (None, None) => None,
+            };
+            if let Some(__potato_resp) = __potato_access {
+                return __potato_resp;
+            }
+        }
+    };
+
+    // 命名的认证方式在进入处理流程前校验，不占用并发许可
+    let auth_schemes_expr = quote! { &[#(#auth_schemes),*] };
+    let auth_schemes_check_code = if auth_schemes.is_empty() {
+        quote! {}
+    } else {
+        quote! {
+            potato::__potato_check_auth_schemes!(#auth_schemes_expr, req);
+        }
+    };
+
+    // 路由级速率限制，令牌桶以 "方法 路径" 命名，在认证之后、占用并发许可之前校验
+    let rate_limit_name =
+        format_ident!("__POTATO_RATE_LIMIT_{}", fn_name.to_string().to_uppercase());
+    let (rate_limit_static, rate_limit_check_code) = match &rate_limit {
+        Some(RateLimitAttr {
+            limit,
+            per_secs,
+            key,
+        }) => {
+            let bucket_name = format!("{req_name} {final_path}");
+            (
+                quote! {
+                    #[doc(hidden)]
+                    #[allow(non_upper_case_globals)]
+                    static #rate_limit_name: std::sync::LazyLock<potato::RateLimit> =
+                        std::sync::LazyLock::new(|| {
+                            potato::RateLimit::new(#limit, std::time::Duration::from_secs(#per_secs))
+                                #key
+                                .with_name(#bucket_name)
+                        });
+                },
+                quote! {
+                    if let Some(__potato_resp) = #rate_limit_name.check(req).await {
+                        return __potato_resp;
+                    }
+                },
+            )
+        }
+        None => (quote! {}, quote! {}),
+    };
+
+    // 路由级 IP 过滤，覆盖 use_ip_filter 的规则，在其他校验之前执行
+    let ip_filter_name = format_ident!("__POTATO_IP_FILTER_{}", fn_name.to_string().to_uppercase());
+    let (ip_filter_static, ip_filter_check_code, ip_filter_flag) = match &ip_filter {
+        Some((allow, deny)) => (
+            quote! {
+                #[doc(hidden)]
+                #[allow(non_upper_case_globals)]
+                static #ip_filter_name: std::sync::LazyLock<potato::IpFilter> =
+                    std::sync::LazyLock::new(|| {
+                        potato::IpFilter::new()
+                            .with_allow(&[#(#allow),*] as &[&str])
+                            .and_then(|filter| filter.with_deny(&[#(#deny),*] as &[&str]))
+                            .expect("cidr validated by ip_filter annotation")
+                    });
+            },
+            quote! {
+                if let Some(__potato_resp) = #ip_filter_name.check_override(req).await {
+                    return __potato_resp;
+                }
+            },
+            quote! {
+                potato::inventory::submit!{potato::IpFilterRouteFlag::new(
+                    potato::HttpMethod::#req_name,
+                    #final_path_expr,
+                    &#ip_filter_name,
+                )}
+            },
+        ),
+        None => (quote! {}, quote! {}, quote! {}),
+    };
+
     // 如果存在CORS配置且是PUT/POST/DELETE,自动生成HEAD handler
     let auto_head_handler = if cors_config.is_some()
         && (req_name == "POST" || req_name == "PUT" || req_name == "DELETE")
@@ -1398,6 +1812,11 @@ fn http_handler_macro(attr: TokenStream, input: TokenStream, req_name: &str) ->
             let semaphore_name =
                 format_ident!("__POTATO_SEMAPHORE_{}", fn_name.to_string().to_uppercase());
             quote! {
+                #security_headers_code
+                #ip_filter_check_code
+                #auth_schemes_check_code
+                #rate_limit_check_code
+
                 let __potato_permit = #semaphore_name.acquire().await;
 
                 // 获取自定义错误处理器
@@ -1416,35 +1835,27 @@ fn http_handler_macro(attr: TokenStream, input: TokenStream, req_name: &str) ->
                 } else {
                     None
                 };
-                let mut __potato_session_cache: Option<potato::SessionCache> = if #need_session_cache {
-                    // 从 Authorization header 中提取 Bearer token 并加载 session
-                    if let Some(h) = req.headers.get(&potato::utils::refstr::HeaderOrHipStr::from_str("Authorization")) {
-                        let header_value = h.as_str();
-                        if header_value.starts_with("Bearer ") {
-                            potato::SessionCache::from_token(&header_value[7..]).await.ok()
-                        } else {
-                            None
-                        }
-                    } else {
-                        None
-                    }
+                // 仅有访问要求的 handler 可直接使用 use_oidc 校验通过的令牌
+                let __potato_oidc_claims = if #oidc_fallback {
+                    req.oidc_claims()
                 } else {
                     None
                 };
+                let mut __potato_session_cache: Option<potato::SessionCache> =
+                    if #need_session_cache && __potato_oidc_claims.is_none() {
+                        // 从 Authorization header 中的 Bearer token 或会话 Cookie 加载 session
+                        potato::SessionCache::from_request(req).await
+                    } else {
+                        None
+                    };
 
                 // 如果 handler 需要 SessionCache 但没有提供 Authorization header，返回 401
-                if #need_session_cache && __potato_session_cache.is_none() {
+                if #need_session_cache && __potato_oidc_claims.is_none() && __potato_session_cache.is_none() {
                     let mut __potato_resp = potato::HttpResponse::text("Unauthorized: Missing or invalid Authorization header");
                     __potato_resp.http_code = 401;
                     return __potato_resp;
                 }
-
-                // 自动解析请求中的Cookie
-                if let Some(ref mut session_cache) = __potato_session_cache {
-                    if let Some(cookie_header) = req.headers.get(&potato::utils::refstr::HeaderOrHipStr::from_str("Cookie")) {
-                        session_cache.parse_request_cookies(cookie_header.as_str());
-                    }
-                }
+                #requirements_check_code
 
                 let mut __potato_pre_response: Option<potato::HttpResponse> = None;
                 #(
@@ -1503,9 +1914,12 @@ fn http_handler_macro(attr: TokenStream, input: TokenStream, req_name: &str) ->
                 #add_headers_code
                 #cors_headers_code
 
-                // 自动应用SessionCache中的cookies到响应
+                // 自动应用SessionCache中的cookies到响应，并保存需要持久化的值
                 if let Some(ref session_cache) = __potato_session_cache {
                     session_cache.apply_cookies(&mut __potato_response);
+                    if let Err(e) = session_cache.save().await {
+                        eprintln!("[Session] Failed to save session: {e}");
+                    }
                 }
 
                 drop(__potato_permit);
@@ -1513,6 +1927,11 @@ fn http_handler_macro(attr: TokenStream, input: TokenStream, req_name: &str) ->
             }
         } else {
             quote! {
+                #security_headers_code
+                #ip_filter_check_code
+                #auth_schemes_check_code
+                #rate_limit_check_code
+
                 // 获取自定义错误处理器
                 let __potato_error_handler: Option<potato::ErrorHandler> = {
                     let mut handler = None;
@@ -1529,35 +1948,27 @@ fn http_handler_macro(attr: TokenStream, input: TokenStream, req_name: &str) ->
                 } else {
                     None
                 };
-                let mut __potato_session_cache: Option<potato::SessionCache> = if #need_session_cache {
-                    // 从 Authorization header 中提取 Bearer token 并加载 session
-                    if let Some(h) = req.headers.get(&potato::utils::refstr::HeaderOrHipStr::from_str("Authorization")) {
-                        let header_value = h.as_str();
-                        if header_value.starts_with("Bearer ") {
-                            potato::SessionCache::from_token(&header_value[7..]).await.ok()
-                        } else {
-                            None
-                        }
-                    } else {
-                        None
-                    }
+                // 仅有访问要求的 handler 可直接使用 use_oidc 校验通过的令牌
+                let __potato_oidc_claims = if #oidc_fallback {
+                    req.oidc_claims()
                 } else {
                     None
                 };
+                let mut __potato_session_cache: Option<potato::SessionCache> =
+                    if #need_session_cache && __potato_oidc_claims.is_none() {
+                        // 从 Authorization header 中的 Bearer token 或会话 Cookie 加载 session
+                        potato::SessionCache::from_request(req).await
+                    } else {
+                        None
+                    };
 
                 // 如果 handler 需要 SessionCache 但没有提供 Authorization header，返回 401
-                if #need_session_cache && __potato_session_cache.is_none() {
+                if #need_session_cache && __potato_oidc_claims.is_none() && __potato_session_cache.is_none() {
                     let mut __potato_resp = potato::HttpResponse::text("Unauthorized: Missing or invalid Authorization header");
                     __potato_resp.http_code = 401;
                     return __potato_resp;
                 }
-
-                // 自动解析请求中的Cookie
-                if let Some(ref mut session_cache) = __potato_session_cache {
-                    if let Some(cookie_header) = req.headers.get(&potato::utils::refstr::HeaderOrHipStr::from_str("Cookie")) {
-                        session_cache.parse_request_cookies(cookie_header.as_str());
-                    }
-                }
+                #requirements_check_code
 
                 let mut __potato_pre_response: Option<potato::HttpResponse> = None;
                 #(
@@ -1615,6 +2026,14 @@ fn http_handler_macro(attr: TokenStream, input: TokenStream, req_name: &str) ->
                 #add_headers_code
                 #cors_headers_code
 
+                // 自动应用SessionCache中的cookies到响应，并保存需要持久化的值
+                if let Some(ref session_cache) = __potato_session_cache {
+                    session_cache.apply_cookies(&mut __potato_response);
+                    if let Err(e) = session_cache.save().await {
+                        eprintln!("[Session] Failed to save session: {e}");
+                    }
+                }
+
                 __potato_response
             }
         }
@@ -1623,6 +2042,11 @@ fn http_handler_macro(attr: TokenStream, input: TokenStream, req_name: &str) ->
             let semaphore_name =
                 format_ident!("__POTATO_SEMAPHORE_{}", fn_name.to_string().to_uppercase());
             quote! {
+                #security_headers_code
+                #ip_filter_check_code
+                #auth_schemes_check_code
+                #rate_limit_check_code
+
                 let __potato_permit = #semaphore_name.acquire().await;
 
                 // 获取自定义错误处理器
@@ -1641,35 +2065,27 @@ fn http_handler_macro(attr: TokenStream, input: TokenStream, req_name: &str) ->
                 } else {
                     None
                 };
-                let mut __potato_session_cache: Option<potato::SessionCache> = if #need_session_cache {
-                    // 从 Authorization header 中提取 Bearer token 并加载 session
-                    if let Some(h) = req.headers.get(&potato::utils::refstr::HeaderOrHipStr::from_str("Authorization")) {
-                        let header_value = h.as_str();
-                        if header_value.starts_with("Bearer ") {
-                            potato::SessionCache::from_token(&header_value[7..]).await.ok()
-                        } else {
-                            None
-                        }
-                    } else {
-                        None
-                    }
+                // 仅有访问要求的 handler 可直接使用 use_oidc 校验通过的令牌
+                let __potato_oidc_claims = if #oidc_fallback {
+                    req.oidc_claims()
                 } else {
                     None
                 };
+                let mut __potato_session_cache: Option<potato::SessionCache> =
+                    if #need_session_cache && __potato_oidc_claims.is_none() {
+                        // 从 Authorization header 中的 Bearer token 或会话 Cookie 加载 session
+                        potato::SessionCache::from_request(req).await
+                    } else {
+                        None
+                    };
 
                 // 如果 handler 需要 SessionCache 但没有提供 Authorization header，返回 401
-                if #need_session_cache && __potato_session_cache.is_none() {
+                if #need_session_cache && __potato_oidc_claims.is_none() && __potato_session_cache.is_none() {
                     let mut __potato_resp = potato::HttpResponse::text("Unauthorized: Missing or invalid Authorization header");
                     __potato_resp.http_code = 401;
                     return __potato_resp;
                 }
-
-                // 自动解析请求中的Cookie
-                if let Some(ref mut session_cache) = __potato_session_cache {
-                    if let Some(cookie_header) = req.headers.get(&potato::utils::refstr::HeaderOrHipStr::from_str("Cookie")) {
-                        session_cache.parse_request_cookies(cookie_header.as_str());
-                    }
-                }
+                #requirements_check_code
 
                 let mut __potato_pre_response: Option<potato::HttpResponse> = None;
                 #(
@@ -1728,9 +2144,12 @@ fn http_handler_macro(attr: TokenStream, input: TokenStream, req_name: &str) ->
                 #add_headers_code
                 #cors_headers_code
 
-                // 自动应用SessionCache中的cookies到响应
+                // 自动应用SessionCache中的cookies到响应，并保存需要持久化的值
                 if let Some(ref session_cache) = __potato_session_cache {
                     session_cache.apply_cookies(&mut __potato_response);
+                    if let Err(e) = session_cache.save().await {
+                        eprintln!("[Session] Failed to save session: {e}");
+                    }
                 }
 
                 drop(__potato_permit);
@@ -1738,6 +2157,11 @@ fn http_handler_macro(attr: TokenStream, input: TokenStream, req_name: &str) ->
             }
         } else {
             quote! {
+                #security_headers_code
+                #ip_filter_check_code
+                #auth_schemes_check_code
+                #rate_limit_check_code
+
                 // 获取自定义错误处理器
                 let __potato_error_handler: Option<potato::ErrorHandler> = {
                     let mut handler = None;
@@ -1754,35 +2178,27 @@ fn http_handler_macro(attr: TokenStream, input: TokenStream, req_name: &str) ->
                 } else {
                     None
                 };
-                let mut __potato_session_cache: Option<potato::SessionCache> = if #need_session_cache {
-                    // 从 Authorization header 中提取 Bearer token 并加载 session
-                    if let Some(h) = req.headers.get(&potato::utils::refstr::HeaderOrHipStr::from_str("Authorization")) {
-                        let header_value = h.as_str();
-                        if header_value.starts_with("Bearer ") {
-                            potato::SessionCache::from_token(&header_value[7..]).await.ok()
-                        } else {
-                            None
-                        }
-                    } else {
-                        None
-                    }
+                // 仅有访问要求的 handler 可直接使用 use_oidc 校验通过的令牌
+                let __potato_oidc_claims = if #oidc_fallback {
+                    req.oidc_claims()
                 } else {
                     None
                 };
+                let mut __potato_session_cache: Option<potato::SessionCache> =
+                    if #need_session_cache && __potato_oidc_claims.is_none() {
+                        // 从 Authorization header 中的 Bearer token 或会话 Cookie 加载 session
+                        potato::SessionCache::from_request(req).await
+                    } else {
+                        None
+                    };
 
                 // 如果 handler 需要 SessionCache 但没有提供 Authorization header，返回 401
-                if #need_session_cache && __potato_session_cache.is_none() {
+                if #need_session_cache && __potato_oidc_claims.is_none() && __potato_session_cache.is_none() {
                     let mut __potato_resp = potato::HttpResponse::text("Unauthorized: Missing or invalid Authorization header");
                     __potato_resp.http_code = 401;
                     return __potato_resp;
                 }
-
-                // 自动解析请求中的Cookie
-                if let Some(ref mut session_cache) = __potato_session_cache {
-                    if let Some(cookie_header) = req.headers.get(&potato::utils::refstr::HeaderOrHipStr::from_str("Cookie")) {
-                        session_cache.parse_request_cookies(cookie_header.as_str());
-                    }
-                }
+                #requirements_check_code
 
                 let mut __potato_pre_response: Option<potato::HttpResponse> = None;
                 #(
@@ -1840,9 +2256,12 @@ fn http_handler_macro(attr: TokenStream, input: TokenStream, req_name: &str) ->
                 #add_headers_code
                 #cors_headers_code
 
-                // 自动应用SessionCache中的cookies到响应
+                // 自动应用SessionCache中的cookies到响应，并保存需要持久化的值
                 if let Some(ref session_cache) = __potato_session_cache {
                     session_cache.apply_cookies(&mut __potato_response);
+                    if let Err(e) = session_cache.save().await {
+                        eprintln!("[Session] Failed to save session: {e}");
+                    }
                 }
 
                 __potato_response
@@ -1868,6 +2287,8 @@ fn http_handler_macro(attr: TokenStream, input: TokenStream, req_name: &str) ->
             #auto_head_handler
 
             #semaphore_static
+            #rate_limit_static
+            #ip_filter_static
 
             #[doc(hidden)]
             async fn #wrap_func_name2(req: &mut potato::HttpRequest) -> potato::HttpResponse {
@@ -1884,7 +2305,12 @@ fn http_handler_macro(attr: TokenStream, input: TokenStream, req_name: &str) ->
                 #final_path_expr,
                 #handler_variant(#wrap_func_name),
                 potato::RequestHandlerFlagDoc::new(#doc_show, #doc_auth, #doc_summary, #doc_desp, #doc_args, #tag_expr)
+                    .with_requirements(#requirements_expr)
+                    .with_auth_schemes(#auth_schemes_expr)
             )}
+
+            #csrf_exempt_flag
+            #ip_filter_flag
         }
         .into()
     } else {
@@ -1905,6 +2331,8 @@ fn http_handler_macro(attr: TokenStream, input: TokenStream, req_name: &str) ->
             #auto_head_handler
 
             #semaphore_static
+            #rate_limit_static
+            #ip_filter_static
 
             #[doc(hidden)]
             async fn #wrap_func_name2(req: &mut potato::HttpRequest) -> potato::HttpResponse {
@@ -1921,7 +2349,12 @@ fn http_handler_macro(attr: TokenStream, input: TokenStream, req_name: &str) ->
                 #final_path_expr,
                 #handler_variant(#wrap_func_name),
                 potato::RequestHandlerFlagDoc::new(#doc_show, #doc_auth, #doc_summary, #doc_desp, #doc_args, #tag_expr)
+                    .with_requirements(#requirements_expr)
+                    .with_auth_schemes(#auth_schemes_expr)
             )}
+
+            #csrf_exempt_flag
+            #ip_filter_flag
         }
         .into()
     }
@@ -2121,21 +2554,8 @@ fn controller_macro(attr: TokenStream, input: TokenStream) -> TokenStream {
                 // 在堆上分配缓存
                 let once_cache = Box::leak(Box::new(potato::OnceCache::new()));
 
-                // 从 Authorization header 中提取 Bearer token 并加载 session
-                let session_cache = {
-                    if let Some(h) = req.headers.get(&potato::utils::refstr::HeaderOrHipStr::from_str("Authorization")) {
-                        let header_value = h.as_str();
-                        if header_value.starts_with("Bearer ") {
-                            potato::SessionCache::from_token(&header_value[7..]).await.ok()
-                        } else {
-                            None
-                        }
-                    } else {
-                        None
-                    }
-                };
-
-                let session_cache = match session_cache {
+                // 从 Authorization header 中的 Bearer token 或会话 Cookie 加载 session
+                let session_cache = match potato::SessionCache::from_request(req).await {
                     Some(cache) => cache,
                     None => {
                         let mut resp = potato::HttpResponse::text("Unauthorized: Missing or invalid Authorization header");
@@ -2274,6 +2694,36 @@ fn controller_impl_macro(attr: TokenStream, item_impl: syn::ItemImpl) -> TokenSt
             });
 
             if has_http_attr {
+                if method.attrs.iter().any(|attr| {
+                    matches!(
+                        attr_last_ident(attr).as_deref(),
+                        Some("require_role" | "require_scope")
+                    )
+                }) {
+                    panic!("`require_role`/`require_scope` are not supported on controller methods, call `SessionCache::check_access` in a preprocess function instead");
+                }
+                if method
+                    .attrs
+                    .iter()
+                    .any(|attr| attr_last_ident(attr).as_deref() == Some("auth_scheme"))
+                {
+                    panic!("`auth_scheme` is not supported on controller methods, use `ctx.use_auth` instead");
+                }
+                if method
+                    .attrs
+                    .iter()
+                    .any(|attr| attr_last_ident(attr).as_deref() == Some("rate_limit"))
+                {
+                    panic!("`rate_limit` is not supported on controller methods, use `ctx.use_rate_limit` instead");
+                }
+                if method
+                    .attrs
+                    .iter()
+                    .any(|attr| attr_last_ident(attr).as_deref() == Some("ip_filter"))
+                {
+                    panic!("`ip_filter` is not supported on controller methods, use `ctx.use_ip_filter` instead");
+                }
+
                 // 有 http_* 标注，创建清理后的方法（移除 http_* 标注）
                 let mut cleaned_method = method.clone();
                 cleaned_method.attrs = method
@@ -2512,7 +2962,7 @@ fn controller_impl_macro(attr: TokenStream, item_impl: syn::ItemImpl) -> TokenSt
                                     }
                                 }
 
-                                // 生成 SessionCache 加载逻辑（从 Authorization header）
+                                // 生成 SessionCache 加载逻辑（从 Authorization header 或会话 Cookie）
                                 let needs_session_cache = other_params.iter().any(|p| {
                                     p.ty.to_token_stream().to_string().type_simplify()
                                         == "& mut SessionCache"
@@ -2521,17 +2971,8 @@ fn controller_impl_macro(attr: TokenStream, item_impl: syn::ItemImpl) -> TokenSt
                                 let session_cache_init = if needs_session_cache {
                                     quote! {
                                         {
-                                            // 从 Authorization header 中提取 Bearer token 并加载 session
-                                            if let Some(h) = req.headers.get(&potato::utils::refstr::HeaderOrHipStr::from_str("Authorization")) {
-                                                let header_value = h.as_str();
-                                                if header_value.starts_with("Bearer ") {
-                                                    potato::SessionCache::from_token(&header_value[7..]).await.ok()
-                                                } else {
-                                                    None
-                                                }
-                                            } else {
-                                                None
-                                            }
+                                            // 从 Authorization header 中的 Bearer token 或会话 Cookie 加载 session
+                                            potato::SessionCache::from_request(req).await
                                         }
                                     }
                                 } else {
@@ -2898,6 +3339,55 @@ pub fn header(_attr: TokenStream, input: TokenStream) -> TokenStream {
     input
 }
 
+/// security_headers 属性宏 - 这是一个占位宏，实际解析在 http_handler_macro 中完成
+/// 这个宏的存在使得 #[potato::security_headers(...)] 语法能够被编译器识别
+#[proc_macro_attribute]
+pub fn security_headers(_attr: TokenStream, input: TokenStream) -> TokenStream {
+    input
+}
+
+/// csrf_exempt 属性宏 - 这是一个占位宏，实际解析在 http_handler_macro 中完成
+/// 这个宏的存在使得 #[potato::csrf_exempt] 语法能够被编译器识别
+#[proc_macro_attribute]
+pub fn csrf_exempt(_attr: TokenStream, input: TokenStream) -> TokenStream {
+    input
+}
+
+/// require_role 属性宏 - 这是一个占位宏，实际解析在 http_handler_macro 中完成
+/// 这个宏的存在使得 #[potato::require_role(...)] 语法能够被编译器识别
+#[proc_macro_attribute]
+pub fn require_role(_attr: TokenStream, input: TokenStream) -> TokenStream {
+    input
+}
+
+/// require_scope 属性宏 - 这是一个占位宏，实际解析在 http_handler_macro 中完成
+/// 这个宏的存在使得 #[potato::require_scope(...)] 语法能够被编译器识别
+#[proc_macro_attribute]
+pub fn require_scope(_attr: TokenStream, input: TokenStream) -> TokenStream {
+    input
+}
+
+/// auth_scheme 属性宏 - 这是一个占位宏，实际解析在 http_handler_macro 中完成
+/// 这个宏的存在使得 #[potato::auth_scheme(...)] 语法能够被编译器识别
+#[proc_macro_attribute]
+pub fn auth_scheme(_attr: TokenStream, input: TokenStream) -> TokenStream {
+    input
+}
+
+/// rate_limit 属性宏 - 这是一个占位宏，实际解析在 http_handler_macro 中完成
+/// 这个宏的存在使得 #[potato::rate_limit(...)] 语法能够被编译器识别
+#[proc_macro_attribute]
+pub fn rate_limit(_attr: TokenStream, input: TokenStream) -> TokenStream {
+    input
+}
+
+/// ip_filter 属性宏 - 这是一个占位宏，实际解析在 http_handler_macro 中完成
+/// 这个宏的存在使得 #[potato::ip_filter(...)] 语法能够被编译器识别
+#[proc_macro_attribute]
+pub fn ip_filter(_attr: TokenStream, input: TokenStream) -> TokenStream {
+    input
+}
+
 /// cors 属性宏 - 这是一个占位宏，实际解析在 http_handler_macro 中完成
 /// 这个宏的存在使得 #[potato::cors(...)] 语法能够被编译器识别
 #[proc_macro_attribute]
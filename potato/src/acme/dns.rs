@@ -0,0 +1,333 @@
+//! DNS-01 验证支持：DNS 服务商接口、RFC 2136 动态更新与 TXT 记录生效检查
+
+use ring::hmac;
+use std::net::SocketAddr;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::net::UdpSocket;
+
+const TYPE_SOA: u16 = 6;
+const TYPE_TXT: u16 = 16;
+const TYPE_TSIG: u16 = 250;
+const CLASS_IN: u16 = 1;
+const CLASS_NONE: u16 = 254;
+const CLASS_ANY: u16 = 255;
+const OPCODE_UPDATE: u16 = 5;
+
+/// DNS 服务商接口，用于 DNS-01 验证时创建与删除 `_acme-challenge` TXT 记录
+///
+/// `fqdn` 形如 `_acme-challenge.example.com`（不带末尾的点），`value` 为 TXT 记录内容
+#[async_trait::async_trait]
+pub trait DnsProvider: Send + Sync {
+    /// 创建 TXT 记录
+    async fn create_txt_record(&self, fqdn: &str, value: &str) -> anyhow::Result<()>;
+    /// 删除 TXT 记录，仅删除内容等于 `value` 的那一条
+    async fn delete_txt_record(&self, fqdn: &str, value: &str) -> anyhow::Result<()>;
+}
+
+/// 基于 RFC 2136 动态更新的 DNS 服务商，适用于 BIND、Knot、PowerDNS 等支持 nsupdate 的服务器
+#[derive(Debug, Clone)]
+pub struct Rfc2136Provider {
+    /// 主 DNS 服务器地址
+    pub server: SocketAddr,
+    /// 记录所在区域，如 `example.com`
+    pub zone: String,
+    /// TXT 记录 TTL（默认 60 秒）
+    pub ttl: u32,
+    /// TSIG 密钥名与 base64 编码的密钥（hmac-sha256）
+    pub tsig: Option<(String, String)>,
+    /// 单次请求超时时间（默认 5 秒）
+    pub timeout: Duration,
+}
+
+impl Rfc2136Provider {
+    pub fn new(server: SocketAddr, zone: impl Into<String>) -> Self {
+        Self {
+            server,
+            zone: zone.into(),
+            ttl: 60,
+            tsig: None,
+            timeout: Duration::from_secs(5),
+        }
+    }
+
+    /// 设置 TXT 记录 TTL
+    pub fn with_ttl(mut self, ttl: u32) -> Self {
+        self.ttl = ttl;
+        self
+    }
+
+    /// 使用 TSIG（hmac-sha256）签名更新请求
+    pub fn with_tsig(mut self, key_name: impl Into<String>, secret: impl Into<String>) -> Self {
+        self.tsig = Some((key_name.into(), secret.into()));
+        self
+    }
+
+    async fn update(&self, fqdn: &str, value: &str, class: u16, ttl: u32) -> anyhow::Result<()> {
+        let id = rand::random::<u16>();
+        let mut msg = header(id, OPCODE_UPDATE << 11, [1, 0, 1, 0]);
+        write_name(&mut msg, &self.zone)?;
+        msg.extend(TYPE_SOA.to_be_bytes());
+        msg.extend(CLASS_IN.to_be_bytes());
+        write_name(&mut msg, fqdn)?;
+        msg.extend(TYPE_TXT.to_be_bytes());
+        msg.extend(class.to_be_bytes());
+        msg.extend(ttl.to_be_bytes());
+        let rdata = txt_rdata(value);
+        msg.extend((rdata.len() as u16).to_be_bytes());
+        msg.extend(rdata);
+        if let Some((key_name, secret)) = &self.tsig {
+            sign_tsig(&mut msg, id, key_name, secret)?;
+        }
+
+        let res = exchange(self.server, &msg, id, self.timeout).await?;
+        match res[3] & 0x0f {
+            0 => Ok(()),
+            rcode => Err(anyhow::anyhow!(
+                "dns update for {fqdn} failed: {}",
+                rcode_name(rcode)
+            )),
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl DnsProvider for Rfc2136Provider {
+    async fn create_txt_record(&self, fqdn: &str, value: &str) -> anyhow::Result<()> {
+        self.update(fqdn, value, CLASS_IN, self.ttl).await
+    }
+
+    async fn delete_txt_record(&self, fqdn: &str, value: &str) -> anyhow::Result<()> {
+        self.update(fqdn, value, CLASS_NONE, 0).await
+    }
+}
+
+/// 向指定 DNS 服务器查询 TXT 记录，每条记录的多个字符串会被拼接
+pub async fn lookup_txt(resolver: SocketAddr, name: &str) -> anyhow::Result<Vec<String>> {
+    let id = rand::random::<u16>();
+    // RD 标志，允许递归查询
+    let mut msg = header(id, 0x0100, [1, 0, 0, 0]);
+    write_name(&mut msg, name)?;
+    msg.extend(TYPE_TXT.to_be_bytes());
+    msg.extend(CLASS_IN.to_be_bytes());
+
+    let res = exchange(resolver, &msg, id, Duration::from_secs(5)).await?;
+    match res[3] & 0x0f {
+        0 => {}
+        // NXDOMAIN：记录尚未生效
+        3 => return Ok(vec![]),
+        rcode => anyhow::bail!("dns query for {name} failed: {}", rcode_name(rcode)),
+    }
+    let qdcount = u16::from_be_bytes([res[4], res[5]]);
+    let ancount = u16::from_be_bytes([res[6], res[7]]);
+    let mut pos = 12;
+    for _ in 0..qdcount {
+        pos = skip_name(&res, pos)? + 4;
+    }
+    let mut records = vec![];
+    for _ in 0..ancount {
+        pos = skip_name(&res, pos)?;
+        let fixed = res
+            .get(pos..pos + 10)
+            .ok_or_else(|| anyhow::anyhow!("truncated dns response"))?;
+        let rtype = u16::from_be_bytes([fixed[0], fixed[1]]);
+        let rdlen = u16::from_be_bytes([fixed[8], fixed[9]]) as usize;
+        pos += 10;
+        let rdata = res
+            .get(pos..pos + rdlen)
+            .ok_or_else(|| anyhow::anyhow!("truncated dns response"))?;
+        pos += rdlen;
+        if rtype != TYPE_TXT {
+            continue;
+        }
+        let (mut text, mut i) = (Vec::new(), 0);
+        while i < rdata.len() {
+            let len = rdata[i] as usize;
+            text.extend(rdata.get(i + 1..i + 1 + len).unwrap_or_default());
+            i += 1 + len;
+        }
+        records.push(String::from_utf8_lossy(&text).to_string());
+    }
+    Ok(records)
+}
+
+/// 轮询直到所有 DNS 服务器都能查到指定 TXT 记录，超时返回错误
+pub async fn wait_for_txt(
+    resolvers: &[SocketAddr],
+    fqdn: &str,
+    value: &str,
+    timeout: Duration,
+) -> anyhow::Result<()> {
+    let resolvers = match resolvers.is_empty() {
+        true => system_resolvers(),
+        false => resolvers.to_vec(),
+    };
+    let deadline = tokio::time::Instant::now() + timeout;
+    loop {
+        let mut pending = None;
+        for resolver in resolvers.iter() {
+            match lookup_txt(*resolver, fqdn).await {
+                Ok(records) if records.iter().any(|r| r == value) => {}
+                Ok(_) => pending = Some(format!("not visible on {resolver}")),
+                Err(err) => pending = Some(err.to_string()),
+            }
+        }
+        let Some(reason) = pending else {
+            return Ok(());
+        };
+        if tokio::time::Instant::now() >= deadline {
+            anyhow::bail!("txt record {fqdn} not propagated after {timeout:?}: {reason}");
+        }
+        tokio::time::sleep(Duration::from_secs(2)).await;
+    }
+}
+
+/// 读取 /etc/resolv.conf 中的 DNS 服务器，读取失败时使用公共 DNS
+fn system_resolvers() -> Vec<SocketAddr> {
+    let resolvers: Vec<SocketAddr> = std::fs::read_to_string("/etc/resolv.conf")
+        .unwrap_or_default()
+        .lines()
+        .filter_map(|line| line.trim().strip_prefix("nameserver"))
+        .filter_map(|addr| addr.trim().split('%').next()?.parse().ok())
+        .map(|ip| SocketAddr::new(ip, 53))
+        .collect();
+    match resolvers.is_empty() {
+        true => vec![([1, 1, 1, 1], 53).into(), ([8, 8, 8, 8], 53).into()],
+        false => resolvers,
+    }
+}
+
+fn header(id: u16, flags: u16, counts: [u16; 4]) -> Vec<u8> {
+    let mut msg = Vec::with_capacity(512);
+    msg.extend(id.to_be_bytes());
+    msg.extend(flags.to_be_bytes());
+    for count in counts {
+        msg.extend(count.to_be_bytes());
+    }
+    msg
+}
+
+fn write_name(buf: &mut Vec<u8>, name: &str) -> anyhow::Result<()> {
+    for label in name
+        .trim_end_matches('.')
+        .split('.')
+        .filter(|l| !l.is_empty())
+    {
+        if label.len() > 63 {
+            anyhow::bail!("dns label too long: {label}");
+        }
+        buf.push(label.len() as u8);
+        buf.extend(label.to_ascii_lowercase().as_bytes());
+    }
+    buf.push(0);
+    Ok(())
+}
+
+fn skip_name(buf: &[u8], mut pos: usize) -> anyhow::Result<usize> {
+    loop {
+        let len = *buf
+            .get(pos)
+            .ok_or_else(|| anyhow::anyhow!("truncated dns response"))?;
+        match len {
+            0 => return Ok(pos + 1),
+            // 压缩指针
+            len if len & 0xc0 == 0xc0 => return Ok(pos + 2),
+            len => pos += 1 + len as usize,
+        }
+    }
+}
+
+fn txt_rdata(value: &str) -> Vec<u8> {
+    let mut rdata = vec![];
+    for chunk in value.as_bytes().chunks(255) {
+        rdata.push(chunk.len() as u8);
+        rdata.extend(chunk);
+    }
+    rdata
+}
+
+/// 按 RFC 8945 追加 TSIG 记录
+fn sign_tsig(msg: &mut Vec<u8>, id: u16, key_name: &str, secret: &str) -> anyhow::Result<()> {
+    use base64::Engine;
+    let secret = base64::engine::general_purpose::STANDARD
+        .decode(secret.trim())
+        .map_err(|err| anyhow::anyhow!("invalid tsig secret: {err}"))?;
+    let time_signed = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+    let fudge: u16 = 300;
+    let (mut key, mut algorithm) = (vec![], vec![]);
+    write_name(&mut key, key_name)?;
+    write_name(&mut algorithm, "hmac-sha256")?;
+
+    let mut mac = hmac::Context::with_key(&hmac::Key::new(hmac::HMAC_SHA256, &secret));
+    mac.update(msg);
+    mac.update(&key);
+    mac.update(&CLASS_ANY.to_be_bytes());
+    mac.update(&0u32.to_be_bytes());
+    mac.update(&algorithm);
+    mac.update(&time_signed.to_be_bytes()[2..]);
+    mac.update(&fudge.to_be_bytes());
+    // error 与 other len
+    mac.update(&[0, 0, 0, 0]);
+    let mac = mac.sign();
+    let mac = mac.as_ref();
+
+    let mut rdata = algorithm;
+    rdata.extend(&time_signed.to_be_bytes()[2..]);
+    rdata.extend(fudge.to_be_bytes());
+    rdata.extend((mac.len() as u16).to_be_bytes());
+    rdata.extend(mac);
+    rdata.extend(id.to_be_bytes());
+    rdata.extend([0, 0, 0, 0]);
+
+    msg.extend(key);
+    msg.extend(TYPE_TSIG.to_be_bytes());
+    msg.extend(CLASS_ANY.to_be_bytes());
+    msg.extend(0u32.to_be_bytes());
+    msg.extend((rdata.len() as u16).to_be_bytes());
+    msg.extend(rdata);
+    let arcount = u16::from_be_bytes([msg[10], msg[11]]) + 1;
+    msg[10..12].copy_from_slice(&arcount.to_be_bytes());
+    Ok(())
+}
+
+/// 通过 UDP 发送请求并等待对应 ID 的响应，丢包时重试
+async fn exchange(
+    server: SocketAddr,
+    msg: &[u8],
+    id: u16,
+    timeout: Duration,
+) -> anyhow::Result<Vec<u8>> {
+    let bind: SocketAddr = match server.is_ipv4() {
+        true => ([0, 0, 0, 0], 0).into(),
+        false => (std::net::Ipv6Addr::UNSPECIFIED, 0).into(),
+    };
+    let socket = UdpSocket::bind(bind).await?;
+    socket.connect(server).await?;
+    let mut buf = vec![0u8; 4096];
+    for _ in 0..3 {
+        socket.send(msg).await?;
+        let deadline = tokio::time::Instant::now() + timeout;
+        while let Ok(res) = tokio::time::timeout_at(deadline, socket.recv(&mut buf)).await {
+            let n = res?;
+            if n >= 12 && buf[..2] == id.to_be_bytes() && buf[2] & 0x80 != 0 {
+                return Ok(buf[..n].to_vec());
+            }
+        }
+    }
+    Err(anyhow::anyhow!("dns server {server} did not respond"))
+}
+
+fn rcode_name(rcode: u8) -> &'static str {
+    match rcode {
+        1 => "FORMERR",
+        2 => "SERVFAIL",
+        3 => "NXDOMAIN",
+        4 => "NOTIMP",
+        5 => "REFUSED",
+        6 => "YXDOMAIN",
+        7 => "YXRRSET",
+        8 => "NXRRSET",
+        9 => "NOTAUTH",
+        10 => "NOTZONE",
+        _ => "unknown rcode",
+    }
+}
@@ -4,14 +4,27 @@
 
 #![cfg(feature = "acme")]
 
+mod dns;
+mod store;
+mod tls_alpn;
+
+pub use dns::{lookup_txt, wait_for_txt, DnsProvider, Rfc2136Provider};
+pub use store::{CertStore, FileCertStore};
+pub use tls_alpn::ACME_TLS_ALPN;
+
+use crate::server::ocsp::OcspStapler;
+use sha2::{Digest, Sha256};
+use tls_alpn::{AcmeCertResolver, AlpnChallenges};
+
 use instant_acme::{
-    Account, AccountCredentials, AuthorizationStatus, ChallengeType, Identifier, LetsEncrypt,
-    NewAccount, NewOrder, OrderStatus, RetryPolicy,
+    Account, AccountBuilder, AccountCredentials, AuthorizationStatus, ChallengeType, Identifier,
+    LetsEncrypt, NewAccount, NewOrder, OrderStatus, RetryPolicy,
 };
 use rustls_pki_types::pem::PemObject;
 use rustls_pki_types::{CertificateDer, PrivateKeyDer};
+use std::net::SocketAddr;
 use std::sync::Arc;
-use std::time::Duration;
+use std::time::{Duration, SystemTime};
 use tokio::sync::RwLock;
 use tokio_rustls::rustls;
 use tokio_rustls::TlsAcceptor;
@@ -25,8 +38,94 @@ pub struct AcmeOptions {
     pub email: String,
     /// ACME目录URL（默认使用Let's Encrypt生产环境）
     pub acme_directory: Option<String>,
-    /// 证书缓存目录（默认 "./acme_certs"）
+    /// 证书缓存目录（默认 "./acme_certs"），未设置 `cert_store` 时使用
     pub cert_dir: Option<String>,
+    /// 账户与证书存储，多实例共享存储时只有一个实例申请证书（默认为 `cert_dir` 目录）
+    pub cert_store: Option<Arc<dyn CertStore>>,
+    /// ACME服务器HTTPS使用的自定义根证书PEM文件（如Pebble测试服务器）
+    pub acme_ca_file: Option<String>,
+    /// 使用TLS-ALPN-01验证，仅需开放TLS端口；首次申请时先使用临时自签名证书启动监听
+    pub tls_alpn01: bool,
+    /// DNS服务商，设置后使用DNS-01验证代替HTTP-01（通配符域名必须使用DNS-01）
+    pub dns_provider: Option<Arc<dyn DnsProvider>>,
+    /// 检查TXT记录是否生效所查询的DNS服务器（默认读取 /etc/resolv.conf）
+    pub dns_resolvers: Vec<SocketAddr>,
+    /// 等待TXT记录生效的超时时间（默认120秒）
+    pub dns_propagation_timeout: Duration,
+    /// 续期检查间隔（默认6小时）
+    pub check_interval: Duration,
+    /// 证书剩余有效期小于该时长时续期（默认30天）
+    pub renew_before: Duration,
+    /// 续期失败后的首次重试间隔，之后每次失败翻倍（默认5分钟）
+    pub retry_backoff: Duration,
+    /// 续期失败重试间隔的上限（默认6小时）
+    pub retry_backoff_max: Duration,
+    /// 证书生命周期事件回调，可用于监控告警
+    pub on_event: Option<AcmeEventHandler>,
+    /// 启用OCSP装订（默认关闭）
+    pub ocsp_stapling: bool,
+    /// `serve_acme` 同时启动的明文HTTP监听地址（如 `0.0.0.0:80`），应答HTTP-01验证并将其余请求跳转到HTTPS
+    pub http_listen: Option<String>,
+    /// 跳转HTTPS使用的状态码，301或308（默认301）
+    pub redirect_status: u16,
+    /// 不为空时HTTPS响应附加 `Strict-Transport-Security` 头，值如 `max-age=31536000; includeSubDomains`
+    pub hsts: Option<String>,
+}
+
+/// ACME证书生命周期事件
+#[derive(Debug, Clone)]
+pub enum AcmeEvent {
+    /// 首次申请到证书
+    Issued {
+        domains: Vec<String>,
+        not_after: Option<SystemTime>,
+    },
+    /// 证书续期成功
+    Renewed {
+        domains: Vec<String>,
+        not_after: Option<SystemTime>,
+    },
+    /// 申请或续期失败，将在 `retry_in` 后重试；`AcmeManager::new` 中首次申请失败时返回错误
+    /// 而不会自动重试，`retry_in` 为零
+    Failed {
+        domains: Vec<String>,
+        error: String,
+        retry_in: Duration,
+    },
+    /// 证书进入续期窗口，即将开始续期
+    ExpiringSoon {
+        domains: Vec<String>,
+        not_after: SystemTime,
+    },
+}
+
+/// 证书生命周期事件回调
+pub type AcmeEventHandler = Arc<dyn Fn(&AcmeEvent) + Send + Sync>;
+
+impl Default for AcmeOptions {
+    fn default() -> Self {
+        Self {
+            domains: vec![],
+            email: String::new(),
+            acme_directory: None,
+            cert_dir: None,
+            cert_store: None,
+            acme_ca_file: None,
+            tls_alpn01: false,
+            dns_provider: None,
+            dns_resolvers: vec![],
+            dns_propagation_timeout: Duration::from_secs(120),
+            check_interval: Duration::from_secs(6 * 3600),
+            renew_before: Duration::from_secs(30 * 24 * 3600),
+            retry_backoff: Duration::from_secs(5 * 60),
+            retry_backoff_max: Duration::from_secs(6 * 3600),
+            on_event: None,
+            ocsp_stapling: false,
+            http_listen: None,
+            redirect_status: 301,
+            hsts: None,
+        }
+    }
 }
 
 impl AcmeOptions {
@@ -34,10 +133,85 @@ impl AcmeOptions {
         Self {
             domains: vec![domain.into()],
             email: email.into(),
-            acme_directory: None,
-            cert_dir: None,
+            ..Default::default()
         }
     }
+
+    /// 使用自定义存储，如多实例共享的存储
+    pub fn with_cert_store(mut self, store: impl CertStore + 'static) -> Self {
+        self.cert_store = Some(Arc::new(store));
+        self
+    }
+
+    /// 使用TLS-ALPN-01验证
+    pub fn with_tls_alpn01(mut self) -> Self {
+        self.tls_alpn01 = true;
+        self
+    }
+
+    /// 使用DNS-01验证
+    pub fn with_dns_provider(mut self, provider: impl DnsProvider + 'static) -> Self {
+        self.dns_provider = Some(Arc::new(provider));
+        self
+    }
+
+    /// 设置检查TXT记录是否生效所查询的DNS服务器，通常为域名的权威DNS服务器
+    pub fn with_dns_resolvers(mut self, resolvers: Vec<SocketAddr>) -> Self {
+        self.dns_resolvers = resolvers;
+        self
+    }
+
+    /// 设置续期检查间隔与续期窗口
+    pub fn with_renewal(mut self, check_interval: Duration, renew_before: Duration) -> Self {
+        self.check_interval = check_interval;
+        self.renew_before = renew_before;
+        self
+    }
+
+    /// 设置续期失败后的重试间隔（指数退避）及其上限
+    pub fn with_retry_backoff(mut self, initial: Duration, max: Duration) -> Self {
+        self.retry_backoff = initial;
+        self.retry_backoff_max = max;
+        self
+    }
+
+    /// 设置证书生命周期事件回调
+    pub fn with_event_handler(
+        mut self,
+        handler: impl Fn(&AcmeEvent) + Send + Sync + 'static,
+    ) -> Self {
+        self.on_event = Some(Arc::new(handler));
+        self
+    }
+
+    /// 启用OCSP装订
+    pub fn with_ocsp_stapling(mut self) -> Self {
+        self.ocsp_stapling = true;
+        self
+    }
+
+    /// 在 `addr` 上启动明文HTTP监听，应答HTTP-01验证，其余请求以301跳转到HTTPS
+    pub fn with_http_redirect(mut self, addr: impl Into<String>) -> Self {
+        self.http_listen = Some(addr.into());
+        self
+    }
+
+    /// 为HTTPS响应启用HSTS
+    pub fn with_hsts(mut self, max_age: Duration, include_subdomains: bool) -> Self {
+        let mut hsts = format!("max-age={}", max_age.as_secs());
+        if include_subdomains {
+            hsts.push_str("; includeSubDomains");
+        }
+        self.hsts = Some(hsts);
+        self
+    }
+}
+
+/// 初始化 rustls CryptoProvider（如果尚未初始化），ACME客户端与TLS接受器均依赖
+fn install_crypto_provider() {
+    use rustls::crypto::ring::default_provider;
+    use rustls::crypto::CryptoProvider;
+    let _ = CryptoProvider::install_default(default_provider());
 }
 
 /// TLS证书状态
@@ -48,15 +222,26 @@ struct TlsCertState {
     acceptor: TlsAcceptor,
 }
 
-/// 动态TLS接受器，支持热重载
+/// 动态TLS接受器，支持热重载与TLS-ALPN-01验证
 #[derive(Clone)]
 pub struct DynamicTlsAcceptor {
     state: Arc<RwLock<Arc<TlsCertState>>>,
+    alpn_challenges: AlpnChallenges,
+    ocsp: Option<OcspStapler>,
 }
 
 impl DynamicTlsAcceptor {
     pub fn new(cert_pem: &str, key_pem: &str) -> anyhow::Result<Self> {
-        let acceptor = Self::create_acceptor(cert_pem, key_pem)?;
+        Self::build(cert_pem, key_pem, AlpnChallenges::default(), None)
+    }
+
+    fn build(
+        cert_pem: &str,
+        key_pem: &str,
+        alpn_challenges: AlpnChallenges,
+        ocsp: Option<OcspStapler>,
+    ) -> anyhow::Result<Self> {
+        let acceptor = Self::create_acceptor(cert_pem, key_pem, &alpn_challenges, &ocsp)?;
         let state = Arc::new(TlsCertState {
             cert_pem: cert_pem.to_string(),
             key_pem: key_pem.to_string(),
@@ -64,32 +249,41 @@ impl DynamicTlsAcceptor {
         });
         Ok(Self {
             state: Arc::new(RwLock::new(state)),
+            alpn_challenges,
+            ocsp,
         })
     }
 
-    fn create_acceptor(cert_pem: &str, key_pem: &str) -> anyhow::Result<TlsAcceptor> {
-        // 初始化 rustls CryptoProvider（如果尚未初始化）
-        {
-            use rustls::crypto::ring::default_provider;
-            use rustls::crypto::CryptoProvider;
-            let _ = CryptoProvider::install_default(default_provider());
-        }
+    fn create_acceptor(
+        cert_pem: &str,
+        key_pem: &str,
+        alpn_challenges: &AlpnChallenges,
+        ocsp: &Option<OcspStapler>,
+    ) -> anyhow::Result<TlsAcceptor> {
+        install_crypto_provider();
 
         let certs =
             CertificateDer::pem_slice_iter(cert_pem.as_bytes()).collect::<Result<Vec<_>, _>>()?;
         let key = PrivateKeyDer::from_pem_slice(key_pem.as_bytes())?;
+        let key = rustls::crypto::ring::sign::any_supported_type(&key)?;
+        let resolver = AcmeCertResolver {
+            cert: Arc::new(rustls::sign::CertifiedKey::new(certs, key)),
+            challenges: Arc::clone(alpn_challenges),
+            ocsp: ocsp.clone(),
+        };
 
         let mut config = rustls::ServerConfig::builder()
             .with_no_client_auth()
-            .with_single_cert(certs, key)?;
-        // 支持HTTP/2和HTTP/1.1
-        config.alpn_protocols = vec![b"h2".to_vec(), b"http/1.1".to_vec()];
+            .with_cert_resolver(Arc::new(resolver));
+        // 支持HTTP/2和HTTP/1.1，以及TLS-ALPN-01验证
+        config.alpn_protocols = vec![b"h2".to_vec(), b"http/1.1".to_vec(), ACME_TLS_ALPN.to_vec()];
 
         Ok(TlsAcceptor::from(Arc::new(config)))
     }
 
     pub async fn reload(&self, cert_pem: &str, key_pem: &str) -> anyhow::Result<()> {
-        let new_acceptor = Self::create_acceptor(cert_pem, key_pem)?;
+        let new_acceptor =
+            Self::create_acceptor(cert_pem, key_pem, &self.alpn_challenges, &self.ocsp)?;
         let new_state = Arc::new(TlsCertState {
             cert_pem: cert_pem.to_string(),
             key_pem: key_pem.to_string(),
@@ -102,6 +296,29 @@ impl DynamicTlsAcceptor {
     pub async fn get_acceptor(&self) -> TlsAcceptor {
         self.state.read().await.acceptor.clone()
     }
+
+    /// 设置域名的TLS-ALPN-01验证证书，`key_authorization` 为ACME挑战的key authorization
+    pub fn set_tls_alpn_challenge(
+        &self,
+        domain: &str,
+        key_authorization: &str,
+    ) -> anyhow::Result<()> {
+        let domain = domain.to_ascii_lowercase();
+        let digest = Sha256::digest(key_authorization.as_bytes());
+        let cert = tls_alpn::challenge_cert(&domain, &digest)?;
+        self.alpn_challenges
+            .write()
+            .map_err(|_| anyhow::anyhow!("alpn challenges lock poisoned"))?
+            .insert(domain, cert);
+        Ok(())
+    }
+
+    /// 清除所有TLS-ALPN-01验证证书
+    pub fn clear_tls_alpn_challenges(&self) {
+        if let Ok(mut challenges) = self.alpn_challenges.write() {
+            challenges.clear();
+        }
+    }
 }
 
 /// ACME挑战信息
@@ -111,78 +328,209 @@ pub struct AcmeChallenge {
     pub key_authorization: String,
 }
 
+/// DNS-01验证配置
+#[derive(Clone)]
+struct Dns01Config {
+    provider: Arc<dyn DnsProvider>,
+    resolvers: Vec<SocketAddr>,
+    propagation_timeout: Duration,
+}
+
+/// 账户创建与证书申请的租约锁名
+const ACCOUNT_LOCK: &str = "account";
+const RENEW_LOCK: &str = "renew";
+/// 租约时长，也是等待其他实例完成申请的最长时间
+const LOCK_TTL: Duration = Duration::from_secs(10 * 60);
+
 /// ACME管理器
 #[derive(Clone)]
 pub struct AcmeManager {
     account: Account,
     domains: Vec<String>,
-    cert_dir: String,
+    store: Arc<dyn CertStore>,
+    /// 当前实例标识，用作租约锁持有者
+    instance_id: String,
     challenges: Arc<RwLock<Vec<AcmeChallenge>>>,
+    dns01: Option<Dns01Config>,
+    tls_alpn01: bool,
+    alpn_challenges: AlpnChallenges,
+    check_interval: Duration,
+    renew_before: Duration,
+    retry_backoff: Duration,
+    retry_backoff_max: Duration,
+    on_event: Option<AcmeEventHandler>,
+    ocsp_stapling: bool,
 }
 
 impl AcmeManager {
     /// 创建或加载ACME账户
     pub async fn new(opts: AcmeOptions) -> anyhow::Result<(Self, DynamicTlsAcceptor)> {
-        let cert_dir = opts
-            .cert_dir
-            .clone()
-            .unwrap_or_else(|| "./acme_certs".to_string());
+        if opts.dns_provider.is_none() && opts.domains.iter().any(|d| d.starts_with("*.")) {
+            return Err(anyhow::anyhow!(
+                "wildcard domains require DNS-01 challenge, set AcmeOptions::dns_provider"
+            ));
+        }
+        let dns01 = opts.dns_provider.clone().map(|provider| Dns01Config {
+            provider,
+            resolvers: opts.dns_resolvers.clone(),
+            propagation_timeout: opts.dns_propagation_timeout,
+        });
+        install_crypto_provider();
+        let store = match opts.cert_store.clone() {
+            Some(store) => store,
+            None => {
+                let cert_dir = opts
+                    .cert_dir
+                    .clone()
+                    .unwrap_or_else(|| "./acme_certs".to_string());
+                std::fs::create_dir_all(&cert_dir)?;
+                Arc::new(FileCertStore::new(cert_dir))
+            }
+        };
+        let instance_id = format!("{:016x}", rand::random::<u64>());
         let email = opts.email.clone();
         let domains = opts.domains.clone();
         let acme_directory = opts.acme_directory.clone();
-
-        std::fs::create_dir_all(&cert_dir)?;
-
-        let account_path = format!("{cert_dir}/account.json");
-
-        // 尝试加载已有账户
-        let account = if let Ok(creds_str) = std::fs::read_to_string(&account_path) {
-            if let Ok(creds) = serde_json::from_str::<AccountCredentials>(&creds_str) {
-                Account::builder()?.from_credentials(creds).await?
-            } else {
-                Self::create_account(&email, &acme_directory, &account_path).await?
+        let acme_ca_file = opts.acme_ca_file.clone();
+
+        // 尝试加载已有账户，多实例同时启动时只由一个实例注册账户
+        let account = match Self::load_account(&store, &acme_ca_file).await? {
+            Some(account) => account,
+            None if store.try_lock(ACCOUNT_LOCK, &instance_id, LOCK_TTL).await? => {
+                let account = match Self::load_account(&store, &acme_ca_file).await {
+                    Ok(Some(account)) => Ok(account),
+                    _ => Self::create_account(&email, &acme_directory, &acme_ca_file, &store).await,
+                };
+                store.unlock(ACCOUNT_LOCK, &instance_id).await?;
+                account?
+            }
+            None => {
+                println!("[ACME] Another instance is creating the account, waiting...");
+                Self::wait_for_key(&store, "account.json").await?;
+                Self::load_account(&store, &acme_ca_file)
+                    .await?
+                    .ok_or_else(|| anyhow::anyhow!("invalid ACME account credentials"))?
             }
-        } else {
-            Self::create_account(&email, &acme_directory, &account_path).await?
         };
 
         let manager = Self {
             account,
             domains,
-            cert_dir: cert_dir.clone(),
+            store,
+            instance_id,
             challenges: Arc::new(RwLock::new(Vec::new())),
+            dns01,
+            tls_alpn01: opts.tls_alpn01,
+            alpn_challenges: AlpnChallenges::default(),
+            check_interval: opts.check_interval,
+            renew_before: opts.renew_before,
+            retry_backoff: opts.retry_backoff,
+            retry_backoff_max: opts.retry_backoff_max,
+            on_event: opts.on_event.clone(),
+            ocsp_stapling: opts.ocsp_stapling,
         };
 
         // 尝试加载已有证书
-        let cert_dir_clone = cert_dir.clone();
-        let cert_path = format!("{cert_dir_clone}/cert.pem");
-        let key_path = format!("{cert_dir_clone}/key.pem");
-
-        let acceptor = if std::path::Path::new(&cert_path).exists()
-            && std::path::Path::new(&key_path).exists()
-        {
-            let cert_pem = std::fs::read_to_string(&cert_path)?;
-            let key_pem = std::fs::read_to_string(&key_path)?;
-            DynamicTlsAcceptor::new(&cert_pem, &key_pem)?
-        } else {
-            // 申请新证书
-            let (cert_pem, key_pem) = manager.obtain_certificate().await?;
-            DynamicTlsAcceptor::new(&cert_pem, &key_pem)?
+        let (cert_pem, key_pem) = match manager.load_cert().await? {
+            Some(cert) => cert,
+            // TLS-ALPN-01需要TLS监听已启动才能验证，先使用临时证书，由续期循环立即申请
+            None if manager.tls_alpn01 && manager.dns01.is_none() => {
+                tls_alpn::placeholder_cert(&manager.domains)?
+            }
+            // 申请新证书，其他实例正在申请时等待其完成
+            None => match manager.obtain_certificate_locked().await {
+                Err(e) => {
+                    manager.emit(AcmeEvent::Failed {
+                        domains: manager.domains.clone(),
+                        error: e.to_string(),
+                        retry_in: Duration::ZERO,
+                    });
+                    return Err(e);
+                }
+                Ok(Some(cert)) => {
+                    manager.emit(AcmeEvent::Issued {
+                        domains: manager.domains.clone(),
+                        not_after: Self::cert_not_after(&cert.0),
+                    });
+                    cert
+                }
+                Ok(None) => {
+                    println!("[ACME] Another instance is obtaining the certificate, waiting...");
+                    Self::wait_for_key(&manager.store, "cert.pem").await?;
+                    manager
+                        .load_cert()
+                        .await?
+                        .ok_or_else(|| anyhow::anyhow!("certificate not found in store"))?
+                }
+            },
         };
+        let acceptor = DynamicTlsAcceptor::build(
+            &cert_pem,
+            &key_pem,
+            Arc::clone(&manager.alpn_challenges),
+            manager.ocsp_stapling.then(OcspStapler::default),
+        )?;
 
         Ok((manager, acceptor))
     }
 
+    fn account_builder(acme_ca_file: &Option<String>) -> anyhow::Result<AccountBuilder> {
+        Ok(match acme_ca_file {
+            Some(ca_file) => Account::builder_with_root(ca_file)?,
+            None => Account::builder()?,
+        })
+    }
+
+    async fn load_account(
+        store: &Arc<dyn CertStore>,
+        acme_ca_file: &Option<String>,
+    ) -> anyhow::Result<Option<Account>> {
+        let Some(creds) = store.load("account.json").await? else {
+            return Ok(None);
+        };
+        match serde_json::from_slice::<AccountCredentials>(&creds) {
+            Ok(creds) => Ok(Some(
+                Self::account_builder(acme_ca_file)?
+                    .from_credentials(creds)
+                    .await?,
+            )),
+            Err(_) => Ok(None),
+        }
+    }
+
+    /// 等待其他实例把数据写入存储
+    async fn wait_for_key(store: &Arc<dyn CertStore>, key: &str) -> anyhow::Result<()> {
+        let deadline = tokio::time::Instant::now() + LOCK_TTL;
+        while store.load(key).await?.is_none() {
+            if tokio::time::Instant::now() >= deadline {
+                return Err(anyhow::anyhow!("timed out waiting for {key} in cert store"));
+            }
+            tokio::time::sleep(Duration::from_secs(2)).await;
+        }
+        Ok(())
+    }
+
+    /// 从存储读取证书链与私钥
+    async fn load_cert(&self) -> anyhow::Result<Option<(String, String)>> {
+        let cert = self.store.load("cert.pem").await?;
+        let key = self.store.load("key.pem").await?;
+        Ok(match (cert, key) {
+            (Some(cert), Some(key)) => Some((String::from_utf8(cert)?, String::from_utf8(key)?)),
+            _ => None,
+        })
+    }
+
     async fn create_account(
         email: &str,
         acme_directory: &Option<String>,
-        account_path: &str,
+        acme_ca_file: &Option<String>,
+        store: &Arc<dyn CertStore>,
     ) -> anyhow::Result<Account> {
         let dir_url = acme_directory
             .clone()
             .unwrap_or_else(|| LetsEncrypt::Production.url().to_string());
 
-        let (account, credentials) = Account::builder()?
+        let (account, credentials) = Self::account_builder(acme_ca_file)?
             .create(
                 &NewAccount {
                     contact: &[&format!("mailto:{email}")],
@@ -194,9 +542,11 @@ impl AcmeManager {
             )
             .await?;
 
-        // 保存账户凭据
+        // 保存账户凭据（包含账户私钥）
         let creds_str = serde_json::to_string_pretty(&credentials)?;
-        std::fs::write(account_path, creds_str)?;
+        store
+            .store("account.json", creds_str.as_bytes(), true)
+            .await?;
 
         Ok(account)
     }
@@ -206,7 +556,41 @@ impl AcmeManager {
         self.challenges.read().await.clone()
     }
 
-    /// 申请证书（HTTP-01验证）
+    /// 查询HTTP-01挑战的响应内容，本实例没有时从共享存储读取（由其他实例发起的申请）
+    pub async fn challenge_response(&self, token: &str) -> Option<String> {
+        let challenges = self.challenges.read().await;
+        if let Some(challenge) = challenges.iter().find(|c| c.token == token) {
+            return Some(challenge.key_authorization.clone());
+        }
+        drop(challenges);
+        if token.is_empty()
+            || !token
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+        {
+            return None;
+        }
+        let data = self.store.load(&format!("http-01/{token}")).await.ok()??;
+        String::from_utf8(data).ok()
+    }
+
+    /// 获得续期租约锁后申请证书，锁被其他实例持有时返回 `None`
+    async fn obtain_certificate_locked(&self) -> anyhow::Result<Option<(String, String)>> {
+        if !self
+            .store
+            .try_lock(RENEW_LOCK, &self.instance_id, LOCK_TTL)
+            .await?
+        {
+            return Ok(None);
+        }
+        let result = self.obtain_certificate().await;
+        if let Err(e) = self.store.unlock(RENEW_LOCK, &self.instance_id).await {
+            eprintln!("[ACME] Failed to release renew lock: {e}");
+        }
+        result.map(Some)
+    }
+
+    /// 申请证书（HTTP-01、TLS-ALPN-01或DNS-01验证）
     async fn obtain_certificate(&self) -> anyhow::Result<(String, String)> {
         println!(
             "[ACME] Starting certificate obtainment for domains: {:?}",
@@ -226,6 +610,59 @@ impl AcmeManager {
 
         println!("[ACME] Order created, processing authorizations...");
 
+        // 无论验证是否成功，都清理已创建的TXT记录
+        let mut dns_records = Vec::new();
+        let status = self.validate_order(&mut order, &mut dns_records).await;
+        if let Ok(mut challenges) = self.alpn_challenges.write() {
+            challenges.clear();
+        }
+        for challenge in self.challenges.read().await.iter() {
+            _ = self
+                .store
+                .delete(&format!("http-01/{}", challenge.token))
+                .await;
+        }
+        if let Some(dns01) = &self.dns01 {
+            for (fqdn, value) in dns_records.iter() {
+                if let Err(e) = dns01.provider.delete_txt_record(fqdn, value).await {
+                    eprintln!("[ACME] Failed to delete TXT record {fqdn}: {e}");
+                }
+            }
+        }
+        let status = status?;
+        if status != OrderStatus::Ready {
+            return Err(anyhow::anyhow!("unexpected order status: {:?}", status));
+        }
+
+        // 完成订单并获取证书
+        println!("[ACME] Order ready, finalizing...");
+        let private_key_pem = order.finalize().await?;
+        let cert_chain_pem = order.poll_certificate(&RetryPolicy::default()).await?;
+
+        // 保存证书到存储，先写私钥，其他实例以证书出现为准读取
+        self.store
+            .store("key.pem", private_key_pem.as_bytes(), true)
+            .await?;
+        self.store
+            .store("cert.pem", cert_chain_pem.as_bytes(), false)
+            .await?;
+
+        println!("[ACME] Certificate obtained and saved successfully");
+        Ok((cert_chain_pem, private_key_pem))
+    }
+
+    /// 部署验证挑战并等待订单就绪，创建的TXT记录写入 `dns_records` 供调用方清理
+    async fn validate_order(
+        &self,
+        order: &mut instant_acme::Order,
+        dns_records: &mut Vec<(String, String)>,
+    ) -> anyhow::Result<OrderStatus> {
+        let challenge_type = match (&self.dns01, self.tls_alpn01) {
+            (Some(_), _) => ChallengeType::Dns01,
+            (None, true) => ChallengeType::TlsAlpn01,
+            (None, false) => ChallengeType::Http01,
+        };
+
         // 处理授权挑战
         let mut authorizations = order.authorizations();
         let mut challenges = Vec::new();
@@ -244,34 +681,75 @@ impl AcmeManager {
             }
 
             let challenge = authz
-                .challenge(ChallengeType::Http01)
-                .ok_or_else(|| anyhow::anyhow!("no http-01 challenge found"))?;
+                .challenge(challenge_type.clone())
+                .ok_or_else(|| anyhow::anyhow!("no {challenge_type:?} challenge found"))?;
 
             let key_auth = challenge.key_authorization();
             println!("[ACME] Challenge token: {}", challenge.token);
 
+            if let Some(dns01) = &self.dns01 {
+                // 通配符域名与主域名共用同一个 _acme-challenge 记录
+                let Identifier::Dns(domain) = challenge.identifier().identifier else {
+                    return Err(anyhow::anyhow!("dns-01 only supports dns identifiers"));
+                };
+                let fqdn = format!("_acme-challenge.{domain}");
+                let value = key_auth.dns_value();
+                println!("[ACME] Creating TXT record: {fqdn}");
+                dns01.provider.create_txt_record(&fqdn, &value).await?;
+                dns_records.push((fqdn, value));
+            } else if challenge_type == ChallengeType::TlsAlpn01 {
+                let domain = challenge.identifier().to_string();
+                let cert = tls_alpn::challenge_cert(&domain, key_auth.digest().as_ref())?;
+                self.alpn_challenges
+                    .write()
+                    .map_err(|_| anyhow::anyhow!("alpn challenges lock poisoned"))?
+                    .insert(domain.to_ascii_lowercase(), cert);
+            }
+
             challenges.push(AcmeChallenge {
                 token: challenge.token.clone(),
                 key_authorization: key_auth.as_str().to_string(),
             });
         }
 
-        // 保存挑战信息供HTTP服务器使用
-        // 注意：这里会更新challenges，HTTP服务器会看到新的挑战
-        println!(
-            "[ACME] Saving {} challenges for HTTP server",
-            challenges.len()
-        );
-        *self.challenges.write().await = challenges.clone();
+        match &self.dns01 {
+            Some(dns01) => {
+                // 等待TXT记录在DNS中生效，否则ACME服务器可能查不到记录导致验证失败
+                for (fqdn, value) in dns_records.iter() {
+                    println!("[ACME] Waiting for TXT record propagation: {fqdn}");
+                    wait_for_txt(&dns01.resolvers, fqdn, value, dns01.propagation_timeout).await?;
+                }
+            }
+            None if challenge_type == ChallengeType::TlsAlpn01 => {}
+            None => {
+                // 保存挑战信息供HTTP服务器使用
+                // 注意：这里会更新challenges，HTTP服务器会看到新的挑战
+                println!(
+                    "[ACME] Saving {} challenges for HTTP server",
+                    challenges.len()
+                );
+                *self.challenges.write().await = challenges.clone();
+                // 同时写入存储，负载均衡到其他实例的验证请求也能响应
+                for challenge in &challenges {
+                    self.store
+                        .store(
+                            &format!("http-01/{}", challenge.token),
+                            challenge.key_authorization.as_bytes(),
+                            false,
+                        )
+                        .await?;
+                }
+            }
+        }
 
         // 标记挑战就绪（通知ACME服务器开始验证）
-        // 必须在保存challenges之后立即执行，以便HTTP服务器可以响应ACME验证请求
+        // 必须在挑战部署完成之后执行，以便ACME服务器验证时能查到挑战
         for challenge in &challenges {
             println!("[ACME] Setting challenge ready: {}", challenge.token);
             let mut authorizations = order.authorizations();
             while let Some(result) = authorizations.next().await {
                 let mut authz = result?;
-                if let Some(mut ch) = authz.challenge(ChallengeType::Http01) {
+                if let Some(mut ch) = authz.challenge(challenge_type.clone()) {
                     if ch.token == challenge.token {
                         ch.set_ready().await?;
                         println!("[ACME] Challenge set to ready");
@@ -280,114 +758,121 @@ impl AcmeManager {
             }
         }
 
-        // 等待ACME服务器验证完成
-        // 注意：这里不再需要额外的sleep，因为set_ready后ACME服务器会立即开始验证
-        // HTTP服务器已经在上一步保存challenges时可以响应验证请求了
-
         // 等待订单就绪
         println!("[ACME] Waiting for order to be ready...");
-        let status = order.poll_ready(&RetryPolicy::default()).await?;
-        if status != OrderStatus::Ready {
-            return Err(anyhow::anyhow!("unexpected order status: {:?}", status));
-        }
-
-        // 完成订单并获取证书
-        println!("[ACME] Order ready, finalizing...");
-        let private_key_pem = order.finalize().await?;
-        let cert_chain_pem = order.poll_certificate(&RetryPolicy::default()).await?;
+        Ok(order.poll_ready(&RetryPolicy::default()).await?)
+    }
 
-        // 保存证书到文件
-        let cert_path = format!("{}/cert.pem", self.cert_dir);
-        let key_path = format!("{}/key.pem", self.cert_dir);
-        std::fs::write(&cert_path, &cert_chain_pem)?;
-        std::fs::write(&key_path, &private_key_pem)?;
+    /// 触发证书生命周期事件
+    fn emit(&self, event: AcmeEvent) {
+        if let Some(on_event) = &self.on_event {
+            on_event(&event);
+        }
+    }
 
-        println!("[ACME] Certificate obtained and saved successfully");
-        Ok((cert_chain_pem, private_key_pem))
+    /// 第 `failures` 次连续失败后的重试间隔
+    fn retry_delay(&self, failures: u32) -> Duration {
+        self.retry_backoff
+            .saturating_mul(2u32.saturating_pow(failures))
+            .min(self.retry_backoff_max)
     }
 
     /// 启动后台续期循环
     /// 注意：由于AcmeManager使用Arc<RwLock>存储challenges，
     /// 续签时更新的challenges会被HTTP服务器看到
     pub async fn start_renewal_loop(self, acceptor: DynamicTlsAcceptor) -> anyhow::Result<()> {
+        // 存储中没有证书时（如TLS-ALPN-01首次申请）立即申请，不等待首次检查间隔
+        let mut delay = match self.load_cert().await {
+            Ok(None) => Duration::ZERO,
+            _ => self.check_interval,
+        };
+        let mut failures = 0;
         loop {
-            // 每6小时检查一次
-            tokio::time::sleep(Duration::from_secs(6 * 3600)).await;
-
-            // 检查证书是否即将过期（30天内）
-            let cert_path = format!("{}/cert.pem", self.cert_dir);
-            if let Ok(_cert_pem) = std::fs::read_to_string(&cert_path) {
-                if Self::should_renew(&cert_path) {
-                    println!("[ACME] Certificate expiring soon, renewing...");
-                    match self.obtain_certificate().await {
-                        Ok((cert_pem, key_pem)) => {
-                            if let Err(e) = acceptor.reload(&cert_pem, &key_pem).await {
-                                eprintln!("[ACME] Failed to reload certificate: {e}");
-                            } else {
-                                println!("[ACME] Certificate renewed successfully");
-                            }
-                        }
-                        Err(e) => {
-                            eprintln!("[ACME] Failed to renew certificate: {e}");
+            tokio::time::sleep(delay).await;
+            delay = self.check_interval;
+
+            // 检查证书是否进入续期窗口
+            let stored = match self.load_cert().await {
+                Ok(stored) => stored,
+                Err(e) => {
+                    eprintln!("[ACME] Failed to load certificate from store: {e}");
+                    continue;
+                }
+            };
+            if let Some((cert_pem, key_pem)) = stored.as_ref() {
+                if !Self::should_renew(cert_pem, self.renew_before) {
+                    // 其他实例续期后写入存储的新证书在此热加载
+                    if acceptor.state.read().await.cert_pem != *cert_pem {
+                        match acceptor.reload(cert_pem, key_pem).await {
+                            Ok(()) => println!("[ACME] Loaded certificate from store"),
+                            Err(e) => eprintln!("[ACME] Failed to reload certificate: {e}"),
                         }
                     }
-                } else {
-                    println!("[ACME] Certificate still valid, next check in 6 hours");
+                    println!("[ACME] Certificate still valid, next check in {delay:?}");
+                    continue;
                 }
+                if let Some(not_after) = Self::cert_not_after(cert_pem) {
+                    self.emit(AcmeEvent::ExpiringSoon {
+                        domains: self.domains.clone(),
+                        not_after,
+                    });
+                }
+                println!("[ACME] Certificate expiring soon, renewing...");
             } else {
-                // 证书文件不存在，尝试申请
-                println!("[ACME] Certificate file not found, applying for new certificate...");
-                match self.obtain_certificate().await {
-                    Ok((cert_pem, key_pem)) => {
-                        if let Err(e) = acceptor.reload(&cert_pem, &key_pem).await {
-                            eprintln!("[ACME] Failed to reload certificate: {e}");
-                        } else {
-                            println!("[ACME] Certificate obtained and loaded successfully");
-                        }
-                    }
-                    Err(e) => {
-                        eprintln!("[ACME] Failed to obtain certificate: {e}");
+                // 证书不存在，尝试申请
+                println!("[ACME] Certificate not found, applying for new certificate...");
+            }
+            match self.obtain_certificate_locked().await {
+                Ok(Some((cert_pem, key_pem))) => {
+                    failures = 0;
+                    if let Err(e) = acceptor.reload(&cert_pem, &key_pem).await {
+                        eprintln!("[ACME] Failed to reload certificate: {e}");
+                    } else {
+                        println!("[ACME] Certificate renewed successfully");
                     }
+                    let domains = self.domains.clone();
+                    let not_after = Self::cert_not_after(&cert_pem);
+                    self.emit(match stored {
+                        Some(_) => AcmeEvent::Renewed { domains, not_after },
+                        None => AcmeEvent::Issued { domains, not_after },
+                    });
                 }
-            }
-        }
-    }
-
-    /// 检查证书是否应该续期（基于证书实际过期时间）
-    fn should_renew(cert_path: &str) -> bool {
-        if let Ok(cert_pem) = std::fs::read_to_string(cert_path) {
-            // 解析证书获取过期时间
-            if let Ok(certs) =
-                CertificateDer::pem_slice_iter(cert_pem.as_bytes()).collect::<Result<Vec<_>, _>>()
-            {
-                if let Some(cert) = certs.first() {
-                    // 使用ASN1解析证书的not_after字段
-                    // CertificateDer内部是DER编码的X.509证书
-                    // 我们需要解析ASN1结构来获取过期时间
-                    return Self::parse_cert_expiry(cert).map_or(false, |expiry| {
-                        let now = std::time::SystemTime::now();
-                        let thirty_days = Duration::from_secs(30 * 24 * 3600);
-
-                        // 如果证书将在30天内过期，或者已经过期，则需要续期
-                        match expiry.duration_since(now) {
-                            Ok(duration) => duration < thirty_days,
-                            Err(_) => true, // 已经过期
-                        }
+                Ok(None) => {
+                    // 稍后从存储加载其他实例申请的证书
+                    println!("[ACME] Another instance is renewing the certificate");
+                    delay = self.retry_backoff.min(self.check_interval);
+                }
+                Err(e) => {
+                    delay = self.retry_delay(failures);
+                    failures = failures.saturating_add(1);
+                    eprintln!("[ACME] Failed to renew certificate: {e}, retrying in {delay:?}");
+                    self.emit(AcmeEvent::Failed {
+                        domains: self.domains.clone(),
+                        error: e.to_string(),
+                        retry_in: delay,
                     });
                 }
             }
         }
+    }
 
-        // 后备方案：基于文件修改时间
-        if let Ok(metadata) = std::fs::metadata(cert_path) {
-            if let Ok(modified) = metadata.modified() {
-                if let Ok(elapsed) = modified.elapsed() {
-                    // 如果证书创建超过60天（Let's Encrypt证书90天有效期，提前30天续期）
-                    return elapsed > Duration::from_secs(60 * 24 * 3600);
-                }
+    /// 检查证书是否应该续期（基于证书实际过期时间），无法解析的证书需要重新申请
+    fn should_renew(cert_pem: &str, renew_before: Duration) -> bool {
+        Self::cert_not_after(cert_pem).is_none_or(|expiry| {
+            // 如果证书将在续期窗口内过期，或者已经过期，则需要续期
+            match expiry.duration_since(SystemTime::now()) {
+                Ok(duration) => duration < renew_before,
+                Err(_) => true, // 已经过期
             }
-        }
-        false
+        })
+    }
+
+    /// 解析PEM证书链中首个证书的过期时间
+    fn cert_not_after(cert_pem: &str) -> Option<SystemTime> {
+        let cert = CertificateDer::pem_slice_iter(cert_pem.as_bytes())
+            .next()?
+            .ok()?;
+        Self::parse_cert_expiry(&cert)
     }
 
     /// 解析证书的过期时间
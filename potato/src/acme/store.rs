@@ -0,0 +1,210 @@
+//! ACME 账户与证书存储，以及多实例续期协调使用的租约锁
+
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// ACME 数据存储接口，默认实现为本地目录 `FileCertStore`
+///
+/// 多个实例共享同一存储（如共享目录、Redis、数据库）时，只有获得租约锁的实例会申请证书，
+/// 其他实例从存储中读取新证书并热加载。`key` 为 `account.json`、`cert.pem`、`key.pem`
+/// 或 `http-01/<token>` 这样的相对路径
+#[async_trait::async_trait]
+pub trait CertStore: Send + Sync {
+    /// 读取数据，不存在时返回 `None`
+    async fn load(&self, key: &str) -> anyhow::Result<Option<Vec<u8>>>;
+    /// 写入数据，`private` 为 true 时为私钥或账户凭据等敏感数据
+    async fn store(&self, key: &str, data: &[u8], private: bool) -> anyhow::Result<()>;
+    /// 删除数据，不存在时不报错
+    async fn delete(&self, key: &str) -> anyhow::Result<()>;
+    /// 尝试获取租约锁：锁空闲、已过期或已由 `owner` 持有时获取成功并续期为 `ttl`
+    async fn try_lock(&self, name: &str, owner: &str, ttl: Duration) -> anyhow::Result<bool>;
+    /// 释放 `owner` 持有的租约锁
+    async fn unlock(&self, name: &str, owner: &str) -> anyhow::Result<()>;
+}
+
+/// 基于本地目录的存储；私钥文件权限为 0600，写入通过临时文件重命名保证原子性
+///
+/// 多个实例挂载同一共享目录时，租约锁通过硬链接独占发布 `<name>.lock` 文件实现，
+/// 锁文件一经出现即包含完整的租约内容
+#[derive(Debug, Clone)]
+pub struct FileCertStore {
+    pub dir: PathBuf,
+}
+
+impl FileCertStore {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    fn path(&self, key: &str) -> anyhow::Result<PathBuf> {
+        if key
+            .split(['/', '\\'])
+            .any(|part| part == ".." || part.is_empty())
+        {
+            anyhow::bail!("invalid cert store key: {key}");
+        }
+        Ok(self.dir.join(key))
+    }
+
+    /// 将数据完整写入 `path` 旁的临时文件，返回临时文件路径
+    fn write_tmp(path: &Path, data: &[u8], private: bool) -> std::io::Result<PathBuf> {
+        use std::io::Write;
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let tmp = path.with_extension(format!("tmp{}", rand::random::<u32>()));
+        let mut options = std::fs::OpenOptions::new();
+        options.write(true).create_new(true);
+        #[cfg(unix)]
+        if private {
+            use std::os::unix::fs::OpenOptionsExt;
+            options.mode(0o600);
+        }
+        #[cfg(not(unix))]
+        let _ = private;
+        let result = options.open(&tmp).and_then(|mut file| {
+            file.write_all(data)?;
+            file.sync_all()
+        });
+        if let Err(err) = result {
+            _ = std::fs::remove_file(&tmp);
+            return Err(err);
+        }
+        Ok(tmp)
+    }
+
+    fn write_file(&self, key: &str, data: &[u8], private: bool) -> anyhow::Result<()> {
+        let path = self.path(key)?;
+        let tmp = Self::write_tmp(&path, data, private)?;
+        if let Err(err) = std::fs::rename(&tmp, &path) {
+            _ = std::fs::remove_file(&tmp);
+            return Err(err.into());
+        }
+        Ok(())
+    }
+
+    fn read_lease(&self, lock_key: &str) -> Option<(String, u64)> {
+        parse_lease(&std::fs::read_to_string(self.path(lock_key).ok()?).ok()?)
+    }
+
+    /// 以硬链接发布写好的租约，锁文件已存在时返回 false
+    fn publish_lease(path: &Path, lease: &str) -> anyhow::Result<bool> {
+        let tmp = Self::write_tmp(path, lease.as_bytes(), false)?;
+        let result = std::fs::hard_link(&tmp, path);
+        _ = std::fs::remove_file(&tmp);
+        match result {
+            Ok(()) => Ok(true),
+            Err(err) if err.kind() == std::io::ErrorKind::AlreadyExists => Ok(false),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    /// 移除已过期或损坏的租约 `observed`
+    ///
+    /// 先将锁文件移到唯一的临时名，确认移走的正是读到的租约后再删除；若其他实例已抢先
+    /// 发布了新租约，则放回原处并返回 false
+    fn remove_stale_lease(path: &Path, observed: &str) -> anyhow::Result<bool> {
+        let stale = path.with_extension(format!("stale{}", rand::random::<u32>()));
+        match std::fs::rename(path, &stale) {
+            Ok(()) => {}
+            // 已被其他实例移走，直接参与竞争
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(true),
+            Err(err) => return Err(err.into()),
+        }
+        let taken = std::fs::read_to_string(&stale).unwrap_or_default();
+        if taken != observed {
+            _ = std::fs::hard_link(&stale, path);
+        }
+        _ = std::fs::remove_file(&stale);
+        Ok(taken == observed)
+    }
+
+    /// 获取租约锁的同步实现，由 `try_lock` 在阻塞线程池中调用
+    fn try_lock_blocking(&self, name: &str, owner: &str, ttl: Duration) -> anyhow::Result<bool> {
+        let lock_key = format!("{name}.lock");
+        let path = self.path(&lock_key)?;
+        std::fs::create_dir_all(&self.dir)?;
+        let lease = format!("{owner} {}", unix_now() + ttl.as_secs());
+        for _ in 0..2 {
+            match std::fs::read_to_string(&path) {
+                Ok(current) => match parse_lease(&current) {
+                    // 自己持有的锁直接续期
+                    Some((holder, _)) if holder == owner => {
+                        self.write_file(&lock_key, lease.as_bytes(), false)?;
+                        return Ok(true);
+                    }
+                    Some((_, expires)) if expires > unix_now() => return Ok(false),
+                    // 已过期或内容损坏，移除后重新竞争
+                    _ => {
+                        if !Self::remove_stale_lease(&path, &current)? {
+                            return Ok(false);
+                        }
+                    }
+                },
+                Err(err) if err.kind() == std::io::ErrorKind::NotFound => {}
+                Err(err) => return Err(err.into()),
+            }
+            if Self::publish_lease(&path, &lease)? {
+                // 再次读取确认锁仍由自己持有
+                return Ok(self
+                    .read_lease(&lock_key)
+                    .is_some_and(|(holder, _)| holder == owner));
+            }
+        }
+        Ok(false)
+    }
+}
+
+fn parse_lease(data: &str) -> Option<(String, u64)> {
+    let (owner, expires) = data.rsplit_once(' ')?;
+    Some((owner.to_string(), expires.trim().parse().ok()?))
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+/// 文件读写在阻塞线程池中执行，不占用异步工作线程
+#[async_trait::async_trait]
+impl CertStore for FileCertStore {
+    async fn load(&self, key: &str) -> anyhow::Result<Option<Vec<u8>>> {
+        match tokio::fs::read(self.path(key)?).await {
+            Ok(data) => Ok(Some(data)),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    async fn store(&self, key: &str, data: &[u8], private: bool) -> anyhow::Result<()> {
+        let (store, key, data) = (self.clone(), key.to_string(), data.to_vec());
+        tokio::task::spawn_blocking(move || store.write_file(&key, &data, private)).await?
+    }
+
+    async fn delete(&self, key: &str) -> anyhow::Result<()> {
+        match tokio::fs::remove_file(self.path(key)?).await {
+            Err(err) if err.kind() != std::io::ErrorKind::NotFound => Err(err.into()),
+            _ => Ok(()),
+        }
+    }
+
+    async fn try_lock(&self, name: &str, owner: &str, ttl: Duration) -> anyhow::Result<bool> {
+        let (store, name, owner) = (self.clone(), name.to_string(), owner.to_string());
+        tokio::task::spawn_blocking(move || store.try_lock_blocking(&name, &owner, ttl)).await?
+    }
+
+    async fn unlock(&self, name: &str, owner: &str) -> anyhow::Result<()> {
+        let lock_key = format!("{name}.lock");
+        let path = self.path(&lock_key)?;
+        let lease = match tokio::fs::read_to_string(&path).await {
+            Ok(lease) => lease,
+            Err(_) => return Ok(()),
+        };
+        if parse_lease(&lease).is_some_and(|(holder, _)| holder == owner) {
+            self.delete(&lock_key).await?;
+        }
+        Ok(())
+    }
+}
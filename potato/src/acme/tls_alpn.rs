@@ -0,0 +1,59 @@
+//! TLS-ALPN-01 验证支持（RFC 8737）：握手协商 `acme-tls/1` 时返回验证证书
+
+use crate::server::ocsp::OcspStapler;
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+use tokio_rustls::rustls;
+use tokio_rustls::rustls::pki_types::PrivateKeyDer;
+use tokio_rustls::rustls::server::{ClientHello, ResolvesServerCert};
+use tokio_rustls::rustls::sign::CertifiedKey;
+
+/// TLS-ALPN-01 验证使用的 ALPN 协议名
+pub const ACME_TLS_ALPN: &[u8] = b"acme-tls/1";
+
+/// 域名到验证证书的映射，由 `AcmeManager` 写入、证书选择时读取
+pub(crate) type AlpnChallenges = Arc<RwLock<HashMap<String, Arc<CertifiedKey>>>>;
+
+/// 证书选择：TLS-ALPN-01 验证握手按 SNI 返回验证证书，其余握手返回当前证书
+#[derive(Debug)]
+pub(crate) struct AcmeCertResolver {
+    pub(crate) cert: Arc<CertifiedKey>,
+    pub(crate) challenges: AlpnChallenges,
+    pub(crate) ocsp: Option<OcspStapler>,
+}
+
+impl ResolvesServerCert for AcmeCertResolver {
+    fn resolve(&self, client_hello: ClientHello<'_>) -> Option<Arc<CertifiedKey>> {
+        let is_challenge = client_hello
+            .alpn()
+            .is_some_and(|mut protocols| protocols.any(|p| p == ACME_TLS_ALPN));
+        if !is_challenge {
+            return match &self.ocsp {
+                Some(ocsp) => Some(ocsp.staple(&self.cert)),
+                None => Some(Arc::clone(&self.cert)),
+            };
+        }
+        let name = client_hello.server_name()?.to_ascii_lowercase();
+        self.challenges.read().ok()?.get(&name).cloned()
+    }
+}
+
+/// 生成验证证书：SAN 为待验证域名，并带有包含 key authorization 摘要的 acmeIdentifier 扩展
+pub(crate) fn challenge_cert(
+    domain: &str,
+    key_auth_digest: &[u8],
+) -> anyhow::Result<Arc<CertifiedKey>> {
+    let mut params = rcgen::CertificateParams::new(vec![domain.to_string()])?;
+    params.custom_extensions = vec![rcgen::CustomExtension::new_acme_identifier(key_auth_digest)];
+    let key_pair = rcgen::KeyPair::generate()?;
+    let cert = params.self_signed(&key_pair)?;
+    let key = PrivateKeyDer::try_from(key_pair.serialize_der()).map_err(anyhow::Error::msg)?;
+    let key = rustls::crypto::ring::sign::any_supported_type(&key)?;
+    Ok(Arc::new(CertifiedKey::new(vec![cert.der().clone()], key)))
+}
+
+/// 首次申请证书前使用的临时自签名证书（证书 PEM，私钥 PEM）
+pub(crate) fn placeholder_cert(domains: &[String]) -> anyhow::Result<(String, String)> {
+    let cert = rcgen::generate_simple_self_signed(domains.to_vec())?;
+    Ok((cert.cert.pem(), cert.signing_key.serialize_pem()))
+}
@@ -2,6 +2,7 @@
 #![cfg(feature = "http2")]
 
 use crate::utils::refstr::Headers;
+use crate::TlsClientConfig;
 use crate::{HttpMethod, HttpRequest, HttpResponse, HttpResponseBody, SERVER_STR};
 use anyhow::anyhow;
 use bytes::Bytes;
@@ -13,24 +14,28 @@ type H2Sender = h2::client::SendRequest<Bytes>;
 
 pub struct H2SessionImpl {
     pub unique_host: (String, u16),
+    pub use_encrypt: bool,
     pub sender: H2Sender,
     pub conn_handle: tokio::task::JoinHandle<()>,
 }
 
 impl H2SessionImpl {
     pub async fn new(host: String, port: u16) -> anyhow::Result<Self> {
+        Self::new_with_tls(host, port, &TlsClientConfig::default()).await
+    }
+
+    /// 按指定的客户端 TLS 配置建立 HTTP/2 连接
+    pub async fn new_with_tls(
+        host: String,
+        port: u16,
+        tls_config: &TlsClientConfig,
+    ) -> anyhow::Result<Self> {
         use tokio::net::TcpStream;
         use tokio_rustls::rustls::pki_types::ServerName;
         use tokio_rustls::TlsConnector;
 
         // 创建 TLS 配置，ALPN 协议设置为 h2
-        let mut root_cert = tokio_rustls::rustls::RootCertStore::empty();
-        root_cert.extend(webpki_roots::TLS_SERVER_ROOTS.iter().cloned());
-        let mut config = tokio_rustls::rustls::ClientConfig::builder()
-            .with_root_certificates(root_cert)
-            .with_no_client_auth();
-        config.alpn_protocols = vec![b"h2".to_vec()];
-
+        let config = tls_config.client_config(vec![b"h2".to_vec()])?;
         let connector = TlsConnector::from(Arc::new(config));
         let dnsname = ServerName::try_from(host.clone())?;
         let tcp_stream = TcpStream::connect((host.as_str(), port)).await?;
@@ -46,6 +51,25 @@ impl H2SessionImpl {
 
         Ok(H2SessionImpl {
             unique_host: (host, port),
+            use_encrypt: true,
+            sender,
+            conn_handle,
+        })
+    }
+
+    /// 创建明文 HTTP/2 (h2c) 连接，使用先验知识直接发送连接前言
+    pub async fn new_without_encrypt(host: String, port: u16) -> anyhow::Result<Self> {
+        let tcp_stream = tokio::net::TcpStream::connect((host.as_str(), port)).await?;
+        _ = tcp_stream.set_nodelay(true);
+        let (sender, connection) = h2::client::handshake(tcp_stream).await?;
+
+        let conn_handle = tokio::spawn(async move {
+            let _ = connection.await;
+        });
+
+        Ok(H2SessionImpl {
+            unique_host: (host, port),
+            use_encrypt: false,
             sender,
             conn_handle,
         })
@@ -54,6 +78,8 @@ impl H2SessionImpl {
 
 pub struct H2Session {
     pub sess_impl: Option<H2SessionImpl>,
+    /// https 连接使用的 TLS 配置
+    pub tls_config: TlsClientConfig,
 }
 
 macro_rules! define_h2_session_method {
@@ -116,7 +142,15 @@ impl Default for H2Session {
 
 impl H2Session {
     pub fn new() -> Self {
-        Self { sess_impl: None }
+        Self::with_tls_config(TlsClientConfig::default())
+    }
+
+    /// 创建使用自定义 TLS 配置的会话
+    pub fn with_tls_config(tls_config: TlsClientConfig) -> Self {
+        Self {
+            sess_impl: None,
+            tls_config,
+        }
     }
 
     async fn new_request(
@@ -124,10 +158,8 @@ impl H2Session {
         method: HttpMethod,
         url: &str,
     ) -> anyhow::Result<(HttpRequest, &mut H2SessionImpl)> {
-        let (mut req, use_ssl, port) = HttpRequest::from_url(url, method)?;
-        if !use_ssl {
-            return Err(anyhow!("HTTP/2 requires TLS connection"));
-        }
+        // https 使用 TLS + ALPN，http 使用 h2c
+        let (mut req, use_encrypt, port) = HttpRequest::from_url(url, method)?;
 
         let host = url
             .parse::<http::Uri>()?
@@ -138,7 +170,7 @@ impl H2Session {
         let mut is_same_host = false;
         if let Some(sess_impl) = &mut self.sess_impl {
             let (host1, port1) = &sess_impl.unique_host;
-            if (host1, port1) == (&host, &port) {
+            if (host1, port1) == (&host, &port) && sess_impl.use_encrypt == use_encrypt {
                 is_same_host = true;
             }
         }
@@ -148,7 +180,12 @@ impl H2Session {
             if let Some(old_impl) = self.sess_impl.take() {
                 old_impl.conn_handle.abort();
             }
-            self.sess_impl = Some(H2SessionImpl::new(host, port).await?);
+            if use_encrypt {
+                self.sess_impl =
+                    Some(H2SessionImpl::new_with_tls(host, port, &self.tls_config).await?);
+            } else {
+                self.sess_impl = Some(H2SessionImpl::new_without_encrypt(host, port).await?);
+            }
         }
 
         req.apply_header(Headers::User_Agent(SERVER_STR.clone()));
@@ -169,7 +206,17 @@ impl H2Session {
             .ok_or_else(|| anyhow!("session implementation not initialized"))?;
 
         // 构建 HTTP/2 请求
-        let uri_str = format!("https://{}{}", sess_impl.unique_host.0, req.url_path);
+        let (scheme, default_port) = match sess_impl.use_encrypt {
+            true => ("https", 443),
+            false => ("http", 80),
+        };
+        let (host, port) = &sess_impl.unique_host;
+        // :authority 需与 Host 头一致，非默认端口时带上端口
+        let authority = match *port == default_port {
+            true => host.clone(),
+            false => format!("{host}:{port}"),
+        };
+        let uri_str = format!("{scheme}://{authority}{}", req.url_path);
         let uri: http::Uri = if !req.url_query.is_empty() {
             format!("{uri_str}{}", req.query_string()).parse()?
         } else {
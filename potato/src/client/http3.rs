@@ -2,6 +2,7 @@
 #![cfg(feature = "http3")]
 
 use crate::utils::refstr::Headers;
+use crate::TlsClientConfig;
 use crate::{HttpMethod, HttpRequest, HttpResponse, HttpResponseBody, SERVER_STR};
 use anyhow::anyhow;
 use bytes::Buf;
@@ -60,6 +61,15 @@ impl rustls::client::danger::ServerCertVerifier for NoCertificateVerification {
     }
 }
 
+/// 解析主机名（或 IP 字面量）得到 QUIC 连接地址
+async fn resolve_addr(host: &str, port: u16) -> anyhow::Result<std::net::SocketAddr> {
+    let host = host.trim_start_matches('[').trim_end_matches(']');
+    tokio::net::lookup_host((host, port))
+        .await?
+        .next()
+        .ok_or_else(|| anyhow!("cannot resolve host {host}"))
+}
+
 pub struct H3SessionImpl {
     pub unique_host: (String, u16),
     pub endpoint: quinn::Endpoint,
@@ -70,13 +80,17 @@ pub struct H3SessionImpl {
 
 impl H3SessionImpl {
     pub async fn new(host: String, port: u16) -> anyhow::Result<Self> {
+        Self::new_with_tls(host, port, &TlsClientConfig::default()).await
+    }
+
+    /// 按指定的客户端 TLS 配置建立 HTTP/3 连接
+    pub async fn new_with_tls(
+        host: String,
+        port: u16,
+        tls_config: &TlsClientConfig,
+    ) -> anyhow::Result<Self> {
         // 创建 TLS 配置，ALPN 协议设置为 h3
-        let mut root_cert = tokio_rustls::rustls::RootCertStore::empty();
-        root_cert.extend(webpki_roots::TLS_SERVER_ROOTS.iter().cloned());
-        let mut tls_config = tokio_rustls::rustls::ClientConfig::builder()
-            .with_root_certificates(root_cert)
-            .with_no_client_auth();
-        tls_config.alpn_protocols = vec![b"h3".to_vec()];
+        let tls_config = tls_config.client_config(vec![b"h3".to_vec()])?;
 
         // 创建 QUIC endpoint
         let mut endpoint = quinn::Endpoint::client("[::]:0".parse()?)?;
@@ -87,7 +101,7 @@ impl H3SessionImpl {
 
         // 连接到服务器
         let quic_conn = endpoint
-            .connect(format!("{host}:{port}").parse()?, &host)?
+            .connect(resolve_addr(&host, port).await?, &host)?
             .await
             .map_err(|e| anyhow!("QUIC connection failed: {e}"))?;
 
@@ -168,6 +182,8 @@ impl H3SessionImpl {
 
 pub struct H3Session {
     pub sess_impl: Option<H3SessionImpl>,
+    /// https 连接使用的 TLS 配置
+    pub tls_config: TlsClientConfig,
 }
 
 macro_rules! define_h3_session_method {
@@ -230,7 +246,15 @@ impl Default for H3Session {
 
 impl H3Session {
     pub fn new() -> Self {
-        Self { sess_impl: None }
+        Self::with_tls_config(TlsClientConfig::default())
+    }
+
+    /// 创建使用自定义 TLS 配置的会话，仅对 https 地址生效
+    pub fn with_tls_config(tls_config: TlsClientConfig) -> Self {
+        Self {
+            sess_impl: None,
+            tls_config,
+        }
     }
 
     /// 从URL判断是否使用加密 (https=加密, http=无加密)
@@ -269,7 +293,8 @@ impl H3Session {
             }
             // 根据 URL scheme 自动选择加密模式
             if use_encrypt {
-                self.sess_impl = Some(H3SessionImpl::new(host, port).await?);
+                self.sess_impl =
+                    Some(H3SessionImpl::new_with_tls(host, port, &self.tls_config).await?);
             } else {
                 self.sess_impl = Some(H3SessionImpl::new_without_encrypt(host, port).await?);
             }
@@ -504,7 +529,16 @@ impl Drop for WebTransport {
 
 impl WebTransport {
     /// 连接到 WebTransport 服务器
-    pub async fn connect(url: &str, _headers: Vec<Headers>) -> anyhow::Result<Self> {
+    pub async fn connect(url: &str, headers: Vec<Headers>) -> anyhow::Result<Self> {
+        Self::connect_with_tls(url, headers, TlsClientConfig::default()).await
+    }
+
+    /// 使用自定义客户端 TLS 配置连接 WebTransport 服务器
+    pub async fn connect_with_tls(
+        url: &str,
+        _headers: Vec<Headers>,
+        tls_config: TlsClientConfig,
+    ) -> anyhow::Result<Self> {
         // 解析 URL
         let uri: http::Uri = url.parse()?;
         let host = uri
@@ -514,14 +548,7 @@ impl WebTransport {
         let path = uri.path().to_string();
 
         // 创建 TLS 配置
-        use tokio_rustls::rustls;
-        let mut roots = rustls::RootCertStore::empty();
-        roots.extend(webpki_roots::TLS_SERVER_ROOTS.iter().cloned());
-
-        let mut tls_config = rustls::ClientConfig::builder()
-            .with_root_certificates(roots)
-            .with_no_client_auth();
-        tls_config.alpn_protocols = vec![b"h3".to_vec()];
+        let tls_config = tls_config.client_config(vec![b"h3".to_vec()])?;
 
         // 创建 QUIC 端点
         let mut endpoint = quinn::Endpoint::client("[::]:0".parse()?)?;
@@ -532,7 +559,7 @@ impl WebTransport {
 
         // 连接到服务器
         let connection = endpoint
-            .connect(format!("{host}:{port}").parse()?, host)?
+            .connect(resolve_addr(host, port).await?, host)?
             .await
             .map_err(|e| anyhow!("QUIC connection failed: {e}"))?;
 
@@ -4,6 +4,9 @@ pub mod agent_session;
 pub mod http2;
 #[cfg(feature = "http3")]
 pub mod http3;
+pub mod oauth2;
+#[cfg(feature = "tls")]
+mod tls;
 
 use crate::utils::bytes::CompressExt;
 use crate::utils::refstr::{HeaderItem, Headers};
@@ -14,6 +17,12 @@ use std::collections::HashMap;
 use tokio::net::TcpStream;
 use tokio::sync::mpsc::Sender;
 
+pub use oauth2::{
+    AuthorizationRequest, DeviceAuthorization, OAuth2Client, OAuth2Config, OAuth2Error, OAuth2Token,
+};
+#[cfg(feature = "tls")]
+pub use tls::TlsClientConfig;
+
 fn transfer_encoding_has_chunked(value: &str) -> bool {
     value
         .split(',')
@@ -299,22 +308,7 @@ impl SessionImpl {
     pub async fn new(host: String, use_ssl: bool, port: u16) -> anyhow::Result<Self> {
         let stream: HttpStream = match use_ssl {
             #[cfg(feature = "tls")]
-            true => {
-                use rustls_pki_types::ServerName;
-                use std::sync::Arc;
-                use tokio_rustls::rustls::{ClientConfig, RootCertStore};
-                use tokio_rustls::TlsConnector;
-                let mut root_cert = RootCertStore::empty();
-                root_cert.extend(webpki_roots::TLS_SERVER_ROOTS.iter().cloned());
-                let config = ClientConfig::builder()
-                    .with_root_certificates(root_cert)
-                    .with_no_client_auth();
-                let connector = TlsConnector::from(Arc::new(config));
-                let dnsname = ServerName::try_from(host.clone())?;
-                let stream = TcpStream::connect((host.as_str(), port)).await?;
-                let stream = connector.connect(dnsname, stream).await?;
-                HttpStream::from_client_tls(stream)
-            }
+            true => return Self::new_with_tls(host, port, &TlsClientConfig::default()).await,
             #[cfg(not(feature = "tls"))]
             true => Err(anyhow!("unsupported tls during non-tls build"))?,
             false => {
@@ -327,10 +321,54 @@ impl SessionImpl {
             stream,
         })
     }
+
+    /// 按指定的客户端 TLS 配置建立 https 连接
+    #[cfg(feature = "tls")]
+    pub async fn new_with_tls(
+        host: String,
+        port: u16,
+        tls_config: &TlsClientConfig,
+    ) -> anyhow::Result<Self> {
+        use rustls_pki_types::ServerName;
+        use std::sync::Arc;
+        use tokio_rustls::TlsConnector;
+        let connector = TlsConnector::from(Arc::new(tls_config.client_config(vec![])?));
+        let dnsname = ServerName::try_from(host.clone())?;
+        let stream = TcpStream::connect((host.as_str(), port)).await?;
+        let stream = connector.connect(dnsname, stream).await?;
+        Ok(SessionImpl {
+            unique_host: (host, true, port),
+            stream: HttpStream::from_client_tls(stream),
+        })
+    }
+
+    /// 通过 Unix 域套接字建立连接，`host`/`port` 仅用于请求头与连接复用判断
+    #[cfg(unix)]
+    pub async fn new_unix(
+        path: &std::path::Path,
+        host: String,
+        use_ssl: bool,
+        port: u16,
+    ) -> anyhow::Result<Self> {
+        if use_ssl {
+            return Err(anyhow!("https is not supported over unix socket"));
+        }
+        let stream = tokio::net::UnixStream::connect(path).await?;
+        Ok(SessionImpl {
+            unique_host: (host, use_ssl, port),
+            stream: HttpStream::from_unix(stream),
+        })
+    }
 }
 
 pub struct Session {
     pub sess_impl: Option<SessionImpl>,
+    /// 不为空时所有请求都经此 Unix 域套接字发送，URL 仅决定路径与 Host 头
+    #[cfg(unix)]
+    pub unix_socket: Option<std::path::PathBuf>,
+    /// https 连接使用的 TLS 配置
+    #[cfg(feature = "tls")]
+    pub tls_config: TlsClientConfig,
 }
 
 impl Default for Session {
@@ -341,7 +379,32 @@ impl Default for Session {
 
 impl Session {
     pub fn new() -> Self {
-        Self { sess_impl: None }
+        Self {
+            sess_impl: None,
+            #[cfg(unix)]
+            unix_socket: None,
+            #[cfg(feature = "tls")]
+            tls_config: TlsClientConfig::default(),
+        }
+    }
+
+    /// 创建使用自定义 TLS 配置（私有 CA、客户端证书、证书固定等）的会话
+    #[cfg(feature = "tls")]
+    pub fn with_tls_config(tls_config: TlsClientConfig) -> Self {
+        Self {
+            tls_config,
+            ..Self::new()
+        }
+    }
+
+    /// 创建经 Unix 域套接字通信的会话，如 `Session::new_unix("/var/run/docker.sock")`
+    /// 之后以 `http://localhost/containers/json` 形式的 URL 发起请求
+    #[cfg(unix)]
+    pub fn new_unix(path: impl Into<std::path::PathBuf>) -> Self {
+        Self {
+            unix_socket: Some(path.into()),
+            ..Self::new()
+        }
     }
 
     pub async fn new_request(
@@ -363,12 +426,29 @@ impl Session {
             }
         }
         if !is_same_host {
-            self.sess_impl = Some(SessionImpl::new(host, use_ssl, port).await?);
+            self.sess_impl = Some(self.open_impl(host, use_ssl, port).await?);
         }
         req.apply_header(Headers::User_Agent(SERVER_STR.clone()));
         Ok(req)
     }
 
+    async fn open_impl(
+        &self,
+        host: String,
+        use_ssl: bool,
+        port: u16,
+    ) -> anyhow::Result<SessionImpl> {
+        #[cfg(unix)]
+        if let Some(path) = &self.unix_socket {
+            return SessionImpl::new_unix(path, host, use_ssl, port).await;
+        }
+        #[cfg(feature = "tls")]
+        if use_ssl {
+            return SessionImpl::new_with_tls(host, port, &self.tls_config).await;
+        }
+        SessionImpl::new(host, use_ssl, port).await
+    }
+
     /// 强制关闭当前连接，下次请求会重新建立连接
     pub fn force_reconnect(&mut self) {
         self.sess_impl = None;
@@ -592,7 +672,7 @@ pub fn http11(url: impl Into<String>) -> VersionedUrl {
     }
 }
 
-/// 创建 HTTP/2 URL
+/// 创建 HTTP/2 URL (根据URL scheme自动选择: https=TLS, http=h2c)
 #[cfg(feature = "http2")]
 pub fn http2(url: impl Into<String>) -> VersionedUrl {
     VersionedUrl {
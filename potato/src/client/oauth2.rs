@@ -0,0 +1,491 @@
+use crate::utils::refstr::Headers;
+use crate::utils::string::{StringExt, StringUtil};
+use crate::{HttpMethod, HttpResponse, Session};
+use anyhow::anyhow;
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::time::Duration;
+
+const DEVICE_CODE_GRANT: &str = "urn:ietf:params:oauth:grant-type:device_code";
+
+/// OAuth2 客户端配置
+#[derive(Clone, Debug)]
+pub struct OAuth2Config {
+    pub client_id: String,
+    /// 公共客户端（如命令行、移动端）不设置密钥
+    pub client_secret: Option<String>,
+    pub token_url: String,
+    /// 授权码模式的授权地址
+    pub auth_url: Option<String>,
+    /// 设备码模式的设备授权地址
+    pub device_auth_url: Option<String>,
+    pub redirect_uri: Option<String>,
+    pub scopes: Vec<String>,
+    /// 客户端凭据放在表单中（client_secret_post），默认使用 Basic 认证头
+    pub auth_in_body: bool,
+    /// 令牌在到期前多久视为过期，默认30秒
+    pub expiry_margin: Duration,
+}
+
+impl OAuth2Config {
+    pub fn new(client_id: impl Into<String>, token_url: impl Into<String>) -> Self {
+        Self {
+            client_id: client_id.into(),
+            client_secret: None,
+            token_url: token_url.into(),
+            auth_url: None,
+            device_auth_url: None,
+            redirect_uri: None,
+            scopes: vec![],
+            auth_in_body: false,
+            expiry_margin: Duration::from_secs(30),
+        }
+    }
+
+    pub fn with_client_secret(mut self, secret: impl Into<String>) -> Self {
+        self.client_secret = Some(secret.into());
+        self
+    }
+
+    pub fn with_auth_url(mut self, url: impl Into<String>) -> Self {
+        self.auth_url = Some(url.into());
+        self
+    }
+
+    pub fn with_device_auth_url(mut self, url: impl Into<String>) -> Self {
+        self.device_auth_url = Some(url.into());
+        self
+    }
+
+    pub fn with_redirect_uri(mut self, uri: impl Into<String>) -> Self {
+        self.redirect_uri = Some(uri.into());
+        self
+    }
+
+    pub fn with_scope(mut self, scope: impl Into<String>) -> Self {
+        self.scopes.push(scope.into());
+        self
+    }
+
+    pub fn with_auth_in_body(mut self) -> Self {
+        self.auth_in_body = true;
+        self
+    }
+
+    pub fn with_expiry_margin(mut self, margin: Duration) -> Self {
+        self.expiry_margin = margin;
+        self
+    }
+}
+
+/// 访问令牌，可序列化后持久化，下次通过 `OAuth2Client::set_token` 恢复
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct OAuth2Token {
+    pub access_token: String,
+    pub token_type: String,
+    pub refresh_token: Option<String>,
+    pub scope: Option<String>,
+    /// 过期时间（Unix 秒），授权服务器未返回 `expires_in` 时为空
+    pub expires_at: Option<i64>,
+}
+
+impl OAuth2Token {
+    /// 距离过期不足 `margin` 时视为已过期
+    pub fn is_expired(&self, margin: Duration) -> bool {
+        self.expires_at
+            .is_some_and(|at| chrono::Utc::now().timestamp() + margin.as_secs() as i64 >= at)
+    }
+}
+
+/// 授权服务器返回的错误，如 `invalid_grant`，可通过 `anyhow::Error::downcast_ref` 取得
+#[derive(Clone, Debug)]
+pub struct OAuth2Error {
+    pub error: String,
+    pub description: Option<String>,
+}
+
+impl std::fmt::Display for OAuth2Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.description {
+            Some(desc) => write!(f, "oauth2 error: {} ({desc})", self.error),
+            None => write!(f, "oauth2 error: {}", self.error),
+        }
+    }
+}
+
+impl std::error::Error for OAuth2Error {}
+
+/// 授权码模式的待完成请求，用户跳转到 `url` 授权后凭回调中的 code 与 state 换取令牌
+#[derive(Clone, Debug)]
+pub struct AuthorizationRequest {
+    pub url: String,
+    pub state: String,
+    pub pkce_verifier: String,
+}
+
+/// 设备码模式的授权信息，`user_code` 与 `verification_uri` 需展示给用户
+#[derive(Clone, Debug, Deserialize)]
+pub struct DeviceAuthorization {
+    pub device_code: String,
+    pub user_code: String,
+    pub verification_uri: String,
+    pub verification_uri_complete: Option<String>,
+    pub expires_in: u64,
+    #[serde(default = "default_device_interval")]
+    pub interval: u64,
+}
+
+fn default_device_interval() -> u64 {
+    5
+}
+
+#[derive(Deserialize)]
+struct TokenResponse {
+    access_token: String,
+    token_type: Option<String>,
+    expires_in: Option<i64>,
+    refresh_token: Option<String>,
+    scope: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct ErrorResponse {
+    error: String,
+    error_description: Option<String>,
+}
+
+/// 基于 `Session` 的 OAuth2 客户端，请求时自动附加 `Authorization` 头，
+/// 令牌过期或收到401时自动刷新并重试一次
+pub struct OAuth2Client {
+    pub config: OAuth2Config,
+    session: Session,
+    token: Option<OAuth2Token>,
+    /// 通过客户端凭据模式获取过令牌，失效后可自动重新获取
+    client_credentials: bool,
+}
+
+macro_rules! define_oauth2_method {
+    ($fn_name:ident, $method:ident) => {
+        pub async fn $fn_name(
+            &mut self,
+            url: &str,
+            args: Vec<Headers>,
+        ) -> anyhow::Result<HttpResponse> {
+            self.send(HttpMethod::$method, url, vec![], args).await
+        }
+    };
+
+    ($fn_name:ident, $fn_name2:ident, $method:ident) => {
+        pub async fn $fn_name(
+            &mut self,
+            url: &str,
+            body: Vec<u8>,
+            args: Vec<Headers>,
+        ) -> anyhow::Result<HttpResponse> {
+            self.send(HttpMethod::$method, url, body, args).await
+        }
+
+        pub async fn $fn_name2(
+            &mut self,
+            url: &str,
+            body: serde_json::Value,
+            mut args: Vec<Headers>,
+        ) -> anyhow::Result<HttpResponse> {
+            args.push(Headers::Content_Type("application/json".into()));
+            self.$fn_name(url, serde_json::to_vec(&body)?, args).await
+        }
+    };
+}
+
+impl OAuth2Client {
+    pub fn new(config: OAuth2Config) -> Self {
+        Self::with_session(config, Session::new())
+    }
+
+    /// 使用已配置好的会话（自定义 TLS、Unix 域套接字等）
+    pub fn with_session(config: OAuth2Config, session: Session) -> Self {
+        Self {
+            config,
+            session,
+            token: None,
+            client_credentials: false,
+        }
+    }
+
+    pub fn token(&self) -> Option<&OAuth2Token> {
+        self.token.as_ref()
+    }
+
+    pub fn set_token(&mut self, token: OAuth2Token) {
+        self.token = Some(token);
+    }
+
+    pub fn clear_token(&mut self) {
+        self.token = None;
+    }
+
+    /// 客户端凭据模式，之后令牌失效时自动重新获取
+    pub async fn client_credentials(&mut self) -> anyhow::Result<OAuth2Token> {
+        let mut params = vec![("grant_type", "client_credentials".to_string())];
+        self.push_scope(&mut params);
+        let token = self.request_token(params).await?;
+        self.client_credentials = true;
+        Ok(token)
+    }
+
+    /// 生成授权码模式（PKCE S256）的授权地址
+    pub fn authorize_url(&self) -> anyhow::Result<AuthorizationRequest> {
+        let auth_url = self
+            .config
+            .auth_url
+            .as_deref()
+            .ok_or_else(|| anyhow!("oauth2 auth_url is not configured"))?;
+        let state = StringUtil::rand(32);
+        let pkce_verifier = StringUtil::rand(64);
+        let challenge = base64::engine::general_purpose::URL_SAFE_NO_PAD
+            .encode(Sha256::digest(pkce_verifier.as_bytes()));
+        let mut params = vec![
+            ("response_type", "code".to_string()),
+            ("client_id", self.config.client_id.clone()),
+            ("state", state.clone()),
+            ("code_challenge", challenge),
+            ("code_challenge_method", "S256".to_string()),
+        ];
+        if let Some(redirect_uri) = &self.config.redirect_uri {
+            params.push(("redirect_uri", redirect_uri.clone()));
+        }
+        self.push_scope(&mut params);
+        let sep = if auth_url.contains('?') { '&' } else { '?' };
+        Ok(AuthorizationRequest {
+            url: format!("{auth_url}{sep}{}", form_encode(&params)),
+            state,
+            pkce_verifier,
+        })
+    }
+
+    /// 以回调中的 code 换取令牌，`state` 与授权请求不一致时拒绝
+    pub async fn exchange_code(
+        &mut self,
+        request: &AuthorizationRequest,
+        code: &str,
+        state: &str,
+    ) -> anyhow::Result<OAuth2Token> {
+        if request.state != state {
+            return Err(anyhow!("oauth2 state mismatch"));
+        }
+        let mut params = vec![
+            ("grant_type", "authorization_code".to_string()),
+            ("code", code.to_string()),
+            ("code_verifier", request.pkce_verifier.clone()),
+        ];
+        if let Some(redirect_uri) = &self.config.redirect_uri {
+            params.push(("redirect_uri", redirect_uri.clone()));
+        }
+        self.request_token(params).await
+    }
+
+    /// 设备码模式第一步，获取展示给用户的 user_code
+    pub async fn start_device_authorization(&mut self) -> anyhow::Result<DeviceAuthorization> {
+        let url = self
+            .config
+            .device_auth_url
+            .clone()
+            .ok_or_else(|| anyhow!("oauth2 device_auth_url is not configured"))?;
+        let mut params = vec![];
+        self.push_scope(&mut params);
+        let body = self.post_form(&url, params).await?;
+        Ok(serde_json::from_slice(&body)?)
+    }
+
+    /// 设备码模式第二步，按服务器要求的间隔轮询直到用户完成授权
+    pub async fn poll_device_token(
+        &mut self,
+        device: &DeviceAuthorization,
+    ) -> anyhow::Result<OAuth2Token> {
+        let deadline = tokio::time::Instant::now() + Duration::from_secs(device.expires_in);
+        let mut interval = Duration::from_secs(device.interval);
+        loop {
+            tokio::time::sleep(interval).await;
+            if tokio::time::Instant::now() >= deadline {
+                return Err(anyhow!("oauth2 device code expired"));
+            }
+            let params = vec![
+                ("grant_type", DEVICE_CODE_GRANT.to_string()),
+                ("device_code", device.device_code.clone()),
+            ];
+            match self.request_token(params).await {
+                Ok(token) => return Ok(token),
+                Err(err) => match err.downcast_ref::<OAuth2Error>() {
+                    Some(e) if e.error == "authorization_pending" => {}
+                    Some(e) if e.error == "slow_down" => interval += Duration::from_secs(5),
+                    _ => return Err(err),
+                },
+            }
+        }
+    }
+
+    /// 刷新令牌模式，授权服务器未返回新的 refresh_token 时保留原值
+    pub async fn refresh(&mut self) -> anyhow::Result<OAuth2Token> {
+        let refresh_token = self
+            .token
+            .as_ref()
+            .and_then(|t| t.refresh_token.clone())
+            .ok_or_else(|| anyhow!("oauth2 refresh_token is not available"))?;
+        let params = vec![
+            ("grant_type", "refresh_token".to_string()),
+            ("refresh_token", refresh_token.clone()),
+        ];
+        let mut token = self.request_token(params).await?;
+        if token.refresh_token.is_none() {
+            token.refresh_token = Some(refresh_token);
+            self.token = Some(token.clone());
+        }
+        Ok(token)
+    }
+
+    /// 发送带令牌的请求
+    pub async fn send(
+        &mut self,
+        method: HttpMethod,
+        url: &str,
+        body: Vec<u8>,
+        args: Vec<Headers>,
+    ) -> anyhow::Result<HttpResponse> {
+        let token = self.valid_token(false).await?;
+        let mut req = self.session.new_request(method, url).await?;
+        req.body = body.into();
+        for arg in args.into_iter() {
+            req.apply_header(arg);
+        }
+        let (headers, body) = (req.headers.clone(), req.body.clone());
+        req.apply_header(Headers::Authorization(format!("Bearer {token}")));
+        let res = self.session.do_request(req).await?;
+        if res.http_code != 401 || !self.can_renew() {
+            return Ok(res);
+        }
+        // 令牌可能已被吊销，刷新后重试一次
+        let token = self.valid_token(true).await?;
+        let mut req = self.session.new_request(method, url).await?;
+        req.headers = headers;
+        req.body = body;
+        req.apply_header(Headers::Authorization(format!("Bearer {token}")));
+        self.session.do_request(req).await
+    }
+
+    define_oauth2_method!(get, GET);
+    define_oauth2_method!(post, post_json, POST);
+    define_oauth2_method!(put, put_json, PUT);
+    define_oauth2_method!(patch, patch_json, PATCH);
+    define_oauth2_method!(delete, DELETE);
+    define_oauth2_method!(head, HEAD);
+
+    fn can_renew(&self) -> bool {
+        self.client_credentials
+            || self
+                .token
+                .as_ref()
+                .is_some_and(|t| t.refresh_token.is_some())
+    }
+
+    /// 返回可用的访问令牌，过期或 `force` 时先刷新
+    async fn valid_token(&mut self, force: bool) -> anyhow::Result<String> {
+        if let Some(token) = &self.token {
+            if !force && !token.is_expired(self.config.expiry_margin) {
+                return Ok(token.access_token.clone());
+            }
+            if token.refresh_token.is_some() {
+                match self.refresh().await {
+                    Ok(token) => return Ok(token.access_token),
+                    Err(err) if !self.client_credentials => return Err(err),
+                    Err(_) => {}
+                }
+            }
+        }
+        if self.client_credentials {
+            return Ok(self.client_credentials().await?.access_token);
+        }
+        match &self.token {
+            Some(_) => Err(anyhow!("oauth2 token expired and cannot be refreshed")),
+            None => Err(anyhow!("oauth2 token is not available, authorize first")),
+        }
+    }
+
+    fn push_scope(&self, params: &mut Vec<(&'static str, String)>) {
+        if !self.config.scopes.is_empty() {
+            params.push(("scope", self.config.scopes.join(" ")));
+        }
+    }
+
+    async fn request_token(
+        &mut self,
+        params: Vec<(&'static str, String)>,
+    ) -> anyhow::Result<OAuth2Token> {
+        let url = self.config.token_url.clone();
+        let body = self.post_form(&url, params).await?;
+        let res: TokenResponse = serde_json::from_slice(&body)?;
+        let token = OAuth2Token {
+            access_token: res.access_token,
+            token_type: res.token_type.unwrap_or_else(|| "Bearer".to_string()),
+            refresh_token: res.refresh_token,
+            scope: res.scope,
+            expires_at: res
+                .expires_in
+                .map(|secs| chrono::Utc::now().timestamp() + secs),
+        };
+        self.token = Some(token.clone());
+        Ok(token)
+    }
+
+    /// 向授权服务器提交表单并附带客户端认证，非2xx响应转换为 `OAuth2Error`
+    async fn post_form(
+        &mut self,
+        url: &str,
+        mut params: Vec<(&'static str, String)>,
+    ) -> anyhow::Result<Vec<u8>> {
+        let mut args = vec![
+            Headers::Content_Type("application/x-www-form-urlencoded".into()),
+            Headers::Accept("application/json".into()),
+        ];
+        match &self.config.client_secret {
+            Some(secret) if !self.config.auth_in_body => {
+                let credentials = format!(
+                    "{}:{}",
+                    self.config.client_id.url_encode(),
+                    secret.url_encode()
+                );
+                let credentials = base64::engine::general_purpose::STANDARD.encode(credentials);
+                args.push(Headers::Authorization(format!("Basic {credentials}")));
+            }
+            secret => {
+                params.push(("client_id", self.config.client_id.clone()));
+                if let Some(secret) = secret {
+                    params.push(("client_secret", secret.clone()));
+                }
+            }
+        }
+        let body = form_encode(&params).into_bytes();
+        let mut res = self.session.post(url, body, args).await?;
+        let code = res.http_code;
+        let body = res.body.data().await.to_vec();
+        if (200..300).contains(&code) {
+            return Ok(body);
+        }
+        Err(match serde_json::from_slice::<ErrorResponse>(&body) {
+            Ok(err) => OAuth2Error {
+                error: err.error,
+                description: err.error_description,
+            }
+            .into(),
+            Err(_) => anyhow!("oauth2 request failed with status {code}"),
+        })
+    }
+}
+
+fn form_encode(params: &[(&str, String)]) -> String {
+    params
+        .iter()
+        .map(|(k, v)| format!("{k}={}", v.url_encode()))
+        .collect::<Vec<_>>()
+        .join("&")
+}
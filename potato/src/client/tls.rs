@@ -0,0 +1,260 @@
+#![cfg(feature = "tls")]
+
+use base64::Engine;
+use sha2::{Digest, Sha256};
+use std::sync::Arc;
+use tokio_rustls::rustls;
+use tokio_rustls::rustls::client::danger::{
+    HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier,
+};
+use tokio_rustls::rustls::client::WebPkiServerVerifier;
+use tokio_rustls::rustls::crypto::{verify_tls12_signature, verify_tls13_signature};
+use tokio_rustls::rustls::pki_types::{
+    pem::PemObject, CertificateDer, PrivateKeyDer, ServerName, UnixTime,
+};
+use tokio_rustls::rustls::{DigitallySignedStruct, SignatureScheme};
+
+/// 客户端 TLS 配置，`Session` / `H2Session` / `H3Session` / `Websocket` / `WebTransport` 共用
+///
+/// 默认仅信任内置的 webpki 根证书且不出示客户端证书，与未配置时的行为一致
+///
+/// ```rust,no_run
+/// # async fn run() -> anyhow::Result<()> {
+/// let tls = potato::TlsClientConfig::new()
+///     .add_root_pem("internal-ca.pem")
+///     .with_client_cert("client.pem", "client.key");
+/// let mut session = potato::Session::with_tls_config(tls);
+/// let res = session.get("https://billing.internal/api/orders", vec![]).await?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct TlsClientConfig {
+    /// 信任内置的 webpki 根证书，默认开启
+    pub webpki_roots: bool,
+    /// 信任操作系统证书库中的根证书
+    pub system_roots: bool,
+    /// 额外信任的 PEM 根证书文件，如内部 CA
+    pub extra_roots: Vec<String>,
+    /// mTLS 使用的客户端证书与私钥 PEM 文件
+    pub client_cert: Option<(String, String)>,
+    /// 服务端证书公钥（SPKI）的 SHA-256（base64），不为空时服务端证书须匹配其中之一
+    pub pinned_spki_sha256: Vec<String>,
+    /// 不校验服务端证书链与主机名，仅用于本地开发测试自签名证书
+    pub danger_accept_invalid_certs: bool,
+}
+
+impl Default for TlsClientConfig {
+    fn default() -> Self {
+        Self {
+            webpki_roots: true,
+            system_roots: false,
+            extra_roots: vec![],
+            client_cert: None,
+            pinned_spki_sha256: vec![],
+            danger_accept_invalid_certs: false,
+        }
+    }
+}
+
+impl TlsClientConfig {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 额外信任 PEM 文件中的根证书
+    pub fn add_root_pem(mut self, pem_file: impl Into<String>) -> Self {
+        self.extra_roots.push(pem_file.into());
+        self
+    }
+
+    /// 信任操作系统证书库
+    pub fn with_system_roots(mut self) -> Self {
+        self.system_roots = true;
+        self
+    }
+
+    /// 不信任内置的 webpki 根证书，仅信任 `add_root_pem` / `with_system_roots` 添加的根证书
+    pub fn without_webpki_roots(mut self) -> Self {
+        self.webpki_roots = false;
+        self
+    }
+
+    /// 设置 mTLS 客户端证书
+    pub fn with_client_cert(
+        mut self,
+        cert_file: impl Into<String>,
+        key_file: impl Into<String>,
+    ) -> Self {
+        self.client_cert = Some((cert_file.into(), key_file.into()));
+        self
+    }
+
+    /// 固定服务端证书公钥，参数为 SPKI SHA-256 的 base64，可由 `spki_sha256` 计算
+    pub fn pin_spki_sha256(mut self, pin: impl Into<String>) -> Self {
+        self.pinned_spki_sha256.push(pin.into());
+        self
+    }
+
+    /// 接受任意服务端证书，仍会校验握手签名与证书固定
+    pub fn danger_accept_invalid_certs(mut self) -> Self {
+        self.danger_accept_invalid_certs = true;
+        self
+    }
+
+    /// 计算 DER 证书公钥（SPKI）的 SHA-256，返回 base64
+    pub fn spki_sha256(cert_der: &[u8]) -> anyhow::Result<String> {
+        use x509_parser::prelude::{FromDer, X509Certificate};
+        let (_, cert) = X509Certificate::from_der(cert_der)
+            .map_err(|err| anyhow::anyhow!("invalid certificate: {err}"))?;
+        let digest = Sha256::digest(cert.public_key().raw);
+        Ok(base64::engine::general_purpose::STANDARD.encode(digest))
+    }
+
+    fn root_store(&self) -> anyhow::Result<rustls::RootCertStore> {
+        let mut roots = rustls::RootCertStore::empty();
+        if self.webpki_roots {
+            roots.extend(webpki_roots::TLS_SERVER_ROOTS.iter().cloned());
+        }
+        if self.system_roots {
+            let native = rustls_native_certs::load_native_certs();
+            if native.certs.is_empty() {
+                if let Some(err) = native.errors.first() {
+                    anyhow::bail!("load system root certificates failed: {err}");
+                }
+            }
+            roots.add_parsable_certificates(native.certs);
+        }
+        for pem_file in self.extra_roots.iter() {
+            for cert in CertificateDer::pem_file_iter(pem_file)? {
+                roots.add(cert?)?;
+            }
+        }
+        Ok(roots)
+    }
+
+    /// 构造 rustls 客户端配置
+    pub(crate) fn client_config(&self, alpn: Vec<Vec<u8>>) -> anyhow::Result<rustls::ClientConfig> {
+        let provider = Arc::new(rustls::crypto::ring::default_provider());
+        let builder = rustls::ClientConfig::builder_with_provider(Arc::clone(&provider))
+            .with_safe_default_protocol_versions()?;
+        let builder = match self.danger_accept_invalid_certs || !self.pinned_spki_sha256.is_empty()
+        {
+            true => {
+                let webpki = match self.danger_accept_invalid_certs {
+                    true => None,
+                    false => Some(
+                        WebPkiServerVerifier::builder_with_provider(
+                            Arc::new(self.root_store()?),
+                            Arc::clone(&provider),
+                        )
+                        .build()?,
+                    ),
+                };
+                let pins = self
+                    .pinned_spki_sha256
+                    .iter()
+                    .map(|pin| base64::engine::general_purpose::STANDARD.decode(pin.trim()))
+                    .collect::<Result<Vec<_>, _>>()
+                    .map_err(|err| anyhow::anyhow!("invalid SPKI pin: {err}"))?;
+                builder
+                    .dangerous()
+                    .with_custom_certificate_verifier(Arc::new(CustomVerifier {
+                        webpki,
+                        pins,
+                        provider,
+                    }))
+            }
+            false => builder.with_root_certificates(self.root_store()?),
+        };
+        let mut config = match &self.client_cert {
+            Some((cert_file, key_file)) => {
+                let certs =
+                    CertificateDer::pem_file_iter(cert_file)?.collect::<Result<Vec<_>, _>>()?;
+                let key = PrivateKeyDer::from_pem_file(key_file)?;
+                builder.with_client_auth_cert(certs, key)?
+            }
+            None => builder.with_no_client_auth(),
+        };
+        config.alpn_protocols = alpn;
+        Ok(config)
+    }
+}
+
+/// 带证书固定或跳过证书链校验时使用的校验器
+#[derive(Debug)]
+struct CustomVerifier {
+    /// 为空时不校验证书链（danger_accept_invalid_certs）
+    webpki: Option<Arc<WebPkiServerVerifier>>,
+    /// SPKI SHA-256，为空时不做证书固定
+    pins: Vec<Vec<u8>>,
+    provider: Arc<rustls::crypto::CryptoProvider>,
+}
+
+impl ServerCertVerifier for CustomVerifier {
+    fn verify_server_cert(
+        &self,
+        end_entity: &CertificateDer<'_>,
+        intermediates: &[CertificateDer<'_>],
+        server_name: &ServerName<'_>,
+        ocsp_response: &[u8],
+        now: UnixTime,
+    ) -> Result<ServerCertVerified, rustls::Error> {
+        if let Some(webpki) = &self.webpki {
+            webpki.verify_server_cert(
+                end_entity,
+                intermediates,
+                server_name,
+                ocsp_response,
+                now,
+            )?;
+        }
+        if !self.pins.is_empty() {
+            use x509_parser::prelude::{FromDer, X509Certificate};
+            let (_, cert) = X509Certificate::from_der(end_entity.as_ref()).map_err(|_| {
+                rustls::Error::InvalidCertificate(rustls::CertificateError::BadEncoding)
+            })?;
+            let digest = Sha256::digest(cert.public_key().raw);
+            if !self.pins.iter().any(|pin| pin[..] == digest[..]) {
+                return Err(rustls::Error::General(
+                    "server certificate does not match pinned public key".to_string(),
+                ));
+            }
+        }
+        Ok(ServerCertVerified::assertion())
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        verify_tls12_signature(
+            message,
+            cert,
+            dss,
+            &self.provider.signature_verification_algorithms,
+        )
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        verify_tls13_signature(
+            message,
+            cert,
+            dss,
+            &self.provider.signature_verification_algorithms,
+        )
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        self.provider
+            .signature_verification_algorithms
+            .supported_schemes()
+    }
+}
@@ -1,12 +1,32 @@
 use crate::utils::string::StringUtil;
+#[cfg(feature = "auth")]
+use crate::AuthScheme;
+use crate::{CookieBuilder, CookieKeys, JwtOptions, MemoryRateLimitStore, RateLimitStore};
 use serde::{Deserialize, Serialize};
 use std::sync::atomic::{AtomicUsize, Ordering};
-use std::sync::LazyLock;
+use std::sync::{Arc, LazyLock};
 use std::time::{Duration, SystemTime, UNIX_EPOCH};
 use tokio::sync::RwLock;
 
 static SERVER_JWT_SECRET: LazyLock<RwLock<String>> =
     LazyLock::new(|| RwLock::new(StringUtil::rand(32)));
+static SERVER_JWT_OPTIONS: LazyLock<RwLock<Arc<JwtOptions>>> =
+    LazyLock::new(|| RwLock::new(Arc::new(JwtOptions::default())));
+#[cfg(feature = "auth")]
+static SERVER_AUTH_SCHEMES: LazyLock<
+    std::sync::RwLock<std::collections::HashMap<String, Arc<AuthScheme>>>,
+> = LazyLock::new(|| std::sync::RwLock::new(std::collections::HashMap::new()));
+static SERVER_COOKIE_KEYS: LazyLock<std::sync::RwLock<Arc<CookieKeys>>> =
+    LazyLock::new(|| std::sync::RwLock::new(Arc::new(CookieKeys::generate())));
+static SERVER_SESSION_COOKIE: LazyLock<std::sync::RwLock<CookieBuilder>> = LazyLock::new(|| {
+    std::sync::RwLock::new(
+        CookieBuilder::new("potato_session", "")
+            .http_only(true)
+            .same_site("Lax"),
+    )
+});
+static SERVER_RATE_LIMIT_STORE: LazyLock<std::sync::RwLock<Arc<dyn RateLimitStore>>> =
+    LazyLock::new(|| std::sync::RwLock::new(Arc::new(MemoryRateLimitStore::new())));
 static SERVER_WS_PING_DURATION: LazyLock<RwLock<Duration>> =
     LazyLock::new(|| RwLock::new(Duration::from_secs(60)));
 static SERVER_MAX_HEADER_COUNT: AtomicUsize = AtomicUsize::new(48);
@@ -29,6 +49,72 @@ impl ServerConfig {
         SERVER_JWT_SECRET.read().await.clone()
     }
 
+    /// 设置JWT签名密钥与校验规则，`keys` 非空时 `set_jwt_secret` 设置的密钥不再使用
+    pub async fn set_jwt_options(opts: JwtOptions) {
+        *SERVER_JWT_OPTIONS.write().await = Arc::new(opts);
+    }
+
+    pub async fn get_jwt_options() -> Arc<JwtOptions> {
+        SERVER_JWT_OPTIONS.read().await.clone()
+    }
+
+    /// 注册命名的认证方式，供 `#[potato::auth_scheme("name")]` 使用；须在 `use_openapi` 之前注册
+    /// 才会写入文档的 `securitySchemes`
+    #[cfg(feature = "auth")]
+    pub fn set_auth_scheme(name: impl Into<String>, scheme: impl Into<AuthScheme>) {
+        if let Ok(mut schemes) = SERVER_AUTH_SCHEMES.write() {
+            schemes.insert(name.into(), Arc::new(scheme.into()));
+        }
+    }
+
+    #[cfg(feature = "auth")]
+    pub fn get_auth_scheme(name: &str) -> Option<Arc<AuthScheme>> {
+        SERVER_AUTH_SCHEMES.read().ok()?.get(name).cloned()
+    }
+
+    /// 设置签名与加密 Cookie 的密钥，默认为启动时随机生成的密钥
+    pub fn set_cookie_keys(keys: CookieKeys) {
+        if let Ok(mut current) = SERVER_COOKIE_KEYS.write() {
+            *current = Arc::new(keys);
+        }
+    }
+
+    pub fn get_cookie_keys() -> Arc<CookieKeys> {
+        match SERVER_COOKIE_KEYS.read() {
+            Ok(keys) => keys.clone(),
+            Err(err) => err.into_inner().clone(),
+        }
+    }
+
+    /// 设置无状态会话 Cookie 的名称与属性，值会被忽略；默认为 `potato_session`、`HttpOnly`、
+    /// `SameSite=Lax`
+    pub fn set_session_cookie(cookie: CookieBuilder) {
+        if let Ok(mut current) = SERVER_SESSION_COOKIE.write() {
+            *current = cookie;
+        }
+    }
+
+    pub fn get_session_cookie() -> CookieBuilder {
+        match SERVER_SESSION_COOKIE.read() {
+            Ok(cookie) => cookie.clone(),
+            Err(err) => err.into_inner().clone(),
+        }
+    }
+
+    /// 设置限流令牌桶的全局存储，默认为进程内的 `MemoryRateLimitStore`，应在服务器启动前调用
+    pub fn set_rate_limit_store(store: impl RateLimitStore + 'static) {
+        if let Ok(mut current) = SERVER_RATE_LIMIT_STORE.write() {
+            *current = Arc::new(store);
+        }
+    }
+
+    pub fn get_rate_limit_store() -> Arc<dyn RateLimitStore> {
+        match SERVER_RATE_LIMIT_STORE.read() {
+            Ok(store) => store.clone(),
+            Err(err) => err.into_inner().clone(),
+        }
+    }
+
     pub async fn set_ws_ping_duration(dur: Duration) {
         *SERVER_WS_PING_DURATION.write().await = dur;
     }
@@ -65,25 +151,20 @@ impl ServerConfig {
 pub struct ServerAuth;
 impl ServerAuth {
     pub async fn jwt_issue(payload: String, expire: Duration) -> anyhow::Result<String> {
-        let secret = &(*SERVER_JWT_SECRET.read().await)[..];
         let claims = Claims {
             sub: payload,
             exp: (SystemTime::now() + expire)
                 .duration_since(UNIX_EPOCH)?
                 .as_micros() as u64,
         };
-        let header = jsonwebtoken::Header::default();
-        let key = jsonwebtoken::EncodingKey::from_secret(secret.as_bytes());
-        Ok(jsonwebtoken::encode(&header, &claims, &key)?)
+        crate::jwt::encode(&claims).await
     }
 
     pub async fn jwt_check(token: &str) -> anyhow::Result<String> {
-        let secret = &(*SERVER_JWT_SECRET.read().await)[..];
-        let decoding_key = jsonwebtoken::DecodingKey::from_secret(secret.as_bytes());
-        let validation = jsonwebtoken::Validation::default();
-        let claims = jsonwebtoken::decode::<Claims>(token, &decoding_key, &validation)?.claims;
+        let claims = crate::jwt::decode::<Claims>(token).await?;
+        let leeway = ServerConfig::get_jwt_options().await.leeway;
         let expired = SystemTime::UNIX_EPOCH + std::time::Duration::from_micros(claims.exp);
-        match SystemTime::now() <= expired {
+        match SystemTime::now() <= expired + leeway {
             true => Ok(claims.sub),
             false => Err(anyhow::Error::msg("token expired")),
         }
@@ -0,0 +1,400 @@
+use crate::{AuthRequirement, HttpResponse};
+use base64::Engine;
+use jsonwebtoken::jwk::{
+    AlgorithmParameters, CommonParameters, EllipticCurve, EllipticCurveKeyParameters, Jwk, JwkSet,
+    KeyAlgorithm, OctetKeyPairParameters, OctetKeyPairType, PublicKeyUse, RSAKeyParameters,
+};
+use jsonwebtoken::{Algorithm, DecodingKey, EncodingKey, Header, Validation};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::{Map, Value};
+use std::fmt;
+use std::time::Duration;
+
+/// JWT 签名密钥，`kid` 写入令牌头部，校验时据此选择密钥
+///
+/// 非对称密钥（RS256/ES256/EdDSA）的公钥可通过 `use_jwks` 发布，供其他服务校验本服务签发的令牌
+#[derive(Clone)]
+pub struct JwtKey {
+    kid: Option<String>,
+    alg: Algorithm,
+    encoding: Option<EncodingKey>,
+    decoding: DecodingKey,
+    jwk: Option<Jwk>,
+}
+
+impl fmt::Debug for JwtKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("JwtKey")
+            .field("kid", &self.kid)
+            .field("alg", &self.alg)
+            .field("can_sign", &self.encoding.is_some())
+            .finish_non_exhaustive()
+    }
+}
+
+impl JwtKey {
+    /// HMAC-SHA256 共享密钥
+    pub fn hs256(kid: impl Into<String>, secret: impl AsRef<[u8]>) -> Self {
+        Self {
+            kid: Some(kid.into()),
+            ..Self::hmac(secret.as_ref())
+        }
+    }
+
+    /// 不带 `kid` 的 HMAC 密钥，对应 `ServerConfig::set_jwt_secret`
+    pub(crate) fn hmac(secret: &[u8]) -> Self {
+        Self {
+            kid: None,
+            alg: Algorithm::HS256,
+            encoding: Some(EncodingKey::from_secret(secret)),
+            decoding: DecodingKey::from_secret(secret),
+            jwk: None,
+        }
+    }
+
+    /// RSA 私钥（PKCS#1 或 PKCS#8 PEM）
+    pub fn rs256(kid: impl Into<String>, private_pem: impl AsRef<[u8]>) -> anyhow::Result<Self> {
+        let private_pem = private_pem.as_ref();
+        let der = pem_to_der(private_pem)?;
+        let pair = ring::rsa::KeyPair::from_pkcs8(&der)
+            .or_else(|_| ring::rsa::KeyPair::from_der(&der))
+            .map_err(|e| anyhow::anyhow!("invalid RSA private key: {e}"))?;
+        let public: ring::rsa::PublicKeyComponents<Vec<u8>> = pair.public().into();
+        let (n, e) = (b64(&public.n), b64(&public.e));
+        Ok(Self::asymmetric(
+            kid.into(),
+            KeyAlgorithm::RS256,
+            EncodingKey::from_rsa_pem(private_pem)?,
+            DecodingKey::from_rsa_components(&n, &e)?,
+            AlgorithmParameters::RSA(RSAKeyParameters {
+                key_type: Default::default(),
+                n,
+                e,
+            }),
+        ))
+    }
+
+    /// P-256 ECDSA 私钥（PKCS#8 PEM）
+    pub fn es256(kid: impl Into<String>, private_pem: impl AsRef<[u8]>) -> anyhow::Result<Self> {
+        let private_pem = private_pem.as_ref();
+        let der = pem_to_der(private_pem)?;
+        let pair = ring::signature::EcdsaKeyPair::from_pkcs8(
+            &ring::signature::ECDSA_P256_SHA256_FIXED_SIGNING,
+            &der,
+            &ring::rand::SystemRandom::new(),
+        )
+        .map_err(|e| anyhow::anyhow!("invalid P-256 private key: {e}"))?;
+        // 未压缩格式：0x04 || x || y
+        let point = ring::signature::KeyPair::public_key(&pair).as_ref();
+        let (x, y) = (b64(&point[1..33]), b64(&point[33..65]));
+        Ok(Self::asymmetric(
+            kid.into(),
+            KeyAlgorithm::ES256,
+            EncodingKey::from_ec_pem(private_pem)?,
+            DecodingKey::from_ec_components(&x, &y)?,
+            AlgorithmParameters::EllipticCurve(EllipticCurveKeyParameters {
+                key_type: Default::default(),
+                curve: EllipticCurve::P256,
+                x,
+                y,
+            }),
+        ))
+    }
+
+    /// Ed25519 私钥（PKCS#8 PEM），算法为 EdDSA
+    pub fn ed25519(kid: impl Into<String>, private_pem: impl AsRef<[u8]>) -> anyhow::Result<Self> {
+        let private_pem = private_pem.as_ref();
+        let der = pem_to_der(private_pem)?;
+        let pair = ring::signature::Ed25519KeyPair::from_pkcs8_maybe_unchecked(&der)
+            .map_err(|e| anyhow::anyhow!("invalid Ed25519 private key: {e}"))?;
+        let x = b64(ring::signature::KeyPair::public_key(&pair).as_ref());
+        Ok(Self::asymmetric(
+            kid.into(),
+            KeyAlgorithm::EdDSA,
+            EncodingKey::from_ed_pem(private_pem)?,
+            DecodingKey::from_ed_components(&x)?,
+            AlgorithmParameters::OctetKeyPair(OctetKeyPairParameters {
+                key_type: OctetKeyPairType::OctetKeyPair,
+                curve: EllipticCurve::Ed25519,
+                x,
+            }),
+        ))
+    }
+
+    /// 由公钥 JWK 创建仅用于校验的密钥，如其他服务发布的密钥；JWK 未包含 `alg` 时按密钥类型
+    /// 取 RS256/ES256/EdDSA
+    pub fn from_jwk(jwk: &Jwk) -> anyhow::Result<Self> {
+        let alg = match (jwk.common.key_algorithm, &jwk.algorithm) {
+            (Some(alg), _) => alg.to_string().parse()?,
+            (None, AlgorithmParameters::RSA(_)) => Algorithm::RS256,
+            (None, AlgorithmParameters::EllipticCurve(params))
+                if params.curve == EllipticCurve::P256 =>
+            {
+                Algorithm::ES256
+            }
+            (None, AlgorithmParameters::EllipticCurve(params))
+                if params.curve == EllipticCurve::P384 =>
+            {
+                Algorithm::ES384
+            }
+            (None, AlgorithmParameters::OctetKeyPair(_)) => Algorithm::EdDSA,
+            (None, _) => anyhow::bail!("JWK has no alg"),
+        };
+        Ok(Self {
+            kid: jwk.common.key_id.clone(),
+            alg,
+            encoding: None,
+            decoding: DecodingKey::from_jwk(jwk)?,
+            jwk: Some(jwk.clone()),
+        })
+    }
+
+    /// 仅保留校验能力，用于轮换后仍需接受旧令牌的密钥
+    pub fn verify_only(mut self) -> Self {
+        self.encoding = None;
+        self
+    }
+
+    pub fn kid(&self) -> Option<&str> {
+        self.kid.as_deref()
+    }
+
+    pub fn algorithm(&self) -> Algorithm {
+        self.alg
+    }
+
+    /// 公钥 JWK，对称密钥返回 `None`
+    pub fn to_jwk(&self) -> Option<&Jwk> {
+        self.jwk.as_ref()
+    }
+
+    pub(crate) fn decoding_key(&self) -> &DecodingKey {
+        &self.decoding
+    }
+
+    fn asymmetric(
+        kid: String,
+        key_algorithm: KeyAlgorithm,
+        encoding: EncodingKey,
+        decoding: DecodingKey,
+        algorithm: AlgorithmParameters,
+    ) -> Self {
+        let jwk = Jwk {
+            common: CommonParameters {
+                public_key_use: Some(PublicKeyUse::Signature),
+                key_algorithm: Some(key_algorithm),
+                key_id: Some(kid.clone()),
+                ..Default::default()
+            },
+            algorithm,
+        };
+        Self {
+            kid: Some(kid),
+            alg: decoding_algorithm(key_algorithm),
+            encoding: Some(encoding),
+            decoding,
+            jwk: Some(jwk),
+        }
+    }
+}
+
+/// JWT 签发与校验配置，通过 `ServerConfig::set_jwt_options` 设置，
+/// 作用于 `SessionCache` 与 `ServerAuth`
+///
+/// `keys` 中第一个可签名的密钥用于签发，所有密钥均可用于校验。轮换时将新密钥放在最前，
+/// 旧密钥以 `verify_only` 保留至其签发的令牌全部过期。`keys` 为空时使用 `set_jwt_secret` 设置的密钥
+#[derive(Debug, Clone, Default)]
+pub struct JwtOptions {
+    pub keys: Vec<JwtKey>,
+    pub issuer: Option<String>, // 签发时写入 `iss`，校验时要求一致
+    pub audience: Vec<String>,  // 签发时写入 `aud`，校验时要求包含其中之一
+    pub leeway: Duration,       // 校验过期时间时允许的时钟偏差，默认为零
+}
+
+impl JwtOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_key(mut self, key: JwtKey) -> Self {
+        self.keys.push(key);
+        self
+    }
+
+    pub fn with_issuer(mut self, issuer: impl Into<String>) -> Self {
+        self.issuer = Some(issuer.into());
+        self
+    }
+
+    pub fn with_audience(mut self, audience: impl Into<String>) -> Self {
+        self.audience.push(audience.into());
+        self
+    }
+
+    pub fn with_leeway(mut self, leeway: Duration) -> Self {
+        self.leeway = leeway;
+        self
+    }
+
+    /// 所有非对称密钥的公钥集合
+    pub fn jwks(&self) -> JwkSet {
+        JwkSet {
+            keys: self.keys.iter().filter_map(|key| key.jwk.clone()).collect(),
+        }
+    }
+}
+
+/// 使用当前配置签发令牌，`iss`/`aud` 未在 `claims` 中给出时按配置写入
+pub(crate) async fn encode<T: Serialize>(claims: &T) -> anyhow::Result<String> {
+    let opts = crate::ServerConfig::get_jwt_options().await;
+    let fallback;
+    let key = match opts.keys.iter().find(|key| key.encoding.is_some()) {
+        Some(key) => key,
+        None if opts.keys.is_empty() => {
+            fallback = JwtKey::hmac(crate::ServerConfig::get_jwt_secret().await.as_bytes());
+            &fallback
+        }
+        None => anyhow::bail!("no JWT signing key configured"),
+    };
+    let mut claims = serde_json::to_value(claims)?;
+    if let Some(map) = claims.as_object_mut() {
+        if let Some(issuer) = &opts.issuer {
+            map.entry("iss").or_insert_with(|| issuer.clone().into());
+        }
+        match &opts.audience[..] {
+            [] => {}
+            [audience] => {
+                map.entry("aud").or_insert_with(|| audience.clone().into());
+            }
+            audience => {
+                map.entry("aud").or_insert_with(|| audience.into());
+            }
+        }
+    }
+    let encoding = key
+        .encoding
+        .as_ref()
+        .ok_or_else(|| anyhow::anyhow!("no JWT signing key configured"))?;
+    let mut header = Header::new(key.alg);
+    header.kid = key.kid.clone();
+    Ok(jsonwebtoken::encode(&header, &claims, encoding)?)
+}
+
+/// 按令牌头部的 `alg` 与 `kid` 选择密钥校验，并检查 `exp`、`iss`、`aud`
+pub(crate) async fn decode<T: DeserializeOwned>(token: &str) -> anyhow::Result<T> {
+    let opts = crate::ServerConfig::get_jwt_options().await;
+    let header = jsonwebtoken::decode_header(token)?;
+    let fallback;
+    let keys = match opts.keys.is_empty() {
+        true => {
+            fallback = [JwtKey::hmac(
+                crate::ServerConfig::get_jwt_secret().await.as_bytes(),
+            )];
+            &fallback[..]
+        }
+        false => &opts.keys[..],
+    };
+    let mut validation = Validation::new(header.alg);
+    validation.leeway = opts.leeway.as_secs();
+    if let Some(issuer) = &opts.issuer {
+        validation.set_issuer(&[issuer]);
+    }
+    match opts.audience.is_empty() {
+        true => validation.validate_aud = false,
+        false => validation.set_audience(&opts.audience),
+    }
+    let mut candidates = keys
+        .iter()
+        .filter(|key| key.alg == header.alg && (header.kid.is_none() || key.kid == header.kid));
+    let Some(first) = candidates.next() else {
+        anyhow::bail!(
+            "no JWT key matches alg {:?} and kid {:?}",
+            header.alg,
+            header.kid
+        );
+    };
+    // 未指定 kid 时依次尝试同算法的密钥
+    let mut ret = jsonwebtoken::decode::<T>(token, &first.decoding, &validation);
+    for key in candidates {
+        if ret.is_ok() {
+            break;
+        }
+        ret = jsonwebtoken::decode::<T>(token, &key.decoding, &validation);
+    }
+    Ok(ret?.claims)
+}
+
+/// 声明中 `roles` 列出的角色
+pub(crate) fn claim_roles(claims: &Map<String, Value>) -> Vec<&str> {
+    match claims.get("roles") {
+        Some(Value::Array(roles)) => roles.iter().filter_map(|role| role.as_str()).collect(),
+        Some(Value::String(role)) => vec![role.as_str()],
+        _ => vec![],
+    }
+}
+
+/// 声明中 `scope`（空格分隔）或 `scp`（数组）列出的 scope
+pub(crate) fn claim_scopes(claims: &Map<String, Value>) -> Vec<&str> {
+    match (claims.get("scope"), claims.get("scp")) {
+        (Some(Value::String(scope)), _) => scope.split_whitespace().collect(),
+        (_, Some(Value::Array(scopes))) => {
+            scopes.iter().filter_map(|scope| scope.as_str()).collect()
+        }
+        _ => vec![],
+    }
+}
+
+/// 按顺序校验访问要求，不满足时返回 403 响应
+pub(crate) fn check_access(
+    claims: &Map<String, Value>,
+    requirements: &[AuthRequirement],
+) -> Option<HttpResponse> {
+    let (roles, scopes) = (claim_roles(claims), claim_scopes(claims));
+    let unmet = requirements.iter().find(|requirement| match requirement {
+        AuthRequirement::Role(names) => !names.iter().any(|name| roles.contains(name)),
+        AuthRequirement::Scope(names) => !names.iter().any(|name| scopes.contains(name)),
+    })?;
+    let mut res = match unmet {
+        AuthRequirement::Role(roles) => {
+            HttpResponse::text(format!("Forbidden: requires role {}", roles.join(" or ")))
+        }
+        AuthRequirement::Scope(scopes) => {
+            let mut res =
+                HttpResponse::text(format!("Forbidden: requires scope {}", scopes.join(" or ")));
+            res.add_header(
+                "WWW-Authenticate".into(),
+                format!(
+                    "Bearer error=\"insufficient_scope\", scope=\"{}\"",
+                    scopes.join(" ")
+                )
+                .into(),
+            );
+            res
+        }
+    };
+    res.http_code = 403;
+    Some(res)
+}
+
+fn decoding_algorithm(alg: KeyAlgorithm) -> Algorithm {
+    match alg {
+        KeyAlgorithm::ES256 => Algorithm::ES256,
+        KeyAlgorithm::EdDSA => Algorithm::EdDSA,
+        _ => Algorithm::RS256,
+    }
+}
+
+fn b64(data: &[u8]) -> String {
+    base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(data)
+}
+
+fn pem_to_der(pem: &[u8]) -> anyhow::Result<Vec<u8>> {
+    let pem = std::str::from_utf8(pem)?;
+    let body: String = pem
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with("-----"))
+        .collect();
+    Ok(base64::engine::general_purpose::STANDARD.decode(body)?)
+}
@@ -2,7 +2,9 @@
 pub mod acme;
 pub mod client;
 pub mod global_config;
+pub mod jwt;
 pub mod server;
+pub mod session_store;
 pub mod utils;
 #[cfg(feature = "webrtc")]
 pub mod webrtc;
@@ -12,12 +14,15 @@ pub use client::*;
 pub use global_config::*;
 pub use hipstr;
 pub use inventory;
+pub use jsonwebtoken;
+pub use jwt::{JwtKey, JwtOptions};
 pub use potato_macro::*;
 pub use regex;
 pub use rust_embed;
 pub use serde_json;
 pub use server::CorsConfig;
 pub use server::*;
+pub use session_store::{FileSessionStore, MemorySessionStore, SessionRecord, SessionStore};
 pub use utils::ai::*;
 pub use utils::refstr::Headers;
 
@@ -140,6 +145,7 @@ impl OnceCache {
 }
 
 use dashmap::DashMap;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use std::sync::RwLock;
 use std::time::{Duration, Instant};
 
@@ -198,6 +204,20 @@ pub struct CookieBuilder {
     http_only: bool,
     /// SameSite策略: Strict, Lax, None
     same_site: Option<String>,
+    /// 是否按顶级站点分区存储（CHIPS）
+    partitioned: bool,
+    /// 优先级: Low, Medium, High
+    priority: Option<String>,
+    /// 值的保护方式
+    seal: CookieSeal,
+}
+
+/// Cookie值的保护方式，密钥由 `ServerConfig::set_cookie_keys` 设置
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum CookieSeal {
+    Plain,
+    Signed,
+    Encrypted,
 }
 
 impl CookieBuilder {
@@ -213,6 +233,9 @@ impl CookieBuilder {
             secure: false,
             http_only: false,
             same_site: None,
+            partitioned: false,
+            priority: None,
+            seal: CookieSeal::Plain,
         }
     }
 
@@ -258,9 +281,43 @@ impl CookieBuilder {
         self
     }
 
+    /// 设置Partitioned标志（按顶级站点分区存储，同时附加Secure）
+    pub fn partitioned(mut self, partitioned: bool) -> Self {
+        self.partitioned = partitioned;
+        self
+    }
+
+    /// 设置优先级 ("Low", "Medium", "High")
+    pub fn priority(mut self, priority: &str) -> Self {
+        self.priority = Some(priority.to_string());
+        self
+    }
+
+    /// 下发时对值签名，客户端可读取但无法篡改，通过 `SessionCache::get_signed_cookie` 读取
+    pub fn signed(mut self) -> Self {
+        self.seal = CookieSeal::Signed;
+        self
+    }
+
+    /// 下发时加密值，客户端无法读取与篡改，通过 `SessionCache::get_encrypted_cookie` 读取
+    pub fn encrypted(mut self) -> Self {
+        self.seal = CookieSeal::Encrypted;
+        self
+    }
+
     /// 生成Set-Cookie header值
+    ///
+    /// 签名或加密失败时下发空值，不会以明文泄露原值
     pub fn to_set_cookie_string(&self) -> String {
-        let mut parts = vec![format!("{}={}", self.name, self.value)];
+        let value = match self.seal {
+            CookieSeal::Plain => Ok(self.value.clone()),
+            CookieSeal::Signed => ServerConfig::get_cookie_keys().sign(&self.name, &self.value),
+            CookieSeal::Encrypted => {
+                ServerConfig::get_cookie_keys().encrypt(&self.name, &self.value)
+            }
+        }
+        .unwrap_or_default();
+        let mut parts = vec![format!("{}={}", self.name, value)];
 
         if let Some(ref path) = self.path {
             parts.push(format!("Path={}", path));
@@ -285,7 +342,7 @@ impl CookieBuilder {
             parts.push(format!("Max-Age={}", max_age));
         }
 
-        if self.secure {
+        if self.secure || self.partitioned {
             parts.push("Secure".to_string());
         }
 
@@ -297,6 +354,14 @@ impl CookieBuilder {
             parts.push(format!("SameSite={}", same_site));
         }
 
+        if self.partitioned {
+            parts.push("Partitioned".to_string());
+        }
+
+        if let Some(ref priority) = self.priority {
+            parts.push(format!("Priority={}", priority));
+        }
+
         parts.join("; ")
     }
 
@@ -312,6 +377,12 @@ impl CookieBuilder {
             parts.push(format!("Domain={}", domain));
         }
 
+        // 分区存储的Cookie须同样带Partitioned才能删除
+        if self.partitioned {
+            parts.push("Secure".to_string());
+            parts.push("Partitioned".to_string());
+        }
+
         parts.join("; ")
     }
 }
@@ -320,14 +391,32 @@ impl CookieBuilder {
 type SessionCacheData = Arc<RwLock<HashMap<(String, TypeId), Box<dyn Any + Send + Sync>>>>;
 
 /// 会话级缓存,用于同一用户的不同请求间传递参数
-/// 基于Bearer token中的id区分不同Session
+/// 基于Bearer token或会话Cookie中的id区分不同Session
+///
+/// `set` 写入的任意类型值仅保存在本进程内；`set_persistent` 写入的值经 serde 编码后
+/// 保存到 `SessionStore`，可在重启后保留并在多个实例间共享
+///
+/// 通过 `generate_cookie` 建立的无状态会话不使用 `SessionStore`，`set_persistent` 写入的值
+/// 加密后随会话Cookie保存在客户端，`set` 写入的值仅在本次请求内有效
 #[derive(Debug, Clone)]
 pub struct SessionCache {
     data: SessionCacheData,
+    /// 需要持久化的值（JSON编码）
+    persisted: Arc<RwLock<HashMap<String, serde_json::Value>>>,
+    /// 持久化的值是否有未保存的修改
+    dirty: Arc<AtomicBool>,
+    /// 所属用户ID，`SessionCache::new()` 创建的实例为空，不写入存储
+    user_id: Option<i64>,
+    /// 会话过期时间（UNIX 秒）
+    expires_at: Arc<AtomicU64>,
+    /// 本次请求token中的自定义声明，同一用户的不同token可携带不同声明
+    claims: Arc<serde_json::Map<String, serde_json::Value>>,
     /// 存储从请求中读取的cookies
     request_cookies: Arc<RwLock<HashMap<String, String>>>,
     /// 存储需要设置到响应的cookies（包含完整属性）
     response_cookies: Arc<RwLock<Vec<CookieBuilder>>>,
+    /// 是否为保存在加密Cookie中的无状态会话
+    cookie_session: bool,
 }
 
 impl Default for SessionCache {
@@ -340,8 +429,14 @@ impl SessionCache {
     pub fn new() -> Self {
         Self {
             data: Arc::new(RwLock::new(HashMap::new())),
+            persisted: Arc::new(RwLock::new(HashMap::new())),
+            dirty: Arc::new(AtomicBool::new(false)),
+            user_id: None,
+            expires_at: Arc::new(AtomicU64::new(0)),
+            claims: Arc::new(serde_json::Map::new()),
             request_cookies: Arc::new(RwLock::new(HashMap::new())),
             response_cookies: Arc::new(RwLock::new(Vec::new())),
+            cookie_session: false,
         }
     }
 
@@ -363,6 +458,16 @@ impl SessionCache {
         cookies.get(name).cloned()
     }
 
+    /// 获取请求中签名cookie的原始值，签名无效时返回 `None`
+    pub fn get_signed_cookie(&self, name: &str) -> Option<String> {
+        ServerConfig::get_cookie_keys().verify(name, &self.get_cookie(name)?)
+    }
+
+    /// 获取请求中加密cookie的原始值，无法解密时返回 `None`
+    pub fn get_encrypted_cookie(&self, name: &str) -> Option<String> {
+        ServerConfig::get_cookie_keys().decrypt(name, &self.get_cookie(name)?)
+    }
+
     /// 设置响应cookie（简单版本，仅设置名称和值）
     pub fn set_cookie(&self, name: &str, value: &str) {
         if let Ok(mut cookies) = self.response_cookies.write() {
@@ -370,6 +475,16 @@ impl SessionCache {
         }
     }
 
+    /// 设置签名的响应cookie
+    pub fn set_signed_cookie(&self, name: &str, value: &str) {
+        self.set_cookie_with_builder(CookieBuilder::new(name, value).signed());
+    }
+
+    /// 设置加密的响应cookie
+    pub fn set_encrypted_cookie(&self, name: &str, value: &str) {
+        self.set_cookie_with_builder(CookieBuilder::new(name, value).encrypted());
+    }
+
     /// 设置响应cookie（完整配置版本）
     pub fn set_cookie_with_builder(&self, cookie: CookieBuilder) {
         if let Ok(mut cookies) = self.response_cookies.write() {
@@ -392,17 +507,17 @@ impl SessionCache {
     }
 
     /// 将所有待设置的cookies应用到HttpResponse
+    ///
+    /// 无状态会话的值有修改时同时重新下发会话Cookie
     pub fn apply_cookies(&self, response: &mut HttpResponse) {
+        if self.cookie_session && self.dirty.swap(false, Ordering::Relaxed) {
+            if let Ok(cookie) = self.session_cookie() {
+                self.set_cookie_with_builder(cookie);
+            }
+        }
         if let Ok(cookies) = self.response_cookies.read() {
             for cookie in cookies.iter() {
-                let cookie_str = if cookie.value.is_empty() {
-                    // 移除cookie
-                    cookie.to_delete_cookie_string()
-                } else {
-                    // 设置cookie
-                    cookie.to_set_cookie_string()
-                };
-                response.add_header(Cow::Borrowed("Set-Cookie"), Cow::Owned(cookie_str));
+                response.set_cookie(cookie.clone());
             }
         }
     }
@@ -456,20 +571,67 @@ impl SessionCache {
             .map(|boxed| *boxed)
     }
 
+    /// 插入或更新需要持久化的值，随请求结束写入 `SessionStore`
+    pub fn set_persistent<T: serde::Serialize>(&self, name: &str, value: &T) -> anyhow::Result<()> {
+        let value = serde_json::to_value(value)?;
+        if let Ok(mut persisted) = self.persisted.write() {
+            persisted.insert(name.to_string(), value);
+            self.dirty.store(true, Ordering::Relaxed);
+        }
+        Ok(())
+    }
+
+    /// 获取需要持久化的值，不存在或无法解码为 `T` 时返回 `None`
+    pub fn get_persistent<T: serde::de::DeserializeOwned>(&self, name: &str) -> Option<T> {
+        let persisted = self.persisted.read().ok()?;
+        T::deserialize(persisted.get(name)?).ok()
+    }
+
+    /// 移除并返回需要持久化的值
+    pub fn remove_persistent<T: serde::de::DeserializeOwned>(&self, name: &str) -> Option<T> {
+        let value = self.persisted.write().ok()?.remove(name)?;
+        self.dirty.store(true, Ordering::Relaxed);
+        serde_json::from_value(value).ok()
+    }
+
+    /// 将持久化的值写入 `SessionStore`，没有未保存的修改时直接返回
+    ///
+    /// 处理函数结束后会自动调用，需要立即写入或处理写入错误时可手动调用
+    pub async fn save(&self) -> anyhow::Result<()> {
+        let Some(user_id) = self.user_id.filter(|_| !self.cookie_session) else {
+            return Ok(());
+        };
+        if !self.dirty.swap(false, Ordering::Relaxed) {
+            return Ok(());
+        }
+        let record = SessionRecord {
+            values: match self.persisted.read() {
+                Ok(persisted) => persisted.clone(),
+                Err(err) => err.into_inner().clone(),
+            },
+            expires_at: self.expires_at.load(Ordering::Relaxed),
+        };
+        let ret = session_store::store().set(user_id, record).await;
+        if ret.is_err() {
+            self.dirty.store(true, Ordering::Relaxed);
+        }
+        ret
+    }
+
     // ==================== 静态方法：Session管理 ====================
 
+    /// 设置会话存储，默认为进程内的 `MemorySessionStore`，应在服务器启动前调用
+    pub fn set_store(store: impl SessionStore + 'static) {
+        session_store::set_store(Arc::new(store));
+    }
+
     /// 设置JWT签发秘钥（与ServerConfig共享同一个密钥）
     pub async fn set_jwt_secret(secret: &[u8]) {
         let secret_str = String::from_utf8_lossy(secret);
         crate::ServerConfig::set_jwt_secret(secret_str).await;
     }
 
-    /// 获取JWT签发秘钥
-    async fn get_jwt_secret() -> Vec<u8> {
-        crate::ServerConfig::get_jwt_secret().await.into_bytes()
-    }
-
-    /// 签发JWT token
+    /// 签发JWT token，签名密钥与 `iss`/`aud` 由 `ServerConfig::set_jwt_options` 配置
     /// 参数:
     /// - user_id: 用户ID
     /// - ttl: token有效期
@@ -479,42 +641,173 @@ impl SessionCache {
         user_id: i64,
         ttl: std::time::Duration,
     ) -> Result<String, anyhow::Error> {
-        use jsonwebtoken::{encode, EncodingKey, Header};
-        use serde::{Deserialize, Serialize};
+        Self::generate_token_with_claims(user_id, ttl, &serde_json::Map::new()).await
+    }
 
-        #[derive(Debug, Serialize, Deserialize)]
-        struct SessionClaims {
-            sub: i64,        // user_id
-            exp: usize,      // token过期时间戳
-            iat: usize,      // 签发时间戳
-            sess_exp: usize, // session过期时间戳（固定，不随访问更新）
+    /// 签发携带自定义声明（角色、租户等）的JWT token，`claims` 须序列化为JSON对象
+    ///
+    /// `roles`（字符串数组）与 `scope`（空格分隔的字符串）由 `#[potato::require_role]`、
+    /// `#[potato::require_scope]` 标注校验；`sub`、`exp`、`iat`、`sess_exp` 为保留字段
+    pub async fn generate_token_with_claims<C: serde::Serialize>(
+        user_id: i64,
+        ttl: std::time::Duration,
+        claims: &C,
+    ) -> Result<String, anyhow::Error> {
+        let serde_json::Value::Object(mut claims) = serde_json::to_value(claims)? else {
+            return Err(anyhow!("custom claims must serialize to a JSON object"));
+        };
+        for name in ["sub", "exp", "iat", "sess_exp"] {
+            if claims.contains_key(name) {
+                return Err(anyhow!("claim `{name}` is reserved"));
+            }
         }
 
         let now = std::time::SystemTime::now()
             .duration_since(std::time::UNIX_EPOCH)?
             .as_secs() as usize;
 
-        let claims = SessionClaims {
-            sub: user_id,
-            exp: now + ttl.as_secs() as usize,
-            iat: now,
-            sess_exp: now + ttl.as_secs() as usize,
+        // user_id
+        claims.insert("sub".into(), user_id.into());
+        // token过期时间戳
+        claims.insert("exp".into(), (now + ttl.as_secs() as usize).into());
+        // 签发时间戳
+        claims.insert("iat".into(), now.into());
+        // session过期时间戳（固定，不随访问更新）
+        claims.insert("sess_exp".into(), (now + ttl.as_secs() as usize).into());
+
+        jwt::encode(&claims).await
+    }
+
+    /// 建立无状态会话，返回加密的会话Cookie，由登录处理函数通过 `HttpResponse::set_cookie` 下发
+    ///
+    /// 浏览器之后的请求携带该Cookie即可取得 `SessionCache`，无需Bearer token；
+    /// Cookie名称与属性由 `ServerConfig::set_session_cookie` 设置
+    pub fn generate_cookie(user_id: i64, ttl: Duration) -> Result<CookieBuilder, anyhow::Error> {
+        Self::generate_cookie_with_claims(user_id, ttl, &serde_json::Map::new())
+    }
+
+    /// 建立携带自定义声明的无状态会话，声明的用法与 `generate_token_with_claims` 相同
+    pub fn generate_cookie_with_claims<C: serde::Serialize>(
+        user_id: i64,
+        ttl: Duration,
+        claims: &C,
+    ) -> Result<CookieBuilder, anyhow::Error> {
+        let serde_json::Value::Object(claims) = serde_json::to_value(claims)? else {
+            return Err(anyhow!("custom claims must serialize to a JSON object"));
+        };
+        let cache = Self {
+            claims: Arc::new(claims),
+            cookie_session: true,
+            ..Self::with_user(user_id)
         };
+        cache
+            .expires_at
+            .store(session_store::unix_now() + ttl.as_secs(), Ordering::Relaxed);
+        cache.session_cookie()
+    }
+
+    /// 结束无状态会话，删除客户端的会话Cookie
+    pub fn clear_session_cookie(&self) {
+        let cookie = ServerConfig::get_session_cookie();
+        self.dirty.store(false, Ordering::Relaxed);
+        self.set_cookie_with_builder(CookieBuilder {
+            value: String::new(),
+            seal: CookieSeal::Plain,
+            ..cookie
+        });
+    }
 
-        let secret = Self::get_jwt_secret().await;
-        let token = encode(
-            &Header::default(),
-            &claims,
-            &EncodingKey::from_secret(&secret),
-        )?;
+    /// 按当前的值生成加密的会话Cookie
+    fn session_cookie(&self) -> anyhow::Result<CookieBuilder> {
+        let expires_at = self.expires_at.load(Ordering::Relaxed);
+        let user_id = self
+            .user_id
+            .ok_or_else(|| anyhow!("session has no user id"))?;
+        let values = self
+            .persisted
+            .read()
+            .map_err(|_| anyhow!("session values lock poisoned"))?;
+        let data = serde_json::json!({
+            "sub": user_id,
+            "sess_exp": expires_at,
+            "values": *values,
+            "claims": *self.claims,
+        });
+        let template = ServerConfig::get_session_cookie();
+        let max_age = expires_at.saturating_sub(session_store::unix_now()) as i64;
+        // 立即加密，失败时由调用方处理，而不是在下发时丢弃
+        let value = ServerConfig::get_cookie_keys().encrypt(&template.name, &data.to_string())?;
+        Ok(CookieBuilder {
+            value,
+            seal: CookieSeal::Plain,
+            ..template.max_age(max_age)
+        })
+    }
+
+    /// 从请求的会话Cookie恢复无状态会话
+    pub fn from_cookie(cookie_header: &str) -> Result<Self, SessionCacheError> {
+        #[derive(serde::Deserialize)]
+        struct CookieSessionData {
+            sub: i64,
+            sess_exp: u64,
+            #[serde(default)]
+            values: HashMap<String, serde_json::Value>,
+            #[serde(default)]
+            claims: serde_json::Map<String, serde_json::Value>,
+        }
+
+        let mut cache = Self::new();
+        cache.parse_request_cookies(cookie_header);
+        let name = ServerConfig::get_session_cookie().name;
+        let sealed = cache
+            .get_cookie(&name)
+            .ok_or(SessionCacheError::MissingAuthHeader)?;
+        let (data, rotated) = ServerConfig::get_cookie_keys()
+            .decrypt_with_rotation(&name, &sealed)
+            .ok_or_else(|| SessionCacheError::InvalidToken("Cookie decrypt failed".to_string()))?;
+        let data: CookieSessionData = serde_json::from_str(&data)
+            .map_err(|e| SessionCacheError::InvalidToken(format!("Cookie decode failed: {e}")))?;
+        if data.sess_exp < session_store::unix_now() {
+            return Err(SessionCacheError::SessionExpired);
+        }
+        cache.user_id = Some(data.sub);
+        cache.cookie_session = true;
+        cache.claims = Arc::new(data.claims);
+        cache.persisted = Arc::new(RwLock::new(data.values));
+        cache.expires_at.store(data.sess_exp, Ordering::Relaxed);
+        // 旧密钥加密的会话Cookie改用当前密钥重新下发
+        cache.dirty.store(rotated, Ordering::Relaxed);
+        Ok(cache)
+    }
 
-        Ok(token)
+    /// 由 `Authorization: Bearer` 或会话Cookie取得会话，前者优先，并解析请求中的cookies
+    #[doc(hidden)]
+    pub async fn from_request(req: &HttpRequest) -> Option<Self> {
+        let cookie_header = req.get_header("Cookie");
+        match req.get_header("Authorization") {
+            Some(value) if value.starts_with("Bearer ") => {
+                let mut cache = Self::from_token(&value[7..]).await.ok()?;
+                if let Some(cookie_header) = cookie_header {
+                    cache.parse_request_cookies(cookie_header);
+                }
+                Some(cache)
+            }
+            _ => Self::from_cookie(cookie_header?).ok(),
+        }
     }
 
-    /// 解析JWT token
+    /// 解析JWT token，按token头部的 `kid` 选择密钥并校验 `iss`/`aud`
     /// 返回: (user_id, session_exp_duration)
     pub async fn parse_token(token: &str) -> Result<(i64, Duration), SessionCacheError> {
-        use jsonwebtoken::{decode, DecodingKey, Validation};
+        let (user_id, ttl, _) = Self::decode_token(token).await?;
+        Ok((user_id, ttl))
+    }
+
+    /// 解析JWT token，同时返回其中的自定义声明
+    async fn decode_token(
+        token: &str,
+    ) -> Result<(i64, Duration, serde_json::Map<String, serde_json::Value>), SessionCacheError>
+    {
         use serde::{Deserialize, Serialize};
 
         #[derive(Debug, Serialize, Deserialize)]
@@ -523,15 +816,17 @@ impl SessionCache {
             exp: usize,
             iat: usize,
             sess_exp: usize,
+            #[serde(flatten)]
+            custom: serde_json::Map<String, serde_json::Value>,
         }
 
-        let secret = Self::get_jwt_secret().await;
-        let token_data = decode::<SessionClaims>(
-            token,
-            &DecodingKey::from_secret(&secret),
-            &Validation::default(),
-        )
-        .map_err(|e| SessionCacheError::InvalidToken(format!("Token decode failed: {e}")))?;
+        let claims = jwt::decode::<SessionClaims>(token)
+            .await
+            .map_err(|e| SessionCacheError::InvalidToken(format!("Token decode failed: {e}")))?;
+        let leeway = crate::ServerConfig::get_jwt_options()
+            .await
+            .leeway
+            .as_secs() as usize;
 
         let now = std::time::SystemTime::now()
             .duration_since(std::time::UNIX_EPOCH)
@@ -541,23 +836,67 @@ impl SessionCache {
             })?
             .as_secs() as usize;
 
-        if token_data.claims.exp < now {
+        if claims.exp + leeway < now {
             return Err(SessionCacheError::TokenExpired);
         }
 
         // 使用固定的session过期时间，不随访问更新
-        let session_exp = token_data.claims.sess_exp;
-        if session_exp < now {
+        let session_exp = claims.sess_exp;
+        if session_exp + leeway < now {
             return Err(SessionCacheError::SessionExpired);
         }
 
-        let remaining_secs = session_exp - now;
+        let remaining_secs = session_exp.saturating_sub(now);
         Ok((
-            token_data.claims.sub,
+            claims.sub,
             Duration::from_secs(remaining_secs as u64),
+            claims.custom,
         ))
     }
 
+    /// 所属用户ID，即token中的 `sub`
+    pub fn user_id(&self) -> Option<i64> {
+        self.user_id
+    }
+
+    /// 将本次请求token中的自定义声明（含 `iss`/`aud`）反序列化为指定类型
+    pub fn claims<C: serde::de::DeserializeOwned>(&self) -> anyhow::Result<C> {
+        Ok(serde_json::from_value(serde_json::Value::Object(
+            (*self.claims).clone(),
+        ))?)
+    }
+
+    /// 读取单个自定义声明，不存在或类型不符时返回 `None`
+    pub fn claim<T: serde::de::DeserializeOwned>(&self, name: &str) -> Option<T> {
+        serde_json::from_value(self.claims.get(name)?.clone()).ok()
+    }
+
+    /// token中 `roles` 声明列出的角色
+    pub fn roles(&self) -> Vec<&str> {
+        jwt::claim_roles(&self.claims)
+    }
+
+    /// token中 `scope`（空格分隔）或 `scp`（数组）声明列出的 scope
+    pub fn scopes(&self) -> Vec<&str> {
+        jwt::claim_scopes(&self.claims)
+    }
+
+    pub fn has_role(&self, role: &str) -> bool {
+        self.roles().contains(&role)
+    }
+
+    pub fn has_scope(&self, scope: &str) -> bool {
+        self.scopes().contains(&scope)
+    }
+
+    /// 按顺序校验访问要求，不满足时返回 403 响应
+    ///
+    /// 由 `#[potato::require_role]`、`#[potato::require_scope]` 标注生成的代码调用，
+    /// 也可在预处理函数中手动调用
+    pub fn check_access(&self, requirements: &[AuthRequirement]) -> Option<HttpResponse> {
+        jwt::check_access(&self.claims, requirements)
+    }
+
     // ==================== 内部缓存管理器 ====================
 
     /// 获取或创建Session缓存
@@ -566,29 +905,77 @@ impl SessionCache {
     ///
     /// 返回: SessionCache实例
     pub async fn from_token(token: &str) -> Result<Self, SessionCacheError> {
-        let (user_id, ttl) = Self::parse_token(token).await?;
+        let (user_id, ttl, claims) = Self::decode_token(token).await?;
 
         let now = Instant::now();
         let expires_at = now + ttl;
+        let store = session_store::store();
+        let record = store
+            .get(user_id)
+            .await
+            .map_err(|e| SessionCacheError::InternalError(format!("Session store failed: {e}")))?;
 
         // 使用 DashMap 的 entry API 一次性完成检查和创建，避免竞态条件
-        let mut entry = SESSION_CACHE_MANAGER
-            .entry(user_id)
-            .or_insert_with(|| (SessionCache::new(), expires_at));
+        let cache = {
+            let mut entry = SESSION_CACHE_MANAGER
+                .entry(user_id)
+                .or_insert_with(|| (SessionCache::with_user(user_id), expires_at));
+
+            // 检查是否过期，如果过期则重新创建
+            if entry.value().1 < now {
+                // 已过期,重新创建
+                *entry.value_mut() = (SessionCache::with_user(user_id), expires_at);
+            }
+
+            // 返回session的克隆
+            entry.value().0.clone()
+        };
+        let cache = SessionCache {
+            claims: Arc::new(claims),
+            ..cache
+        };
 
-        // 检查是否过期，如果过期则重新创建
-        if entry.value().1 < now {
-            // 已过期,重新创建
-            *entry.value_mut() = (SessionCache::new(), expires_at);
+        // 使用存储中的最新数据，本进程尚未保存的修改优先
+        let session_exp = session_store::unix_now() + ttl.as_secs();
+        let stored_exp = record.as_ref().map_or(0, |record| record.expires_at);
+        if let Some(record) = record {
+            if !cache.dirty.load(Ordering::Relaxed) {
+                if let Ok(mut persisted) = cache.persisted.write() {
+                    *persisted = record.values;
+                }
+            }
+        }
+        cache.expires_at.fetch_max(session_exp, Ordering::Relaxed);
+        // 重新签发的token延长了会话有效期
+        if stored_exp != 0 && stored_exp < session_exp {
+            store.touch(user_id, session_exp).await.map_err(|e| {
+                SessionCacheError::InternalError(format!("Session store failed: {e}"))
+            })?;
         }
+        Ok(cache)
+    }
 
-        // 返回session的克隆
-        Ok(entry.value().0.clone())
+    fn with_user(user_id: i64) -> Self {
+        Self {
+            user_id: Some(user_id),
+            ..Self::new()
+        }
     }
 
     /// 使指定用户的session失效（用于登出等场景）
+    ///
+    /// `SessionStore` 中的数据在后台删除，需要确认删除完成时使用 `invalidate_async`
     pub fn invalidate(user_id: i64) {
         SESSION_CACHE_MANAGER.remove(&user_id);
+        if let Ok(handle) = tokio::runtime::Handle::try_current() {
+            handle.spawn(async move { session_store::store().remove(user_id).await });
+        }
+    }
+
+    /// 使指定用户的session失效，并等待 `SessionStore` 中的数据删除完成
+    pub async fn invalidate_async(user_id: i64) -> anyhow::Result<()> {
+        SESSION_CACHE_MANAGER.remove(&user_id);
+        session_store::store().remove(user_id).await
     }
 
     /// 内部方法:清理过期的session缓存（已废弃，使用后台清理任务替代）
@@ -606,9 +993,12 @@ impl SessionCache {
 
     /// 内部方法:提供对session manager的访问，用于后台清理任务
     /// 注意：这是一个完全私有的内部方法，仅供服务器清理任务使用
-    pub(crate) fn cleanup_expired_sessions() {
+    pub(crate) async fn cleanup_expired_sessions() {
         let now = Instant::now();
         SESSION_CACHE_MANAGER.retain(|_, (_, expires_at)| *expires_at > now);
+        if let Err(e) = session_store::store().expire().await {
+            eprintln!("[Session] Failed to expire stored sessions: {e}");
+        }
     }
 }
 
@@ -623,6 +1013,16 @@ pub enum PreflightResult {
     PreconditionFailed,
 }
 
+fn header_lines<'a>(
+    headers: &'a HashMap<Cow<'static, str>, Cow<'static, str>>,
+    repeated: &'a [(Cow<'static, str>, Cow<'static, str>)],
+) -> impl Iterator<Item = (&'a str, &'a str)> {
+    headers
+        .iter()
+        .chain(repeated.iter().map(|(key, value)| (key, value)))
+        .map(|(key, value)| (key.as_ref(), value.as_ref()))
+}
+
 fn parse_declared_trailer_names(raw: Option<&str>) -> HashSet<String> {
     raw.map(|value| {
         value
@@ -773,6 +1173,23 @@ pub enum HttpHandler {
     Sync(SyncHttpHandler),
 }
 
+/// 处理函数的访问要求，由 `#[potato::require_role]`、`#[potato::require_scope]` 标注生成
+///
+/// 同一项内的多个值满足其一即可，多项之间须全部满足
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AuthRequirement {
+    Role(&'static [&'static str]),
+    Scope(&'static [&'static str]),
+}
+
+impl AuthRequirement {
+    pub const fn names(&self) -> &'static [&'static str] {
+        match self {
+            AuthRequirement::Role(names) | AuthRequirement::Scope(names) => names,
+        }
+    }
+}
+
 pub struct RequestHandlerFlagDoc {
     pub show: bool,
     pub auth: bool,
@@ -780,6 +1197,8 @@ pub struct RequestHandlerFlagDoc {
     pub desp: &'static str,
     pub args: &'static str,
     pub tag: &'static str, // Controller 名称，用于 Swagger 分组
+    pub requirements: &'static [AuthRequirement],
+    pub auth_schemes: &'static [&'static str], // `#[potato::auth_scheme]` 中的认证方式名
 }
 
 impl RequestHandlerFlagDoc {
@@ -798,8 +1217,20 @@ impl RequestHandlerFlagDoc {
             desp,
             args,
             tag,
+            requirements: &[],
+            auth_schemes: &[],
         }
     }
+
+    pub const fn with_requirements(mut self, requirements: &'static [AuthRequirement]) -> Self {
+        self.requirements = requirements;
+        self
+    }
+
+    pub const fn with_auth_schemes(mut self, auth_schemes: &'static [&'static str]) -> Self {
+        self.auth_schemes = auth_schemes;
+        self
+    }
 }
 
 pub struct RequestHandlerFlag {
@@ -917,7 +1348,24 @@ pub struct Websocket {
 
 impl Websocket {
     pub async fn connect(url: &str, args: Vec<Headers>) -> anyhow::Result<Self> {
-        let mut sess = Session::new();
+        Self::connect_with_session(Session::new(), url, args).await
+    }
+
+    /// 使用自定义客户端 TLS 配置连接 `wss://` 地址
+    #[cfg(feature = "tls")]
+    pub async fn connect_with_tls(
+        url: &str,
+        args: Vec<Headers>,
+        tls_config: TlsClientConfig,
+    ) -> anyhow::Result<Self> {
+        Self::connect_with_session(Session::with_tls_config(tls_config), url, args).await
+    }
+
+    async fn connect_with_session(
+        mut sess: Session,
+        url: &str,
+        args: Vec<Headers>,
+    ) -> anyhow::Result<Self> {
         let mut req = sess.new_request(HttpMethod::GET, url).await?;
         for arg in args.into_iter() {
             req.apply_header(arg);
@@ -1365,7 +1813,7 @@ impl HttpRequest {
                 .unwrap_or("localhost")
                 .into(),
         );
-        let use_ssl = uri.scheme() == Some(&Scheme::HTTPS);
+        let use_ssl = uri.scheme() == Some(&Scheme::HTTPS) || uri.scheme_str() == Some("wss");
         let port = uri.port_u16().unwrap_or(if use_ssl { 443 } else { 80 });
         Ok((req, use_ssl, port))
     }
@@ -1690,14 +2138,46 @@ impl HttpRequest {
         Ok(Websocket { stream })
     }
 
+    /// 获取客户端地址，配置了 `use_trusted_proxies` 时采信可信代理转发的地址
     pub async fn get_client_addr(&self) -> anyhow::Result<SocketAddr> {
-        if let Some(addr) = self.client_addr {
-            return Ok(addr);
-        }
-        match self.get_ext::<SocketAddr>() {
-            Some(addr) => Ok(*addr),
-            None => Err(anyhow!("no addr info")),
-        }
+        let addr = match self.client_addr {
+            Some(addr) => addr,
+            None => match self.get_ext::<SocketAddr>() {
+                Some(addr) => *addr,
+                None => return Err(anyhow!("no addr info")),
+            },
+        };
+        Ok(match self.get_ext::<server::TrustedProxies>() {
+            Some(trusted) => trusted.resolve(self, addr),
+            None => addr,
+        })
+    }
+
+    /// 获取本次请求的 CSP nonce，未启用 `use_security_headers` 时为 `None`
+    pub fn csp_nonce(&self) -> Option<Arc<server::CspNonce>> {
+        self.get_ext::<server::CspNonce>()
+    }
+
+    /// 获取本次请求的 CSRF 令牌，未启用 `use_csrf` 时为 `None`
+    pub fn csrf_token(&self) -> Option<Arc<server::CsrfToken>> {
+        self.get_ext::<server::CsrfToken>()
+    }
+
+    /// 获取 `use_oidc` 校验通过的令牌声明，未启用或请求未携带令牌时为 `None`
+    pub fn oidc_claims(&self) -> Option<Arc<server::OidcClaims>> {
+        self.get_ext::<server::OidcClaims>()
+    }
+
+    /// 获取通过 `use_auth` 或 `#[potato::auth_scheme]` 认证的调用方，未认证时为 `None`
+    #[cfg(feature = "auth")]
+    pub fn auth_user(&self) -> Option<Arc<server::AuthUser>> {
+        self.get_ext::<server::AuthUser>()
+    }
+
+    /// 获取 mTLS 握手中已校验的客户端证书，未启用 `set_client_auth` 或客户端未出示证书时为 `None`
+    #[cfg(feature = "tls")]
+    pub fn client_cert(&self) -> Option<Arc<server::ClientCert>> {
+        self.get_ext::<server::ClientCert>()
     }
 
     async fn from_stream_impl(
@@ -2219,6 +2699,8 @@ pub struct HttpResponse {
     pub version: String,
     pub http_code: u16,
     pub headers: HashMap<Cow<'static, str>, Cow<'static, str>>,
+    /// 可重复的响应头（如 `Set-Cookie`），每项单独发送一行
+    pub repeated_headers: Vec<(Cow<'static, str>, Cow<'static, str>)>,
     pub trailers: HashMap<Cow<'static, str>, Cow<'static, str>>,
     pub body: HttpResponseBody,
 }
@@ -2230,6 +2712,7 @@ impl Clone for HttpResponse {
             version: self.version.clone(),
             http_code: self.http_code,
             headers: self.headers.clone(),
+            repeated_headers: self.repeated_headers.clone(),
             trailers: self.trailers.clone(),
             body: match &self.body {
                 HttpResponseBody::Data(data) => HttpResponseBody::Data(data.clone()),
@@ -2247,6 +2730,7 @@ macro_rules! make_resp_by_text {
                 version: "HTTP/1.1".into(),
                 http_code: 200,
                 headers: Self::default_headers($cnt_type),
+                repeated_headers: vec![],
                 trailers: HashMap::with_capacity(4),
                 body: HttpResponseBody::Data(body.as_bytes().to_vec()),
             }
@@ -2261,6 +2745,7 @@ macro_rules! make_resp_by_binary {
                 version: "HTTP/1.1".into(),
                 http_code: 200,
                 headers: Self::default_headers($cnt_type),
+                repeated_headers: vec![],
                 trailers: HashMap::with_capacity(4),
                 body: HttpResponseBody::Data(body.to_vec()),
             }
@@ -2316,6 +2801,7 @@ impl HttpResponse {
             version: "".into(),
             http_code: 0,
             headers: HashMap::with_capacity(16),
+            repeated_headers: vec![],
             trailers: HashMap::with_capacity(4),
             body: HttpResponseBody::Data(vec![]),
         }
@@ -2325,6 +2811,30 @@ impl HttpResponse {
         self.headers.insert(key, value);
     }
 
+    /// 追加可重复的响应头（如 `Set-Cookie`），同名头已存在时不覆盖，存入 `repeated_headers` 单独发送一行
+    pub fn append_header(&mut self, key: Cow<'static, str>, value: Cow<'static, str>) {
+        if self.headers.keys().any(|k| k.eq_ignore_ascii_case(&key)) {
+            self.repeated_headers.push((key, value));
+        } else {
+            self.headers.insert(key, value);
+        }
+    }
+
+    /// 追加 `Set-Cookie` 响应头，值为空时删除同名cookie
+    pub fn set_cookie(&mut self, cookie: CookieBuilder) {
+        let cookie_str = if cookie.value.is_empty() {
+            cookie.to_delete_cookie_string()
+        } else {
+            cookie.to_set_cookie_string()
+        };
+        self.append_header(Cow::Borrowed("Set-Cookie"), Cow::Owned(cookie_str));
+    }
+
+    /// 逐行列出响应头，包括 `append_header` 追加的各个值
+    pub fn header_lines(&self) -> impl Iterator<Item = (&str, &str)> {
+        header_lines(&self.headers, &self.repeated_headers)
+    }
+
     pub fn not_found() -> Self {
         let mut ret = Self::html("404 not found");
         ret.http_code = 404;
@@ -2358,6 +2868,7 @@ impl HttpResponse {
                 ("Connection".into(), "keep-alive".into()),
             ]
             .into(),
+            repeated_headers: vec![],
             trailers: HashMap::with_capacity(4),
             body: HttpResponseBody::Stream(rx),
         }
@@ -2469,6 +2980,7 @@ impl HttpResponse {
                 ("Sec-WebSocket-Accept".into(), ws_accept.into()),
             ]
             .into(),
+            repeated_headers: vec![],
             trailers: HashMap::with_capacity(4),
             body: HttpResponseBody::Data(vec![]),
         }
@@ -2625,7 +3137,7 @@ impl HttpResponse {
                     self.version,
                     self.http_code
                 ));
-                for (key, value) in self.headers.iter() {
+                for (key, value) in self.header_lines() {
                     if key == "Content-Length" || key.eq_ignore_ascii_case("Transfer-Encoding") {
                         continue;
                     }
@@ -2724,8 +3236,11 @@ impl HttpResponse {
                         ret.push_str(&ssformat!(512, "Content-Type: {content_type}\r\n"));
                         ret.push_str(&ssformat!(512, "Pragma: {pragma}\r\n"));
                         ret.push_str(&ssformat!(512, "Cache-Control: {cache_control}\r\n"));
+                        for (key, value) in self.repeated_headers.iter() {
+                            ret.push_str(&ssformat!(512, "{key}: {value}\r\n"));
+                        }
                     } else {
-                        for (key, value) in self.headers.iter() {
+                        for (key, value) in header_lines(&self.headers, &self.repeated_headers) {
                             if key == "Content-Length"
                                 || key.eq_ignore_ascii_case("Transfer-Encoding")
                             {
@@ -2735,7 +3250,7 @@ impl HttpResponse {
                         }
                     }
                 } else {
-                    for (key, value) in self.headers.iter() {
+                    for (key, value) in header_lines(&self.headers, &self.repeated_headers) {
                         if key == "Content-Length" || key.eq_ignore_ascii_case("Transfer-Encoding")
                         {
                             continue;
@@ -2769,7 +3284,7 @@ impl HttpResponse {
                     self.version,
                     self.http_code
                 ));
-                for (key, value) in self.headers.iter() {
+                for (key, value) in header_lines(&self.headers, &self.repeated_headers) {
                     if key == "Content-Length"
                         || (suppress_body && key.eq_ignore_ascii_case("Transfer-Encoding"))
                     {
@@ -2928,10 +3443,18 @@ impl HttpResponse {
                 }
                 continue;
             }
-            req.headers.insert(
-                h.name.http_std_case().into(),
-                header_value.to_string().into(),
-            );
+            let name: Cow<'static, str> = h.name.http_std_case().into();
+            // 可重复且值中可能含逗号的响应头，与 append_header 一致，后续的值存入 repeated_headers
+            if req.headers.contains_key(&name)
+                && ["Set-Cookie", "WWW-Authenticate"]
+                    .iter()
+                    .any(|repeated| name.eq_ignore_ascii_case(repeated))
+            {
+                req.repeated_headers
+                    .push((name, header_value.to_string().into()));
+                continue;
+            }
+            req.headers.insert(name, header_value.to_string().into());
         }
         if transfer_encoding_seen && content_length_seen.is_some() {
             Err(anyhow!(
@@ -0,0 +1,479 @@
+use crate::{HttpRequest, HttpResponse, ServerConfig};
+use base64::Engine;
+use md5::Md5;
+use rand::RngCore;
+use ring::hmac;
+use sha1::Sha1;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::sync::{Arc, LazyLock};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// 通过 `use_auth` 或 `#[potato::auth_scheme]` 认证的调用方，处理函数通过 `req.auth_user()` 读取
+#[derive(Debug, Clone)]
+pub struct AuthUser {
+    pub scheme: &'static str, // `Basic`、`Digest` 或 `ApiKey`
+    pub name: String,         // 用户名或 API Key 的名称
+}
+
+/// 认证方式，通过 `use_auth` 作用于之后的所有路由，或经 `ServerConfig::set_auth_scheme`
+/// 命名后由 `#[potato::auth_scheme("name")]` 作用于单个处理函数
+pub enum AuthScheme {
+    Basic(BasicAuth),
+    Digest(DigestAuth),
+    ApiKey(ApiKeyAuth),
+}
+
+impl From<BasicAuth> for AuthScheme {
+    fn from(value: BasicAuth) -> Self {
+        Self::Basic(value)
+    }
+}
+
+impl From<DigestAuth> for AuthScheme {
+    fn from(value: DigestAuth) -> Self {
+        Self::Digest(value)
+    }
+}
+
+impl From<ApiKeyAuth> for AuthScheme {
+    fn from(value: ApiKeyAuth) -> Self {
+        Self::ApiKey(value)
+    }
+}
+
+enum Verdict {
+    Granted(AuthUser),
+    Stale, // 凭据正确但 Digest nonce 已过期，客户端应使用新 nonce 重试
+    Denied,
+}
+
+impl AuthScheme {
+    async fn verify(&self, req: &HttpRequest) -> Verdict {
+        match self {
+            AuthScheme::Basic(auth) => auth.verify(req).await,
+            AuthScheme::Digest(auth) => auth.verify(req),
+            AuthScheme::ApiKey(auth) => auth.verify(req),
+        }
+    }
+
+    fn challenge(&self, stale: bool) -> String {
+        match self {
+            AuthScheme::Basic(auth) => {
+                format!("Basic realm=\"{}\", charset=\"UTF-8\"", auth.realm)
+            }
+            AuthScheme::Digest(auth) => auth.challenge(stale),
+            AuthScheme::ApiKey(auth) => format!("ApiKey header=\"{}\"", auth.header),
+        }
+    }
+
+    /// 全局认证写入文档时使用的名称
+    #[cfg(feature = "openapi")]
+    pub(crate) fn openapi_name(&self) -> String {
+        match self {
+            AuthScheme::Basic(_) => "basicAuth".to_string(),
+            AuthScheme::Digest(_) => "digestAuth".to_string(),
+            AuthScheme::ApiKey(auth) => format!("apiKeyAuth_{}", auth.header),
+        }
+    }
+
+    /// OpenAPI `components.securitySchemes` 中的描述
+    #[cfg(feature = "openapi")]
+    pub(crate) fn openapi_scheme(&self) -> serde_json::Value {
+        match self {
+            AuthScheme::Basic(_) => serde_json::json!({ "type": "http", "scheme": "basic" }),
+            AuthScheme::Digest(_) => serde_json::json!({ "type": "http", "scheme": "digest" }),
+            AuthScheme::ApiKey(auth) => {
+                serde_json::json!({ "type": "apiKey", "in": "header", "name": auth.header })
+            }
+        }
+    }
+}
+
+/// 依次尝试各认证方式，全部失败时返回附带各自 `WWW-Authenticate` 质询的 401 响应
+pub(crate) async fn authenticate(
+    schemes: &[Arc<AuthScheme>],
+    req: &HttpRequest,
+) -> Result<AuthUser, HttpResponse> {
+    let mut stale = false;
+    for scheme in schemes.iter() {
+        match scheme.verify(req).await {
+            Verdict::Granted(user) => return Ok(user),
+            Verdict::Stale => stale = true,
+            Verdict::Denied => {}
+        }
+    }
+    let mut res = HttpResponse::text("Unauthorized");
+    res.http_code = 401;
+    for scheme in schemes.iter() {
+        res.append_header("WWW-Authenticate".into(), scheme.challenge(stale).into());
+    }
+    Err(res)
+}
+
+/// `#[potato::auth_scheme(...)]` 生成的校验代码调用，满足任一命名的认证方式即可
+#[doc(hidden)]
+pub async fn check_auth_schemes(names: &[&str], req: &mut HttpRequest) -> Option<HttpResponse> {
+    let mut schemes = Vec::with_capacity(names.len());
+    for name in names.iter() {
+        match ServerConfig::get_auth_scheme(name) {
+            Some(scheme) => schemes.push(scheme),
+            None => {
+                eprintln!("[Auth] auth scheme `{name}` is not registered");
+                return Some(HttpResponse::error("auth scheme is not configured"));
+            }
+        }
+    }
+    match authenticate(&schemes, req).await {
+        Ok(user) => {
+            req.add_ext(Arc::new(user));
+            None
+        }
+        Err(res) => Some(res),
+    }
+}
+
+/// HTTP Basic 认证，口令以 htpasswd 格式的哈希保存，支持 bcrypt（`$2y$`）、argon2（`$argon2id$`）
+/// 与 `{SHA}`
+#[derive(Clone)]
+pub struct BasicAuth {
+    pub realm: String,
+    users: HashMap<String, String>,
+}
+
+impl BasicAuth {
+    pub fn new(realm: impl Into<String>) -> Self {
+        Self {
+            realm: realm.into(),
+            users: HashMap::new(),
+        }
+    }
+
+    /// 添加用户，`hash` 为 `htpasswd -B` 等工具生成的口令哈希
+    pub fn with_user(mut self, name: impl Into<String>, hash: impl Into<String>) -> Self {
+        self.users.insert(name.into(), hash.into());
+        self
+    }
+
+    /// 从 htpasswd 文件加载用户
+    pub fn from_htpasswd(realm: impl Into<String>, path: &str) -> anyhow::Result<Self> {
+        Self::new(realm).with_htpasswd(&std::fs::read_to_string(path)?)
+    }
+
+    /// 解析 htpasswd 内容（每行 `用户名:哈希`），不支持的哈希格式（如 `$apr1$`）返回错误
+    pub fn with_htpasswd(mut self, content: &str) -> anyhow::Result<Self> {
+        for line in content.lines().map(str::trim) {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let Some((name, hash)) = line.split_once(':') else {
+                return Err(anyhow::anyhow!("invalid htpasswd line: {line}"));
+            };
+            if !["$2a$", "$2b$", "$2y$", "$argon2", "{SHA}"]
+                .iter()
+                .any(|prefix| hash.starts_with(prefix))
+            {
+                return Err(anyhow::anyhow!("unsupported htpasswd hash for user {name}"));
+            }
+            self.users.insert(name.to_string(), hash.to_string());
+        }
+        Ok(self)
+    }
+
+    async fn verify(&self, req: &HttpRequest) -> Verdict {
+        let Some((name, password)) = req
+            .get_header("Authorization")
+            .and_then(|value| strip_scheme(value, "Basic"))
+            .and_then(|cred| base64::engine::general_purpose::STANDARD.decode(cred).ok())
+            .and_then(|cred| String::from_utf8(cred).ok())
+            .and_then(|cred| {
+                let (name, password) = cred.split_once(':')?;
+                Some((name.to_string(), password.to_string()))
+            })
+        else {
+            return Verdict::Denied;
+        };
+        let Some(hash) = self.users.get(&name).cloned() else {
+            return Verdict::Denied;
+        };
+        // bcrypt 与 argon2 计算耗时，避免阻塞运行时
+        let ok = tokio::task::spawn_blocking(move || verify_password(&password, &hash))
+            .await
+            .unwrap_or(false);
+        match ok {
+            true => Verdict::Granted(AuthUser {
+                scheme: "Basic",
+                name,
+            }),
+            false => Verdict::Denied,
+        }
+    }
+}
+
+fn verify_password(password: &str, hash: &str) -> bool {
+    if hash.starts_with("$2") {
+        bcrypt::verify(password, hash).unwrap_or(false)
+    } else if hash.starts_with("$argon2") {
+        use argon2::PasswordVerifier;
+        argon2::PasswordHash::new(hash).is_ok_and(|hash| {
+            argon2::Argon2::default()
+                .verify_password(password.as_bytes(), &hash)
+                .is_ok()
+        })
+    } else if let Some(expected) = hash.strip_prefix("{SHA}") {
+        let actual = base64::engine::general_purpose::STANDARD.encode(Sha1::digest(password));
+        secure_eq(actual.as_bytes(), expected.as_bytes())
+    } else {
+        false
+    }
+}
+
+/// 以 HMAC 校验比较两段数据，耗时与内容无关
+fn secure_eq(actual: &[u8], expected: &[u8]) -> bool {
+    static KEY: LazyLock<hmac::Key> = LazyLock::new(|| {
+        let mut secret = [0u8; 32];
+        rand::thread_rng().fill_bytes(&mut secret);
+        hmac::Key::new(hmac::HMAC_SHA256, &secret)
+    });
+    hmac::verify(&KEY, actual, hmac::sign(&KEY, expected).as_ref()).is_ok()
+}
+
+/// HTTP Digest 认证（MD5，`qop=auth`），供不支持其他方式的旧设备使用
+///
+/// 服务端保存 `HA1 = MD5(用户名:realm:口令)`；nonce 带有签名的时间戳，无需服务端状态，
+/// 过期后返回 `stale=true` 让客户端静默重试
+#[derive(Clone)]
+pub struct DigestAuth {
+    pub realm: String,
+    pub nonce_ttl: Duration, // nonce 有效期，默认 5 分钟
+    users: HashMap<String, String>,
+    key: hmac::Key,
+}
+
+impl DigestAuth {
+    pub fn new(realm: impl Into<String>) -> Self {
+        let mut secret = [0u8; 32];
+        rand::thread_rng().fill_bytes(&mut secret);
+        Self {
+            realm: realm.into(),
+            nonce_ttl: Duration::from_secs(300),
+            users: HashMap::new(),
+            key: hmac::Key::new(hmac::HMAC_SHA256, &secret),
+        }
+    }
+
+    pub fn with_user(self, name: impl Into<String>, password: &str) -> Self {
+        let name = name.into();
+        let ha1 = md5_hex(&format!("{name}:{}:{password}", self.realm));
+        self.with_ha1(name, ha1)
+    }
+
+    /// 添加用户，`ha1` 为 htdigest 文件中的十六进制摘要
+    pub fn with_ha1(mut self, name: impl Into<String>, ha1: impl Into<String>) -> Self {
+        self.users
+            .insert(name.into(), ha1.into().to_ascii_lowercase());
+        self
+    }
+
+    /// 从 htdigest 文件（每行 `用户名:realm:HA1`）加载与 `realm` 相同的用户
+    pub fn from_htdigest(realm: impl Into<String>, path: &str) -> anyhow::Result<Self> {
+        let mut auth = Self::new(realm);
+        for line in std::fs::read_to_string(path)?.lines().map(str::trim) {
+            let mut parts = line.splitn(3, ':');
+            if let (Some(name), Some(realm), Some(ha1)) = (parts.next(), parts.next(), parts.next())
+            {
+                if realm == auth.realm {
+                    auth = auth.with_ha1(name, ha1);
+                }
+            }
+        }
+        Ok(auth)
+    }
+
+    pub fn with_nonce_ttl(mut self, ttl: Duration) -> Self {
+        self.nonce_ttl = ttl;
+        self
+    }
+
+    fn challenge(&self, stale: bool) -> String {
+        let stale = if stale { ", stale=true" } else { "" };
+        format!(
+            "Digest realm=\"{}\", qop=\"auth\", algorithm=MD5, nonce=\"{}\"{stale}",
+            self.realm,
+            self.issue_nonce()
+        )
+    }
+
+    /// nonce 为 `时间戳.签名`
+    fn issue_nonce(&self) -> String {
+        let ts = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs()
+            .to_string();
+        let sig = base64::engine::general_purpose::URL_SAFE_NO_PAD
+            .encode(hmac::sign(&self.key, ts.as_bytes()));
+        format!("{ts}.{sig}")
+    }
+
+    /// 签名有效时返回 nonce 是否仍在有效期内
+    fn check_nonce(&self, nonce: &str) -> Option<bool> {
+        let (ts, sig) = nonce.split_once('.')?;
+        let sig = base64::engine::general_purpose::URL_SAFE_NO_PAD
+            .decode(sig)
+            .ok()?;
+        hmac::verify(&self.key, ts.as_bytes(), &sig).ok()?;
+        let issued = UNIX_EPOCH + Duration::from_secs(ts.parse().ok()?);
+        Some(issued + self.nonce_ttl >= SystemTime::now())
+    }
+
+    fn verify(&self, req: &HttpRequest) -> Verdict {
+        let Some(params) = req
+            .get_header("Authorization")
+            .and_then(|value| strip_scheme(value, "Digest"))
+            .map(parse_auth_params)
+        else {
+            return Verdict::Denied;
+        };
+        let param = |key: &str| params.get(key).map(String::as_str).unwrap_or_default();
+        let (name, uri, nonce) = (param("username"), param("uri"), param("nonce"));
+        let Some(ha1) = self.users.get(name) else {
+            return Verdict::Denied;
+        };
+        if param("realm") != self.realm || uri.split('?').next() != Some(&req.url_path[..]) {
+            return Verdict::Denied;
+        }
+        if !matches!(param("algorithm"), "" | "MD5") {
+            return Verdict::Denied;
+        }
+        let Some(fresh) = self.check_nonce(nonce) else {
+            return Verdict::Denied;
+        };
+        let ha2 = md5_hex(&format!("{}:{uri}", req.method));
+        let expected = match param("qop") {
+            "auth" => md5_hex(&format!(
+                "{ha1}:{nonce}:{}:{}:auth:{ha2}",
+                param("nc"),
+                param("cnonce")
+            )),
+            // RFC 2069 兼容模式
+            "" => md5_hex(&format!("{ha1}:{nonce}:{ha2}")),
+            _ => return Verdict::Denied,
+        };
+        if !secure_eq(
+            expected.as_bytes(),
+            param("response").to_ascii_lowercase().as_bytes(),
+        ) {
+            return Verdict::Denied;
+        }
+        match fresh {
+            true => Verdict::Granted(AuthUser {
+                scheme: "Digest",
+                name: name.to_string(),
+            }),
+            false => Verdict::Stale,
+        }
+    }
+}
+
+fn md5_hex(data: &str) -> String {
+    Md5::digest(data.as_bytes())
+        .iter()
+        .map(|b| format!("{b:02x}"))
+        .collect()
+}
+
+/// 解析 `key=value, key="quoted, value"` 形式的认证参数
+fn parse_auth_params(input: &str) -> HashMap<String, String> {
+    let mut params = HashMap::new();
+    let mut rest = input.trim();
+    while let Some((key, after)) = rest.split_once('=') {
+        let key = key
+            .trim()
+            .trim_start_matches(',')
+            .trim()
+            .to_ascii_lowercase();
+        let after = after.trim_start();
+        let (value, remain) = match after.strip_prefix('"') {
+            Some(quoted) => {
+                let mut value = String::new();
+                let mut chars = quoted.char_indices();
+                let mut end = quoted.len();
+                while let Some((idx, ch)) = chars.next() {
+                    match ch {
+                        '\\' => value.extend(chars.next().map(|(_, ch)| ch)),
+                        '"' => {
+                            end = idx + 1;
+                            break;
+                        }
+                        _ => value.push(ch),
+                    }
+                }
+                (value, &quoted[end..])
+            }
+            None => match after.split_once(',') {
+                Some((value, remain)) => (value.trim().to_string(), remain),
+                None => (after.trim().to_string(), ""),
+            },
+        };
+        params.insert(key, value);
+        rest = remain.trim_start().trim_start_matches(',');
+    }
+    params
+}
+
+/// API Key 认证，从指定请求头读取密钥，服务端只保存其 SHA-256 摘要
+#[derive(Clone)]
+pub struct ApiKeyAuth {
+    pub header: String,
+    keys: HashMap<String, String>, // 摘要 -> 名称
+}
+
+impl ApiKeyAuth {
+    /// 如 `ApiKeyAuth::new("X-API-Key")`
+    pub fn new(header: impl Into<String>) -> Self {
+        Self {
+            header: header.into(),
+            keys: HashMap::new(),
+        }
+    }
+
+    /// 添加密钥，`hash` 为 `ApiKeyAuth::hash_key` 生成的十六进制摘要
+    pub fn with_key_hash(mut self, name: impl Into<String>, hash: impl Into<String>) -> Self {
+        self.keys
+            .insert(hash.into().to_ascii_lowercase(), name.into());
+        self
+    }
+
+    /// 添加明文密钥，仅保存其摘要
+    pub fn with_key(self, name: impl Into<String>, key: &str) -> Self {
+        self.with_key_hash(name, Self::hash_key(key))
+    }
+
+    /// 计算用于保存的密钥摘要
+    pub fn hash_key(key: &str) -> String {
+        Sha256::digest(key.as_bytes())
+            .iter()
+            .map(|b| format!("{b:02x}"))
+            .collect()
+    }
+
+    fn verify(&self, req: &HttpRequest) -> Verdict {
+        let Some(key) = req.get_header(&self.header) else {
+            return Verdict::Denied;
+        };
+        match self.keys.get(&Self::hash_key(key.trim())) {
+            Some(name) => Verdict::Granted(AuthUser {
+                scheme: "ApiKey",
+                name: name.clone(),
+            }),
+            None => Verdict::Denied,
+        }
+    }
+}
+
+/// 去掉 `Authorization` 头的方案名前缀，方案名不区分大小写
+fn strip_scheme<'a>(value: &'a str, scheme: &str) -> Option<&'a str> {
+    let (name, rest) = value.trim().split_once(' ')?;
+    name.eq_ignore_ascii_case(scheme).then(|| rest.trim())
+}
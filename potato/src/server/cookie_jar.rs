@@ -0,0 +1,139 @@
+use base64::Engine;
+use rand::RngCore;
+use ring::aead::{Aad, LessSafeKey, Nonce, UnboundKey, AES_256_GCM, NONCE_LEN};
+use ring::{hkdf, hmac};
+
+/// 由同一密钥经 HKDF 派生签名与加密子密钥
+#[derive(Clone)]
+struct CookieKey {
+    prk: hkdf::Prk,
+}
+
+impl CookieKey {
+    fn derive(secret: &[u8]) -> Self {
+        Self {
+            prk: hkdf::Salt::new(hkdf::HKDF_SHA256, b"potato-cookie").extract(secret),
+        }
+    }
+
+    fn sign_key(&self) -> anyhow::Result<hmac::Key> {
+        Ok(self
+            .prk
+            .expand(&[b"potato-cookie-sign"], hmac::HMAC_SHA256)?
+            .into())
+    }
+
+    fn aead(&self) -> anyhow::Result<LessSafeKey> {
+        let key: UnboundKey = self
+            .prk
+            .expand(&[b"potato-cookie-encrypt"], &AES_256_GCM)?
+            .into();
+        Ok(LessSafeKey::new(key))
+    }
+}
+
+/// 签名与加密 Cookie 使用的密钥环
+///
+/// 第一个密钥用于签发，`with_previous` 添加的旧密钥仅用于校验与解密，轮换密钥时已下发的
+/// Cookie 在过期前仍然有效。Cookie 名称参与签名与加密，值不能挪用到其他 Cookie
+#[derive(Clone)]
+pub struct CookieKeys {
+    keys: Vec<CookieKey>,
+}
+
+impl std::fmt::Debug for CookieKeys {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CookieKeys")
+            .field("keys", &self.keys.len())
+            .finish()
+    }
+}
+
+impl CookieKeys {
+    /// 使用指定密钥签发，密钥应为至少 32 字节的随机数据
+    pub fn new(secret: impl AsRef<[u8]>) -> Self {
+        Self {
+            keys: vec![CookieKey::derive(secret.as_ref())],
+        }
+    }
+
+    /// 随机生成密钥，重启后之前下发的 Cookie 全部失效
+    pub fn generate() -> Self {
+        let mut secret = [0u8; 32];
+        rand::thread_rng().fill_bytes(&mut secret);
+        Self::new(secret)
+    }
+
+    /// 添加轮换前的旧密钥
+    pub fn with_previous(mut self, secret: impl AsRef<[u8]>) -> Self {
+        self.keys.push(CookieKey::derive(secret.as_ref()));
+        self
+    }
+
+    /// 签名 Cookie 值：`值.签名`，值本身仍以明文传输
+    pub fn sign(&self, name: &str, value: &str) -> anyhow::Result<String> {
+        let tag = hmac::sign(
+            &self.keys[0].sign_key()?,
+            format!("{name}={value}").as_bytes(),
+        );
+        let sig = base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(tag);
+        Ok(format!("{value}.{sig}"))
+    }
+
+    /// 校验签名 Cookie，返回原始值
+    pub fn verify(&self, name: &str, signed: &str) -> Option<String> {
+        let (value, sig) = signed.rsplit_once('.')?;
+        let sig = base64::engine::general_purpose::URL_SAFE_NO_PAD
+            .decode(sig)
+            .ok()?;
+        let data = format!("{name}={value}");
+        self.keys
+            .iter()
+            .filter_map(|key| key.sign_key().ok())
+            .any(|key| hmac::verify(&key, data.as_bytes(), &sig).is_ok())
+            .then(|| value.to_string())
+    }
+
+    /// 加密 Cookie 值（AES-256-GCM），结果为 base64url 编码的 `nonce || 密文`
+    pub fn encrypt(&self, name: &str, value: &str) -> anyhow::Result<String> {
+        let mut nonce = [0u8; NONCE_LEN];
+        rand::thread_rng().fill_bytes(&mut nonce);
+        let mut sealed = value.as_bytes().to_vec();
+        self.keys[0].aead()?.seal_in_place_append_tag(
+            Nonce::assume_unique_for_key(nonce),
+            Aad::from(name.as_bytes()),
+            &mut sealed,
+        )?;
+        let mut data = nonce.to_vec();
+        data.extend_from_slice(&sealed);
+        Ok(base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(data))
+    }
+
+    /// 解密 Cookie，返回原始值
+    pub fn decrypt(&self, name: &str, sealed: &str) -> Option<String> {
+        self.decrypt_with_rotation(name, sealed)
+            .map(|(value, _)| value)
+    }
+
+    /// 解密，同时返回是否由旧密钥加密
+    pub(crate) fn decrypt_with_rotation(&self, name: &str, sealed: &str) -> Option<(String, bool)> {
+        let data = base64::engine::general_purpose::URL_SAFE_NO_PAD
+            .decode(sealed)
+            .ok()?;
+        if data.len() < NONCE_LEN {
+            return None;
+        }
+        let (nonce, ciphertext) = data.split_at(NONCE_LEN);
+        self.keys.iter().enumerate().find_map(|(index, key)| {
+            let nonce = Nonce::try_assume_unique_for_key(nonce).ok()?;
+            let mut buf = ciphertext.to_vec();
+            let plain = key
+                .aead()
+                .ok()?
+                .open_in_place(nonce, Aad::from(name.as_bytes()), &mut buf)
+                .ok()?;
+            let value = String::from_utf8(plain.to_vec()).ok()?;
+            Some((value, index > 0))
+        })
+    }
+}
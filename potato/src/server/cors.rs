@@ -0,0 +1,249 @@
+use super::HANDLERS;
+use crate::{HttpMethod, HttpRequest, HttpResponse};
+use std::borrow::Cow;
+use std::collections::HashSet;
+use std::time::Duration;
+
+/// CORS配置
+///
+/// 单个路由通过 `#[potato::cors(...)]` 标注使用，全局通过 `use_cors` 启用
+#[derive(Debug, Clone)]
+pub struct CorsConfig {
+    pub origin: Option<String>,         // Access-Control-Allow-Origin
+    pub methods: Option<String>,        // Access-Control-Allow-Methods
+    pub headers: Option<String>,        // Access-Control-Allow-Headers
+    pub max_age: Option<String>,        // Access-Control-Max-Age
+    pub credentials: bool,              // Access-Control-Allow-Credentials
+    pub expose_headers: Option<String>, // Access-Control-Expose-Headers
+}
+
+impl CorsConfig {
+    /// 创建最小限制默认配置
+    pub fn default_minimal() -> Self {
+        Self {
+            origin: Some("*".to_string()),
+            methods: None, // 自动计算
+            headers: Some("*".to_string()),
+            max_age: Some("86400".to_string()),
+            credentials: false,
+            expose_headers: None,
+        }
+    }
+
+    /// 添加允许的来源，`*` 匹配不含 `/` 与 `:` 的任意字符，
+    /// 如 `https://*.example.com`、`http://localhost:*`
+    ///
+    /// 来源以逗号分隔追加到 `origin`，首次添加时取代默认的 `*`
+    pub fn with_origin(mut self, pattern: impl Into<String>) -> Self {
+        let pattern = pattern.into();
+        self.origin = match self.origin.take() {
+            Some(origin) if origin.trim() != "*" => Some(format!("{origin},{pattern}")),
+            _ => Some(pattern),
+        };
+        self
+    }
+
+    /// 允许的方法，如 `"GET,POST"`；未设置时按路由已注册的方法计算
+    pub fn with_methods(mut self, methods: impl Into<String>) -> Self {
+        self.methods = Some(methods.into());
+        self
+    }
+
+    /// 允许的请求头，如 `"Content-Type,Authorization"`
+    pub fn with_headers(mut self, headers: impl Into<String>) -> Self {
+        self.headers = Some(headers.into());
+        self
+    }
+
+    /// 允许浏览器读取的响应头
+    pub fn with_expose_headers(mut self, headers: impl Into<String>) -> Self {
+        self.expose_headers = Some(headers.into());
+        self
+    }
+
+    /// 预检结果的缓存时间
+    pub fn with_max_age(mut self, max_age: Duration) -> Self {
+        self.max_age = Some(max_age.as_secs().to_string());
+        self
+    }
+
+    /// 允许携带凭证（Cookie、Authorization），此时总是回显请求的 Origin
+    ///
+    /// 须先通过 `with_origin` 限定来源，允许任意来源（`*`）时返回错误，否则任意站点均可携带用户凭证读取响应
+    pub fn with_credentials(mut self) -> anyhow::Result<Self> {
+        if self.allows_any_origin() {
+            anyhow::bail!("CORS credentials require an explicit origin allow-list, not `*`");
+        }
+        self.credentials = true;
+        Ok(self)
+    }
+
+    /// 允许的来源模式，即 `origin` 中逗号分隔的各项
+    fn origin_patterns(&self) -> Vec<&str> {
+        self.origin
+            .as_deref()
+            .unwrap_or("*")
+            .split(',')
+            .map(str::trim)
+            .collect()
+    }
+
+    pub(crate) fn allows_any_origin(&self) -> bool {
+        self.origin_patterns().contains(&"*")
+    }
+
+    /// 响应是否随 Origin 变化，允许任意来源且不带凭证时固定返回 `*`
+    fn varies_by_origin(&self) -> bool {
+        self.credentials || !self.allows_any_origin()
+    }
+
+    /// 返回 `Access-Control-Allow-Origin` 的值，来源不被允许时为 `None`
+    fn allow_origin<'a>(&self, origin: &'a str) -> Option<Cow<'a, str>> {
+        if !self.varies_by_origin() {
+            return Some(Cow::Borrowed("*"));
+        }
+        let allowed = self
+            .origin_patterns()
+            .into_iter()
+            .any(|pattern| pattern == "*" || origin_matches(pattern, origin));
+        allowed.then_some(Cow::Borrowed(origin))
+    }
+
+    /// 处理 CORS 预检请求（带 `Access-Control-Request-Method` 的 OPTIONS），其他请求返回 `None`
+    ///
+    /// 来源不被允许时返回 403
+    pub(crate) fn preflight(&self, req: &HttpRequest) -> Option<HttpResponse> {
+        if req.method != HttpMethod::OPTIONS {
+            return None;
+        }
+        let origin = req.get_header("Origin")?;
+        let request_method = req.get_header("Access-Control-Request-Method")?;
+        let mut res = HttpResponse::text("");
+        res.add_header(
+            "Vary".into(),
+            "Origin, Access-Control-Request-Method, Access-Control-Request-Headers".into(),
+        );
+        let Some(allow_origin) = self.allow_origin(origin) else {
+            res.http_code = 403;
+            res.body = crate::HttpResponseBody::Data(b"Forbidden: origin not allowed".to_vec());
+            return Some(res);
+        };
+        res.http_code = 204;
+        res.add_header(
+            "Access-Control-Allow-Origin".into(),
+            allow_origin.into_owned().into(),
+        );
+        let methods = match &self.methods {
+            Some(methods) => methods.clone(),
+            None => Self::route_methods(req, request_method),
+        };
+        res.add_header("Access-Control-Allow-Methods".into(), methods.into());
+        let headers = match self.headers.as_deref() {
+            // 携带凭证时浏览器不认可通配符，回显请求的头
+            None | Some("*") if self.credentials => req
+                .get_header("Access-Control-Request-Headers")
+                .map(str::to_string),
+            None => Some("*".to_string()),
+            Some(headers) => Some(headers.to_string()),
+        };
+        if let Some(headers) = headers {
+            res.add_header("Access-Control-Allow-Headers".into(), headers.into());
+        }
+        if let Some(max_age) = &self.max_age {
+            res.add_header("Access-Control-Max-Age".into(), max_age.clone().into());
+        }
+        if self.credentials {
+            res.add_header("Access-Control-Allow-Credentials".into(), "true".into());
+        }
+        Some(res)
+    }
+
+    /// 路由已注册的方法；静态文件、反向代理等未注册处理函数的路由回显请求的方法
+    fn route_methods(req: &HttpRequest, request_method: &str) -> String {
+        let Some(handlers) = HANDLERS.get(&req.url_path[..]) else {
+            return request_method.trim().to_string();
+        };
+        let mut methods: HashSet<HttpMethod> = handlers.keys().copied().collect();
+        if methods.contains(&HttpMethod::GET) {
+            methods.insert(HttpMethod::HEAD);
+        }
+        methods.insert(HttpMethod::OPTIONS);
+        let mut methods: Vec<String> = methods.into_iter().map(|m| m.to_string()).collect();
+        methods.sort();
+        methods.join(",")
+    }
+
+    /// 为非预检响应写入 CORS 头，路由或上游已写入 `Access-Control-Allow-Origin` 时不做修改
+    fn apply(&self, origin: Option<&str>, res: &mut HttpResponse) {
+        if res
+            .headers
+            .keys()
+            .any(|key| key.eq_ignore_ascii_case("Access-Control-Allow-Origin"))
+        {
+            return;
+        }
+        if self.varies_by_origin() {
+            append_vary(res, "Origin");
+        }
+        let Some(allow_origin) = origin.and_then(|origin| self.allow_origin(origin)) else {
+            return;
+        };
+        res.add_header(
+            "Access-Control-Allow-Origin".into(),
+            allow_origin.into_owned().into(),
+        );
+        if self.credentials {
+            res.add_header("Access-Control-Allow-Credentials".into(), "true".into());
+        }
+        if let Some(expose_headers) = &self.expose_headers {
+            res.add_header(
+                "Access-Control-Expose-Headers".into(),
+                expose_headers.clone().into(),
+            );
+        }
+    }
+
+    /// 应答前写入 CORS 头，由管线在请求处理完毕后调用
+    pub(crate) fn finish(req: &mut HttpRequest, res: &mut HttpResponse) {
+        let Some(config) = req.remove_ext::<CorsConfig>() else {
+            return;
+        };
+        config.apply(req.get_header("Origin"), res);
+    }
+}
+
+/// 按模式匹配来源（不区分大小写），`*` 匹配不含 `/` 与 `:` 的任意非空字符
+fn origin_matches(pattern: &str, origin: &str) -> bool {
+    fn glob(pattern: &[u8], origin: &[u8]) -> bool {
+        match pattern.split_first() {
+            None => origin.is_empty(),
+            Some((b'*', rest)) => (1..=origin.len())
+                .take_while(|&end| !matches!(origin[end - 1], b'/' | b':'))
+                .any(|end| glob(rest, &origin[end..])),
+            Some((c, rest)) => {
+                origin.first().is_some_and(|o| o.eq_ignore_ascii_case(c))
+                    && glob(rest, &origin[1..])
+            }
+        }
+    }
+    glob(pattern.as_bytes(), origin.trim().as_bytes())
+}
+
+/// 在 `Vary` 头中追加字段，已包含该字段或为 `*` 时不重复添加
+fn append_vary(res: &mut HttpResponse, field: &'static str) {
+    match res
+        .headers
+        .iter_mut()
+        .find(|(key, _)| key.eq_ignore_ascii_case("Vary"))
+    {
+        Some((_, value)) => {
+            let present = value
+                .split(',')
+                .any(|item| item.trim() == "*" || item.trim().eq_ignore_ascii_case(field));
+            if !present {
+                *value = format!("{value}, {field}").into();
+            }
+        }
+        None => res.add_header("Vary".into(), field.into()),
+    }
+}
@@ -0,0 +1,301 @@
+use crate::{CookieBuilder, HttpMethod, HttpRequest, HttpResponse, SessionCache};
+use base64::Engine;
+use rand::RngCore;
+use ring::hmac;
+use std::collections::HashSet;
+use std::fmt;
+use std::sync::{Arc, LazyLock};
+use std::time::Duration;
+
+/// 标注了 `#[potato::csrf_exempt]` 的路由，由宏提交
+pub struct CsrfExemptFlag {
+    pub method: HttpMethod,
+    pub path: &'static str,
+}
+
+impl CsrfExemptFlag {
+    pub const fn new(method: HttpMethod, path: &'static str) -> Self {
+        Self { method, path }
+    }
+}
+
+inventory::collect!(CsrfExemptFlag);
+
+static CSRF_EXEMPT: LazyLock<HashSet<(&'static str, HttpMethod)>> = LazyLock::new(|| {
+    inventory::iter::<CsrfExemptFlag>
+        .into_iter()
+        .map(|flag| (flag.path, flag.method))
+        .collect()
+});
+
+/// CSRF 防护配置，通过 `use_csrf` 启用
+///
+/// 采用签名的双重提交 Cookie：首次访问时下发令牌 Cookie，非安全方法须通过请求头或表单字段
+/// 回传相同令牌，并校验 `Origin` / `Sec-Fetch-Site`。请求带有 Bearer 会话令牌或会话 Cookie 时，
+/// 签名同时覆盖其中的用户 id，其他用户或未登录时获得的令牌不被接受
+#[derive(Clone)]
+pub struct CsrfConfig {
+    pub cookie_name: String,              // 令牌 Cookie 名
+    pub header_name: String,              // 回传令牌的请求头
+    pub form_field: String,               // 回传令牌的表单字段
+    pub cookie_path: String,              // Cookie 路径
+    pub cookie_domain: Option<String>,    // Cookie 域名
+    pub cookie_max_age: Option<Duration>, // 为空时为会话 Cookie
+    pub secure: bool,                     // Cookie 仅通过 HTTPS 发送
+    pub same_site: String,                // Cookie 的 SameSite 策略
+    pub trusted_origins: Vec<String>,     // 除同源外允许的 Origin，如 `https://app.example.com`
+    pub exempt_paths: Vec<String>,        // 不校验的路径前缀，如 webhook 回调
+    pub secret: Vec<u8>,                  // 令牌签名密钥，默认进程启动时随机生成
+}
+
+impl Default for CsrfConfig {
+    fn default() -> Self {
+        let mut secret = vec![0u8; 32];
+        rand::thread_rng().fill_bytes(&mut secret);
+        Self {
+            cookie_name: "csrf_token".to_string(),
+            header_name: "X-CSRF-Token".to_string(),
+            form_field: "csrf_token".to_string(),
+            cookie_path: "/".to_string(),
+            cookie_domain: None,
+            cookie_max_age: None,
+            secure: false,
+            same_site: "Lax".to_string(),
+            trusted_origins: vec![],
+            exempt_paths: vec![],
+            secret,
+        }
+    }
+}
+
+impl fmt::Debug for CsrfConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CsrfConfig")
+            .field("cookie_name", &self.cookie_name)
+            .field("header_name", &self.header_name)
+            .field("form_field", &self.form_field)
+            .field("trusted_origins", &self.trusted_origins)
+            .field("exempt_paths", &self.exempt_paths)
+            .finish_non_exhaustive()
+    }
+}
+
+impl CsrfConfig {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 使用固定密钥签名，多实例部署时各实例须一致
+    pub fn with_secret(mut self, secret: impl Into<Vec<u8>>) -> Self {
+        self.secret = secret.into();
+        self
+    }
+
+    /// 允许来自其他源的请求，如前后端分离部署时的前端地址
+    pub fn with_trusted_origin(mut self, origin: impl Into<String>) -> Self {
+        self.trusted_origins.push(origin.into());
+        self
+    }
+
+    /// 指定路径前缀下的请求不做校验
+    pub fn with_exempt_path(mut self, prefix: impl Into<String>) -> Self {
+        self.exempt_paths.push(prefix.into());
+        self
+    }
+
+    /// 令牌 Cookie 附加 `Secure`
+    pub fn with_secure_cookie(mut self) -> Self {
+        self.secure = true;
+        self
+    }
+
+    fn key(&self) -> hmac::Key {
+        hmac::Key::new(hmac::HMAC_SHA256, &self.secret)
+    }
+
+    /// 签名内容：`用户id.随机数`，未登录时用户id为空
+    fn signed_message(session: Option<i64>, nonce: &str) -> String {
+        let user = session.map(|id| id.to_string()).unwrap_or_default();
+        format!("{user}.{nonce}")
+    }
+
+    /// 生成令牌：`随机数.签名`
+    fn issue(&self, session: Option<i64>) -> String {
+        let mut nonce = [0u8; 18];
+        rand::thread_rng().fill_bytes(&mut nonce);
+        let nonce = base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(nonce);
+        let message = Self::signed_message(session, &nonce);
+        let sig = base64::engine::general_purpose::URL_SAFE_NO_PAD
+            .encode(hmac::sign(&self.key(), message.as_bytes()));
+        format!("{nonce}.{sig}")
+    }
+
+    fn verify(&self, token: &str, session: Option<i64>) -> bool {
+        let Some((nonce, sig)) = token.split_once('.') else {
+            return false;
+        };
+        let Ok(sig) = base64::engine::general_purpose::URL_SAFE_NO_PAD.decode(sig) else {
+            return false;
+        };
+        let message = Self::signed_message(session, nonce);
+        hmac::verify(&self.key(), message.as_bytes(), &sig).is_ok()
+    }
+
+    fn cookie(&self, token: &str) -> CookieBuilder {
+        let mut cookie = CookieBuilder::new(&self.cookie_name, token)
+            .path(&self.cookie_path)
+            .secure(self.secure)
+            .same_site(&self.same_site);
+        if let Some(domain) = &self.cookie_domain {
+            cookie = cookie.domain(domain);
+        }
+        if let Some(max_age) = self.cookie_max_age {
+            cookie = cookie.max_age(max_age.as_secs() as i64);
+        }
+        cookie
+    }
+
+    /// 管线执行到该项时调用：确定本次请求的令牌，非安全方法校验失败时返回 403 响应
+    pub(crate) async fn check(&self, req: &mut HttpRequest) -> Option<HttpResponse> {
+        let cookie_token = req.get_header("Cookie").and_then(|cookies| {
+            cookies.split(';').find_map(|pair| {
+                let (key, value) = pair.trim().split_once('=')?;
+                (key.trim() == self.cookie_name).then(|| value.trim().to_string())
+            })
+        });
+        // 令牌与会话绑定，防止通过同站子域写入他人获得的令牌
+        let session = session_user(req).await;
+        let cookie_token = cookie_token.filter(|token| self.verify(token, session));
+        let issued = cookie_token.is_none();
+        let token = cookie_token.unwrap_or_else(|| self.issue(session));
+        req.add_ext(Arc::new(CsrfToken(token.clone())));
+        if issued {
+            req.add_ext(Arc::new(CsrfPendingCookie(self.cookie(&token))));
+        }
+
+        if matches!(
+            req.method,
+            HttpMethod::GET | HttpMethod::HEAD | HttpMethod::OPTIONS | HttpMethod::TRACE
+        ) || self.is_exempt(req)
+        {
+            return None;
+        }
+        let reason = if req
+            .get_header("Sec-Fetch-Site")
+            .is_some_and(|site| site.trim().eq_ignore_ascii_case("cross-site"))
+        {
+            "cross-site request"
+        } else if !self.origin_allowed(req) {
+            "origin not allowed"
+        } else if issued {
+            "missing CSRF cookie"
+        } else {
+            let submitted = req
+                .get_header(&self.header_name)
+                .map(str::to_string)
+                .or_else(|| {
+                    req.body_pairs
+                        .get(&self.form_field[..])
+                        .map(|value| value.to_string())
+                });
+            // 比较两者的 HMAC，耗时与令牌内容无关
+            let key = self.key();
+            let expected = hmac::sign(&key, token.as_bytes());
+            match submitted {
+                Some(submitted)
+                    if hmac::verify(&key, submitted.as_bytes(), expected.as_ref()).is_ok() =>
+                {
+                    return None
+                }
+                Some(_) => "CSRF token mismatch",
+                None => "missing CSRF token",
+            }
+        };
+        let mut res = HttpResponse::text(format!("Forbidden: {reason}"));
+        res.http_code = 403;
+        Some(res)
+    }
+
+    fn is_exempt(&self, req: &HttpRequest) -> bool {
+        self.exempt_paths
+            .iter()
+            .any(|prefix| req.url_path.starts_with(&prefix[..]))
+            || CSRF_EXEMPT.contains(&(&req.url_path[..], req.method))
+    }
+
+    /// `Origin` 缺失时取 `Referer` 的源，均缺失时仅依赖令牌校验
+    fn origin_allowed(&self, req: &HttpRequest) -> bool {
+        let origin = match req.get_header("Origin") {
+            Some(origin) => origin.trim(),
+            None => match req.get_header("Referer") {
+                Some(referer) => referer_origin(referer.trim()),
+                None => return true,
+            },
+        };
+        if self
+            .trusted_origins
+            .iter()
+            .any(|trusted| trusted.trim_end_matches('/').eq_ignore_ascii_case(origin))
+        {
+            return true;
+        }
+        let Some((_, authority)) = origin.split_once("://") else {
+            return false;
+        };
+        req.get_header_host()
+            .is_some_and(|host| host.trim().eq_ignore_ascii_case(authority))
+    }
+}
+
+/// 请求所属会话的用户 id，取自 Bearer 会话令牌或无状态会话 Cookie
+async fn session_user(req: &HttpRequest) -> Option<i64> {
+    match req.get_header("Authorization") {
+        Some(value) if value.starts_with("Bearer ") => SessionCache::parse_token(&value[7..])
+            .await
+            .ok()
+            .map(|(user_id, _)| user_id),
+        _ => SessionCache::from_cookie(req.get_header("Cookie")?)
+            .ok()?
+            .user_id(),
+    }
+}
+
+/// 本次请求的 CSRF 令牌，启用 `use_csrf` 后可在处理函数中通过 `req.csrf_token()` 获取，
+/// 用于写入表单隐藏字段或页面 meta
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CsrfToken(pub String);
+
+impl CsrfToken {
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Display for CsrfToken {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+/// 待下发的令牌 Cookie
+pub(crate) struct CsrfPendingCookie(CookieBuilder);
+
+impl CsrfPendingCookie {
+    /// 应答前写入令牌 Cookie，由管线在请求处理完毕后调用
+    pub(crate) fn finish(req: &mut HttpRequest, res: &mut HttpResponse) {
+        if let Some(pending) = req.remove_ext::<CsrfPendingCookie>() {
+            res.append_header("Set-Cookie".into(), pending.0.to_set_cookie_string().into());
+        }
+    }
+}
+
+/// `https://host:port/path` -> `https://host:port`
+fn referer_origin(referer: &str) -> &str {
+    match referer.split_once("://") {
+        Some((scheme, rest)) => {
+            let end = rest.find(['/', '?', '#']).unwrap_or(rest.len());
+            &referer[..scheme.len() + 3 + end]
+        }
+        None => referer,
+    }
+}
@@ -0,0 +1,60 @@
+use super::listener::is_unix_peer;
+use crate::utils::cidr::IpCidr;
+use crate::HttpRequest;
+use std::net::{IpAddr, SocketAddr};
+
+/// 可信代理网段，连接来自其中时 `get_client_addr` 采信 `Forwarded` / `X-Forwarded-For`
+pub struct TrustedProxies(pub(crate) Vec<IpCidr>);
+
+impl TrustedProxies {
+    /// 从右向左跳过可信代理，返回第一个不可信的地址；无法解析的节点处停止
+    pub(crate) fn resolve(&self, req: &HttpRequest, peer: SocketAddr) -> SocketAddr {
+        if is_unix_peer(&peer) || !IpCidr::any_contains(&self.0, &peer.ip()) {
+            return peer;
+        }
+        let hops: Vec<Option<SocketAddr>> = match req.get_header("Forwarded") {
+            Some(forwarded) => forwarded
+                .split(',')
+                .map(|element| {
+                    element.split(';').find_map(|pair| {
+                        let (key, value) = pair.split_once('=')?;
+                        key.trim()
+                            .eq_ignore_ascii_case("for")
+                            .then(|| parse_node(value))
+                    })?
+                })
+                .collect(),
+            None => match req.get_header("X-Forwarded-For") {
+                Some(xff) => xff.split(',').map(parse_node).collect(),
+                None => return peer,
+            },
+        };
+
+        let mut client = peer;
+        for hop in hops.into_iter().rev() {
+            match hop {
+                Some(addr) => {
+                    client = addr;
+                    if !IpCidr::any_contains(&self.0, &addr.ip()) {
+                        break;
+                    }
+                }
+                None => break,
+            }
+        }
+        client
+    }
+}
+
+/// 解析节点，如 `192.0.2.1`、`192.0.2.1:8080`、`"[2001:db8::1]:4711"`；未带端口时端口为 0
+fn parse_node(node: &str) -> Option<SocketAddr> {
+    let node = node.trim().trim_matches('"');
+    if let Ok(addr) = node.parse::<SocketAddr>() {
+        return Some(addr);
+    }
+    let ip = match node.strip_prefix('[') {
+        Some(rest) => rest.strip_suffix(']')?,
+        None => node,
+    };
+    ip.parse::<IpAddr>().ok().map(|ip| SocketAddr::new(ip, 0))
+}
@@ -2,43 +2,184 @@
 
 use crate::utils::refstr::HeaderOrHipStr;
 use crate::utils::string::StringExt;
+use crate::utils::tcp_stream::HttpStream;
 use crate::{HttpMethod, HttpRequest, HttpRequestTargetForm};
 use h2::server as h2_server;
+use h2::Reason;
+use std::collections::VecDeque;
 use std::net::SocketAddr;
+use std::pin::Pin;
 use std::sync::Arc;
-use tokio_rustls::rustls;
-use tokio_rustls::rustls::pki_types::{pem::PemObject, CertificateDer, PrivateKeyDer};
+use std::task::{Context, Poll};
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, ReadBuf};
+use tokio::net::TcpStream;
+use tokio::select;
+use tokio::sync::{watch, Notify, Semaphore};
+use tokio::time::{sleep_until, Instant};
 use tokio_rustls::TlsAcceptor;
 
-use super::PipeContext;
+use super::{ConnOptions, PipeContext};
+
+/// HTTP/2 连接参数与防滥用配置
+#[derive(Clone, Debug)]
+pub struct Http2Config {
+    /// SETTINGS_MAX_CONCURRENT_STREAMS，同时也是单连接内并发执行的处理函数上限
+    pub max_concurrent_streams: u32,
+    /// SETTINGS_INITIAL_WINDOW_SIZE（流级接收窗口）
+    pub initial_stream_window_size: u32,
+    /// 连接级接收窗口
+    pub initial_connection_window_size: u32,
+    /// SETTINGS_MAX_FRAME_SIZE，取值范围 16384 ~ 16777215
+    pub max_frame_size: u32,
+    /// SETTINGS_MAX_HEADER_LIST_SIZE，同时限制 CONTINUATION 帧累积的头部大小
+    pub max_header_list_size: u32,
+    /// 连接级 PING 保活间隔，为 None 时不发送 PING
+    pub keepalive_interval: Option<Duration>,
+    /// 等待 PING 响应的超时时间，超时则关闭连接
+    pub keepalive_timeout: Duration,
+    /// 连接上没有活动流超过该时长后发送 GOAWAY 关闭，为 None 时不限制
+    pub idle_timeout: Option<Duration>,
+    /// 发送 GOAWAY 后等待进行中的流完成的最长时间
+    pub graceful_shutdown_timeout: Duration,
+    /// 在 `reset_window` 时间窗口内允许客户端重置的流数量，超出后以 ENHANCE_YOUR_CALM 关闭连接（CVE-2023-44487）
+    pub max_resets_per_window: usize,
+    /// 重置计数的时间窗口
+    pub reset_window: Duration,
+    /// 尚未被接受就被重置的流数量上限（由 h2 协议栈处理）
+    pub max_pending_accept_reset_streams: usize,
+    /// 因协议错误由本端重置的流数量上限，超出后关闭连接
+    pub max_local_error_reset_streams: Option<usize>,
+    /// 明文监听（`serve_http`/`listen_http`）是否接受 h2c（先验知识或 `Upgrade: h2c`），默认关闭
+    pub h2c: bool,
+}
+
+impl Default for Http2Config {
+    fn default() -> Self {
+        Self {
+            max_concurrent_streams: 100,
+            initial_stream_window_size: 65_535,
+            initial_connection_window_size: 65_535,
+            max_frame_size: 16_384,
+            max_header_list_size: 64 * 1024,
+            keepalive_interval: None,
+            keepalive_timeout: Duration::from_secs(20),
+            idle_timeout: None,
+            graceful_shutdown_timeout: Duration::from_secs(30),
+            max_resets_per_window: 200,
+            reset_window: Duration::from_secs(30),
+            max_pending_accept_reset_streams: 20,
+            max_local_error_reset_streams: Some(1024),
+            h2c: false,
+        }
+    }
+}
+
+impl Http2Config {
+    fn builder(&self) -> h2_server::Builder {
+        let mut builder = h2_server::Builder::new();
+        builder
+            .max_concurrent_streams(self.max_concurrent_streams)
+            .initial_window_size(self.initial_stream_window_size)
+            .initial_connection_window_size(self.initial_connection_window_size)
+            .max_frame_size(self.max_frame_size.clamp(16_384, 16_777_215))
+            .max_header_list_size(self.max_header_list_size)
+            .max_pending_accept_reset_streams(self.max_pending_accept_reset_streams)
+            .max_local_error_reset_streams(self.max_local_error_reset_streams);
+        builder
+    }
+}
+
+/// 统计时间窗口内被客户端重置的流，超出阈值时通知连接关闭
+struct ResetGuard {
+    max: usize,
+    window: Duration,
+    events: std::sync::Mutex<VecDeque<std::time::Instant>>,
+    tripped: Notify,
+}
+
+impl ResetGuard {
+    fn new(max: usize, window: Duration) -> Self {
+        Self {
+            max,
+            window,
+            events: std::sync::Mutex::new(VecDeque::new()),
+            tripped: Notify::new(),
+        }
+    }
+
+    fn record(&self) {
+        let now = std::time::Instant::now();
+        let mut events = match self.events.lock() {
+            Ok(events) => events,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+        events.push_back(now);
+        while let Some(first) = events.front() {
+            if now.duration_since(*first) <= self.window {
+                break;
+            }
+            events.pop_front();
+        }
+        if events.len() > self.max {
+            self.tripped.notify_one();
+        }
+    }
+}
 
 pub async fn serve_http2_impl(
     addr: &str,
-    cert_file: &str,
-    key_file: &str,
+    acceptor: TlsAcceptor,
     pipe_ctx: Arc<PipeContext>,
+    config: Arc<Http2Config>,
+    opts: ConnOptions,
 ) -> anyhow::Result<()> {
     #[cfg(all(feature = "jemalloc", not(target_os = "windows")))]
     crate::init_jemalloc()?;
 
     let addr: SocketAddr = addr.parse()?;
     let listener = tokio::net::TcpListener::bind(&addr).await?;
-    let acceptor = tls_acceptor_with_alpn(
-        cert_file,
-        key_file,
-        Some(vec![b"h2".to_vec(), b"http/1.1".to_vec()]),
-    )?;
+    serve_http2_on(listener, acceptor, pipe_ctx, config, opts).await
+}
 
+/// 在已绑定的监听器上接受 TLS 连接，按 ALPN 协商结果分派到 HTTP/2 或 HTTP/1.1
+pub(crate) async fn serve_http2_on(
+    listener: tokio::net::TcpListener,
+    acceptor: TlsAcceptor,
+    pipe_ctx: Arc<PipeContext>,
+    config: Arc<Http2Config>,
+    opts: ConnOptions,
+) -> anyhow::Result<()> {
+    // 本函数退出（如收到关闭信号）时 shutdown_tx 被释放，各连接据此发送 GOAWAY 优雅关闭
+    let (_shutdown_tx, shutdown_rx) = watch::channel(());
     loop {
         let (stream, client_addr) = listener.accept().await?;
         _ = stream.set_nodelay(true);
         let acceptor = acceptor.clone();
         let pipe_ctx2 = Arc::clone(&pipe_ctx);
+        let config2 = Arc::clone(&config);
+        let opts2 = opts.clone();
+        let shutdown_rx2 = shutdown_rx.clone();
         _ = tokio::task::spawn(async move {
+            let mut stream = stream;
+            let client_addr = match super::proxy_protocol::resolve_client_addr(
+                &mut stream,
+                client_addr,
+                opts2.proxy_protocol,
+            )
+            .await
+            {
+                Ok(client_addr) => client_addr,
+                Err(_) => return,
+            };
             let stream = match acceptor.accept(stream).await {
                 Ok(stream) => stream,
                 Err(_) => return,
             };
+            let opts2 = ConnOptions {
+                client_cert: super::tls::peer_client_cert(stream.get_ref().1),
+                ..opts2
+            };
 
             let negotiated_h2 = stream
                 .get_ref()
@@ -49,58 +190,444 @@ pub async fn serve_http2_impl(
 
             if !negotiated_h2 {
                 // HTTP/1.1连接，使用spawn_http1_connection处理
-                use crate::utils::tcp_stream::HttpStream;
                 super::HttpServer::spawn_http1_connection(
                     pipe_ctx2,
                     client_addr,
                     HttpStream::from_server_tls(stream),
+                    ConnOptions {
+                        alt_svc: opts2.alt_svc,
+                        client_cert: opts2.client_cert,
+                        ..Default::default()
+                    },
                 );
                 return;
             }
 
-            let mut h2_conn = match h2_server::handshake(stream).await {
-                Ok(conn) => conn,
-                Err(_) => return,
-            };
-
-            while let Some(next) = h2_conn.accept().await {
-                let (req_head, respond) = match next {
-                    Ok(parts) => parts,
-                    Err(_) => break,
-                };
-                let pipe_ctx3 = Arc::clone(&pipe_ctx2);
-                _ = tokio::task::spawn(async move {
-                    let _ = handle_h2_request(req_head, respond, pipe_ctx3, client_addr).await;
-                });
-            }
+            serve_h2_connection(stream, pipe_ctx2, client_addr, config2, opts2, shutdown_rx2).await;
         });
     }
 }
 
-fn tls_acceptor_with_alpn(
-    cert_file: &str,
-    key_file: &str,
-    alpn: Option<Vec<Vec<u8>>>,
-) -> anyhow::Result<TlsAcceptor> {
-    // 初始化 rustls CryptoProvider（如果尚未初始化）
+/// HTTP/2 连接前言
+const H2_PREFACE: &[u8] = b"PRI * HTTP/2.0\r\n\r\nSM\r\n\r\n";
+
+/// 明文监听上处理 h2c 所需的参数
+#[derive(Clone)]
+pub(crate) struct H2cContext {
+    pub(crate) config: Arc<Http2Config>,
+    pub(crate) shutdown_rx: watch::Receiver<()>,
+}
+
+/// 先回放已读取的数据，再继续读取底层连接
+pub(crate) struct Rewind<T> {
+    prefix: Vec<u8>,
+    pos: usize,
+    inner: T,
+}
+
+impl<T> Rewind<T> {
+    pub(crate) fn new(inner: T, prefix: Vec<u8>) -> Self {
+        Self {
+            prefix,
+            pos: 0,
+            inner,
+        }
+    }
+
+    fn into_parts(self) -> (T, Vec<u8>) {
+        let mut prefix = self.prefix;
+        prefix.drain(..self.pos);
+        (self.inner, prefix)
+    }
+}
+
+impl<T: AsyncRead + Unpin> AsyncRead for Rewind<T> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<std::io::Result<()>> {
+        let this = self.get_mut();
+        if this.pos < this.prefix.len() {
+            let len = std::cmp::min(this.prefix.len() - this.pos, buf.remaining());
+            buf.put_slice(&this.prefix[this.pos..this.pos + len]);
+            this.pos += len;
+            return Poll::Ready(Ok(()));
+        }
+        Pin::new(&mut this.inner).poll_read(cx, buf)
+    }
+}
+
+impl<T: AsyncWrite + Unpin> AsyncWrite for Rewind<T> {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<std::io::Result<usize>> {
+        Pin::new(&mut self.get_mut().inner).poll_write(cx, buf)
+    }
+
+    fn poll_write_vectored(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        bufs: &[std::io::IoSlice<'_>],
+    ) -> Poll<std::io::Result<usize>> {
+        Pin::new(&mut self.get_mut().inner).poll_write_vectored(cx, bufs)
+    }
+
+    fn is_write_vectored(&self) -> bool {
+        self.inner.is_write_vectored()
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        Pin::new(&mut self.get_mut().inner).poll_flush(cx)
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        Pin::new(&mut self.get_mut().inner).poll_shutdown(cx)
+    }
+}
+
+/// 处理明文连接：以 HTTP/2 连接前言开头时按 h2c 先验知识处理，否则交给 HTTP/1.1
+pub(crate) async fn serve_plain_connection<S>(
+    mut stream: S,
+    into_http: fn(S) -> HttpStream,
+    pipe_ctx: Arc<PipeContext>,
+    client_addr: SocketAddr,
+    opts: ConnOptions,
+) where
+    S: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+{
+    let mut initial = Vec::with_capacity(H2_PREFACE.len());
+    let mut buf = [0u8; 4096];
+    while initial.len() < H2_PREFACE.len() && H2_PREFACE.starts_with(&initial) {
+        match stream.read(&mut buf).await {
+            Ok(0) | Err(_) => return,
+            Ok(n) => initial.extend_from_slice(&buf[..n]),
+        }
+    }
+
+    match &opts.h2c {
+        Some(h2c) if initial.starts_with(H2_PREFACE) => {
+            serve_h2_connection(
+                Rewind::new(stream, initial),
+                pipe_ctx,
+                client_addr,
+                Arc::clone(&h2c.config),
+                opts.clone(),
+                h2c.shutdown_rx.clone(),
+            )
+            .await;
+        }
+        _ => {
+            super::HttpServer::spawn_http1_connection(
+                pipe_ctx,
+                client_addr,
+                HttpStream::with_pre_read(into_http(stream), initial),
+                opts,
+            );
+        }
+    }
+}
+
+fn hpack_encode_int(value: usize, prefix_bits: u8, out: &mut Vec<u8>) {
+    let max = (1usize << prefix_bits) - 1;
+    if value < max {
+        out.push(value as u8);
+        return;
+    }
+    out.push(max as u8);
+    let mut value = value - max;
+    while value >= 128 {
+        out.push((value % 128 + 128) as u8);
+        value /= 128;
+    }
+    out.push(value as u8);
+}
+
+/// 以“不索引的字面量”形式编码头部，不影响对端的动态表
+fn hpack_encode_literal(name: &str, value: &str, out: &mut Vec<u8>) {
+    out.push(0);
+    hpack_encode_int(name.len(), 7, out);
+    out.extend_from_slice(name.as_bytes());
+    hpack_encode_int(value.len(), 7, out);
+    out.extend_from_slice(value.as_bytes());
+}
+
+fn header_has_token(value: Option<&str>, token: &str) -> bool {
+    value.is_some_and(|value| {
+        value
+            .split(',')
+            .any(|item| item.trim().eq_ignore_ascii_case(token))
+    })
+}
+
+/// 可接受的 `Upgrade: h2c` 请求
+pub(crate) struct H2cUpgrade {
+    /// `HTTP2-Settings` 头解码得到的 SETTINGS 负载
+    settings: Vec<u8>,
+    /// 代表升级请求的 HEADERS 帧（流 1）
+    request_frame: Vec<u8>,
+}
+
+/// 判断是否为可接受的 `Upgrade: h2c` 请求
+///
+/// 仅处理不带请求体且 `HTTP2-Settings` 合法的请求，其余情况忽略升级按 HTTP/1.1 响应
+pub(crate) fn h2c_upgrade(req: &HttpRequest) -> Option<H2cUpgrade> {
+    use base64::Engine;
+
+    if req.version != 11 || !req.body.is_empty() {
+        return None;
+    }
+    if !header_has_token(req.get_header("Upgrade"), "h2c")
+        || !header_has_token(req.get_header("Connection"), "upgrade")
+        || !header_has_token(req.get_header("Connection"), "http2-settings")
+        || req.get_header("Transfer-Encoding").is_some()
     {
-        use rustls::crypto::ring::default_provider;
-        use rustls::crypto::CryptoProvider;
-        let _ = CryptoProvider::install_default(default_provider());
+        return None;
+    }
+    let settings = base64::engine::general_purpose::URL_SAFE_NO_PAD
+        .decode(
+            req.get_header("HTTP2-Settings")?
+                .trim()
+                .trim_end_matches('='),
+        )
+        .ok()?;
+    if settings.len() % 6 != 0 || settings.len() > 16_384 {
+        return None;
+    }
+
+    let path = match req.url_query.is_empty() {
+        true => req.url_path.to_string(),
+        false => format!("{}{}", req.url_path, req.query_string()),
+    };
+    let mut block = Vec::with_capacity(256);
+    hpack_encode_literal(":method", &req.method.to_string(), &mut block);
+    hpack_encode_literal(":scheme", "http", &mut block);
+    hpack_encode_literal(":path", &path, &mut block);
+    if let Some(host) = req.get_header("Host") {
+        hpack_encode_literal(":authority", host, &mut block);
+    }
+    for (key, value) in req.headers.iter() {
+        let name = key.to_str().to_ascii_lowercase();
+        if matches!(
+            name.as_str(),
+            "host"
+                | "connection"
+                | "upgrade"
+                | "http2-settings"
+                | "keep-alive"
+                | "proxy-connection"
+                | "transfer-encoding"
+                | "te"
+        ) {
+            continue;
+        }
+        hpack_encode_literal(&name, value, &mut block);
+    }
+    // 对端 SETTINGS 生效前只能假定默认的最大帧长度
+    if block.len() > 16_384 {
+        return None;
     }
 
-    let certs = CertificateDer::pem_file_iter(cert_file)?.collect::<Result<Vec<_>, _>>()?;
-    let key = PrivateKeyDer::from_pem_file(key_file)?;
+    let mut frame = Vec::with_capacity(9 + block.len());
+    frame.extend_from_slice(&(block.len() as u32).to_be_bytes()[1..]);
+    frame.push(0x1); // HEADERS
+    frame.push(0x1 | 0x4); // END_STREAM | END_HEADERS
+    frame.extend_from_slice(&1u32.to_be_bytes());
+    frame.extend_from_slice(&block);
+    Some(H2cUpgrade {
+        settings,
+        request_frame: frame,
+    })
+}
 
-    let mut server_config = rustls::ServerConfig::builder()
-        .with_no_client_auth()
-        .with_single_cert(certs, key)?;
+/// 响应 101 后在同一连接上切换到 HTTP/2，升级前的请求作为流 1 处理
+pub(crate) async fn serve_h2c_upgrade(
+    stream: TcpStream,
+    pre_read: Vec<u8>,
+    upgrade: H2cUpgrade,
+    pipe_ctx: Arc<PipeContext>,
+    client_addr: SocketAddr,
+    opts: ConnOptions,
+) {
+    let Some(h2c) = opts.h2c.clone() else {
+        return;
+    };
+    let mut io = Rewind::new(stream, pre_read);
+    let switching =
+        b"HTTP/1.1 101 Switching Protocols\r\nConnection: Upgrade\r\nUpgrade: h2c\r\n\r\n";
+    if io.write_all(switching).await.is_err() {
+        return;
+    }
 
-    if let Some(alpn) = alpn {
-        server_config.alpn_protocols = alpn;
+    // 客户端随后发送连接前言与 SETTINGS 帧，代表升级请求的 HEADERS 帧需排在其后
+    let mut head = vec![0u8; H2_PREFACE.len() + 9];
+    let read_head = tokio::time::timeout(Duration::from_secs(10), io.read_exact(&mut head));
+    if !matches!(read_head.await, Ok(Ok(_))) {
+        return;
+    }
+    let frame_head = &head[H2_PREFACE.len()..];
+    if !head.starts_with(H2_PREFACE) || frame_head[3] != 0x4 || frame_head[4] & 0x1 != 0 {
+        return;
+    }
+    let len = u32::from_be_bytes([0, frame_head[0], frame_head[1], frame_head[2]]) as usize;
+    if len > 16_384 {
+        return;
+    }
+    let mut payload = vec![0u8; len];
+    if io.read_exact(&mut payload).await.is_err() {
+        return;
     }
 
-    Ok(TlsAcceptor::from(Arc::new(server_config)))
+    // `HTTP2-Settings` 等同于连接开始时收到的 SETTINGS，与客户端的首个 SETTINGS 帧合并为一帧，
+    // 后者的同名参数覆盖前者，且客户端只会收到一次 ACK
+    let mut settings = upgrade.settings;
+    settings.extend_from_slice(&payload);
+    if settings.len() > 16_384 {
+        return;
+    }
+    let (stream, rest) = io.into_parts();
+    let mut prefix = Vec::with_capacity(H2_PREFACE.len() + 9 + settings.len());
+    prefix.extend_from_slice(H2_PREFACE);
+    prefix.extend_from_slice(&(settings.len() as u32).to_be_bytes()[1..]);
+    prefix.extend_from_slice(&frame_head[3..]);
+    prefix.extend_from_slice(&settings);
+    prefix.extend_from_slice(&upgrade.request_frame);
+    prefix.extend_from_slice(&rest);
+    serve_h2_connection(
+        Rewind::new(stream, prefix),
+        pipe_ctx,
+        client_addr,
+        h2c.config,
+        opts,
+        h2c.shutdown_rx,
+    )
+    .await;
+}
+
+async fn keepalive_loop(mut ping_pong: h2::PingPong, interval: Duration, timeout: Duration) {
+    loop {
+        tokio::time::sleep(interval).await;
+        match tokio::time::timeout(timeout, ping_pong.ping(h2::Ping::opaque())).await {
+            Ok(Ok(_)) => {}
+            _ => return,
+        }
+    }
+}
+
+/// 在一条已建立的连接上运行 HTTP/2 协议，直到连接关闭
+pub(crate) async fn serve_h2_connection<T>(
+    io: T,
+    pipe_ctx: Arc<PipeContext>,
+    client_addr: SocketAddr,
+    config: Arc<Http2Config>,
+    opts: ConnOptions,
+    mut shutdown_rx: watch::Receiver<()>,
+) where
+    T: AsyncRead + AsyncWrite + Unpin,
+{
+    let mut h2_conn = match config.builder().handshake::<_, bytes::Bytes>(io).await {
+        Ok(conn) => conn,
+        Err(_) => return,
+    };
+
+    // 被重置的流其处理函数仍可能在执行，用信号量限制单连接内并发执行的处理函数数量
+    let max_streams = config.max_concurrent_streams.max(1) as usize;
+    let stream_permits = Arc::new(Semaphore::new(max_streams));
+    let reset_guard = Arc::new(ResetGuard::new(
+        config.max_resets_per_window,
+        config.reset_window,
+    ));
+
+    let keepalive = match (config.keepalive_interval, h2_conn.ping_pong()) {
+        (Some(interval), Some(ping_pong)) => Some(keepalive_loop(
+            ping_pong,
+            interval,
+            config.keepalive_timeout,
+        )),
+        _ => None,
+    };
+    let keepalive_enabled = keepalive.is_some();
+    let keepalive = async move {
+        match keepalive {
+            Some(keepalive) => keepalive.await,
+            None => std::future::pending().await,
+        }
+    };
+    tokio::pin!(keepalive);
+
+    let now = Instant::now();
+    let mut idle_deadline = config.idle_timeout.map(|timeout| now + timeout);
+    let mut goaway_deadline: Option<Instant> = None;
+    let mut calm_down = false;
+
+    loop {
+        select! {
+            next = h2_conn.accept() => {
+                let (req_head, mut respond) = match next {
+                    Some(Ok(parts)) => parts,
+                    _ => break,
+                };
+                if let Some(timeout) = config.idle_timeout {
+                    idle_deadline = Some(Instant::now() + timeout);
+                }
+
+                // 接受时已被客户端重置的流不再分派给处理函数
+                let reset = std::future::poll_fn(|cx| Poll::Ready(respond.poll_reset(cx))).await;
+                if let Poll::Ready(Ok(_)) = reset {
+                    reset_guard.record();
+                    continue;
+                }
+                let permit = match Arc::clone(&stream_permits).try_acquire_owned() {
+                    Ok(permit) => permit,
+                    Err(_) => {
+                        respond.send_reset(Reason::REFUSED_STREAM);
+                        continue;
+                    }
+                };
+
+                let pipe_ctx2 = Arc::clone(&pipe_ctx);
+                let opts2 = opts.clone();
+                let reset_guard2 = Arc::clone(&reset_guard);
+                _ = tokio::task::spawn(async move {
+                    let ret =
+                        handle_h2_request(req_head, respond, pipe_ctx2, client_addr, opts2)
+                            .await;
+                    if let Err(err) = ret {
+                        let remote_reset = err
+                            .downcast_ref::<h2::Error>()
+                            .is_some_and(|err| err.is_reset() && err.is_remote());
+                        if remote_reset {
+                            reset_guard2.record();
+                        }
+                    }
+                    drop(permit);
+                });
+            }
+            _ = reset_guard.tripped.notified(), if !calm_down => {
+                calm_down = true;
+                h2_conn.abrupt_shutdown(Reason::ENHANCE_YOUR_CALM);
+                goaway_deadline.get_or_insert(Instant::now() + config.graceful_shutdown_timeout);
+            }
+            _ = &mut keepalive, if keepalive_enabled => break,
+            _ = sleep_until(idle_deadline.unwrap_or(now)),
+                if idle_deadline.is_some() && goaway_deadline.is_none() => {
+                let in_flight = stream_permits.available_permits() < max_streams;
+                if h2_conn.has_streams() || in_flight {
+                    idle_deadline = config.idle_timeout.map(|timeout| Instant::now() + timeout);
+                } else {
+                    h2_conn.graceful_shutdown();
+                    goaway_deadline = Some(Instant::now() + config.graceful_shutdown_timeout);
+                }
+            }
+            _ = shutdown_rx.changed(), if goaway_deadline.is_none() => {
+                h2_conn.graceful_shutdown();
+                goaway_deadline = Some(Instant::now() + config.graceful_shutdown_timeout);
+            }
+            _ = sleep_until(goaway_deadline.unwrap_or(now)), if goaway_deadline.is_some() => break,
+        }
+    }
 }
 
 fn h2_method_to_http_method(method: &http::Method) -> anyhow::Result<HttpMethod> {
@@ -160,12 +687,16 @@ async fn handle_h2_request(
     mut respond: h2_server::SendResponse<bytes::Bytes>,
     pipe_ctx: Arc<PipeContext>,
     client_addr: SocketAddr,
+    opts: ConnOptions,
 ) -> anyhow::Result<()> {
     let mut req = HttpRequest::new();
     req.method = h2_method_to_http_method(req_head.method())?;
     req.target_form = HttpRequestTargetForm::Origin;
     req.version = 20;
     req.client_addr = Some(client_addr);
+    if let Some(client_cert) = &opts.client_cert {
+        req.add_ext(Arc::clone(client_cert));
+    }
 
     let path_and_query = req_head
         .uri()
@@ -224,14 +755,17 @@ async fn handle_h2_request(
     }
     req.body = request_body.into();
 
-    let res = PipeContext::handle_request(pipe_ctx.as_ref(), &mut req, 0).await;
+    let mut res = PipeContext::handle_request(pipe_ctx.as_ref(), &mut req, 0).await;
+    if let Some(alt_svc) = &opts.alt_svc {
+        res.add_header("Alt-Svc".into(), alt_svc.to_string().into());
+    }
 
     let mut response_builder = http::Response::builder().status(res.http_code);
-    for (key, value) in res.headers.iter() {
+    for (key, value) in res.header_lines() {
         if is_h2_h3_forbidden_response_header(key) {
             continue;
         }
-        response_builder = response_builder.header(key.as_ref(), value.as_ref());
+        response_builder = response_builder.header(key, value);
     }
     let response = response_builder
         .body(())
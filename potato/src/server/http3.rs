@@ -13,7 +13,7 @@ use std::net::SocketAddr;
 use std::pin::Pin;
 use std::sync::Arc;
 use tokio_rustls::rustls;
-use tokio_rustls::rustls::pki_types::{pem::PemObject, CertificateDer, PrivateKeyDer};
+use tokio_rustls::rustls::pki_types::{CertificateDer, PrivateKeyDer};
 
 use super::PipeContext;
 
@@ -235,23 +235,7 @@ fn should_suppress_response_body(status: u16, request_method: HttpMethod) -> boo
         || request_method == HttpMethod::HEAD
 }
 
-fn quinn_server_config(cert_file: &str, key_file: &str) -> anyhow::Result<quinn::ServerConfig> {
-    // 初始化 rustls CryptoProvider（如果尚未初始化）
-    {
-        use rustls::crypto::ring::default_provider;
-        use rustls::crypto::CryptoProvider;
-        let _ = CryptoProvider::install_default(default_provider());
-    }
-
-    let certs = CertificateDer::pem_file_iter(cert_file)?.collect::<Result<Vec<_>, _>>()?;
-    let key = PrivateKeyDer::from_pem_file(key_file)?;
-
-    let mut server_config = rustls::ServerConfig::builder()
-        .with_no_client_auth()
-        .with_single_cert(certs, key)?;
-
-    server_config.alpn_protocols = vec![b"h3".to_vec()];
-
+fn quinn_server_config(server_config: rustls::ServerConfig) -> anyhow::Result<quinn::ServerConfig> {
     let server_config = QuicServerConfig::try_from(server_config)?;
 
     let mut config = quinn::ServerConfig::with_crypto(Arc::new(server_config));
@@ -303,15 +287,14 @@ fn quinn_server_config_without_encrypt() -> anyhow::Result<quinn::ServerConfig>
 
 pub async fn serve_http3_impl(
     addr: &str,
-    cert_file: &str,
-    key_file: &str,
+    tls_config: rustls::ServerConfig,
     pipe_ctx: Arc<PipeContext>,
 ) -> anyhow::Result<()> {
     #[cfg(all(feature = "jemalloc", not(target_os = "windows")))]
     crate::init_jemalloc()?;
 
     let addr: SocketAddr = addr.parse()?;
-    let server_config = quinn_server_config(cert_file, key_file)?;
+    let server_config = quinn_server_config(tls_config)?;
     let endpoint = quinn::Endpoint::server(server_config, addr)?;
 
     while let Some(new_conn) = endpoint.accept().await {
@@ -322,6 +305,10 @@ pub async fn serve_http3_impl(
                 Err(_) => return,
             };
             let client_addr = conn.remote_address();
+            let client_cert = conn
+                .peer_identity()
+                .and_then(|identity| identity.downcast::<Vec<CertificateDer<'static>>>().ok())
+                .and_then(|chain| super::ClientCert::from_chain(&chain));
             // 为 WebTransport 克隆连接
             let wt_conn = conn.clone();
             let mut h3_conn: h3_server::Connection<_, bytes::Bytes> =
@@ -338,6 +325,7 @@ pub async fn serve_http3_impl(
                 };
                 let pipe_ctx3 = Arc::clone(&pipe_ctx2);
                 let wt_conn = wt_conn.clone();
+                let client_cert = client_cert.clone();
                 _ = tokio::task::spawn(async move {
                     let (req_head, mut stream) = match resolver.resolve_request().await {
                         Ok(req_stream) => req_stream,
@@ -401,6 +389,9 @@ pub async fn serve_http3_impl(
                     req.target_form = HttpRequestTargetForm::Origin;
                     req.version = 30;
                     req.client_addr = Some(client_addr);
+                    if let Some(client_cert) = client_cert {
+                        req.add_ext(client_cert);
+                    }
 
                     let path_and_query = req_head
                         .uri()
@@ -473,11 +464,11 @@ pub async fn serve_http3_impl(
                     let res = PipeContext::handle_request(pipe_ctx3.as_ref(), &mut req, 0).await;
 
                     let mut response_builder = http::Response::builder().status(res.http_code);
-                    for (key, value) in res.headers.iter() {
+                    for (key, value) in res.header_lines() {
                         if is_h2_h3_forbidden_response_header(key) {
                             continue;
                         }
-                        response_builder = response_builder.header(key.as_ref(), value.as_ref());
+                        response_builder = response_builder.header(key, value);
                     }
                     let response = match response_builder.body(()) {
                         Ok(resp) => resp,
@@ -702,11 +693,11 @@ pub async fn serve_http3_without_encrypt_impl(
                     let res = PipeContext::handle_request(pipe_ctx3.as_ref(), &mut req, 0).await;
 
                     let mut response_builder = http::Response::builder().status(res.http_code);
-                    for (key, value) in res.headers.iter() {
+                    for (key, value) in res.header_lines() {
                         if is_h2_h3_forbidden_response_header(key) {
                             continue;
                         }
-                        response_builder = response_builder.header(key.as_ref(), value.as_ref());
+                        response_builder = response_builder.header(key, value);
                     }
                     let response = match response_builder.body(()) {
                         Ok(resp) => resp,
@@ -0,0 +1,261 @@
+use super::listener::is_unix_peer;
+use crate::utils::cidr::IpCidr;
+use crate::{HttpMethod, HttpRequest, HttpResponse};
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::path::PathBuf;
+use std::sync::{Arc, LazyLock, RwLock, RwLockReadGuard, RwLockWriteGuard};
+
+/// 标注了 `#[potato::ip_filter(...)]` 的路由及其规则，由宏提交
+///
+/// `use_ip_filter` 先按路由自身的规则校验，路由规则放行而全局规则拒绝时，拒绝推迟到处理函数
+pub struct IpFilterRouteFlag {
+    pub method: HttpMethod,
+    pub path: &'static str,
+    pub filter: &'static LazyLock<IpFilter>,
+}
+
+impl IpFilterRouteFlag {
+    pub const fn new(
+        method: HttpMethod,
+        path: &'static str,
+        filter: &'static LazyLock<IpFilter>,
+    ) -> Self {
+        Self {
+            method,
+            path,
+            filter,
+        }
+    }
+}
+
+inventory::collect!(IpFilterRouteFlag);
+
+type IpFilterRoutes = HashMap<(&'static str, HttpMethod), &'static LazyLock<IpFilter>>;
+
+static IP_FILTER_ROUTES: LazyLock<IpFilterRoutes> = LazyLock::new(|| {
+    inventory::iter::<IpFilterRouteFlag>
+        .into_iter()
+        .map(|flag| ((flag.path, flag.method), flag.filter))
+        .collect()
+});
+
+/// `use_ip_filter` 对标注路由作出的拒绝，暂存于请求扩展中
+///
+/// 仅在路由自身的规则放行时暂存；标注生成的处理函数会移除它，请求最终由其他路由（静态文件、
+/// 目录、反向代理等）响应时，`finish` 将响应替换为 403
+struct IpFilterRejection {
+    client: String,
+    reason: String,
+}
+
+impl IpFilterRejection {
+    fn respond(&self, req: &HttpRequest) -> HttpResponse {
+        eprintln!(
+            "[IpFilter] rejected {} for {} {}: {}",
+            self.client, req.method, req.url_path, self.reason
+        );
+        let mut res = HttpResponse::text("Forbidden");
+        res.http_code = 403;
+        res
+    }
+}
+
+#[derive(Debug, Default)]
+struct IpFilterRules {
+    allow: Vec<IpCidr>,
+    deny: Vec<IpCidr>,
+}
+
+impl IpFilterRules {
+    /// 返回拒绝请求时命中的规则，拒绝列表优先于允许列表
+    fn reject_reason(&self, ip: &IpAddr) -> Option<String> {
+        if let Some(cidr) = self.deny.iter().find(|cidr| cidr.contains(ip)) {
+            return Some(format!("deny {cidr}"));
+        }
+        if !self.allow.is_empty() && !IpCidr::any_contains(&self.allow, ip) {
+            return Some("not in allow list".to_string());
+        }
+        None
+    }
+
+    /// 解析规则文件：每行一条 `allow <网段>` 或 `deny <网段>`，省略动作时为 allow，`#` 之后为注释
+    fn parse(content: &str) -> anyhow::Result<Self> {
+        let mut rules = Self::default();
+        for line in content.lines() {
+            let line = line.split('#').next().unwrap_or_default().trim();
+            if line.is_empty() {
+                continue;
+            }
+            match line.split_once(char::is_whitespace) {
+                Some(("allow", cidr)) => rules.allow.push(cidr.parse()?),
+                Some(("deny", cidr)) => rules.deny.push(cidr.parse()?),
+                Some((action, _)) => anyhow::bail!("unknown ip filter action: {action}"),
+                None => rules.allow.push(line.parse()?),
+            }
+        }
+        Ok(rules)
+    }
+}
+
+/// 按客户端 IP 的 CIDR 列表（IPv4 与 IPv6）放行或拒绝请求，拒绝时返回 403
+///
+/// 命中拒绝列表或允许列表非空但未命中时拒绝，并记录命中的规则。客户端地址取自
+/// `get_client_addr`，配置了 `use_trusted_proxies` 时采信可信代理转发的地址。
+/// 克隆后的实例共享同一份规则，运行中可通过 `set_allow` / `set_deny` / `reload` 更新
+#[derive(Clone, Default)]
+pub struct IpFilter {
+    rules: Arc<RwLock<IpFilterRules>>,
+    source: Option<Arc<PathBuf>>,
+}
+
+impl std::fmt::Debug for IpFilter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let rules = self.read_rules();
+        f.debug_struct("IpFilter")
+            .field("allow", &rules.allow)
+            .field("deny", &rules.deny)
+            .field("source", &self.source)
+            .finish()
+    }
+}
+
+impl IpFilter {
+    fn read_rules(&self) -> RwLockReadGuard<'_, IpFilterRules> {
+        self.rules.read().unwrap_or_else(|err| err.into_inner())
+    }
+
+    fn write_rules(&self) -> RwLockWriteGuard<'_, IpFilterRules> {
+        self.rules.write().unwrap_or_else(|err| err.into_inner())
+    }
+
+    /// 空规则，放行所有请求
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 从规则文件加载，之后可调用 `reload` 重新读取
+    ///
+    /// 文件每行一条 `allow <网段>` 或 `deny <网段>`，省略动作时为 allow，`#` 之后为注释
+    pub fn from_file(path: impl Into<PathBuf>) -> anyhow::Result<Self> {
+        let filter = Self {
+            rules: Arc::default(),
+            source: Some(Arc::new(path.into())),
+        };
+        filter.reload()?;
+        Ok(filter)
+    }
+
+    /// 追加允许的网段（如 `["10.0.0.0/8", "::1"]`），任一项无效即返回错误
+    pub fn with_allow<I, S>(self, cidrs: I) -> anyhow::Result<Self>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let cidrs = IpCidr::parse_list(cidrs)?;
+        self.write_rules().allow.extend(cidrs);
+        Ok(self)
+    }
+
+    /// 追加拒绝的网段，任一项无效即返回错误
+    pub fn with_deny<I, S>(self, cidrs: I) -> anyhow::Result<Self>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let cidrs = IpCidr::parse_list(cidrs)?;
+        self.write_rules().deny.extend(cidrs);
+        Ok(self)
+    }
+
+    /// 替换允许列表，立即对之后的请求生效；解析失败时保留原列表
+    pub fn set_allow<I, S>(&self, cidrs: I) -> anyhow::Result<()>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let cidrs = IpCidr::parse_list(cidrs)?;
+        self.write_rules().allow = cidrs;
+        Ok(())
+    }
+
+    /// 替换拒绝列表，立即对之后的请求生效；解析失败时保留原列表
+    pub fn set_deny<I, S>(&self, cidrs: I) -> anyhow::Result<()>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let cidrs = IpCidr::parse_list(cidrs)?;
+        self.write_rules().deny = cidrs;
+        Ok(())
+    }
+
+    /// 重新读取 `from_file` 指定的规则文件；读取或解析失败时保留原规则
+    pub fn reload(&self) -> anyhow::Result<()> {
+        let Some(path) = &self.source else {
+            anyhow::bail!("ip filter is not loaded from a file");
+        };
+        let content = std::fs::read_to_string(path.as_path())?;
+        *self.write_rules() = IpFilterRules::parse(&content)?;
+        Ok(())
+    }
+
+    /// 地址是否被放行
+    pub fn is_allowed(&self, ip: &IpAddr) -> bool {
+        self.read_rules().reject_reason(ip).is_none()
+    }
+
+    /// 校验客户端地址，拒绝时返回 403 响应；无法获取地址或来自 Unix 域套接字时仅在允许列表为空时放行
+    ///
+    /// 可在自定义处理器中直接调用；`use_ip_filter` 与 `#[potato::ip_filter]` 标注也基于它校验
+    pub async fn check(&self, req: &HttpRequest) -> Option<HttpResponse> {
+        Some(self.rejection(req).await?.respond(req))
+    }
+
+    async fn rejection(&self, req: &HttpRequest) -> Option<IpFilterRejection> {
+        let (client, reason) = match req.get_client_addr().await {
+            Ok(addr) if !is_unix_peer(&addr) => (
+                addr.ip().to_string(),
+                self.read_rules().reject_reason(&addr.ip())?,
+            ),
+            _ if self.read_rules().allow.is_empty() => return None,
+            Ok(_) => ("unix".to_string(), "no client ip address".to_string()),
+            Err(_) => ("unknown".to_string(), "no client address".to_string()),
+        };
+        Some(IpFilterRejection { client, reason })
+    }
+
+    /// 管线中的校验；请求指向 `#[potato::ip_filter]` 标注的路由时先按路由规则校验，
+    /// 路由规则放行而全局规则拒绝时，拒绝结果暂存为请求扩展，由处理函数决定是否覆盖
+    pub(crate) async fn check_route(&self, req: &mut HttpRequest) -> Option<HttpResponse> {
+        let path = &req.url_path[..];
+        let route_filter = IP_FILTER_ROUTES.get(&(path, req.method)).or_else(|| {
+            (req.method == HttpMethod::HEAD)
+                .then(|| IP_FILTER_ROUTES.get(&(path, HttpMethod::GET)))
+                .flatten()
+        });
+        let Some(route_filter) = route_filter else {
+            return Some(self.rejection(req).await?.respond(req));
+        };
+        if let Some(rejection) = route_filter.rejection(req).await {
+            return Some(rejection.respond(req));
+        }
+        let rejection = self.rejection(req).await?;
+        req.add_ext(Arc::new(rejection));
+        None
+    }
+
+    /// 路由级校验，覆盖 `use_ip_filter` 暂存的拒绝结果，由 `#[potato::ip_filter]` 标注生成的代码调用
+    #[doc(hidden)]
+    pub async fn check_override(&self, req: &mut HttpRequest) -> Option<HttpResponse> {
+        req.remove_ext::<IpFilterRejection>();
+        self.check(req).await
+    }
+
+    /// 未被标注路由覆盖的拒绝结果替换为 403 响应
+    pub(crate) fn finish(req: &mut HttpRequest, res: &mut HttpResponse) {
+        if let Some(rejection) = req.remove_ext::<IpFilterRejection>() {
+            *res = rejection.respond(req);
+        }
+    }
+}
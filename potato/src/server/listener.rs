@@ -0,0 +1,135 @@
+use std::net::{Ipv4Addr, SocketAddr};
+#[cfg(unix)]
+use std::os::unix::io::OwnedFd;
+use tokio::net::{TcpListener, TcpStream};
+#[cfg(unix)]
+use tokio::net::{UnixListener, UnixStream};
+
+/// Unix 域套接字地址前缀，如 `unix:/run/app.sock`
+pub(crate) const UNIX_ADDR_PREFIX: &str = "unix:";
+
+/// Unix 域套接字连接没有 IP 地址，`client_addr` 统一填为 `127.0.0.1:0`
+pub(crate) const UNIX_PEER_ADDR: SocketAddr =
+    SocketAddr::new(std::net::IpAddr::V4(Ipv4Addr::LOCALHOST), 0);
+
+/// 是否为 Unix 域套接字连接的占位地址，TCP 连接的对端端口不会为 0
+///
+/// 这类连接既不视为可信代理，也不参与 IP 规则匹配
+pub(crate) fn is_unix_peer(addr: &SocketAddr) -> bool {
+    *addr == UNIX_PEER_ADDR
+}
+
+/// systemd socket activation 传入的第一个文件描述符
+#[cfg(unix)]
+const SD_LISTEN_FDS_START: i32 = 3;
+
+/// 明文 HTTP 监听：TCP 或 Unix 域套接字
+pub(crate) enum PlainListener {
+    Tcp(TcpListener),
+    #[cfg(unix)]
+    Unix(UnixListener),
+}
+
+pub(crate) enum PlainStream {
+    Tcp(TcpStream),
+    #[cfg(unix)]
+    Unix(UnixStream),
+}
+
+impl PlainListener {
+    /// 绑定监听地址，`unix:` 前缀的地址绑定为 Unix 域套接字并按 `unix_mode` 设置文件权限
+    #[cfg_attr(not(unix), allow(unused_variables))]
+    pub(crate) async fn bind(addr: &str, unix_mode: Option<u32>) -> anyhow::Result<Self> {
+        match addr.strip_prefix(UNIX_ADDR_PREFIX) {
+            #[cfg(unix)]
+            Some(path) => Ok(Self::Unix(bind_unix(path, unix_mode)?)),
+            #[cfg(not(unix))]
+            Some(_) => anyhow::bail!("unix domain socket is not supported on this platform"),
+            None => {
+                let addr: SocketAddr = addr.parse()?;
+                Ok(Self::Tcp(TcpListener::bind(&addr).await?))
+            }
+        }
+    }
+
+    pub(crate) async fn accept(&self) -> std::io::Result<(PlainStream, SocketAddr)> {
+        match self {
+            Self::Tcp(listener) => {
+                let (stream, client_addr) = listener.accept().await?;
+                _ = stream.set_nodelay(true);
+                Ok((PlainStream::Tcp(stream), client_addr))
+            }
+            #[cfg(unix)]
+            Self::Unix(listener) => {
+                let (stream, _) = listener.accept().await?;
+                Ok((PlainStream::Unix(stream), UNIX_PEER_ADDR))
+            }
+        }
+    }
+
+    /// 将已打开的监听套接字（TCP 或 Unix）转换为监听，需在 tokio 运行时中调用
+    #[cfg(unix)]
+    pub(crate) fn from_fd(fd: OwnedFd) -> anyhow::Result<Self> {
+        let listener = std::os::unix::net::UnixListener::from(fd);
+        if listener.local_addr().is_ok() {
+            listener.set_nonblocking(true)?;
+            return Ok(Self::Unix(UnixListener::from_std(listener)?));
+        }
+        let listener = std::net::TcpListener::from(OwnedFd::from(listener));
+        if let Err(err) = listener.local_addr() {
+            anyhow::bail!("fd is not a listening socket: {err}");
+        }
+        listener.set_nonblocking(true)?;
+        Ok(Self::Tcp(TcpListener::from_std(listener)?))
+    }
+}
+
+#[cfg(unix)]
+fn bind_unix(path: &str, unix_mode: Option<u32>) -> anyhow::Result<UnixListener> {
+    use std::os::unix::fs::{FileTypeExt, PermissionsExt};
+
+    // 清理上次进程遗留的套接字文件；仍有进程在监听时不抢占
+    if let Ok(meta) = std::fs::symlink_metadata(path) {
+        if meta.file_type().is_socket() {
+            if std::os::unix::net::UnixStream::connect(path).is_ok() {
+                anyhow::bail!("unix socket {path} is already in use");
+            }
+            std::fs::remove_file(path)?;
+        }
+    }
+    let listener = UnixListener::bind(path)?;
+    if let Some(mode) = unix_mode {
+        std::fs::set_permissions(path, std::fs::Permissions::from_mode(mode))?;
+    }
+    Ok(listener)
+}
+
+/// 取出 systemd socket activation 传入的监听套接字（`LISTEN_PID` / `LISTEN_FDS`）
+///
+/// 每个进程只能取出一次，重复调用返回错误
+#[cfg(unix)]
+pub(crate) fn systemd_fds() -> anyhow::Result<Vec<OwnedFd>> {
+    use std::os::unix::io::FromRawFd;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    static ADOPTED: AtomicBool = AtomicBool::new(false);
+
+    let pid = std::env::var("LISTEN_PID")
+        .map_err(|_| anyhow::anyhow!("LISTEN_PID not set, not started by socket activation"))?;
+    if pid.trim().parse::<u32>()? != std::process::id() {
+        anyhow::bail!("LISTEN_PID {pid} does not match current process");
+    }
+    let count: i32 = std::env::var("LISTEN_FDS")
+        .map_err(|_| anyhow::anyhow!("LISTEN_FDS not set, not started by socket activation"))?
+        .trim()
+        .parse()?;
+    if count <= 0 {
+        anyhow::bail!("LISTEN_FDS contains no socket");
+    }
+    if ADOPTED.swap(true, Ordering::SeqCst) {
+        anyhow::bail!("systemd sockets already adopted");
+    }
+    // SAFETY: LISTEN_PID 与本进程一致时，这些描述符由 systemd 传入且仅在此处取出一次
+    Ok((SD_LISTEN_FDS_START..SD_LISTEN_FDS_START + count)
+        .map(|fd| unsafe { OwnedFd::from_raw_fd(fd) })
+        .collect())
+}
@@ -1,7 +1,23 @@
+#[cfg(feature = "auth")]
+mod auth;
+mod cookie_jar;
+mod cors;
+mod csrf;
+mod forwarded;
 #[cfg(feature = "http2")]
 mod http2;
 #[cfg(feature = "http3")]
 mod http3;
+mod ip_filter;
+mod listener;
+#[cfg(feature = "tls")]
+pub(crate) mod ocsp;
+mod oidc;
+mod proxy_protocol;
+mod rate_limit;
+mod security_headers;
+#[cfg(feature = "tls")]
+mod tls;
 
 use crate::utils::enums::HttpConnection;
 use crate::utils::refstr::HeaderItem;
@@ -11,6 +27,21 @@ use crate::{
     HttpHandler, HttpMethod, HttpRequest, HttpRequestTargetForm, HttpResponse, PreflightResult,
 };
 use crate::{RequestHandlerFlag, TransferSession};
+#[cfg(feature = "auth")]
+pub use auth::{check_auth_schemes, ApiKeyAuth, AuthScheme, AuthUser, BasicAuth, DigestAuth};
+pub use cookie_jar::CookieKeys;
+pub use cors::CorsConfig;
+pub use csrf::{CsrfConfig, CsrfExemptFlag, CsrfToken};
+pub use forwarded::TrustedProxies;
+pub use ip_filter::{IpFilter, IpFilterRouteFlag};
+use listener::{PlainListener, PlainStream};
+pub use oidc::{OidcClaims, OidcConfig, OidcValidator};
+pub use rate_limit::{
+    MemoryRateLimitStore, RateLimit, RateLimitKey, RateLimitKeyFn, RateLimitState, RateLimitStore,
+};
+pub use security_headers::{
+    CspNonce, SecurityHeaders, SecurityHeadersOverride, CSP_NONCE_PLACEHOLDER,
+};
 use std::any::TypeId;
 use std::borrow::Cow;
 use std::collections::{HashMap, HashSet};
@@ -22,40 +53,56 @@ use std::path::{Component, Path, PathBuf};
 use std::pin::Pin;
 use std::sync::{Arc, LazyLock};
 use std::time::UNIX_EPOCH;
+#[cfg(feature = "tls")]
+pub use tls::{ClientAuth, ClientCert, SniCertResolver};
+#[cfg(feature = "tls")]
 use tokio::net::TcpListener;
 use tokio::select;
 use tokio::sync::{oneshot, Mutex};
 use tokio::time::{interval, Duration};
 #[cfg(any(feature = "tls", feature = "http3"))]
 use tokio_rustls::rustls;
-#[cfg(any(feature = "tls", feature = "http3"))]
-use tokio_rustls::rustls::pki_types::{pem::PemObject, CertificateDer, PrivateKeyDer};
 #[cfg(feature = "tls")]
 use tokio_rustls::TlsAcceptor;
 
-/// CORS配置
-#[derive(Debug, Clone)]
-pub struct CorsConfig {
-    pub origin: Option<String>,         // Access-Control-Allow-Origin
-    pub methods: Option<String>,        // Access-Control-Allow-Methods
-    pub headers: Option<String>,        // Access-Control-Allow-Headers
-    pub max_age: Option<String>,        // Access-Control-Max-Age
-    pub credentials: bool,              // Access-Control-Allow-Credentials
-    pub expose_headers: Option<String>, // Access-Control-Expose-Headers
+/// `#[potato::auth_scheme(...)]` 生成的校验代码，未启用 `auth` 特性时给出编译错误
+#[cfg(feature = "auth")]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __potato_check_auth_schemes {
+    ($names:expr, $req:expr) => {
+        if let Some(__potato_resp) = $crate::check_auth_schemes($names, $req).await {
+            return __potato_resp;
+        }
+    };
 }
 
-impl CorsConfig {
-    /// 创建最小限制默认配置
-    pub fn default_minimal() -> Self {
-        Self {
-            origin: Some("*".to_string()),
-            methods: None, // 自动计算
-            headers: Some("*".to_string()),
-            max_age: Some("86400".to_string()),
-            credentials: false,
-            expose_headers: None,
-        }
-    }
+#[cfg(not(feature = "auth"))]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __potato_check_auth_schemes {
+    ($names:expr, $req:expr) => {
+        compile_error!("`#[potato::auth_scheme]` requires the `auth` feature of potato");
+    };
+}
+
+/// 组合两组 OpenAPI security：结果中的每项同时满足两侧各一项
+#[cfg(feature = "openapi")]
+fn merge_security(
+    left: &[serde_json::Value],
+    right: &[serde_json::Value],
+) -> Vec<serde_json::Value> {
+    left.iter()
+        .flat_map(|l| {
+            right.iter().map(move |r| {
+                let mut item = l.clone();
+                if let (Some(item), Some(r)) = (item.as_object_mut(), r.as_object()) {
+                    item.extend(r.clone());
+                }
+                item
+            })
+        })
+        .collect()
 }
 
 type AsyncCustomHandler = dyn Fn(&mut HttpRequest) -> Pin<Box<dyn Future<Output = Option<HttpResponse>> + Send + '_>>
@@ -77,6 +124,8 @@ type GlobalPostprocessHandler =
     ) -> Pin<Box<dyn Future<Output = anyhow::Result<()>> + Send + 'a>>;
 
 // Re-export WebTransport types from http3 module
+#[cfg(feature = "http2")]
+pub use http2::Http2Config;
 #[cfg(feature = "http3")]
 pub use http3::{WebTransportConfig, WebTransportHandler, WebTransportSession, WebTransportStream};
 
@@ -128,6 +177,16 @@ pub enum PipeContextItem {
     LimitSize(usize, usize), // (max_header_bytes, max_body_bytes)
     TransferRate(u64, u64),  // (入站速率限制 bits/sec, 出站速率限制 bits/sec)
     ReverseProxy(String, String, bool),
+    TrustedProxies(Arc<TrustedProxies>),
+    IpFilter(IpFilter),
+    Cors(Arc<CorsConfig>),
+    SecurityHeaders(Arc<SecurityHeaders>),
+    Csrf(Arc<CsrfConfig>),
+    Jwks(String),
+    Oidc(Arc<oidc::OidcValidator>),
+    #[cfg(feature = "auth")]
+    Auth(Vec<Arc<AuthScheme>>),
+    RateLimit(Arc<RateLimit>),
     #[cfg(all(feature = "jemalloc", not(target_os = "windows")))]
     Jemalloc(String),
     #[cfg(feature = "webdav")]
@@ -154,6 +213,16 @@ impl Clone for PipeContextItem {
             PipeContextItem::ReverseProxy(v1, v2, v3) => {
                 PipeContextItem::ReverseProxy(v1.clone(), v2.clone(), *v3)
             }
+            PipeContextItem::TrustedProxies(v) => PipeContextItem::TrustedProxies(Arc::clone(v)),
+            PipeContextItem::IpFilter(v) => PipeContextItem::IpFilter(v.clone()),
+            PipeContextItem::Cors(v) => PipeContextItem::Cors(Arc::clone(v)),
+            PipeContextItem::SecurityHeaders(v) => PipeContextItem::SecurityHeaders(Arc::clone(v)),
+            PipeContextItem::Csrf(v) => PipeContextItem::Csrf(Arc::clone(v)),
+            PipeContextItem::Jwks(v) => PipeContextItem::Jwks(v.clone()),
+            PipeContextItem::Oidc(v) => PipeContextItem::Oidc(Arc::clone(v)),
+            #[cfg(feature = "auth")]
+            PipeContextItem::Auth(v) => PipeContextItem::Auth(v.clone()),
+            PipeContextItem::RateLimit(v) => PipeContextItem::RateLimit(Arc::clone(v)),
             #[cfg(all(feature = "jemalloc", not(target_os = "windows")))]
             PipeContextItem::Jemalloc(v) => PipeContextItem::Jemalloc(v.clone()),
             #[cfg(feature = "webdav")]
@@ -173,6 +242,8 @@ impl Clone for PipeContextItem {
 
 pub struct PipeContext {
     items: Vec<PipeContextItem>,
+    #[cfg(feature = "openapi")]
+    openapi_index: Option<(usize, String)>, // use_openapi 生成的文档项位置与 index.json 路径
 }
 
 impl PipeContext {
@@ -434,11 +505,17 @@ impl PipeContext {
     pub fn new() -> Self {
         Self {
             items: vec![PipeContextItem::Handlers],
+            #[cfg(feature = "openapi")]
+            openapi_index: None,
         }
     }
 
     pub fn empty() -> Self {
-        Self { items: vec![] }
+        Self {
+            items: vec![],
+            #[cfg(feature = "openapi")]
+            openapi_index: None,
+        }
     }
 
     pub fn clone_items(&self) -> Vec<PipeContextItem> {
@@ -679,15 +756,248 @@ impl PipeContext {
         ));
     }
 
+    /// 设置可信代理网段（如 `["10.0.0.0/8", "::1"]`）
+    ///
+    /// 之后的中间件与处理函数中，连接来自可信代理时 `get_client_addr` 按 `Forwarded`
+    /// （优先）或 `X-Forwarded-For` 从右向左跳过可信代理，返回第一个不可信的地址，
+    /// 应放在管线最前；`req.client_addr` 始终保留连接的原始地址
+    pub fn use_trusted_proxies<I, S>(&mut self, cidrs: I) -> anyhow::Result<()>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let cidrs = crate::utils::cidr::IpCidr::parse_list(cidrs)?;
+        self.items
+            .push(PipeContextItem::TrustedProxies(Arc::new(TrustedProxies(
+                cidrs,
+            ))));
+        Ok(())
+    }
+
+    /// 按客户端 IP 的 CIDR 列表放行或拒绝之后的请求，拒绝时返回 403 并记录命中的规则
+    ///
+    /// 应放在 `use_trusted_proxies` 之后，以便按转发的客户端地址过滤；保留 `IpFilter` 的克隆
+    /// 可在运行中更新规则。单个路由可用 `#[potato::ip_filter(...)]` 覆盖
+    ///
+    /// # 示例
+    /// ```rust
+    /// let mut server = potato::HttpServer::new("127.0.0.1:8080");
+    /// server.configure(|ctx| {
+    ///     ctx.use_handlers();
+    ///     // 仅对之后的管理页面生效
+    ///     let admin = potato::IpFilter::new().with_allow(["10.0.0.0/8", "::1"]).unwrap();
+    ///     ctx.use_ip_filter(admin);
+    ///     ctx.use_location_route("/admin", "./admin", false);
+    /// });
+    /// ```
+    pub fn use_ip_filter(&mut self, filter: IpFilter) {
+        self.items.push(PipeContextItem::IpFilter(filter));
+    }
+
+    /// 为之后的所有路由（含静态文件、嵌入资源、WebDAV 与反向代理）启用 CORS
+    ///
+    /// 直接应答预检请求，不再经过之后的认证等中间件，应放在管线靠前位置；其他响应按配置附加
+    /// CORS 头，路由的 `#[potato::cors]` 标注或上游已写入 `Access-Control-Allow-Origin` 时以其为准
+    ///
+    /// 直接设置 `credentials` 字段且允许任意来源（`*`）时不允许携带凭证
+    ///
+    /// # 示例
+    /// ```rust
+    /// let mut server = potato::HttpServer::new("127.0.0.1:8080");
+    /// server.configure(|ctx| {
+    ///     ctx.use_cors(
+    ///         potato::CorsConfig::default_minimal()
+    ///             .with_origin("https://*.example.com")
+    ///             .with_credentials()
+    ///             .unwrap(),
+    ///     );
+    ///     ctx.use_handlers();
+    /// });
+    /// ```
+    pub fn use_cors(&mut self, mut config: CorsConfig) {
+        if config.credentials && config.allows_any_origin() {
+            eprintln!("[CORS] credentials disabled: an explicit origin allow-list is required");
+            config.credentials = false;
+        }
+        self.items.push(PipeContextItem::Cors(Arc::new(config)));
+    }
+
+    /// 为之后各项处理产生的响应附加安全头，并为每个请求生成 CSP nonce
+    ///
+    /// 处理函数通过 `req.csp_nonce()` 获取 nonce，CSP 中的 `{nonce}` 占位符替换为同一值；
+    /// 单个路由可用 `#[potato::security_headers(...)]` 覆盖
+    ///
+    /// # 示例
+    /// ```rust
+    /// let mut server = potato::HttpServer::new("127.0.0.1:8080");
+    /// server.configure(|ctx| {
+    ///     ctx.use_security_headers(potato::SecurityHeaders::strict());
+    ///     ctx.use_handlers();
+    /// });
+    /// ```
+    pub fn use_security_headers(&mut self, policy: SecurityHeaders) {
+        self.items
+            .push(PipeContextItem::SecurityHeaders(Arc::new(policy)));
+    }
+
+    /// 启用 CSRF 防护：下发签名的令牌 Cookie，之后的非安全方法（POST、PUT、DELETE 等）须通过
+    /// 请求头或表单字段回传相同令牌，且 `Origin` / `Sec-Fetch-Site` 不得为跨站，否则返回 403
+    ///
+    /// 处理函数通过 `req.csrf_token()` 获取令牌；`#[potato::csrf_exempt]` 标注的路由不做校验
+    ///
+    /// # 示例
+    /// ```rust
+    /// let mut server = potato::HttpServer::new("127.0.0.1:8080");
+    /// server.configure(|ctx| {
+    ///     ctx.use_csrf(potato::CsrfConfig::new().with_exempt_path("/webhooks/"));
+    ///     ctx.use_handlers();
+    /// });
+    /// ```
+    pub fn use_csrf(&mut self, config: CsrfConfig) {
+        self.items.push(PipeContextItem::Csrf(Arc::new(config)));
+    }
+
+    /// 在指定路径发布 `ServerConfig::set_jwt_options` 中非对称密钥的公钥（JWKS），
+    /// 供其他服务校验本服务签发的令牌；密钥轮换后立即生效，对称密钥不会发布
+    ///
+    /// # 示例
+    /// ```rust
+    /// let mut server = potato::HttpServer::new("127.0.0.1:8080");
+    /// server.configure(|ctx| {
+    ///     ctx.use_jwks("/.well-known/jwks.json");
+    ///     ctx.use_handlers();
+    /// });
+    /// ```
+    pub fn use_jwks(&mut self, url_path: impl Into<String>) {
+        self.items.push(PipeContextItem::Jwks(url_path.into()));
+    }
+
+    /// 以 OIDC 资源服务器模式校验身份提供方签发的 Bearer 令牌，校验通过后处理函数可通过
+    /// `req.oidc_claims()` 读取声明；令牌无效时返回 401，无法获取签名公钥时返回 503
+    ///
+    /// # 示例
+    /// ```rust
+    /// let mut server = potato::HttpServer::new("127.0.0.1:8080");
+    /// server.configure(|ctx| {
+    ///     ctx.use_oidc(potato::OidcConfig::new("https://idp.example.com").with_audience("orders"));
+    ///     ctx.use_handlers();
+    /// });
+    /// ```
+    pub fn use_oidc(&mut self, config: OidcConfig) {
+        self.items
+            .push(PipeContextItem::Oidc(Arc::new(oidc::OidcValidator::new(
+                config,
+            ))));
+    }
+
+    /// 之后的所有路由要求通过指定方式认证，否则返回附带 `WWW-Authenticate` 质询的 401；
+    /// 处理函数通过 `req.auth_user()` 读取调用方
+    ///
+    /// # 示例
+    /// ```rust
+    /// let mut server = potato::HttpServer::new("127.0.0.1:8080");
+    /// server.configure(|ctx| {
+    ///     ctx.use_auth(potato::ApiKeyAuth::new("X-API-Key").with_key("ci", "secret-key"));
+    ///     ctx.use_handlers();
+    /// });
+    /// ```
+    #[cfg(feature = "auth")]
+    pub fn use_auth(&mut self, scheme: impl Into<AuthScheme>) {
+        self.items
+            .push(PipeContextItem::Auth(vec![Arc::new(scheme.into())]));
+    }
+
+    /// 同 `use_auth`，满足其中任一认证方式即可，401 响应中列出全部质询
+    #[cfg(feature = "auth")]
+    pub fn use_auth_any(&mut self, schemes: Vec<AuthScheme>) {
+        self.items.push(PipeContextItem::Auth(
+            schemes.into_iter().map(Arc::new).collect(),
+        ));
+    }
+
+    /// 限制之后各路由的请求速率，超出时返回附带 `Retry-After` 的 429
+    ///
+    /// 单个路由可用 `#[potato::rate_limit(...)]` 另行限制，共享存储见 `ServerConfig::set_rate_limit_store`
+    ///
+    /// # 示例
+    /// ```rust
+    /// use std::time::Duration;
+    ///
+    /// let mut server = potato::HttpServer::new("127.0.0.1:8080");
+    /// server.configure(|ctx| {
+    ///     ctx.use_rate_limit(potato::RateLimit::new(100, Duration::from_secs(60)).per_subject());
+    ///     ctx.use_handlers();
+    /// });
+    /// ```
+    pub fn use_rate_limit(&mut self, mut limit: RateLimit) {
+        // 未命名的限流按配置顺序编号，同一配置的多个实例共享存储时编号一致
+        static NEXT_ID: std::sync::atomic::AtomicUsize = std::sync::atomic::AtomicUsize::new(0);
+        if limit.name().is_empty() {
+            let id = NEXT_ID.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+            limit = limit.with_name(format!("pipe-{id}"));
+        }
+        self.items.push(PipeContextItem::RateLimit(Arc::new(limit)));
+    }
+
     #[cfg(all(feature = "jemalloc", not(target_os = "windows")))]
     pub fn use_jemalloc(&mut self, url_path: impl Into<String>) {
         self.items.push(PipeContextItem::Jemalloc(url_path.into()));
     }
 
+    /// 管线中 `use_handlers` 之前的 `use_auth` / `use_auth_any`，转换为文档的全局 `security`
+    ///
+    /// 各项须同时满足，同一项中满足任一认证方式即可
+    #[cfg(all(feature = "openapi", feature = "auth"))]
+    fn openapi_global_security(
+        &self,
+        security_schemes: &mut serde_json::Map<String, serde_json::Value>,
+    ) -> Vec<serde_json::Value> {
+        let mut security = vec![];
+        for item in self.items.iter() {
+            let schemes = match item {
+                PipeContextItem::Handlers => break,
+                PipeContextItem::Auth(schemes) => schemes,
+                _ => continue,
+            };
+            let alternatives: Vec<_> = schemes
+                .iter()
+                .map(|scheme| {
+                    let name = scheme.openapi_name();
+                    security_schemes.insert(name.clone(), scheme.openapi_scheme());
+                    serde_json::json!({ name: [] })
+                })
+                .collect();
+            security = match security.is_empty() {
+                true => alternatives,
+                false => merge_security(&security, &alternatives),
+            };
+        }
+        security
+    }
+
+    /// 在 `configure` 结束时按完整的管线重新生成文档，`use_openapi` 之后添加的全局认证同样写入
+    #[cfg(feature = "openapi")]
+    fn refresh_openapi_index(&mut self) {
+        let Some((index, path)) = self.openapi_index.clone() else {
+            return;
+        };
+        let json = self.openapi_index_json();
+        if let Some(PipeContextItem::EmbeddedRoute(routes)) = self.items.get_mut(index) {
+            routes.insert(path, Cow::Owned(json.into_bytes()));
+        }
+    }
+
     #[cfg(feature = "openapi")]
-    fn openapi_index_json() -> String {
+    fn openapi_index_json(&self) -> String {
         use crate::utils::number::HttpCodeExt;
         let mut any_use_auth = false;
+        // 认证方式仅在启用 auth 特性时写入
+        #[cfg_attr(not(feature = "auth"), allow(unused_mut))]
+        let mut security_schemes = serde_json::Map::new();
+        #[cfg(feature = "auth")]
+        let global_security = self.openapi_global_security(&mut security_schemes);
+        #[cfg(not(feature = "auth"))]
+        let global_security: Vec<serde_json::Value> = vec![];
         static AUTHOR_REGEX: std::sync::LazyLock<Result<regex::Regex, regex::Error>> =
             std::sync::LazyLock::new(|| regex::Regex::new(r"([[:word:]]+)\s*<([^>]+)>"));
         let contact = {
@@ -800,10 +1110,49 @@ impl PipeContext {
                     }
                 }
                 if flag.doc.auth {
-                    root_cur_path["security"] = serde_json::json!([{ "bearerAuth": [] }]);
-                    response_http_codes = vec![200u16, 401, 500];
+                    // OpenAPI 3.1 允许在非 OAuth2 方案中列出所需的角色
+                    let mut names = vec![];
+                    for name in flag.doc.requirements.iter().flat_map(|r| r.names()) {
+                        if !names.contains(name) {
+                            names.push(*name);
+                        }
+                    }
+                    root_cur_path["security"] = serde_json::json!([{ "bearerAuth": names }]);
+                    response_http_codes = match flag.doc.requirements.is_empty() {
+                        true => vec![200u16, 401, 500],
+                        false => vec![200u16, 401, 403, 500],
+                    };
                     any_use_auth = true;
                 }
+                if !flag.doc.auth_schemes.is_empty() {
+                    // 满足任一认证方式即可，同时要求 Bearer 令牌时与之组合
+                    let mut security = vec![];
+                    for name in flag.doc.auth_schemes.iter() {
+                        let mut item = root_cur_path["security"]
+                            .get(0)
+                            .cloned()
+                            .unwrap_or_else(|| serde_json::json!({}));
+                        item[*name] = serde_json::json!([]);
+                        security.push(item);
+                        #[cfg(feature = "auth")]
+                        if let Some(scheme) = crate::ServerConfig::get_auth_scheme(name) {
+                            security_schemes.insert(name.to_string(), scheme.openapi_scheme());
+                        }
+                    }
+                    root_cur_path["security"] = serde_json::Value::Array(security);
+                    if !response_http_codes.contains(&401) {
+                        response_http_codes = vec![200u16, 401, 500];
+                    }
+                }
+                if !global_security.is_empty() {
+                    // 路由自身的 security 会取代全局设置，因此与全局认证组合
+                    if let Some(own) = root_cur_path["security"].as_array() {
+                        root_cur_path["security"] = merge_security(&global_security, own).into();
+                    }
+                    if !response_http_codes.contains(&401) {
+                        response_http_codes.insert(1, 401);
+                    }
+                }
                 for http_code in response_http_codes.into_iter() {
                     let http_code_str = http_code.to_string();
                     root_cur_path["responses"][http_code_str]["description"] =
@@ -841,6 +1190,12 @@ impl PipeContext {
                 "bearerFormat": "JWT",
             });
         }
+        for (name, scheme) in security_schemes {
+            root["components"]["securitySchemes"][name] = scheme;
+        }
+        if !global_security.is_empty() {
+            root["security"] = global_security.into();
+        }
         serde_json::to_string(&root).unwrap_or("{}".to_string())
     }
 
@@ -860,7 +1215,7 @@ impl PipeContext {
         };
         //
         ret.insert(format!("{url_path}index.json"), {
-            let bytes = Self::openapi_index_json().into_bytes();
+            let bytes = self.openapi_index_json().into_bytes();
             let static_bytes: &'static [u8] = Box::leak(bytes.into_boxed_slice());
             Cow::Borrowed(static_bytes)
         });
@@ -899,6 +1254,7 @@ impl PipeContext {
                 ret.insert(format!("{url_path}{name}"), file.data);
             }
         }
+        self.openapi_index = Some((self.items.len(), format!("{url_path}index.json")));
         self.items.push(PipeContextItem::EmbeddedRoute(ret));
     }
 
@@ -962,6 +1318,20 @@ impl PipeContext {
         self2: &PipeContext,
         req: &mut HttpRequest,
         skip: usize,
+    ) -> HttpResponse {
+        let mut res = Self::handle_request_impl(self2, req, skip).await;
+        IpFilter::finish(req, &mut res);
+        SecurityHeaders::finish(req, &mut res);
+        CorsConfig::finish(req, &mut res);
+        csrf::CsrfPendingCookie::finish(req, &mut res);
+        rate_limit::RateLimitStatus::finish(req, &mut res);
+        res
+    }
+
+    async fn handle_request_impl(
+        self2: &PipeContext,
+        req: &mut HttpRequest,
+        skip: usize,
     ) -> HttpResponse {
         if req.method == HttpMethod::CONNECT {
             let mut res = HttpResponse::text("CONNECT method is not implemented");
@@ -1302,6 +1672,75 @@ impl PipeContext {
                     // 速率限制在连接层处理，此处不需要额外处理
                     continue;
                 }
+                PipeContextItem::TrustedProxies(trusted) => {
+                    req.add_ext(Arc::clone(trusted));
+                    continue;
+                }
+                PipeContextItem::IpFilter(filter) => {
+                    if let Some(mut res) = filter.check_route(req).await {
+                        execute_postprocess(&postprocess_handlers, req, &mut res).await;
+                        return res;
+                    }
+                    continue;
+                }
+                PipeContextItem::Cors(config) => {
+                    if let Some(mut res) = config.preflight(req) {
+                        execute_postprocess(&postprocess_handlers, req, &mut res).await;
+                        return res;
+                    }
+                    req.add_ext(Arc::clone(config));
+                    continue;
+                }
+                PipeContextItem::SecurityHeaders(policy) => {
+                    req.add_ext(Arc::new(CspNonce::generate()));
+                    req.add_ext(Arc::clone(policy));
+                    continue;
+                }
+                PipeContextItem::Csrf(config) => {
+                    if let Some(mut res) = config.check(req).await {
+                        execute_postprocess(&postprocess_handlers, req, &mut res).await;
+                        return res;
+                    }
+                    continue;
+                }
+                PipeContextItem::Oidc(validator) => {
+                    if let Some(mut res) = validator.check(req).await {
+                        execute_postprocess(&postprocess_handlers, req, &mut res).await;
+                        return res;
+                    }
+                    continue;
+                }
+                #[cfg(feature = "auth")]
+                PipeContextItem::Auth(schemes) => match auth::authenticate(schemes, req).await {
+                    Ok(user) => {
+                        req.add_ext(Arc::new(user));
+                        continue;
+                    }
+                    Err(mut res) => {
+                        execute_postprocess(&postprocess_handlers, req, &mut res).await;
+                        return res;
+                    }
+                },
+                PipeContextItem::RateLimit(limit) => {
+                    if let Some(mut res) = limit.check(req).await {
+                        execute_postprocess(&postprocess_handlers, req, &mut res).await;
+                        return res;
+                    }
+                    continue;
+                }
+                PipeContextItem::Jwks(path) => {
+                    if path != &req.url_path[..]
+                        || !matches!(req.method, HttpMethod::GET | HttpMethod::HEAD)
+                    {
+                        continue;
+                    }
+                    let jwks = crate::ServerConfig::get_jwt_options().await.jwks();
+                    let mut res =
+                        HttpResponse::json(serde_json::to_string(&jwks).unwrap_or_default());
+                    res.add_header("Cache-Control".into(), "public, max-age=300".into());
+                    execute_postprocess(&postprocess_handlers, req, &mut res).await;
+                    return res;
+                }
                 PipeContextItem::Custom(handler) => match handler {
                     CustomHandler::Sync(handler) => match handler.as_ref()(req) {
                         Some(mut res) => {
@@ -1554,10 +1993,49 @@ impl PipeContext {
     }
 }
 
+/// 监听器传递给每条连接的选项
+#[derive(Clone, Default)]
+pub(crate) struct ConnOptions {
+    /// 不为空时为每个响应附加 `Alt-Svc` 头
+    pub(crate) alt_svc: Option<Arc<str>>,
+    /// 不为空时为每个响应附加 `Strict-Transport-Security` 头
+    pub(crate) hsts: Option<Arc<str>>,
+    /// 连接开头须携带 PROXY 协议头，`client_addr` 取其中的源地址
+    pub(crate) proxy_protocol: bool,
+    /// 明文连接上接受 h2c 时使用的参数
+    #[cfg(feature = "http2")]
+    pub(crate) h2c: Option<http2::H2cContext>,
+    /// mTLS 握手得到的客户端证书，附加到该连接的每个请求上
+    #[cfg(feature = "tls")]
+    pub(crate) client_cert: Option<Arc<ClientCert>>,
+}
+
+/// `HttpServer::serve` 使用的监听配置
+enum ListenerSpec {
+    Http(String), // 地址，`unix:` 前缀表示 Unix 域套接字
+    #[cfg(unix)]
+    Fd(std::os::unix::io::OwnedFd), // 已打开的监听套接字
+    #[cfg(feature = "tls")]
+    Https(String, String, String), // (地址, 证书文件, 私钥文件)
+    #[cfg(feature = "http3")]
+    H3(String, String, String), // (地址, 证书文件, 私钥文件)
+}
+
 pub struct HttpServer {
     addr: String,
     pipe_ctx: Arc<PipeContext>,
     shutdown_signal: Option<oneshot::Receiver<()>>,
+    listeners: Vec<ListenerSpec>,
+    unix_socket_mode: Option<u32>,
+    proxy_protocol: bool,
+    #[cfg(feature = "tls")]
+    cert_resolver: Option<SniCertResolver>,
+    #[cfg(feature = "tls")]
+    client_auth: Option<ClientAuth>,
+    #[cfg(feature = "tls")]
+    ocsp_stapler: Option<ocsp::OcspStapler>,
+    #[cfg(feature = "http2")]
+    http2_config: Arc<Http2Config>,
     #[cfg(feature = "acme")]
     acme_manager: Option<crate::acme::AcmeManager>,
     #[cfg(feature = "acme")]
@@ -1570,6 +2048,17 @@ impl HttpServer {
             addr: addr.into(),
             pipe_ctx: Arc::new(PipeContext::new()),
             shutdown_signal: None,
+            listeners: vec![],
+            unix_socket_mode: None,
+            proxy_protocol: false,
+            #[cfg(feature = "tls")]
+            cert_resolver: None,
+            #[cfg(feature = "tls")]
+            client_auth: None,
+            #[cfg(feature = "tls")]
+            ocsp_stapler: None,
+            #[cfg(feature = "http2")]
+            http2_config: Arc::new(Http2Config::default()),
             #[cfg(feature = "acme")]
             acme_manager: None,
             #[cfg(feature = "acme")]
@@ -1593,7 +2082,7 @@ impl HttpServer {
             loop {
                 interval.tick().await;
                 // 调用SessionCache的清理方法
-                crate::SessionCache::cleanup_expired_sessions();
+                crate::SessionCache::cleanup_expired_sessions().await;
             }
         });
     }
@@ -1601,6 +2090,8 @@ impl HttpServer {
     pub fn configure(&mut self, callback: impl Fn(&mut PipeContext)) {
         let mut ctx = PipeContext::empty();
         callback(&mut ctx);
+        #[cfg(feature = "openapi")]
+        ctx.refresh_openapi_index();
         self.pipe_ctx = Arc::new(ctx);
     }
 
@@ -1613,6 +2104,126 @@ impl HttpServer {
         Ok(tx)
     }
 
+    /// 设置 HTTP/2 连接参数（SETTINGS、保活、空闲超时及防滥用限制）
+    #[cfg(feature = "http2")]
+    pub fn set_http2_config(&mut self, config: Http2Config) -> &mut Self {
+        self.http2_config = Arc::new(config);
+        self
+    }
+
+    /// 要求所有 TCP/Unix 监听的连接以 HAProxy PROXY 协议（v1/v2）头开头，默认关闭
+    /// 仅应在服务只能经由 L4 负载均衡访问时开启，`client_addr` 将取协议头中的源地址
+    pub fn set_proxy_protocol(&mut self, enabled: bool) -> &mut Self {
+        self.proxy_protocol = enabled;
+        self
+    }
+
+    /// 设置按 SNI 选择证书的证书库，供 `serve_https` / `serve_http2` / `serve_http3`
+    /// 及 `listen_https` / `listen_h3` 使用；此时 `cert_file`/`key_file` 参数可为空，
+    /// 不为空时作为证书库无匹配（且无默认证书）时的兜底证书
+    #[cfg(feature = "tls")]
+    pub fn set_cert_resolver(&mut self, resolver: SniCertResolver) -> &mut Self {
+        self.cert_resolver = Some(resolver);
+        self
+    }
+
+    /// 启用双向 TLS，按 `ClientAuth` 校验客户端证书，对所有 TLS 监听（含 HTTP/2、HTTP/3）生效
+    /// 校验通过的证书信息可在处理函数中通过 `req.client_cert()` 获取
+    #[cfg(feature = "tls")]
+    pub fn set_client_auth(&mut self, client_auth: ClientAuth) -> &mut Self {
+        self.client_auth = Some(client_auth);
+        self
+    }
+
+    /// 启用 OCSP 装订，对所有 TLS 监听及 `serve_acme` 生效，默认关闭
+    /// 证书首次用于握手后在后台向证书中的 OCSP 地址获取响应，并在过期前自动刷新
+    #[cfg(feature = "tls")]
+    pub fn set_ocsp_stapling(&mut self, enabled: bool) -> &mut Self {
+        self.ocsp_stapler = enabled.then(ocsp::OcspStapler::default);
+        self
+    }
+
+    /// 添加明文 HTTP/1.1 监听地址，需配合 `serve` 启动
+    /// 地址形如 `unix:/run/app.sock` 时监听 Unix 域套接字
+    pub fn listen_http(&mut self, addr: impl Into<String>) -> &mut Self {
+        self.listeners.push(ListenerSpec::Http(addr.into()));
+        self
+    }
+
+    /// 设置 Unix 域套接字文件的权限（如 `0o660`），默认由 umask 决定
+    #[cfg(unix)]
+    pub fn set_unix_socket_mode(&mut self, mode: u32) -> &mut Self {
+        self.unix_socket_mode = Some(mode);
+        self
+    }
+
+    /// 添加已打开的监听套接字（TCP 或 Unix），按明文 HTTP 提供服务，需配合 `serve` 启动
+    /// 可用于从父进程继承监听套接字实现无中断重启
+    #[cfg(unix)]
+    pub fn listen_fd(&mut self, fd: std::os::unix::io::OwnedFd) -> &mut Self {
+        self.listeners.push(ListenerSpec::Fd(fd));
+        self
+    }
+
+    /// 接管 systemd socket activation 传入的全部监听套接字（`LISTEN_PID` / `LISTEN_FDS`）
+    /// 未由 systemd 启动或已接管过时返回错误
+    #[cfg(unix)]
+    pub fn listen_systemd(&mut self) -> anyhow::Result<&mut Self> {
+        for fd in listener::systemd_fds()? {
+            self.listeners.push(ListenerSpec::Fd(fd));
+        }
+        Ok(self)
+    }
+
+    /// 添加 HTTPS 监听地址，需配合 `serve` 启动
+    /// 启用 `http2` 特性时通过 ALPN 同时提供 HTTP/2 与 HTTP/1.1
+    #[cfg(feature = "tls")]
+    pub fn listen_https(
+        &mut self,
+        addr: impl Into<String>,
+        cert_file: impl Into<String>,
+        key_file: impl Into<String>,
+    ) -> &mut Self {
+        self.listeners.push(ListenerSpec::Https(
+            addr.into(),
+            cert_file.into(),
+            key_file.into(),
+        ));
+        self
+    }
+
+    /// 添加 HTTP/3 (UDP) 监听地址，需配合 `serve` 启动
+    /// HTTPS 监听的响应会自动携带 `Alt-Svc` 头声明此端口
+    #[cfg(feature = "http3")]
+    pub fn listen_h3(
+        &mut self,
+        addr: impl Into<String>,
+        cert_file: impl Into<String>,
+        key_file: impl Into<String>,
+    ) -> &mut Self {
+        self.listeners.push(ListenerSpec::H3(
+            addr.into(),
+            cert_file.into(),
+            key_file.into(),
+        ));
+        self
+    }
+
+    /// 同时启动所有 `listen_*` 添加的监听，共享同一个 `PipeContext`
+    /// 任意一个监听出错时返回该错误并停止其余监听
+    pub async fn serve(&mut self) -> anyhow::Result<()> {
+        let shutdown_signal = self.shutdown_signal.take();
+        match shutdown_signal {
+            Some(shutdown_signal) => {
+                select! {
+                    result = self.serve_listeners_impl() => result,
+                    _ = shutdown_signal => Ok(()),
+                }
+            }
+            None => self.serve_listeners_impl().await,
+        }
+    }
+
     pub async fn serve_http(&mut self) -> anyhow::Result<()> {
         let shutdown_signal = self.shutdown_signal.take();
         match shutdown_signal {
@@ -1644,18 +2255,22 @@ impl HttpServer {
     pub async fn serve_http2(&mut self, cert_file: &str, key_file: &str) -> anyhow::Result<()> {
         let shutdown_signal = self.shutdown_signal.take();
         let pipe_ctx = Arc::clone(&self.pipe_ctx);
+        let config = Arc::clone(&self.http2_config);
+        let opts = ConnOptions {
+            proxy_protocol: self.proxy_protocol,
+            ..Default::default()
+        };
+        let alpn = vec![b"h2".to_vec(), b"http/1.1".to_vec()];
+        let acceptor = self.tls_acceptor(cert_file, key_file, alpn)?;
         let addr = self.addr.clone();
         match shutdown_signal {
             Some(shutdown_signal) => {
                 select! {
-                    result = http2::serve_http2_impl(&addr, cert_file, key_file, pipe_ctx) => result,
+                    result = http2::serve_http2_impl(&addr, acceptor, pipe_ctx, config, opts) => result,
                     _ = shutdown_signal => Ok(()),
                 }
             }
-            None => {
-                http2::serve_http2_impl(&self.addr, cert_file, key_file, Arc::clone(&self.pipe_ctx))
-                    .await
-            }
+            None => http2::serve_http2_impl(&addr, acceptor, pipe_ctx, config, opts).await,
         }
     }
 
@@ -1663,18 +2278,16 @@ impl HttpServer {
     pub async fn serve_http3(&mut self, cert_file: &str, key_file: &str) -> anyhow::Result<()> {
         let shutdown_signal = self.shutdown_signal.take();
         let pipe_ctx = Arc::clone(&self.pipe_ctx);
+        let tls_config = self.tls_server_config(cert_file, key_file, vec![b"h3".to_vec()])?;
         let addr = self.addr.clone();
         match shutdown_signal {
             Some(shutdown_signal) => {
                 select! {
-                    result = http3::serve_http3_impl(&addr, cert_file, key_file, pipe_ctx) => result,
+                    result = http3::serve_http3_impl(&addr, tls_config, pipe_ctx) => result,
                     _ = shutdown_signal => Ok(()),
                 }
             }
-            None => {
-                http3::serve_http3_impl(&self.addr, cert_file, key_file, Arc::clone(&self.pipe_ctx))
-                    .await
-            }
+            None => http3::serve_http3_impl(&addr, tls_config, pipe_ctx).await,
         }
     }
 
@@ -1711,8 +2324,18 @@ impl HttpServer {
     #[cfg(feature = "acme")]
     pub async fn serve_acme_with_opts(
         &mut self,
-        opts: crate::acme::AcmeOptions,
+        mut opts: crate::acme::AcmeOptions,
     ) -> anyhow::Result<()> {
+        if !matches!(opts.redirect_status, 301 | 308) {
+            anyhow::bail!(
+                "unsupported redirect status {}, use 301 or 308",
+                opts.redirect_status
+            );
+        }
+        opts.ocsp_stapling |= self.ocsp_stapler.is_some();
+        let http_listen = opts.http_listen.take();
+        let redirect_status = opts.redirect_status;
+        let hsts: Option<Arc<str>> = opts.hsts.take().map(Into::into);
         let (acme_manager, acme_acceptor) = crate::acme::AcmeManager::new(opts).await?;
 
         // 启动后台续期循环
@@ -1724,25 +2347,122 @@ impl HttpServer {
             }
         });
 
+        // 明文HTTP监听：应答HTTP-01验证，其余请求跳转HTTPS
+        let http_task = match http_listen {
+            Some(http_listen) => {
+                let listener = TcpListener::bind(http_listen.parse::<SocketAddr>()?).await?;
+                let https_port = self.addr.parse::<SocketAddr>()?.port();
+                let pipe_ctx = Arc::new(Self::acme_http_pipe(
+                    acme_manager.clone(),
+                    https_port,
+                    redirect_status,
+                ));
+                let proxy_protocol = self.proxy_protocol;
+                Some(tokio::spawn(async move {
+                    loop {
+                        let Ok((stream, client_addr)) = listener.accept().await else {
+                            continue;
+                        };
+                        _ = stream.set_nodelay(true);
+                        let opts = ConnOptions {
+                            proxy_protocol,
+                            ..Default::default()
+                        };
+                        Self::spawn_plain_connection(
+                            stream,
+                            HttpStream::from_tcp,
+                            Arc::clone(&pipe_ctx),
+                            client_addr,
+                            opts,
+                        );
+                    }
+                }))
+            }
+            None => None,
+        };
+
         self.acme_manager = Some(acme_manager);
         self.acme_acceptor = Some(acme_acceptor);
 
         let shutdown_signal = self.shutdown_signal.take();
-        match shutdown_signal {
+        let result = match shutdown_signal {
             Some(shutdown_signal) => {
                 select! {
-                    result = self.serve_acme_impl() => result,
+                    result = self.serve_acme_impl(hsts) => result,
                     _ = shutdown_signal => Ok(()),
                 }
             }
-            None => self.serve_acme_impl().await,
+            None => self.serve_acme_impl(hsts).await,
+        };
+        if let Some(http_task) = http_task {
+            http_task.abort();
+        }
+        result
+    }
+
+    /// ACME明文HTTP监听的处理管线：`/.well-known/acme-challenge/` 返回验证内容，
+    /// 其余请求以 `redirect_status` 跳转到 `https_port` 上的同一地址
+    #[cfg(feature = "acme")]
+    fn acme_http_pipe(
+        acme_manager: crate::acme::AcmeManager,
+        https_port: u16,
+        redirect_status: u16,
+    ) -> PipeContext {
+        let mut pipe_ctx = PipeContext::new();
+        pipe_ctx.use_custom(move |req| {
+            let token = req
+                .url_path
+                .strip_prefix("/.well-known/acme-challenge/")
+                .map(|token| token.to_string());
+            let location = req
+                .get_header_host()
+                .and_then(|host| Self::https_location(host, https_port, &req.request_target()));
+            let acme_manager = acme_manager.clone();
+            async move {
+                if let Some(token) = token {
+                    return Some(match acme_manager.challenge_response(&token).await {
+                        Some(key_authorization) => HttpResponse::text(key_authorization),
+                        None => HttpResponse::not_found(),
+                    });
+                }
+                let Some(location) = location else {
+                    return Some(HttpResponse::bad_request("missing or invalid Host header"));
+                };
+                let mut res = HttpResponse::empty();
+                res.http_code = redirect_status;
+                res.add_header("Location".into(), location.into());
+                Some(res)
+            }
+        });
+        pipe_ctx
+    }
+
+    /// 构造跳转地址，`host` 中的端口替换为 `https_port`（443时省略），非法主机名返回 `None`
+    #[cfg(feature = "acme")]
+    fn https_location(host: &str, https_port: u16, target: &str) -> Option<String> {
+        let hostname = match host.strip_prefix('[') {
+            Some(rest) => &host[..rest.find(']')? + 2],
+            None => host.split(':').next()?,
+        };
+        let valid = |c: char| c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | ':' | '[' | ']');
+        if hostname.is_empty() || !hostname.chars().all(valid) {
+            return None;
         }
+        let target = match target.starts_with('/') {
+            true => target,
+            false => "/",
+        };
+        Some(match https_port {
+            443 => format!("https://{hostname}{target}"),
+            port => format!("https://{hostname}:{port}{target}"),
+        })
     }
 
     pub(crate) fn spawn_http1_connection(
         pipe_ctx: Arc<PipeContext>,
         client_addr: SocketAddr,
         stream: HttpStream,
+        opts: ConnOptions,
     ) {
         // 检查是否有速率限制配置
         let rate_limit = pipe_ctx.items.iter().find_map(|item| {
@@ -1796,11 +2516,58 @@ impl HttpServer {
                         }
                     }
                 };
+                #[cfg(feature = "http2")]
+                if opts.h2c.is_some() {
+                    let upgrade = match http2::h2c_upgrade(&req) {
+                        Some(upgrade) if stream.lock().await.is_plain_tcp() => Some(upgrade),
+                        _ => None,
+                    };
+                    if let Some(upgrade) = upgrade {
+                        let mut pre_read = buf.get(n..).unwrap_or_default().to_vec();
+                        match Arc::try_unwrap(stream) {
+                            Ok(stream) => {
+                                if let Some((tcp, rest)) = stream.into_inner().into_plain_tcp() {
+                                    pre_read.extend_from_slice(&rest);
+                                    http2::serve_h2c_upgrade(
+                                        tcp,
+                                        pre_read,
+                                        upgrade,
+                                        pipe_ctx,
+                                        client_addr,
+                                        opts,
+                                    )
+                                    .await;
+                                }
+                            }
+                            Err(stream) => {
+                                // 连接仍被其他引用持有，无法移交给 HTTP/2，以 500 告知客户端后关闭
+                                let mut res =
+                                    HttpResponse::error("h2c upgrade failed: connection is in use");
+                                res.add_header("Connection".into(), "close".into());
+                                let mut stream_guard = stream.lock().await;
+                                let _ = res
+                                    .write_to_stream(&mut stream_guard, CompressMode::None, None)
+                                    .await;
+                            }
+                        }
+                        break;
+                    }
+                }
                 req.client_addr = Some(client_addr);
                 req.add_ext(Arc::clone(&stream));
+                #[cfg(feature = "tls")]
+                if let Some(client_cert) = &opts.client_cert {
+                    req.add_ext(Arc::clone(client_cert));
+                }
                 let cmode = req.get_header_accept_encoding();
                 let conn = req.get_header_connection();
                 let mut res = PipeContext::handle_request(pipe_ctx.as_ref(), &mut req, 0).await;
+                if let Some(alt_svc) = &opts.alt_svc {
+                    res.add_header("Alt-Svc".into(), alt_svc.to_string().into());
+                }
+                if let Some(hsts) = &opts.hsts {
+                    res.add_header("Strict-Transport-Security".into(), hsts.to_string().into());
+                }
                 if conn != HttpConnection::KeepAlive {
                     res.add_header("Connection".into(), "close".into());
                 }
@@ -1838,27 +2605,32 @@ impl HttpServer {
         });
     }
 
+    /// 构造服务端 TLS 配置，设置了证书库时按 SNI 选择证书
     #[cfg(feature = "tls")]
-    fn tls_acceptor_with_alpn(
+    fn tls_server_config(
+        &self,
         cert_file: &str,
         key_file: &str,
-        alpn: Option<Vec<Vec<u8>>>,
-    ) -> anyhow::Result<TlsAcceptor> {
-        // 初始化 rustls CryptoProvider（如果尚未初始化）
-        {
-            use rustls::crypto::ring::default_provider;
-            use rustls::crypto::CryptoProvider;
-            let _ = CryptoProvider::install_default(default_provider());
-        }
+        alpn: Vec<Vec<u8>>,
+    ) -> anyhow::Result<rustls::ServerConfig> {
+        tls::server_config(
+            cert_file,
+            key_file,
+            self.cert_resolver.as_ref(),
+            self.client_auth.as_ref(),
+            self.ocsp_stapler.as_ref(),
+            alpn,
+        )
+    }
 
-        let certs = CertificateDer::pem_file_iter(cert_file)?.collect::<Result<Vec<_>, _>>()?;
-        let key = PrivateKeyDer::from_pem_file(key_file)?;
-        let mut config = rustls::ServerConfig::builder()
-            .with_no_client_auth()
-            .with_single_cert(certs, key)?;
-        if let Some(alpn) = alpn {
-            config.alpn_protocols = alpn;
-        }
+    #[cfg(feature = "tls")]
+    fn tls_acceptor(
+        &self,
+        cert_file: &str,
+        key_file: &str,
+        alpn: Vec<Vec<u8>>,
+    ) -> anyhow::Result<TlsAcceptor> {
+        let config = self.tls_server_config(cert_file, key_file, alpn)?;
         Ok(TlsAcceptor::from(Arc::new(config)))
     }
 
@@ -1869,18 +2641,198 @@ impl HttpServer {
         // 启动后台SessionCache清理任务
         Self::start_session_cache_cleanup();
 
-        let addr: SocketAddr = self.addr.parse()?;
-        let listener = TcpListener::bind(&addr).await?;
-        let pipe_ctx = Arc::clone(&self.pipe_ctx);
+        let listener = PlainListener::bind(&self.addr, self.unix_socket_mode).await?;
+        // 本函数退出（如收到关闭信号）时 shutdown_tx 被释放，h2c 连接据此优雅关闭
+        let (_shutdown_tx, shutdown_rx) = tokio::sync::watch::channel(());
+        let opts = self.plain_conn_options(&shutdown_rx);
+        Self::accept_http_loop(listener, Arc::clone(&self.pipe_ctx), opts).await
+    }
+
+    /// 明文监听的连接选项，启用 `http2` 特性时按配置接受 h2c
+    #[cfg_attr(not(feature = "http2"), allow(unused_variables))]
+    fn plain_conn_options(&self, shutdown_rx: &tokio::sync::watch::Receiver<()>) -> ConnOptions {
+        ConnOptions {
+            proxy_protocol: self.proxy_protocol,
+            #[cfg(feature = "http2")]
+            h2c: self.http2_config.h2c.then(|| http2::H2cContext {
+                config: Arc::clone(&self.http2_config),
+                shutdown_rx: shutdown_rx.clone(),
+            }),
+            ..Default::default()
+        }
+    }
 
+    async fn accept_http_loop(
+        listener: PlainListener,
+        pipe_ctx: Arc<PipeContext>,
+        opts: ConnOptions,
+    ) -> anyhow::Result<()> {
         loop {
             let (stream, client_addr) = listener.accept().await?;
-            _ = stream.set_nodelay(true);
-            Self::spawn_http1_connection(
-                Arc::clone(&pipe_ctx),
+            let pipe_ctx = Arc::clone(&pipe_ctx);
+            match stream {
+                PlainStream::Tcp(stream) => Self::spawn_plain_connection(
+                    stream,
+                    HttpStream::from_tcp,
+                    pipe_ctx,
+                    client_addr,
+                    opts.clone(),
+                ),
+                #[cfg(unix)]
+                PlainStream::Unix(stream) => Self::spawn_plain_connection(
+                    stream,
+                    HttpStream::from_unix,
+                    pipe_ctx,
+                    client_addr,
+                    opts.clone(),
+                ),
+            }
+        }
+    }
+
+    /// 处理明文连接，启用 PROXY 协议时先读取协议头
+    fn spawn_plain_connection<S>(
+        mut stream: S,
+        into_http: fn(S) -> HttpStream,
+        pipe_ctx: Arc<PipeContext>,
+        client_addr: SocketAddr,
+        opts: ConnOptions,
+    ) where
+        S: tokio::io::AsyncRead + tokio::io::AsyncWrite + Unpin + Send + 'static,
+    {
+        if !opts.proxy_protocol {
+            Self::dispatch_plain_connection(stream, into_http, pipe_ctx, client_addr, opts);
+            return;
+        }
+        _ = tokio::task::spawn(async move {
+            if let Ok(client_addr) =
+                proxy_protocol::resolve_client_addr(&mut stream, client_addr, true).await
+            {
+                Self::dispatch_plain_connection(stream, into_http, pipe_ctx, client_addr, opts);
+            }
+        });
+    }
+
+    /// 启用 h2c 时先识别 HTTP/2 连接前言，否则按 HTTP/1.1 处理
+    fn dispatch_plain_connection<S>(
+        stream: S,
+        into_http: fn(S) -> HttpStream,
+        pipe_ctx: Arc<PipeContext>,
+        client_addr: SocketAddr,
+        opts: ConnOptions,
+    ) where
+        S: tokio::io::AsyncRead + tokio::io::AsyncWrite + Unpin + Send + 'static,
+    {
+        #[cfg(feature = "http2")]
+        if opts.h2c.is_some() {
+            _ = tokio::task::spawn(http2::serve_plain_connection(
+                stream,
+                into_http,
+                pipe_ctx,
                 client_addr,
-                HttpStream::from_tcp(stream),
-            );
+                opts,
+            ));
+            return;
+        }
+        Self::spawn_http1_connection(pipe_ctx, client_addr, into_http(stream), opts);
+    }
+
+    /// 根据 HTTP/3 监听生成 `Alt-Svc` 头的值
+    fn listeners_alt_svc(&self) -> anyhow::Result<Option<Arc<str>>> {
+        #[allow(unused_mut)]
+        let mut entries: Vec<String> = vec![];
+        #[cfg(feature = "http3")]
+        for listener in self.listeners.iter() {
+            if let ListenerSpec::H3(addr, _, _) = listener {
+                let addr: SocketAddr = addr.parse()?;
+                entries.push(format!("h3=\":{}\"; ma=86400", addr.port()));
+            }
+        }
+        Ok(match entries.is_empty() {
+            true => None,
+            false => Some(entries.join(", ").into()),
+        })
+    }
+
+    async fn serve_listeners_impl(&mut self) -> anyhow::Result<()> {
+        if self.listeners.is_empty() {
+            anyhow::bail!("no listener configured, call listen_http/listen_https/listen_h3 first");
+        }
+
+        #[cfg(all(feature = "jemalloc", not(target_os = "windows")))]
+        crate::init_jemalloc()?;
+
+        // 启动后台SessionCache清理任务
+        Self::start_session_cache_cleanup();
+
+        #[cfg_attr(not(feature = "tls"), allow(unused_variables))]
+        let alt_svc = self.listeners_alt_svc()?;
+        // 本函数退出时 shutdown_tx 被释放，h2c 连接据此优雅关闭
+        let (_shutdown_tx, shutdown_rx) = tokio::sync::watch::channel(());
+        let mut tasks = tokio::task::JoinSet::new();
+        for listener in self.listeners.iter() {
+            let pipe_ctx = Arc::clone(&self.pipe_ctx);
+            match listener {
+                ListenerSpec::Http(addr) => {
+                    let listener = PlainListener::bind(addr, self.unix_socket_mode).await?;
+                    let opts = self.plain_conn_options(&shutdown_rx);
+                    tasks.spawn(Self::accept_http_loop(listener, pipe_ctx, opts));
+                }
+                #[cfg(unix)]
+                ListenerSpec::Fd(fd) => {
+                    let listener = PlainListener::from_fd(fd.try_clone()?)?;
+                    let opts = self.plain_conn_options(&shutdown_rx);
+                    tasks.spawn(Self::accept_http_loop(listener, pipe_ctx, opts));
+                }
+                #[cfg(feature = "tls")]
+                ListenerSpec::Https(addr, cert_file, key_file) => {
+                    let addr: SocketAddr = addr.parse()?;
+                    let listener = TcpListener::bind(&addr).await?;
+                    #[cfg(feature = "http2")]
+                    {
+                        let alpn = vec![b"h2".to_vec(), b"http/1.1".to_vec()];
+                        let acceptor = self.tls_acceptor(cert_file, key_file, alpn)?;
+                        let opts = ConnOptions {
+                            alt_svc: alt_svc.clone(),
+                            proxy_protocol: self.proxy_protocol,
+                            ..Default::default()
+                        };
+                        tasks.spawn(http2::serve_http2_on(
+                            listener,
+                            acceptor,
+                            pipe_ctx,
+                            Arc::clone(&self.http2_config),
+                            opts,
+                        ));
+                    }
+                    #[cfg(not(feature = "http2"))]
+                    {
+                        let acceptor = self.tls_acceptor(cert_file, key_file, vec![])?;
+                        let opts = ConnOptions {
+                            alt_svc: alt_svc.clone(),
+                            proxy_protocol: self.proxy_protocol,
+                            ..Default::default()
+                        };
+                        tasks.spawn(Self::accept_https_loop(listener, acceptor, pipe_ctx, opts));
+                    }
+                }
+                #[cfg(feature = "http3")]
+                ListenerSpec::H3(addr, cert_file, key_file) => {
+                    let addr = addr.clone();
+                    let tls_config =
+                        self.tls_server_config(cert_file, key_file, vec![b"h3".to_vec()])?;
+                    tasks.spawn(async move {
+                        http3::serve_http3_impl(&addr, tls_config, pipe_ctx).await
+                    });
+                }
+            }
+        }
+
+        // 监听循环正常情况下不会退出，任一结束即视为整体结束（JoinSet 析构时中止其余任务）
+        match tasks.join_next().await {
+            Some(Ok(result)) => result,
+            Some(Err(err)) => Err(err.into()),
+            None => Ok(()),
         }
     }
 
@@ -1891,30 +2843,59 @@ impl HttpServer {
 
         let addr: SocketAddr = self.addr.parse()?;
         let listener = TcpListener::bind(&addr).await?;
-        let acceptor = Self::tls_acceptor_with_alpn(cert_file, key_file, None)?;
-        let pipe_ctx = Arc::clone(&self.pipe_ctx);
+        let acceptor = self.tls_acceptor(cert_file, key_file, vec![])?;
+        let opts = ConnOptions {
+            proxy_protocol: self.proxy_protocol,
+            ..Default::default()
+        };
+        Self::accept_https_loop(listener, acceptor, Arc::clone(&self.pipe_ctx), opts).await
+    }
 
+    #[cfg(feature = "tls")]
+    async fn accept_https_loop(
+        listener: TcpListener,
+        acceptor: TlsAcceptor,
+        pipe_ctx: Arc<PipeContext>,
+        opts: ConnOptions,
+    ) -> anyhow::Result<()> {
         loop {
             let (stream, client_addr) = listener.accept().await?;
             _ = stream.set_nodelay(true);
             let acceptor = acceptor.clone();
             let pipe_ctx2 = Arc::clone(&pipe_ctx);
+            let opts2 = opts.clone();
             _ = tokio::task::spawn(async move {
+                let mut stream = stream;
+                let client_addr = match proxy_protocol::resolve_client_addr(
+                    &mut stream,
+                    client_addr,
+                    opts2.proxy_protocol,
+                )
+                .await
+                {
+                    Ok(client_addr) => client_addr,
+                    Err(_) => return,
+                };
                 let stream = match acceptor.accept(stream).await {
                     Ok(stream) => stream,
                     Err(_) => return,
                 };
+                let opts2 = ConnOptions {
+                    client_cert: tls::peer_client_cert(stream.get_ref().1),
+                    ..opts2
+                };
                 Self::spawn_http1_connection(
                     pipe_ctx2,
                     client_addr,
                     HttpStream::from_server_tls(stream),
+                    opts2,
                 );
             });
         }
     }
 
     #[cfg(feature = "acme")]
-    async fn serve_acme_impl(&mut self) -> anyhow::Result<()> {
+    async fn serve_acme_impl(&mut self, hsts: Option<Arc<str>>) -> anyhow::Result<()> {
         #[cfg(all(feature = "jemalloc", not(target_os = "windows")))]
         crate::init_jemalloc()?;
 
@@ -1930,6 +2911,7 @@ impl HttpServer {
             .as_ref()
             .ok_or_else(|| anyhow::anyhow!("ACME manager not initialized"))?;
         let acme_manager_clone = acme_manager.clone();
+        let proxy_protocol = self.proxy_protocol;
 
         loop {
             let (stream, client_addr) = listener.accept().await?;
@@ -1938,12 +2920,32 @@ impl HttpServer {
             let pipe_ctx2 = Arc::clone(&pipe_ctx);
             let acme_manager2 = acme_manager_clone.clone();
             let acceptor_clone = acceptor.clone();
+            let opts = ConnOptions {
+                hsts: hsts.clone(),
+                ..Default::default()
+            };
 
             _ = tokio::task::spawn(async move {
-                let stream = match acceptor_clone.accept(stream).await {
+                let mut stream = stream;
+                let client_addr = match proxy_protocol::resolve_client_addr(
+                    &mut stream,
+                    client_addr,
+                    proxy_protocol,
+                )
+                .await
+                {
+                    Ok(client_addr) => client_addr,
+                    Err(_) => return,
+                };
+                let mut stream = match acceptor_clone.accept(stream).await {
                     Ok(stream) => stream,
                     Err(_) => return,
                 };
+                // TLS-ALPN-01验证只需完成握手
+                if stream.get_ref().1.alpn_protocol() == Some(crate::acme::ACME_TLS_ALPN) {
+                    _ = tokio::io::AsyncWriteExt::shutdown(&mut stream).await;
+                    return;
+                }
 
                 // 直接处理ACME挑战请求
                 Self::handle_acme_or_normal(
@@ -1951,6 +2953,7 @@ impl HttpServer {
                     client_addr,
                     HttpStream::from_server_tls(stream),
                     &acme_manager2,
+                    opts,
                 )
                 .await;
             });
@@ -1963,6 +2966,7 @@ impl HttpServer {
         client_addr: SocketAddr,
         mut stream: HttpStream,
         acme_manager: &crate::acme::AcmeManager,
+        opts: ConnOptions,
     ) {
         // 先读取部分数据检查是否是ACME挑战
         let mut buf = vec![0u8; 4096];
@@ -1987,18 +2991,15 @@ impl HttpServer {
                 let full_path = &initial_data[path_start..path_end];
                 let token = &full_path["/.well-known/acme-challenge/".len()..];
 
-                let challenges = acme_manager.get_challenges().await;
-                for challenge in challenges {
-                    if challenge.token == token {
-                        // 返回ACME挑战响应
-                        let response = format!(
-                            "HTTP/1.1 200 OK\r\nContent-Type: text/plain\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
-                            challenge.key_authorization.len(),
-                            challenge.key_authorization
-                        );
-                        let _ = stream.write_all(response.as_bytes()).await;
-                        return;
-                    }
+                if let Some(key_authorization) = acme_manager.challenge_response(token).await {
+                    // 返回ACME挑战响应
+                    let response = format!(
+                        "HTTP/1.1 200 OK\r\nContent-Type: text/plain\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                        key_authorization.len(),
+                        key_authorization
+                    );
+                    let _ = stream.write_all(response.as_bytes()).await;
+                    return;
                 }
             }
         }
@@ -2006,7 +3007,7 @@ impl HttpServer {
         // 正常HTTP请求处理 - 需要重新实现完整请求处理
         // 这里简化处理，实际应该将initial_data和后续数据一起处理
         // 由于复杂度较高，暂时只支持已缓存证书的常规请求
-        Self::spawn_http1_connection_with_initial(pipe_ctx, client_addr, stream, &buf[..n]);
+        Self::spawn_http1_connection_with_initial(pipe_ctx, client_addr, stream, &buf[..n], opts);
     }
 
     #[cfg(feature = "acme")]
@@ -2015,9 +3016,10 @@ impl HttpServer {
         client_addr: SocketAddr,
         stream: HttpStream,
         initial_data: &[u8],
+        opts: ConnOptions,
     ) {
         // 使用WithPreRead包装流，将initial_data作为预读取数据
         let stream_with_pre_read = HttpStream::with_pre_read(stream, initial_data.to_vec());
-        Self::spawn_http1_connection(pipe_ctx, client_addr, stream_with_pre_read);
+        Self::spawn_http1_connection(pipe_ctx, client_addr, stream_with_pre_read, opts);
     }
 }
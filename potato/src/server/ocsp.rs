@@ -0,0 +1,314 @@
+#![cfg(feature = "tls")]
+//! OCSP 装订：后台从证书 AIA 扩展中的 OCSP 地址获取响应，TLS 握手时随证书发送
+
+use sha1::{Digest, Sha1};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, RwLock, Weak};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio_rustls::rustls::pki_types::CertificateDer;
+use tokio_rustls::rustls::sign::CertifiedKey;
+use x509_parser::extensions::{GeneralName, ParsedExtension};
+use x509_parser::oid_registry::OID_PKIX_ACCESS_DESCRIPTOR_OCSP;
+use x509_parser::prelude::{FromDer, X509Certificate};
+
+/// 响应未给出 nextUpdate 时的刷新间隔
+const DEFAULT_REFRESH: Duration = Duration::from_secs(12 * 3600);
+/// 获取失败后的重试间隔
+const RETRY_INTERVAL: Duration = Duration::from_secs(10 * 60);
+/// 最短刷新间隔
+const MIN_REFRESH: Duration = Duration::from_secs(60);
+/// 证书超过该时间未被使用（如已被热重载替换）时停止刷新
+const IDLE_TIMEOUT: Duration = Duration::from_secs(24 * 3600);
+/// 请求 OCSP 服务器的超时时间
+const FETCH_TIMEOUT: Duration = Duration::from_secs(10);
+
+struct OcspEntry {
+    /// 附带 OCSP 响应的证书及响应过期时间，尚未获取到时为 `None`
+    stapled: Option<(Arc<CertifiedKey>, SystemTime)>,
+    /// 最近一次握手使用的时间（Unix 秒）
+    last_used: AtomicU64,
+}
+
+type OcspEntries = RwLock<HashMap<Vec<u8>, OcspEntry>>;
+
+/// OCSP 装订缓存，按叶子证书缓存响应
+///
+/// 证书首次用于握手时登记并在后台获取响应，获取完成后的握手即附带响应；
+/// 在 nextUpdate 之前刷新，获取失败时继续使用未过期的旧响应
+#[derive(Clone, Default)]
+pub(crate) struct OcspStapler {
+    entries: Arc<OcspEntries>,
+}
+
+impl std::fmt::Debug for OcspStapler {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let count = self.entries.read().map_or(0, |entries| entries.len());
+        f.debug_struct("OcspStapler")
+            .field("certs", &count)
+            .finish()
+    }
+}
+
+impl OcspStapler {
+    /// 返回附带 OCSP 响应的证书，尚无有效响应时返回原证书
+    pub(crate) fn staple(&self, key: &Arc<CertifiedKey>) -> Arc<CertifiedKey> {
+        let Some(leaf) = key.cert.first() else {
+            return Arc::clone(key);
+        };
+        if let Ok(entries) = self.entries.read() {
+            if let Some(entry) = entries.get(leaf.as_ref()) {
+                entry.last_used.store(unix_now(), Ordering::Relaxed);
+                return match &entry.stapled {
+                    Some((stapled, expires)) if *expires > SystemTime::now() => Arc::clone(stapled),
+                    _ => Arc::clone(key),
+                };
+            }
+        }
+        self.register(key);
+        Arc::clone(key)
+    }
+
+    /// 登记证书并启动后台刷新任务，需在 tokio 运行时中调用
+    fn register(&self, key: &Arc<CertifiedKey>) {
+        let Ok(runtime) = tokio::runtime::Handle::try_current() else {
+            return;
+        };
+        let Ok(mut entries) = self.entries.write() else {
+            return;
+        };
+        let leaf = key.cert[0].as_ref().to_vec();
+        if entries.contains_key(&leaf) {
+            return;
+        }
+        entries.insert(
+            leaf.clone(),
+            OcspEntry {
+                stapled: None,
+                last_used: AtomicU64::new(unix_now()),
+            },
+        );
+        runtime.spawn(refresh_loop(
+            Arc::downgrade(&self.entries),
+            leaf,
+            Arc::clone(key),
+        ));
+    }
+}
+
+async fn refresh_loop(entries: Weak<OcspEntries>, leaf: Vec<u8>, key: Arc<CertifiedKey>) {
+    loop {
+        let result = tokio::time::timeout(FETCH_TIMEOUT, fetch_ocsp(&key.cert)).await;
+        let delay = match result {
+            Ok(Ok(Some((response, next_update)))) => {
+                let Some(entries) = entries.upgrade() else {
+                    return;
+                };
+                let mut stapled = (*key).clone();
+                stapled.ocsp = Some(response);
+                let now = SystemTime::now();
+                let expires = next_update.unwrap_or(now + DEFAULT_REFRESH * 2);
+                if let Ok(mut entries) = entries.write() {
+                    if let Some(entry) = entries.get_mut(&leaf) {
+                        entry.stapled = Some((Arc::new(stapled), expires));
+                    }
+                }
+                // 在响应有效期过半时刷新
+                match next_update.and_then(|t| t.duration_since(now).ok()) {
+                    Some(remaining) => (remaining / 2).clamp(MIN_REFRESH, DEFAULT_REFRESH),
+                    None => DEFAULT_REFRESH,
+                }
+            }
+            // 证书未提供 OCSP 地址
+            Ok(Ok(None)) => DEFAULT_REFRESH,
+            Ok(Err(err)) => {
+                eprintln!("[OCSP] Failed to fetch OCSP response: {err}");
+                RETRY_INTERVAL
+            }
+            Err(_) => {
+                eprintln!("[OCSP] OCSP request timed out");
+                RETRY_INTERVAL
+            }
+        };
+        tokio::time::sleep(delay).await;
+
+        let Some(entries) = entries.upgrade() else {
+            return;
+        };
+        let Ok(mut entries) = entries.write() else {
+            return;
+        };
+        let idle = entries.get(&leaf).is_none_or(|entry| {
+            unix_now().saturating_sub(entry.last_used.load(Ordering::Relaxed))
+                > IDLE_TIMEOUT.as_secs()
+        });
+        if idle {
+            entries.remove(&leaf);
+            return;
+        }
+    }
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+/// 向证书的 OCSP 服务器请求响应，返回（DER 响应，nextUpdate）；证书未提供 OCSP 地址时返回 `None`
+pub(crate) async fn fetch_ocsp(
+    chain: &[CertificateDer<'_>],
+) -> anyhow::Result<Option<(Vec<u8>, Option<SystemTime>)>> {
+    let Some((url, request)) = ocsp_request(chain)? else {
+        return Ok(None);
+    };
+    let mut res = crate::client::post(
+        &url,
+        request,
+        vec![crate::Headers::Content_Type(
+            "application/ocsp-request".into(),
+        )],
+    )
+    .await?;
+    if res.http_code != 200 {
+        anyhow::bail!("OCSP responder {url} returned HTTP {}", res.http_code);
+    }
+    let body = res.body.data().await.to_vec();
+    let next_update = parse_ocsp_response(&body)?;
+    Ok(Some((body, next_update)))
+}
+
+/// 构造 OCSP 请求（RFC 6960），返回（OCSP 地址，DER 请求）
+fn ocsp_request(chain: &[CertificateDer<'_>]) -> anyhow::Result<Option<(String, Vec<u8>)>> {
+    let Some(leaf) = chain.first() else {
+        return Ok(None);
+    };
+    let (_, cert) = X509Certificate::from_der(leaf.as_ref())
+        .map_err(|err| anyhow::anyhow!("invalid certificate: {err}"))?;
+    let url = cert.extensions().iter().find_map(|ext| {
+        let ParsedExtension::AuthorityInfoAccess(aia) = ext.parsed_extension() else {
+            return None;
+        };
+        aia.iter().find_map(|desc| match &desc.access_location {
+            GeneralName::URI(uri) if desc.access_method == OID_PKIX_ACCESS_DESCRIPTOR_OCSP => {
+                Some(uri.to_string())
+            }
+            _ => None,
+        })
+    });
+    let Some(url) = url else {
+        return Ok(None);
+    };
+    let Some(issuer) = chain.get(1) else {
+        anyhow::bail!("certificate chain has no issuer certificate for OCSP request");
+    };
+    let (_, issuer) = X509Certificate::from_der(issuer.as_ref())
+        .map_err(|err| anyhow::anyhow!("invalid issuer certificate: {err}"))?;
+
+    // CertID：SHA-1 算法标识、签发者名称摘要、签发者公钥摘要、序列号
+    let sha1 = der(
+        0x30,
+        &[0x06, 0x05, 0x2b, 0x0e, 0x03, 0x02, 0x1a, 0x05, 0x00],
+    );
+    let name_hash = Sha1::digest(cert.issuer().as_raw());
+    let key_hash = Sha1::digest(issuer.public_key().subject_public_key.data.as_ref());
+    let cert_id = [
+        sha1,
+        der(0x04, &name_hash),
+        der(0x04, &key_hash),
+        der(0x02, cert.raw_serial()),
+    ]
+    .concat();
+    // OCSPRequest { TBSRequest { requestList { Request { CertID } } } }
+    let request = der(0x30, &der(0x30, &der(0x30, &der(0x30, &cert_id))));
+    Ok(Some((url, request)))
+}
+
+/// DER 编码一个元素
+fn der(tag: u8, content: &[u8]) -> Vec<u8> {
+    let mut out = vec![tag];
+    let len = content.len();
+    if len < 0x80 {
+        out.push(len as u8);
+    } else {
+        let bytes = len.to_be_bytes();
+        let skip = bytes.iter().take_while(|b| **b == 0).count();
+        out.push(0x80 | (bytes.len() - skip) as u8);
+        out.extend_from_slice(&bytes[skip..]);
+    }
+    out.extend_from_slice(content);
+    out
+}
+
+/// 读取一个 DER 元素，返回（标签，内容，剩余数据）
+fn read_tlv(data: &[u8]) -> anyhow::Result<(u8, &[u8], &[u8])> {
+    let malformed = || anyhow::anyhow!("malformed OCSP response");
+    let (&tag, rest) = data.split_first().ok_or_else(malformed)?;
+    let (&first, rest) = rest.split_first().ok_or_else(malformed)?;
+    let (len, rest) = match first < 0x80 {
+        true => (first as usize, rest),
+        false => {
+            let n = (first & 0x7f) as usize;
+            if n == 0 || n > 4 || rest.len() < n {
+                return Err(malformed());
+            }
+            let len = rest[..n].iter().fold(0, |acc, b| acc << 8 | *b as usize);
+            (len, &rest[n..])
+        }
+    };
+    if rest.len() < len {
+        return Err(malformed());
+    }
+    Ok((tag, &rest[..len], &rest[len..]))
+}
+
+/// 校验 OCSP 响应成功且证书状态为 good，返回 nextUpdate
+fn parse_ocsp_response(data: &[u8]) -> anyhow::Result<Option<SystemTime>> {
+    // OCSPResponse { responseStatus, [0] ResponseBytes }
+    let (_, response, _) = read_tlv(data)?;
+    let (_, status, rest) = read_tlv(response)?;
+    if status != [0] {
+        anyhow::bail!("OCSP responder returned status {status:?}");
+    }
+    let (_, response_bytes, _) = read_tlv(rest)?;
+    let (_, response_bytes, _) = read_tlv(response_bytes)?;
+    // ResponseBytes { responseType, response } -> BasicOCSPResponse { tbsResponseData, ... }
+    let (_, _, rest) = read_tlv(response_bytes)?;
+    let (_, basic, _) = read_tlv(rest)?;
+    let (_, basic, _) = read_tlv(basic)?;
+    let (_, tbs, _) = read_tlv(basic)?;
+    // ResponseData { [0] version OPTIONAL, responderID, producedAt, responses, ... }
+    let (tag, _, mut rest) = read_tlv(tbs)?;
+    if tag == 0xa0 {
+        (_, _, rest) = read_tlv(rest)?;
+    }
+    let (_, _, rest) = read_tlv(rest)?;
+    let (_, responses, _) = read_tlv(rest)?;
+    // SingleResponse { certID, certStatus, thisUpdate, [0] nextUpdate OPTIONAL }
+    let (_, single, _) = read_tlv(responses)?;
+    let (_, _, rest) = read_tlv(single)?;
+    let (cert_status, _, rest) = read_tlv(rest)?;
+    match cert_status {
+        0x80 => {}
+        0xa1 => anyhow::bail!("certificate has been revoked"),
+        _ => anyhow::bail!("certificate status unknown to OCSP responder"),
+    }
+    let (_, _, rest) = read_tlv(rest)?;
+    match read_tlv(rest) {
+        Ok((0xa0, next_update, _)) => {
+            let (_, time, _) = read_tlv(next_update)?;
+            Ok(Some(parse_generalized_time(time)?))
+        }
+        _ => Ok(None),
+    }
+}
+
+/// 解析 GeneralizedTime（如 `20250101120000Z`）
+fn parse_generalized_time(time: &[u8]) -> anyhow::Result<SystemTime> {
+    let time = std::str::from_utf8(time)?;
+    let time = time.split(['.', 'Z']).next().unwrap_or_default();
+    let time = chrono::NaiveDateTime::parse_from_str(time, "%Y%m%d%H%M%S")?;
+    let secs = u64::try_from(time.and_utc().timestamp())?;
+    Ok(UNIX_EPOCH + Duration::from_secs(secs))
+}
@@ -0,0 +1,359 @@
+use crate::{AuthRequirement, HttpRequest, HttpResponse, JwtKey};
+use jsonwebtoken::jwk::JwkSet;
+use jsonwebtoken::{Algorithm, Validation};
+use serde_json::{Map, Value};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::{Mutex, RwLock};
+
+/// 重新拉取 JWKS 的最小间隔，避免伪造的 `kid` 或身份提供方故障时反复请求
+const JWKS_MIN_REFRESH: Duration = Duration::from_secs(30);
+/// 请求身份提供方的超时时间
+const FETCH_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// OIDC 资源服务器配置，通过 `use_oidc` 启用
+///
+/// 从 `{issuer}/.well-known/openid-configuration` 发现 `jwks_uri`，拉取并缓存签名公钥，
+/// 校验请求 `Authorization: Bearer` 中由该身份提供方签发的令牌（签名、`exp`、`nbf`、`iss`、`aud`）
+#[derive(Debug, Clone)]
+pub struct OidcConfig {
+    pub issuer: String,            // 身份提供方标识，须与令牌的 `iss` 一致
+    pub audience: Vec<String>,     // 令牌 `aud` 须包含其中之一，为空时不校验
+    pub jwks_uri: Option<String>,  // 指定后跳过发现流程
+    pub leeway: Duration,          // 校验时间时允许的时钟偏差
+    pub jwks_refresh: Duration,    // JWKS 缓存时间，默认 10 分钟
+    pub require_token: bool,       // 未携带令牌时返回 401，默认放行由处理函数决定
+    pub exempt_paths: Vec<String>, // 不要求令牌的路径前缀，仅在 `require_token` 时生效
+}
+
+impl OidcConfig {
+    pub fn new(issuer: impl Into<String>) -> Self {
+        Self {
+            issuer: issuer.into().trim_end_matches('/').to_string(),
+            audience: vec![],
+            jwks_uri: None,
+            leeway: Duration::from_secs(60),
+            jwks_refresh: Duration::from_secs(600),
+            require_token: false,
+            exempt_paths: vec![],
+        }
+    }
+
+    pub fn with_audience(mut self, audience: impl Into<String>) -> Self {
+        self.audience.push(audience.into());
+        self
+    }
+
+    /// 直接指定 JWKS 地址，不请求发现文档
+    pub fn with_jwks_uri(mut self, uri: impl Into<String>) -> Self {
+        self.jwks_uri = Some(uri.into());
+        self
+    }
+
+    pub fn with_leeway(mut self, leeway: Duration) -> Self {
+        self.leeway = leeway;
+        self
+    }
+
+    pub fn with_jwks_refresh(mut self, refresh: Duration) -> Self {
+        self.jwks_refresh = refresh;
+        self
+    }
+
+    /// 所有请求都必须携带有效令牌
+    pub fn require_token(mut self) -> Self {
+        self.require_token = true;
+        self
+    }
+
+    /// 指定路径前缀下的请求可不携带令牌，如健康检查
+    pub fn with_exempt_path(mut self, prefix: impl Into<String>) -> Self {
+        self.exempt_paths.push(prefix.into());
+        self
+    }
+}
+
+/// 已缓存的签名公钥
+struct JwksCache {
+    jwks_uri: Option<String>,
+    keys: Vec<JwtKey>,
+    fetched_at: Option<Instant>,   // 最近一次成功拉取的时间
+    attempted_at: Option<Instant>, // 最近一次尝试拉取的时间
+}
+
+/// 管线中的 OIDC 校验项，持有配置与 JWKS 缓存
+pub struct OidcValidator {
+    config: OidcConfig,
+    cache: RwLock<JwksCache>,
+    fetching: Mutex<()>, // 拉取 JWKS 期间持有，不阻塞读取缓存
+}
+
+impl OidcValidator {
+    pub(crate) fn new(config: OidcConfig) -> Self {
+        let jwks_uri = config.jwks_uri.clone();
+        Self {
+            config,
+            cache: RwLock::new(JwksCache {
+                jwks_uri,
+                keys: vec![],
+                fetched_at: None,
+                attempted_at: None,
+            }),
+            fetching: Mutex::new(()),
+        }
+    }
+
+    /// 管线执行到该项时调用：校验通过时附加 `OidcClaims`，失败时返回 401/503 响应
+    pub(crate) async fn check(&self, req: &mut HttpRequest) -> Option<HttpResponse> {
+        let token = req
+            .get_header("Authorization")
+            .and_then(|value| value.trim().strip_prefix("Bearer "))
+            .map(|token| token.trim().to_string());
+        let Some(token) = token else {
+            let exempt = self
+                .config
+                .exempt_paths
+                .iter()
+                .any(|prefix| req.url_path.starts_with(&prefix[..]));
+            return match self.config.require_token && !exempt {
+                true => Some(unauthorized("Bearer", "missing bearer token")),
+                false => None,
+            };
+        };
+        match self.validate(&token).await {
+            Ok(claims) => {
+                req.add_ext(Arc::new(claims));
+                None
+            }
+            Err(OidcError::Unavailable(err)) => {
+                eprintln!("[OIDC] Failed to fetch signing keys: {err}");
+                let mut res =
+                    HttpResponse::text("Service Unavailable: identity provider unreachable");
+                res.http_code = 503;
+                Some(res)
+            }
+            Err(OidcError::InvalidToken(reason)) => Some(unauthorized(
+                &format!(
+                    "Bearer error=\"invalid_token\", error_description=\"{}\"",
+                    reason.replace('"', "'")
+                ),
+                &reason,
+            )),
+        }
+    }
+
+    /// 按令牌头部的 `kid` 选择公钥校验，未知 `kid` 时重新拉取 JWKS 以支持身份提供方轮换密钥
+    pub(crate) async fn validate(&self, token: &str) -> Result<OidcClaims, OidcError> {
+        let header = jsonwebtoken::decode_header(token).map_err(OidcError::invalid)?;
+        let key = match self.find_key(&header).await? {
+            Some(key) => key,
+            None if self.refresh(false).await? => self
+                .find_key(&header)
+                .await?
+                .ok_or_else(|| OidcError::InvalidToken("unknown signing key".to_string()))?,
+            None => return Err(OidcError::InvalidToken("unknown signing key".to_string())),
+        };
+
+        let mut validation = Validation::new(key.algorithm());
+        validation.leeway = self.config.leeway.as_secs();
+        validation.validate_nbf = true;
+        validation.set_issuer(&[&self.config.issuer]);
+        validation.set_required_spec_claims(&["exp", "iss", "sub"]);
+        match self.config.audience.is_empty() {
+            true => validation.validate_aud = false,
+            false => validation.set_audience(&self.config.audience),
+        }
+        let mut claims =
+            jsonwebtoken::decode::<Map<String, Value>>(token, key.decoding_key(), &validation)
+                .map_err(OidcError::invalid)?
+                .claims;
+        let Some(Value::String(sub)) = claims.remove("sub") else {
+            return Err(OidcError::InvalidToken("missing sub".to_string()));
+        };
+        Ok(OidcClaims { sub, claims })
+    }
+
+    async fn find_key(&self, header: &jsonwebtoken::Header) -> Result<Option<JwtKey>, OidcError> {
+        let expired = {
+            let cache = self.cache.read().await;
+            cache
+                .fetched_at
+                .is_none_or(|at| at.elapsed() >= self.config.jwks_refresh)
+        };
+        if expired {
+            if let Err(err) = self.refresh(true).await {
+                // 刷新失败时继续使用已缓存的公钥
+                if self.cache.read().await.keys.is_empty() {
+                    return Err(err);
+                }
+                eprintln!("[OIDC] Failed to refresh signing keys, using cached keys: {err:?}");
+            }
+        }
+        let cache = self.cache.read().await;
+        Ok(cache
+            .keys
+            .iter()
+            .filter(|key| key.algorithm() == header.alg)
+            .find(|key| header.kid.is_none() || key.kid() == header.kid.as_deref())
+            .cloned())
+    }
+
+    /// 拉取 JWKS，返回是否更新了缓存；`expired` 为假时表示因未知 `kid` 触发
+    ///
+    /// 两次拉取至少间隔 `JWKS_MIN_REFRESH`（不超过缓存时间），身份提供方不可用时不会每个请求都重试。
+    /// 同一时间只有一个任务拉取，期间其他请求继续使用已缓存的公钥
+    async fn refresh(&self, expired: bool) -> Result<bool, OidcError> {
+        let _fetching = match self.fetching.try_lock() {
+            Ok(guard) => guard,
+            Err(_) => {
+                if !self.cache.read().await.keys.is_empty() {
+                    return Ok(false);
+                }
+                // 尚无可用公钥时等待进行中的拉取完成
+                drop(self.fetching.lock().await);
+                return match self.cache.read().await.keys.is_empty() {
+                    true => Err(OidcError::Unavailable(anyhow::anyhow!(
+                        "signing keys unavailable, retrying later"
+                    ))),
+                    false => Ok(true),
+                };
+            }
+        };
+        let jwks_uri = {
+            let mut cache = self.cache.write().await;
+            if expired
+                && cache
+                    .fetched_at
+                    .is_some_and(|at| at.elapsed() < self.config.jwks_refresh)
+            {
+                return Ok(false);
+            }
+            let min_interval = JWKS_MIN_REFRESH.min(self.config.jwks_refresh);
+            if cache
+                .attempted_at
+                .is_some_and(|at| at.elapsed() < min_interval)
+            {
+                return match cache.keys.is_empty() {
+                    true => Err(OidcError::Unavailable(anyhow::anyhow!(
+                        "signing keys unavailable, retrying later"
+                    ))),
+                    false => Ok(false),
+                };
+            }
+            cache.attempted_at = Some(Instant::now());
+            cache.jwks_uri.clone()
+        };
+        let jwks_uri = match jwks_uri {
+            Some(uri) => uri,
+            None => self.discover().await.map_err(OidcError::Unavailable)?,
+        };
+        let jwks: JwkSet = fetch_json(&jwks_uri)
+            .await
+            .map_err(OidcError::Unavailable)?;
+        // 跳过不支持的密钥与对称密钥
+        let keys = jwks
+            .keys
+            .iter()
+            .filter_map(|jwk| JwtKey::from_jwk(jwk).ok())
+            .filter(|key| {
+                !matches!(
+                    key.algorithm(),
+                    Algorithm::HS256 | Algorithm::HS384 | Algorithm::HS512
+                )
+            })
+            .collect();
+        let mut cache = self.cache.write().await;
+        cache.keys = keys;
+        cache.jwks_uri = Some(jwks_uri);
+        cache.fetched_at = Some(Instant::now());
+        Ok(true)
+    }
+
+    /// 读取发现文档中的 `jwks_uri`，并确认其 `issuer` 与配置一致
+    async fn discover(&self) -> anyhow::Result<String> {
+        let url = format!("{}/.well-known/openid-configuration", self.config.issuer);
+        let doc: Value = fetch_json(&url).await?;
+        let issuer = doc["issuer"].as_str().unwrap_or_default();
+        if issuer.trim_end_matches('/') != self.config.issuer {
+            anyhow::bail!("discovery document issuer {issuer:?} does not match");
+        }
+        doc["jwks_uri"]
+            .as_str()
+            .map(str::to_string)
+            .ok_or_else(|| anyhow::anyhow!("discovery document has no jwks_uri"))
+    }
+}
+
+async fn fetch_json<T: serde::de::DeserializeOwned>(url: &str) -> anyhow::Result<T> {
+    let mut res = tokio::time::timeout(FETCH_TIMEOUT, crate::get(url, vec![]))
+        .await
+        .map_err(|_| anyhow::anyhow!("request to {url} timed out"))??;
+    if res.http_code != 200 {
+        anyhow::bail!("{url} returned {}", res.http_code);
+    }
+    Ok(serde_json::from_slice(res.body.data().await)?)
+}
+
+fn unauthorized(www_authenticate: &str, reason: &str) -> HttpResponse {
+    let mut res = HttpResponse::text(format!("Unauthorized: {reason}"));
+    res.http_code = 401;
+    res.add_header(
+        "WWW-Authenticate".into(),
+        www_authenticate.to_string().into(),
+    );
+    res
+}
+
+#[derive(Debug)]
+pub(crate) enum OidcError {
+    /// 令牌无效，返回 401
+    InvalidToken(String),
+    /// 无法获取签名公钥，返回 503
+    Unavailable(anyhow::Error),
+}
+
+impl OidcError {
+    fn invalid(err: jsonwebtoken::errors::Error) -> Self {
+        Self::InvalidToken(err.to_string())
+    }
+}
+
+/// 校验通过的 OIDC 令牌声明，启用 `use_oidc` 后可在处理函数中通过 `req.oidc_claims()` 获取
+#[derive(Debug, Clone)]
+pub struct OidcClaims {
+    pub sub: String,
+    claims: Map<String, Value>,
+}
+
+impl OidcClaims {
+    /// 将除 `sub` 外的声明（含 `iss`/`aud`/`exp` 等）反序列化为指定类型
+    pub fn claims<C: serde::de::DeserializeOwned>(&self) -> anyhow::Result<C> {
+        Ok(serde_json::from_value(Value::Object(self.claims.clone()))?)
+    }
+
+    /// 读取单个声明，不存在或类型不符时返回 `None`
+    pub fn claim<T: serde::de::DeserializeOwned>(&self, name: &str) -> Option<T> {
+        serde_json::from_value(self.claims.get(name)?.clone()).ok()
+    }
+
+    pub fn roles(&self) -> Vec<&str> {
+        crate::jwt::claim_roles(&self.claims)
+    }
+
+    pub fn scopes(&self) -> Vec<&str> {
+        crate::jwt::claim_scopes(&self.claims)
+    }
+
+    pub fn has_role(&self, role: &str) -> bool {
+        self.roles().contains(&role)
+    }
+
+    pub fn has_scope(&self, scope: &str) -> bool {
+        self.scopes().contains(&scope)
+    }
+
+    /// 按顺序校验访问要求，不满足时返回 403 响应
+    pub fn check_access(&self, requirements: &[AuthRequirement]) -> Option<HttpResponse> {
+        crate::jwt::check_access(&self.claims, requirements)
+    }
+}
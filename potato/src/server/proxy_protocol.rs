@@ -0,0 +1,100 @@
+//! HAProxy PROXY 协议 v1/v2 解析，见 <https://www.haproxy.org/download/2.9/doc/proxy-protocol.txt>
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncReadExt};
+
+const V2_SIGNATURE: &[u8; 12] = b"\r\n\r\n\0\r\nQUIT\n";
+/// v1 头（含 `\r\n`）的最大长度
+const V1_MAX_LEN: usize = 107;
+/// 等待 PROXY 协议头的超时时间
+const HEADER_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// 启用时读取连接开头的 PROXY 协议头并返回其中的客户端地址，未启用时直接返回 `peer`
+///
+/// 只读取协议头本身，之后的数据原样留在流中；LOCAL 命令或 UNKNOWN 协议族时返回 `peer`
+pub(crate) async fn resolve_client_addr<S: AsyncRead + Unpin>(
+    stream: &mut S,
+    peer: SocketAddr,
+    enabled: bool,
+) -> anyhow::Result<SocketAddr> {
+    if !enabled {
+        return Ok(peer);
+    }
+    let addr = tokio::time::timeout(HEADER_TIMEOUT, read_header(stream))
+        .await
+        .map_err(|_| anyhow::anyhow!("timeout waiting for PROXY protocol header"))??;
+    Ok(addr.unwrap_or(peer))
+}
+
+async fn read_header<S: AsyncRead + Unpin>(stream: &mut S) -> anyhow::Result<Option<SocketAddr>> {
+    // v1 最短头 `PROXY UNKNOWN\r\n` 为 15 字节，v2 固定头为 16 字节
+    let mut head = [0u8; 12];
+    stream.read_exact(&mut head[..6]).await?;
+    if &head[..6] == b"PROXY " {
+        let mut line = head[..6].to_vec();
+        while !line.ends_with(b"\r\n") {
+            if line.len() >= V1_MAX_LEN {
+                anyhow::bail!("PROXY protocol v1 header too long");
+            }
+            line.push(stream.read_u8().await?);
+        }
+        return parse_v1(&line);
+    }
+
+    stream.read_exact(&mut head[6..]).await?;
+    if &head != V2_SIGNATURE {
+        anyhow::bail!("missing PROXY protocol header");
+    }
+    let mut fixed = [0u8; 4];
+    stream.read_exact(&mut fixed).await?;
+    let len = u16::from_be_bytes([fixed[2], fixed[3]]) as usize;
+    let mut payload = vec![0u8; len];
+    stream.read_exact(&mut payload).await?;
+    parse_v2(fixed[0], fixed[1], &payload)
+}
+
+/// 解析 v1 文本头，如 `PROXY TCP4 192.0.2.1 198.51.100.1 56324 443\r\n`
+fn parse_v1(line: &[u8]) -> anyhow::Result<Option<SocketAddr>> {
+    let line = std::str::from_utf8(line)?.trim_end_matches("\r\n");
+    let parts: Vec<&str> = line.split(' ').collect();
+    match parts.get(1).copied() {
+        Some("UNKNOWN") => Ok(None),
+        Some(proto @ ("TCP4" | "TCP6")) if parts.len() == 6 => {
+            let ip: IpAddr = parts[2].parse()?;
+            let port: u16 = parts[4].parse()?;
+            if ip.is_ipv4() != (proto == "TCP4") {
+                anyhow::bail!("PROXY protocol v1 address family mismatch");
+            }
+            Ok(Some(SocketAddr::new(ip, port)))
+        }
+        _ => anyhow::bail!("invalid PROXY protocol v1 header"),
+    }
+}
+
+/// 解析 v2 二进制头（签名之后的部分）
+fn parse_v2(ver_cmd: u8, family: u8, payload: &[u8]) -> anyhow::Result<Option<SocketAddr>> {
+    if ver_cmd >> 4 != 2 {
+        anyhow::bail!("unsupported PROXY protocol version");
+    }
+    match ver_cmd & 0x0f {
+        0x0 => return Ok(None), // LOCAL：健康检查等由代理自身发起的连接
+        0x1 => {}
+        _ => anyhow::bail!("unsupported PROXY protocol command"),
+    }
+    match family >> 4 {
+        0x1 if payload.len() >= 12 => {
+            let ip = Ipv4Addr::new(payload[0], payload[1], payload[2], payload[3]);
+            let port = u16::from_be_bytes([payload[8], payload[9]]);
+            Ok(Some(SocketAddr::new(ip.into(), port)))
+        }
+        0x2 if payload.len() >= 36 => {
+            let mut octets = [0u8; 16];
+            octets.copy_from_slice(&payload[..16]);
+            let port = u16::from_be_bytes([payload[32], payload[33]]);
+            Ok(Some(SocketAddr::new(Ipv6Addr::from(octets).into(), port)))
+        }
+        0x1 | 0x2 => anyhow::bail!("truncated PROXY protocol v2 address"),
+        // UNSPEC 与 UNIX 地址族不携带 IP 地址
+        _ => Ok(None),
+    }
+}
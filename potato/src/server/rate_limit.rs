@@ -0,0 +1,286 @@
+use super::listener::is_unix_peer;
+use crate::{HttpRequest, HttpResponse, ServerConfig, SessionCache};
+use dashmap::DashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// 一次取令牌的结果
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RateLimitState {
+    pub allowed: bool,
+    pub remaining: u32,        // 取令牌后桶中剩余的令牌数
+    pub reset: Duration,       // 令牌桶补满所需的时间
+    pub retry_after: Duration, // 被拒绝时距下一个令牌可用的时间，放行时为 0
+}
+
+/// 令牌桶的存储接口，默认实现为进程内的 `MemoryRateLimitStore`
+///
+/// 多个实例共享限流额度时可自行实现（如基于 Redis 的 Lua 脚本），`take` 须原子地完成补充与扣减
+#[async_trait::async_trait]
+pub trait RateLimitStore: Send + Sync {
+    /// 从 `key` 对应的令牌桶取一个令牌，桶容量为 `limit`，每 `per` 补满一次
+    async fn take(&self, key: &str, limit: u32, per: Duration) -> anyhow::Result<RateLimitState>;
+}
+
+/// 基于进程内 DashMap 的令牌桶存储
+#[derive(Debug, Default)]
+pub struct MemoryRateLimitStore {
+    buckets: DashMap<String, MemoryBucket>,
+    calls: AtomicU64,
+}
+
+#[derive(Debug)]
+struct MemoryBucket {
+    tokens: f64,
+    updated: Instant,
+    full_at: Instant,
+}
+
+impl MemoryRateLimitStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[async_trait::async_trait]
+impl RateLimitStore for MemoryRateLimitStore {
+    async fn take(&self, key: &str, limit: u32, per: Duration) -> anyhow::Result<RateLimitState> {
+        let now = Instant::now();
+        // 每 1024 次调用清理一次已补满的桶，补满的桶与新建的桶等价
+        if self.calls.fetch_add(1, Ordering::Relaxed) % 1024 == 1023 {
+            self.buckets.retain(|_, bucket| bucket.full_at > now);
+        }
+
+        let capacity = limit.max(1) as f64;
+        let secs_per_token = per.as_secs_f64() / capacity;
+        let mut bucket = self
+            .buckets
+            .entry(key.to_string())
+            .or_insert_with(|| MemoryBucket {
+                tokens: capacity,
+                updated: now,
+                full_at: now,
+            });
+        let elapsed = now.duration_since(bucket.updated).as_secs_f64();
+        let mut tokens = match secs_per_token > 0.0 {
+            true => (bucket.tokens + elapsed / secs_per_token).min(capacity),
+            false => capacity,
+        };
+        let allowed = tokens >= 1.0;
+        if allowed {
+            tokens -= 1.0;
+        }
+        let reset = Duration::from_secs_f64((capacity - tokens) * secs_per_token);
+        bucket.tokens = tokens;
+        bucket.updated = now;
+        bucket.full_at = now + reset;
+        Ok(RateLimitState {
+            allowed,
+            remaining: tokens as u32,
+            reset,
+            retry_after: match allowed {
+                true => Duration::ZERO,
+                false => Duration::from_secs_f64((1.0 - tokens) * secs_per_token),
+            },
+        })
+    }
+}
+
+/// 自定义限流键，返回 `None` 时该请求不受此项限流
+pub type RateLimitKeyFn = Arc<dyn Fn(&HttpRequest) -> Option<String> + Send + Sync>;
+
+/// 区分令牌桶的方式
+#[derive(Clone)]
+pub enum RateLimitKey {
+    /// 客户端 IP，配置了 `use_trusted_proxies` 时采信可信代理转发的地址；
+    /// Unix 域套接字连接没有客户端 IP，共用一个令牌桶
+    Ip,
+    /// 已认证的调用方：`use_auth`/`use_oidc` 认证的用户、Bearer token 或会话 Cookie 中的用户ID，
+    /// 匿名请求按客户端 IP 计算
+    Subject,
+    Custom(RateLimitKeyFn),
+}
+
+impl RateLimitKey {
+    async fn resolve(&self, req: &HttpRequest) -> Option<String> {
+        match self {
+            RateLimitKey::Ip => Self::ip(req).await,
+            RateLimitKey::Subject => match Self::subject(req).await {
+                Some(subject) => Some(subject),
+                None => Self::ip(req).await,
+            },
+            RateLimitKey::Custom(key) => key(req).map(|key| format!("key:{key}")),
+        }
+    }
+
+    async fn ip(req: &HttpRequest) -> Option<String> {
+        let addr = req.get_client_addr().await.ok()?;
+        if is_unix_peer(&addr) {
+            return Some("unix".to_string());
+        }
+        Some(format!("ip:{}", addr.ip()))
+    }
+
+    async fn subject(req: &HttpRequest) -> Option<String> {
+        #[cfg(feature = "auth")]
+        if let Some(user) = req.auth_user() {
+            return Some(format!("user:{}", user.name));
+        }
+        if let Some(claims) = req.oidc_claims() {
+            return Some(format!("sub:{}", claims.sub));
+        }
+        let user_id = match req.get_header("Authorization") {
+            Some(value) if value.starts_with("Bearer ") => {
+                SessionCache::parse_token(&value[7..]).await.ok()?.0
+            }
+            _ => SessionCache::from_cookie(req.get_header("Cookie")?)
+                .ok()?
+                .user_id()?,
+        };
+        Some(format!("sub:{user_id}"))
+    }
+}
+
+/// 请求速率限制，每个限流键对应一个容量为 `limit`、每 `per` 补满一次的令牌桶
+///
+/// 超出限制时返回 429 并附带 `Retry-After`，所有响应附带 `RateLimit-Limit`、
+/// `RateLimit-Remaining`、`RateLimit-Reset` 与 `RateLimit-Policy` 头
+#[derive(Clone)]
+pub struct RateLimit {
+    limit: u32,
+    per: Duration,
+    key: RateLimitKey,
+    name: String,
+    store: Option<Arc<dyn RateLimitStore>>,
+}
+
+impl RateLimit {
+    /// 每个客户端 IP 在 `per` 时间内最多 `limit` 个请求
+    pub fn new(limit: u32, per: Duration) -> Self {
+        Self {
+            limit: limit.max(1),
+            per,
+            key: RateLimitKey::Ip,
+            name: String::new(),
+            store: None,
+        }
+    }
+
+    /// 按客户端 IP 限流（默认）
+    pub fn per_ip(mut self) -> Self {
+        self.key = RateLimitKey::Ip;
+        self
+    }
+
+    /// 按已认证的调用方限流，匿名请求按客户端 IP 计算
+    pub fn per_subject(mut self) -> Self {
+        self.key = RateLimitKey::Subject;
+        self
+    }
+
+    /// 按自定义键限流（如租户、API Key），返回 `None` 的请求不受此项限流
+    pub fn with_key(
+        mut self,
+        key: impl Fn(&HttpRequest) -> Option<String> + Send + Sync + 'static,
+    ) -> Self {
+        self.key = RateLimitKey::Custom(Arc::new(key));
+        self
+    }
+
+    /// 令牌桶在存储中的名称前缀，共享存储的多项限流须使用不同名称
+    pub fn with_name(mut self, name: impl Into<String>) -> Self {
+        self.name = name.into();
+        self
+    }
+
+    /// 使用指定存储，默认为 `ServerConfig::set_rate_limit_store` 设置的全局存储
+    pub fn with_store(mut self, store: impl RateLimitStore + 'static) -> Self {
+        self.store = Some(Arc::new(store));
+        self
+    }
+
+    pub(crate) fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// 校验请求速率，超出限制时返回 429 响应；存储出错时放行
+    ///
+    /// 由 `use_rate_limit` 与 `#[potato::rate_limit]` 标注生成的代码调用
+    pub async fn check(&self, req: &mut HttpRequest) -> Option<HttpResponse> {
+        let key = self.key.resolve(req).await?;
+        let store = match &self.store {
+            Some(store) => Arc::clone(store),
+            None => ServerConfig::get_rate_limit_store(),
+        };
+        let state = match store
+            .take(&format!("{}:{key}", self.name), self.limit, self.per)
+            .await
+        {
+            Ok(state) => state,
+            Err(err) => {
+                eprintln!("[RateLimit] store failed: {err}");
+                return None;
+            }
+        };
+        RateLimitStatus::attach(req, self, state);
+        if state.allowed {
+            return None;
+        }
+        let mut res = HttpResponse::text("Too Many Requests");
+        res.http_code = 429;
+        res.add_header(
+            "Retry-After".into(),
+            ceil_secs(state.retry_after).max(1).to_string().into(),
+        );
+        Some(res)
+    }
+}
+
+fn ceil_secs(duration: Duration) -> u64 {
+    duration.as_secs() + (duration.subsec_nanos() > 0) as u64
+}
+
+/// 待写入响应的限流状态，同一请求经过多项限流时保留剩余额度最少的一项
+pub(crate) struct RateLimitStatus {
+    limit: u32,
+    window: u64,
+    state: RateLimitState,
+}
+
+impl RateLimitStatus {
+    fn attach(req: &mut HttpRequest, limit: &RateLimit, state: RateLimitState) {
+        // 拒绝优先于放行，其次比较剩余额度
+        let rank = |state: &RateLimitState| (state.allowed, state.remaining);
+        if let Some(current) = req.get_ext::<RateLimitStatus>() {
+            if rank(&current.state) <= rank(&state) {
+                return;
+            }
+        }
+        req.add_ext(Arc::new(RateLimitStatus {
+            limit: limit.limit,
+            window: ceil_secs(limit.per),
+            state,
+        }));
+    }
+
+    /// 应答前写入 `RateLimit-*` 头，由管线在请求处理完毕后调用
+    pub(crate) fn finish(req: &mut HttpRequest, res: &mut HttpResponse) {
+        let Some(status) = req.remove_ext::<RateLimitStatus>() else {
+            return;
+        };
+        res.add_header("RateLimit-Limit".into(), status.limit.to_string().into());
+        res.add_header(
+            "RateLimit-Remaining".into(),
+            status.state.remaining.to_string().into(),
+        );
+        res.add_header(
+            "RateLimit-Reset".into(),
+            ceil_secs(status.state.reset).to_string().into(),
+        );
+        res.add_header(
+            "RateLimit-Policy".into(),
+            format!("{};w={}", status.limit, status.window).into(),
+        );
+    }
+}
@@ -0,0 +1,223 @@
+use crate::{HttpRequest, HttpResponse};
+use base64::Engine;
+use rand::RngCore;
+use std::fmt;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// CSP 中的占位符，应答时替换为本次请求的 nonce
+pub const CSP_NONCE_PLACEHOLDER: &str = "{nonce}";
+
+/// 安全响应头策略，通过 `use_security_headers` 附加到之后各项处理产生的响应上
+///
+/// 字段为 `None` 时不发送对应的头；处理函数或后处理函数已设置的同名头保持不变
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SecurityHeaders {
+    pub hsts: Option<String>,                       // Strict-Transport-Security
+    pub content_type_options: Option<String>,       // X-Content-Type-Options
+    pub frame_options: Option<String>,              // X-Frame-Options
+    pub referrer_policy: Option<String>,            // Referrer-Policy
+    pub permissions_policy: Option<String>,         // Permissions-Policy
+    pub cross_origin_opener_policy: Option<String>, // Cross-Origin-Opener-Policy
+    pub cross_origin_embedder_policy: Option<String>, // Cross-Origin-Embedder-Policy
+    pub cross_origin_resource_policy: Option<String>, // Cross-Origin-Resource-Policy
+    pub csp: Option<String>, // Content-Security-Policy，`{nonce}` 替换为本次请求的 nonce
+    pub csp_report_only: bool, // 为 true 时以 Content-Security-Policy-Report-Only 发送
+}
+
+impl SecurityHeaders {
+    /// 不发送任何安全头
+    pub fn none() -> Self {
+        Self::default()
+    }
+
+    /// 适用于大多数站点的基础配置，不限制资源来源
+    pub fn basic() -> Self {
+        Self {
+            hsts: Some("max-age=31536000".to_string()),
+            content_type_options: Some("nosniff".to_string()),
+            frame_options: Some("SAMEORIGIN".to_string()),
+            referrer_policy: Some("strict-origin-when-cross-origin".to_string()),
+            cross_origin_opener_policy: Some("same-origin".to_string()),
+            ..Self::default()
+        }
+    }
+
+    /// 严格配置：禁止被嵌入、跨源隔离，脚本与样式仅允许同源或携带本次请求的 nonce
+    pub fn strict() -> Self {
+        Self {
+            hsts: Some("max-age=63072000; includeSubDomains".to_string()),
+            content_type_options: Some("nosniff".to_string()),
+            frame_options: Some("DENY".to_string()),
+            referrer_policy: Some("no-referrer".to_string()),
+            permissions_policy: Some(
+                "camera=(), microphone=(), geolocation=(), payment=(), usb=()".to_string(),
+            ),
+            cross_origin_opener_policy: Some("same-origin".to_string()),
+            cross_origin_embedder_policy: Some("require-corp".to_string()),
+            cross_origin_resource_policy: Some("same-origin".to_string()),
+            csp: Some(
+                "default-src 'self'; script-src 'self' 'nonce-{nonce}'; style-src 'self' 'nonce-{nonce}'; object-src 'none'; base-uri 'self'; frame-ancestors 'none'"
+                    .to_string(),
+            ),
+            csp_report_only: false,
+        }
+    }
+
+    /// 设置 HSTS，`max_age` 为零时浏览器清除已记录的策略
+    pub fn with_hsts(mut self, max_age: Duration, include_subdomains: bool, preload: bool) -> Self {
+        let mut value = format!("max-age={}", max_age.as_secs());
+        if include_subdomains {
+            value.push_str("; includeSubDomains");
+        }
+        if preload {
+            value.push_str("; preload");
+        }
+        self.hsts = Some(value);
+        self
+    }
+
+    /// 设置 CSP，可使用 `{nonce}` 占位符，如 `script-src 'self' 'nonce-{nonce}'`
+    pub fn with_csp(mut self, policy: impl Into<String>) -> Self {
+        self.csp = Some(policy.into());
+        self.csp_report_only = false;
+        self
+    }
+
+    /// 以 Report-Only 方式下发 CSP，仅上报违规而不拦截
+    pub fn with_csp_report_only(mut self, policy: impl Into<String>) -> Self {
+        self.csp = Some(policy.into());
+        self.csp_report_only = true;
+        self
+    }
+
+    /// 按字段名设置，`None` 表示不发送；字段名不存在时返回 false
+    ///
+    /// `csp_report_only` 的值为 `"true"` 时启用
+    pub fn set(&mut self, field: &str, value: Option<String>) -> bool {
+        let slot = match field {
+            "hsts" => &mut self.hsts,
+            "content_type_options" => &mut self.content_type_options,
+            "frame_options" => &mut self.frame_options,
+            "referrer_policy" => &mut self.referrer_policy,
+            "permissions_policy" => &mut self.permissions_policy,
+            "cross_origin_opener_policy" => &mut self.cross_origin_opener_policy,
+            "cross_origin_embedder_policy" => &mut self.cross_origin_embedder_policy,
+            "cross_origin_resource_policy" => &mut self.cross_origin_resource_policy,
+            "csp" => &mut self.csp,
+            "csp_report_only" => {
+                self.csp_report_only = value.as_deref() == Some("true");
+                return true;
+            }
+            _ => return false,
+        };
+        *slot = value;
+        true
+    }
+
+    /// 将策略写入响应，已存在的同名头不覆盖
+    pub(crate) fn apply(&self, nonce: Option<&CspNonce>, res: &mut HttpResponse) {
+        let csp_name = match self.csp_report_only {
+            true => "Content-Security-Policy-Report-Only",
+            false => "Content-Security-Policy",
+        };
+        let csp = self.csp.as_ref().map(|csp| match nonce {
+            Some(nonce) => csp.replace(CSP_NONCE_PLACEHOLDER, &nonce.0),
+            None => csp.clone(),
+        });
+        let headers = [
+            ("Strict-Transport-Security", self.hsts.clone()),
+            ("X-Content-Type-Options", self.content_type_options.clone()),
+            ("X-Frame-Options", self.frame_options.clone()),
+            ("Referrer-Policy", self.referrer_policy.clone()),
+            ("Permissions-Policy", self.permissions_policy.clone()),
+            (
+                "Cross-Origin-Opener-Policy",
+                self.cross_origin_opener_policy.clone(),
+            ),
+            (
+                "Cross-Origin-Embedder-Policy",
+                self.cross_origin_embedder_policy.clone(),
+            ),
+            (
+                "Cross-Origin-Resource-Policy",
+                self.cross_origin_resource_policy.clone(),
+            ),
+            (csp_name, csp),
+        ];
+        for (name, value) in headers {
+            let Some(value) = value else { continue };
+            if res.headers.keys().any(|key| key.eq_ignore_ascii_case(name)) {
+                continue;
+            }
+            res.add_header(name.into(), value.into());
+        }
+    }
+
+    /// 应答前合并路由覆盖并写入响应，由管线在请求处理完毕后调用
+    ///
+    /// 未启用 `use_security_headers` 时，路由覆盖在 `SecurityHeaders::none()` 上生效
+    pub(crate) fn finish(req: &mut HttpRequest, res: &mut HttpResponse) {
+        let policy = req.remove_ext::<SecurityHeaders>();
+        let route = req.remove_ext::<SecurityHeadersOverride>();
+        let nonce = req.get_ext::<CspNonce>();
+        match (route, policy) {
+            (Some(route), Some(policy)) => route.merge(&policy).apply(nonce.as_deref(), res),
+            (Some(route), None) => route
+                .merge(&SecurityHeaders::none())
+                .apply(nonce.as_deref(), res),
+            (None, Some(policy)) => policy.apply(nonce.as_deref(), res),
+            (None, None) => {}
+        }
+    }
+}
+
+/// 本次请求的 CSP nonce，启用 `use_security_headers` 后可在处理函数中通过 `req.csp_nonce()` 获取，
+/// 用于内联脚本的 `<script nonce="...">`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CspNonce(pub String);
+
+impl CspNonce {
+    /// 128 位随机数的 Base64 编码
+    pub(crate) fn generate() -> Self {
+        let mut buf = [0u8; 16];
+        rand::thread_rng().fill_bytes(&mut buf);
+        Self(base64::engine::general_purpose::STANDARD.encode(buf))
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Display for CspNonce {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+/// 路由级覆盖，由 `#[potato::security_headers(...)]` 生成
+#[doc(hidden)]
+pub struct SecurityHeadersOverride {
+    pub preset: Option<SecurityHeaders>, // 为空时在全局策略上修改
+    pub fields: &'static [(&'static str, &'static str)], // (字段名, 值)，值为空字符串时不发送
+}
+
+impl SecurityHeadersOverride {
+    /// 记录到请求上，应答时与全局策略合并；未启用全局策略时在此生成 CSP nonce
+    pub fn attach(self, req: &mut HttpRequest) {
+        if req.get_ext::<CspNonce>().is_none() {
+            req.add_ext(Arc::new(CspNonce::generate()));
+        }
+        req.add_ext(Arc::new(self));
+    }
+
+    fn merge(&self, global: &SecurityHeaders) -> SecurityHeaders {
+        let mut policy = self.preset.clone().unwrap_or_else(|| global.clone());
+        for (field, value) in self.fields {
+            let value = (!value.is_empty()).then(|| value.to_string());
+            policy.set(field, value);
+        }
+        policy
+    }
+}
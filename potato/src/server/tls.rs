@@ -0,0 +1,510 @@
+#![cfg(feature = "tls")]
+
+use super::ocsp::OcspStapler;
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock, Weak};
+use std::time::{Duration, SystemTime};
+use tokio_rustls::rustls;
+use tokio_rustls::rustls::pki_types::{
+    pem::PemObject, CertificateDer, CertificateRevocationListDer, PrivateKeyDer,
+};
+use tokio_rustls::rustls::server::{ClientHello, ResolvesServerCert, WebPkiClientVerifier};
+use tokio_rustls::rustls::sign::CertifiedKey;
+
+/// 初始化 rustls CryptoProvider（如果尚未初始化）
+pub(crate) fn install_crypto_provider() {
+    use rustls::crypto::ring::default_provider;
+    use rustls::crypto::CryptoProvider;
+    let _ = CryptoProvider::install_default(default_provider());
+}
+
+/// 从 PEM 文件加载证书链与私钥
+pub(crate) fn load_certified_key(
+    cert_file: impl AsRef<Path>,
+    key_file: impl AsRef<Path>,
+) -> anyhow::Result<Arc<CertifiedKey>> {
+    install_crypto_provider();
+    let certs = CertificateDer::pem_file_iter(cert_file)?.collect::<Result<Vec<_>, _>>()?;
+    if certs.is_empty() {
+        anyhow::bail!("no certificate found in PEM file");
+    }
+    let key = PrivateKeyDer::from_pem_file(key_file)?;
+    let key = rustls::crypto::ring::sign::any_supported_type(&key)?;
+    Ok(Arc::new(CertifiedKey::new(certs, key)))
+}
+
+/// 构造服务端 TLS 配置；设置了证书库时按 SNI 选择证书，`cert_file` 不为空时作为兜底证书
+/// 启用 OCSP 装订时证书均经由 `ListenerCertResolver` 选择，以便附带 OCSP 响应
+pub(crate) fn server_config(
+    cert_file: &str,
+    key_file: &str,
+    resolver: Option<&SniCertResolver>,
+    client_auth: Option<&ClientAuth>,
+    ocsp: Option<&OcspStapler>,
+    alpn: Vec<Vec<u8>>,
+) -> anyhow::Result<rustls::ServerConfig> {
+    install_crypto_provider();
+    let builder = rustls::ServerConfig::builder();
+    let builder = match client_auth {
+        Some(client_auth) => builder.with_client_cert_verifier(client_auth.verifier()?),
+        None => builder.with_no_client_auth(),
+    };
+    let mut config = match (resolver, ocsp) {
+        (None, None) => {
+            let certs = CertificateDer::pem_file_iter(cert_file)?.collect::<Result<Vec<_>, _>>()?;
+            let key = PrivateKeyDer::from_pem_file(key_file)?;
+            builder.with_single_cert(certs, key)?
+        }
+        _ => {
+            let fallback = match cert_file.is_empty() {
+                true => None,
+                false => Some(load_certified_key(cert_file, key_file)?),
+            };
+            builder.with_cert_resolver(Arc::new(ListenerCertResolver {
+                store: resolver.cloned().unwrap_or_default(),
+                fallback,
+                ocsp: ocsp.cloned(),
+            }))
+        }
+    };
+    config.alpn_protocols = alpn;
+    Ok(config)
+}
+
+/// 双向 TLS（mTLS）客户端证书校验配置
+///
+/// ```rust,no_run
+/// # async fn run() -> anyhow::Result<()> {
+/// let mut server = potato::HttpServer::new("0.0.0.0:8443");
+/// server.set_client_auth(potato::ClientAuth::required("ca.pem").with_crl("ca.crl"));
+/// server.serve_https("cert.pem", "key.pem").await
+/// # }
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct ClientAuth {
+    /// PEM 格式的 CA 证书包，客户端证书须由其中的 CA 签发
+    pub ca_file: String,
+    /// PEM 格式的证书吊销列表
+    pub crl_files: Vec<String>,
+    /// 为 false 时允许不带证书的客户端连接，此时 `req.client_cert()` 为 `None`
+    pub required: bool,
+}
+
+impl ClientAuth {
+    /// 要求客户端出示证书，否则握手失败
+    pub fn required(ca_file: impl Into<String>) -> Self {
+        Self {
+            ca_file: ca_file.into(),
+            crl_files: vec![],
+            required: true,
+        }
+    }
+
+    /// 客户端可不出示证书；出示时仍须校验通过
+    pub fn optional(ca_file: impl Into<String>) -> Self {
+        Self {
+            required: false,
+            ..Self::required(ca_file)
+        }
+    }
+
+    /// 添加证书吊销列表（CRL）文件
+    pub fn with_crl(mut self, crl_file: impl Into<String>) -> Self {
+        self.crl_files.push(crl_file.into());
+        self
+    }
+
+    fn verifier(&self) -> anyhow::Result<Arc<dyn rustls::server::danger::ClientCertVerifier>> {
+        let mut roots = rustls::RootCertStore::empty();
+        for cert in CertificateDer::pem_file_iter(&self.ca_file)? {
+            roots.add(cert?)?;
+        }
+        if roots.is_empty() {
+            anyhow::bail!("no CA certificate found in {}", self.ca_file);
+        }
+        let mut crls = vec![];
+        for crl_file in self.crl_files.iter() {
+            for crl in CertificateRevocationListDer::pem_file_iter(crl_file)? {
+                crls.push(crl?);
+            }
+        }
+        let builder = WebPkiClientVerifier::builder(Arc::new(roots)).with_crls(crls);
+        let builder = match self.required {
+            true => builder,
+            false => builder.allow_unauthenticated(),
+        };
+        Ok(builder.build()?)
+    }
+}
+
+/// 取出连接上已校验的客户端证书
+pub(crate) fn peer_client_cert(conn: &rustls::ServerConnection) -> Option<Arc<ClientCert>> {
+    ClientCert::from_chain(conn.peer_certificates()?)
+}
+
+/// 已通过校验的客户端证书信息，启用 `ClientAuth` 后通过 `req.client_cert()` 获取
+#[derive(Debug, Clone)]
+pub struct ClientCert {
+    /// 客户端出示的证书链，首个为终端实体证书（DER 格式）
+    pub chain: Vec<CertificateDer<'static>>,
+    /// 主题，如 `CN=svc-a, O=example`
+    pub subject: String,
+    /// 主题中的 CN
+    pub common_name: Option<String>,
+    /// 签发者
+    pub issuer: String,
+    /// 十六进制序列号
+    pub serial: String,
+    /// SAN 中的 DNS 名称
+    pub dns_names: Vec<String>,
+    /// SAN 中的 URI，如 SPIFFE ID `spiffe://example.org/svc-a`
+    pub uris: Vec<String>,
+    /// SAN 中的电子邮件地址
+    pub emails: Vec<String>,
+    /// SAN 中的 IP 地址
+    pub ip_addrs: Vec<IpAddr>,
+}
+
+impl ClientCert {
+    /// 从握手得到的证书链构造，证书链为空或无法解析时返回 `None`
+    pub(crate) fn from_chain(chain: &[CertificateDer<'_>]) -> Option<Arc<Self>> {
+        use x509_parser::extensions::GeneralName;
+        use x509_parser::prelude::{FromDer, X509Certificate};
+
+        let (_, cert) = X509Certificate::from_der(chain.first()?.as_ref()).ok()?;
+        let mut ret = ClientCert {
+            chain: chain.iter().map(|cert| cert.clone().into_owned()).collect(),
+            subject: cert.subject().to_string(),
+            common_name: cert
+                .subject()
+                .iter_common_name()
+                .next()
+                .and_then(|cn| cn.as_str().ok())
+                .map(str::to_string),
+            issuer: cert.issuer().to_string(),
+            serial: cert.raw_serial_as_string().replace(':', ""),
+            dns_names: vec![],
+            uris: vec![],
+            emails: vec![],
+            ip_addrs: vec![],
+        };
+        if let Ok(Some(san)) = cert.subject_alternative_name() {
+            for name in san.value.general_names.iter() {
+                match name {
+                    GeneralName::DNSName(name) => ret.dns_names.push(name.to_string()),
+                    GeneralName::URI(uri) => ret.uris.push(uri.to_string()),
+                    GeneralName::RFC822Name(email) => ret.emails.push(email.to_string()),
+                    GeneralName::IPAddress(bytes) => {
+                        if let Ok(octets) = <[u8; 4]>::try_from(*bytes) {
+                            ret.ip_addrs.push(IpAddr::from(octets));
+                        } else if let Ok(octets) = <[u8; 16]>::try_from(*bytes) {
+                            ret.ip_addrs.push(IpAddr::from(octets));
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        }
+        Some(Arc::new(ret))
+    }
+}
+
+struct CertEntry {
+    names: Vec<String>,
+    cert_file: PathBuf,
+    key_file: PathBuf,
+    modified: Option<SystemTime>,
+    key: Arc<CertifiedKey>,
+}
+
+impl CertEntry {
+    fn load(names: Vec<String>, cert_file: PathBuf, key_file: PathBuf) -> anyhow::Result<Self> {
+        let modified = Self::modified_time(&cert_file, &key_file);
+        let key = load_certified_key(&cert_file, &key_file)?;
+        Ok(Self {
+            names,
+            cert_file,
+            key_file,
+            modified,
+            key,
+        })
+    }
+
+    /// 证书与私钥文件中较新的修改时间
+    fn modified_time(cert_file: &Path, key_file: &Path) -> Option<SystemTime> {
+        let cert = std::fs::metadata(cert_file).and_then(|m| m.modified()).ok();
+        let key = std::fs::metadata(key_file).and_then(|m| m.modified()).ok();
+        cert.max(key)
+    }
+
+    fn files(&self) -> CertFiles {
+        CertFiles {
+            cert_file: self.cert_file.clone(),
+            key_file: self.key_file.clone(),
+            modified: self.modified,
+        }
+    }
+}
+
+/// 证书条目的文件路径与加载时的修改时间，重新加载时在锁外读取文件
+struct CertFiles {
+    cert_file: PathBuf,
+    key_file: PathBuf,
+    modified: Option<SystemTime>,
+}
+
+impl CertFiles {
+    /// 读取并解析证书，`only_changed` 为 true 时修改时间未变的证书返回 `None`
+    fn load(self, only_changed: bool) -> Option<(Self, anyhow::Result<Arc<CertifiedKey>>)> {
+        let modified = CertEntry::modified_time(&self.cert_file, &self.key_file);
+        if only_changed && modified == self.modified {
+            return None;
+        }
+        let key = load_certified_key(&self.cert_file, &self.key_file)
+            .map_err(|err| anyhow::anyhow!("reload {}: {err}", self.cert_file.display()));
+        Some((Self { modified, ..self }, key))
+    }
+}
+
+#[derive(Default)]
+struct CertState {
+    entries: Vec<CertEntry>,
+    default: Option<CertEntry>,
+    /// 小写主机名或 `*.` 开头的通配符 -> 证书
+    names: HashMap<String, Arc<CertifiedKey>>,
+}
+
+impl CertState {
+    fn rebuild_names(&mut self) {
+        self.names.clear();
+        for entry in self.entries.iter() {
+            for name in entry.names.iter() {
+                self.names.insert(name.clone(), Arc::clone(&entry.key));
+            }
+        }
+    }
+
+    fn resolve(&self, server_name: Option<&str>) -> Option<Arc<CertifiedKey>> {
+        if let Some(name) = server_name {
+            let name = name.trim_end_matches('.').to_ascii_lowercase();
+            if let Some(key) = self.names.get(&name) {
+                return Some(Arc::clone(key));
+            }
+            // 通配符只匹配一级子域名
+            if let Some((_, parent)) = name.split_once('.') {
+                if let Some(key) = self.names.get(&format!("*.{parent}")) {
+                    return Some(Arc::clone(key));
+                }
+            }
+        }
+        self.default.as_ref().map(|entry| Arc::clone(&entry.key))
+    }
+}
+
+/// 按 SNI 主机名选择证书的证书库，支持 `*.example.com` 通配符与默认证书
+///
+/// 可在 `serve_https` / `serve_http2` / `serve_http3` / `listen_https` / `listen_h3` 间共享，
+/// 证书文件更新后调用 `reload` 或通过 `watch` 自动重新加载，新连接即使用新证书
+///
+/// ```rust,no_run
+/// # async fn run() -> anyhow::Result<()> {
+/// let certs = potato::SniCertResolver::new();
+/// certs.add(["example.com", "www.example.com"], "example.pem", "example.key")?;
+/// certs.add(["*.example.org"], "example.org.pem", "example.org.key")?;
+/// certs.set_default("default.pem", "default.key")?;
+/// certs.watch(std::time::Duration::from_secs(30));
+///
+/// let mut server = potato::HttpServer::new("0.0.0.0:443");
+/// server.set_cert_resolver(certs);
+/// server.serve_https("", "").await
+/// # }
+/// ```
+#[derive(Clone, Default)]
+pub struct SniCertResolver {
+    state: Arc<RwLock<CertState>>,
+}
+
+impl std::fmt::Debug for SniCertResolver {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let state = self.read();
+        f.debug_struct("SniCertResolver")
+            .field("names", &state.names.keys().collect::<Vec<_>>())
+            .field("has_default", &state.default.is_some())
+            .finish()
+    }
+}
+
+impl SniCertResolver {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn read(&self) -> std::sync::RwLockReadGuard<'_, CertState> {
+        self.state
+            .read()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    fn write(&self) -> std::sync::RwLockWriteGuard<'_, CertState> {
+        self.state
+            .write()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// 添加证书，`names` 为该证书服务的主机名，可使用 `*.example.com` 形式的通配符
+    pub fn add<I, S>(
+        &self,
+        names: I,
+        cert_file: impl Into<PathBuf>,
+        key_file: impl Into<PathBuf>,
+    ) -> anyhow::Result<()>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let names: Vec<String> = names
+            .into_iter()
+            .map(|name| name.as_ref().trim_end_matches('.').to_ascii_lowercase())
+            .collect();
+        if names.is_empty() {
+            anyhow::bail!("at least one host name is required");
+        }
+        if let Some(name) = names
+            .iter()
+            .find(|n| n.contains('*') && !n.starts_with("*."))
+        {
+            anyhow::bail!("unsupported wildcard pattern: {name}");
+        }
+        let entry = CertEntry::load(names, cert_file.into(), key_file.into())?;
+        let mut state = self.write();
+        state.entries.push(entry);
+        state.rebuild_names();
+        Ok(())
+    }
+
+    /// 设置 SNI 缺失或无匹配时使用的默认证书
+    pub fn set_default(
+        &self,
+        cert_file: impl Into<PathBuf>,
+        key_file: impl Into<PathBuf>,
+    ) -> anyhow::Result<()> {
+        let entry = CertEntry::load(vec![], cert_file.into(), key_file.into())?;
+        self.write().default = Some(entry);
+        Ok(())
+    }
+
+    /// 从磁盘重新加载全部证书；加载失败的证书保留旧版本并返回错误
+    pub fn reload(&self) -> anyhow::Result<()> {
+        self.replace(Self::load_files(self.files(), false))
+    }
+
+    fn files(&self) -> Vec<CertFiles> {
+        let state = self.read();
+        let entries = state.entries.iter().chain(state.default.iter());
+        entries.map(CertEntry::files).collect()
+    }
+
+    fn load_files(
+        files: Vec<CertFiles>,
+        only_changed: bool,
+    ) -> Vec<(CertFiles, anyhow::Result<Arc<CertifiedKey>>)> {
+        files
+            .into_iter()
+            .filter_map(|files| files.load(only_changed))
+            .collect()
+    }
+
+    /// 在写锁内替换已在锁外加载好的证书，按文件路径匹配条目
+    fn replace(
+        &self,
+        loaded: Vec<(CertFiles, anyhow::Result<Arc<CertifiedKey>>)>,
+    ) -> anyhow::Result<()> {
+        let mut errors = vec![];
+        let mut state = self.write();
+        let state = &mut *state;
+        for (files, key) in loaded {
+            let key = match key {
+                Ok(key) => key,
+                Err(err) => {
+                    errors.push(err.to_string());
+                    continue;
+                }
+            };
+            let entries = state.entries.iter_mut().chain(state.default.iter_mut());
+            for entry in entries.filter(|entry| {
+                entry.cert_file == files.cert_file && entry.key_file == files.key_file
+            }) {
+                entry.key = Arc::clone(&key);
+                entry.modified = files.modified;
+            }
+        }
+        state.rebuild_names();
+        match errors.is_empty() {
+            true => Ok(()),
+            false => Err(anyhow::anyhow!(errors.join("; "))),
+        }
+    }
+
+    /// 启动后台任务，每隔 `interval` 检查证书文件的修改时间并重新加载有变化的证书
+    /// 证书库的所有副本都被释放后任务自动退出，需在 tokio 运行时中调用
+    pub fn watch(&self, interval: Duration) {
+        let state: Weak<RwLock<CertState>> = Arc::downgrade(&self.state);
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            ticker.tick().await;
+            loop {
+                ticker.tick().await;
+                let Some(state) = state.upgrade() else {
+                    break;
+                };
+                let resolver = SniCertResolver { state };
+                let files = resolver.files();
+                let Ok(loaded) =
+                    tokio::task::spawn_blocking(move || Self::load_files(files, true)).await
+                else {
+                    continue;
+                };
+                if loaded.is_empty() {
+                    continue;
+                }
+                if let Err(err) = resolver.replace(loaded) {
+                    eprintln!("certificate reload failed: {err}");
+                }
+            }
+        });
+    }
+
+    /// 按主机名查找证书，无匹配时返回默认证书
+    pub fn resolve_name(&self, server_name: Option<&str>) -> Option<Arc<CertifiedKey>> {
+        self.read().resolve(server_name)
+    }
+}
+
+impl ResolvesServerCert for SniCertResolver {
+    fn resolve(&self, client_hello: ClientHello<'_>) -> Option<Arc<CertifiedKey>> {
+        self.resolve_name(client_hello.server_name())
+    }
+}
+
+/// 单个监听使用的证书选择：证书库无匹配时使用该监听配置的证书文件
+#[derive(Debug)]
+struct ListenerCertResolver {
+    store: SniCertResolver,
+    fallback: Option<Arc<CertifiedKey>>,
+    ocsp: Option<OcspStapler>,
+}
+
+impl ResolvesServerCert for ListenerCertResolver {
+    fn resolve(&self, client_hello: ClientHello<'_>) -> Option<Arc<CertifiedKey>> {
+        let key = self
+            .store
+            .resolve_name(client_hello.server_name())
+            .or_else(|| self.fallback.clone())?;
+        match &self.ocsp {
+            Some(ocsp) => Some(ocsp.staple(&key)),
+            None => Some(key),
+        }
+    }
+}
@@ -0,0 +1,213 @@
+use dashmap::DashMap;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::{Arc, LazyLock, RwLock};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tokio::fs;
+
+/// 持久化的会话数据，`values` 为通过 `SessionCache::set_persistent` 写入的值
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SessionRecord {
+    pub values: HashMap<String, serde_json::Value>,
+    pub expires_at: u64, // 过期时间（UNIX 秒）
+}
+
+impl SessionRecord {
+    pub fn is_expired(&self) -> bool {
+        self.expires_at <= unix_now()
+    }
+}
+
+/// SessionCache 的持久化存储接口，默认实现为进程内的 `MemorySessionStore`
+///
+/// 使用 `FileSessionStore` 或自行实现（如 Redis、数据库）可使会话在重启后保留，
+/// 并在负载均衡后的多个实例间共享。仅 `set_persistent` 写入的值会被持久化，
+/// `set` 写入的任意类型值仍只保存在本进程内
+#[async_trait::async_trait]
+pub trait SessionStore: Send + Sync {
+    /// 读取会话，不存在或已过期时返回 `None`
+    async fn get(&self, id: i64) -> anyhow::Result<Option<SessionRecord>>;
+    /// 写入会话，覆盖已有数据
+    async fn set(&self, id: i64, record: SessionRecord) -> anyhow::Result<()>;
+    /// 更新会话过期时间，会话不存在时不报错
+    async fn touch(&self, id: i64, expires_at: u64) -> anyhow::Result<()>;
+    /// 删除会话，不存在时不报错
+    async fn remove(&self, id: i64) -> anyhow::Result<()>;
+    /// 清理所有已过期的会话，由服务器后台任务定期调用
+    async fn expire(&self) -> anyhow::Result<()>;
+}
+
+/// 基于进程内 DashMap 的存储
+#[derive(Debug, Default)]
+pub struct MemorySessionStore {
+    records: DashMap<i64, SessionRecord>,
+}
+
+impl MemorySessionStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[async_trait::async_trait]
+impl SessionStore for MemorySessionStore {
+    async fn get(&self, id: i64) -> anyhow::Result<Option<SessionRecord>> {
+        Ok(self
+            .records
+            .get(&id)
+            .map(|record| record.clone())
+            .filter(|record| !record.is_expired()))
+    }
+
+    async fn set(&self, id: i64, record: SessionRecord) -> anyhow::Result<()> {
+        self.records.insert(id, record);
+        Ok(())
+    }
+
+    async fn touch(&self, id: i64, expires_at: u64) -> anyhow::Result<()> {
+        if let Some(mut record) = self.records.get_mut(&id) {
+            record.expires_at = expires_at;
+        }
+        Ok(())
+    }
+
+    async fn remove(&self, id: i64) -> anyhow::Result<()> {
+        self.records.remove(&id);
+        Ok(())
+    }
+
+    async fn expire(&self) -> anyhow::Result<()> {
+        self.records.retain(|_, record| !record.is_expired());
+        Ok(())
+    }
+}
+
+/// 基于本地目录的存储，每个会话保存为 `<id>.json`，写入通过临时文件重命名保证原子性
+///
+/// 多个实例挂载同一共享目录即可共享会话
+#[derive(Debug, Clone)]
+pub struct FileSessionStore {
+    pub dir: PathBuf,
+}
+
+impl FileSessionStore {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    fn path(&self, id: i64) -> PathBuf {
+        self.dir.join(format!("{id}.json"))
+    }
+
+    async fn read(&self, id: i64) -> anyhow::Result<Option<SessionRecord>> {
+        match fs::read(self.path(id)).await {
+            Ok(data) => Ok(Some(serde_json::from_slice(&data)?)),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    async fn write(&self, id: i64, record: &SessionRecord) -> anyhow::Result<()> {
+        fs::create_dir_all(&self.dir).await?;
+        let tmp = self.dir.join(format!(
+            ".{id}.json.{}.{}.tmp",
+            std::process::id(),
+            rand::random::<u32>()
+        ));
+        fs::write(&tmp, serde_json::to_vec(record)?).await?;
+        if let Err(err) = fs::rename(&tmp, self.path(id)).await {
+            _ = fs::remove_file(&tmp).await;
+            return Err(err.into());
+        }
+        Ok(())
+    }
+
+    async fn delete(&self, id: i64) -> anyhow::Result<()> {
+        match fs::remove_file(self.path(id)).await {
+            Err(err) if err.kind() != std::io::ErrorKind::NotFound => Err(err.into()),
+            _ => Ok(()),
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl SessionStore for FileSessionStore {
+    async fn get(&self, id: i64) -> anyhow::Result<Option<SessionRecord>> {
+        match self.read(id).await? {
+            Some(record) if record.is_expired() => {
+                self.delete(id).await?;
+                Ok(None)
+            }
+            record => Ok(record),
+        }
+    }
+
+    async fn set(&self, id: i64, record: SessionRecord) -> anyhow::Result<()> {
+        self.write(id, &record).await
+    }
+
+    async fn touch(&self, id: i64, expires_at: u64) -> anyhow::Result<()> {
+        if let Some(mut record) = self.read(id).await? {
+            record.expires_at = expires_at;
+            self.write(id, &record).await?;
+        }
+        Ok(())
+    }
+
+    async fn remove(&self, id: i64) -> anyhow::Result<()> {
+        self.delete(id).await
+    }
+
+    async fn expire(&self) -> anyhow::Result<()> {
+        let mut entries = match fs::read_dir(&self.dir).await {
+            Ok(entries) => entries,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+            Err(err) => return Err(err.into()),
+        };
+        while let Ok(Some(entry)) = entries.next_entry().await {
+            let name = entry.file_name();
+            let Some(id) = name
+                .to_str()
+                .and_then(|name| name.strip_suffix(".json"))
+                .and_then(|id| id.parse::<i64>().ok())
+            else {
+                continue;
+            };
+            // 内容损坏的文件同样清理
+            let expired = match self.read(id).await {
+                Ok(Some(record)) => record.is_expired(),
+                Ok(None) => false,
+                Err(_) => true,
+            };
+            if expired {
+                self.delete(id).await?;
+            }
+        }
+        Ok(())
+    }
+}
+
+static SESSION_STORE: LazyLock<RwLock<Arc<dyn SessionStore>>> =
+    LazyLock::new(|| RwLock::new(Arc::new(MemorySessionStore::new())));
+
+/// 设置全局会话存储，应在服务器启动前调用
+pub(crate) fn set_store(store: Arc<dyn SessionStore>) {
+    if let Ok(mut slot) = SESSION_STORE.write() {
+        *slot = store;
+    }
+}
+
+pub(crate) fn store() -> Arc<dyn SessionStore> {
+    match SESSION_STORE.read() {
+        Ok(store) => store.clone(),
+        Err(err) => err.into_inner().clone(),
+    }
+}
+
+pub(crate) fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
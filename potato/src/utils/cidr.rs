@@ -0,0 +1,106 @@
+use std::fmt;
+use std::net::IpAddr;
+use std::str::FromStr;
+
+/// IP 网段，如 `10.0.0.0/8`、`2001:db8::/32`；不带前缀长度时表示单个地址
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct IpCidr {
+    addr: IpAddr,
+    prefix: u8,
+}
+
+impl IpCidr {
+    pub fn new(addr: IpAddr, prefix: u8) -> anyhow::Result<Self> {
+        let addr = addr.to_canonical();
+        let max_prefix = match addr {
+            IpAddr::V4(_) => 32,
+            IpAddr::V6(_) => 128,
+        };
+        if prefix > max_prefix {
+            anyhow::bail!("invalid prefix length /{prefix} for {addr}");
+        }
+        Ok(Self {
+            addr: Self::mask(addr, prefix),
+            prefix,
+        })
+    }
+
+    pub fn addr(&self) -> IpAddr {
+        self.addr
+    }
+
+    pub fn prefix(&self) -> u8 {
+        self.prefix
+    }
+
+    /// 地址是否在网段内，IPv4 映射的 IPv6 地址按 IPv4 处理
+    pub fn contains(&self, addr: &IpAddr) -> bool {
+        let addr = addr.to_canonical();
+        match (self.addr, addr) {
+            (IpAddr::V4(_), IpAddr::V4(_)) | (IpAddr::V6(_), IpAddr::V6(_)) => {
+                Self::mask(addr, self.prefix) == self.addr
+            }
+            _ => false,
+        }
+    }
+
+    /// 任一网段包含该地址
+    pub fn any_contains(cidrs: &[IpCidr], addr: &IpAddr) -> bool {
+        cidrs.iter().any(|cidr| cidr.contains(addr))
+    }
+
+    /// 解析网段列表，任一项无效即返回错误
+    pub fn parse_list<I, S>(cidrs: I) -> anyhow::Result<Vec<IpCidr>>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        cidrs.into_iter().map(|s| s.as_ref().parse()).collect()
+    }
+
+    fn mask(addr: IpAddr, prefix: u8) -> IpAddr {
+        match addr {
+            IpAddr::V4(v4) => {
+                let mask = u32::MAX.checked_shl(32 - prefix as u32).unwrap_or(0);
+                IpAddr::V4((u32::from(v4) & mask).into())
+            }
+            IpAddr::V6(v6) => {
+                let mask = u128::MAX.checked_shl(128 - prefix as u32).unwrap_or(0);
+                IpAddr::V6((u128::from(v6) & mask).into())
+            }
+        }
+    }
+}
+
+impl FromStr for IpCidr {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        match s.split_once('/') {
+            Some((addr, prefix)) => {
+                let addr: IpAddr = addr
+                    .parse()
+                    .map_err(|_| anyhow::anyhow!("invalid cidr: {s}"))?;
+                let prefix: u8 = prefix
+                    .parse()
+                    .map_err(|_| anyhow::anyhow!("invalid cidr: {s}"))?;
+                Self::new(addr, prefix)
+            }
+            None => {
+                let addr: IpAddr = s.parse().map_err(|_| anyhow::anyhow!("invalid ip: {s}"))?;
+                let prefix = match addr.to_canonical() {
+                    IpAddr::V4(_) => 32,
+                    IpAddr::V6(_) => 128,
+                };
+                Self::new(addr, prefix)
+            }
+        }
+    }
+}
+
+impl fmt::Display for IpCidr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}", self.addr, self.prefix)
+    }
+}
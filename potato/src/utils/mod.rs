@@ -1,5 +1,6 @@
 pub mod ai;
 pub mod bytes;
+pub mod cidr;
 pub mod enums;
 #[cfg(all(feature = "jemalloc", not(target_os = "windows")))]
 pub mod jemalloc_helper;
@@ -6,6 +6,8 @@ use std::time::{Duration, Instant};
 use tokio::io::AsyncWriteExt;
 use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite};
 use tokio::net::TcpStream;
+#[cfg(unix)]
+use tokio::net::UnixStream;
 use tokio::sync::Mutex;
 #[cfg(feature = "tls")]
 use tokio_rustls::client::TlsStream as ClientTlsStream;
@@ -19,6 +21,9 @@ pub enum HttpStream {
     #[cfg(feature = "tls")]
     ClientTls(ClientTlsStream<TcpStream>),
     DuplexStream(tokio::io::DuplexStream),
+    /// Unix 域套接字连接
+    #[cfg(unix)]
+    Unix(UnixStream),
     /// 带预读取缓冲区的流，用于ACME挑战检测后继续处理HTTP请求
     WithPreRead {
         stream: Box<HttpStream>,
@@ -48,6 +53,11 @@ impl HttpStream {
         HttpStream::DuplexStream(stream)
     }
 
+    #[cfg(unix)]
+    pub fn from_unix(s: UnixStream) -> Self {
+        Self::Unix(s)
+    }
+
     /// 创建一个带预读取缓冲区的HttpStream
     pub fn with_pre_read(stream: HttpStream, pre_read_data: Vec<u8>) -> Self {
         HttpStream::WithPreRead {
@@ -56,6 +66,30 @@ impl HttpStream {
         }
     }
 
+    /// 是否为明文 TCP 连接（可带预读取缓冲区）
+    pub fn is_plain_tcp(&self) -> bool {
+        match self {
+            HttpStream::Tcp(_) => true,
+            HttpStream::WithPreRead { stream, .. } => matches!(stream.as_ref(), HttpStream::Tcp(_)),
+            _ => false,
+        }
+    }
+
+    /// 取出明文 TCP 连接及尚未消费的预读取数据，非明文连接返回 None
+    pub fn into_plain_tcp(self) -> Option<(TcpStream, Vec<u8>)> {
+        match self {
+            HttpStream::Tcp(s) => Some((s, vec![])),
+            HttpStream::WithPreRead {
+                stream,
+                pre_read_data,
+            } => match *stream {
+                HttpStream::Tcp(s) => Some((s, pre_read_data)),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
     pub async fn read(&mut self, buf: &mut [u8]) -> anyhow::Result<usize> {
         Ok(match self {
             HttpStream::Tcp(s) => s.read(buf).await?,
@@ -64,6 +98,8 @@ impl HttpStream {
             #[cfg(feature = "tls")]
             HttpStream::ClientTls(s) => s.read(buf).await?,
             HttpStream::DuplexStream(s) => s.read(buf).await?,
+            #[cfg(unix)]
+            HttpStream::Unix(s) => s.read(buf).await?,
             HttpStream::WithPreRead {
                 stream,
                 pre_read_data,
@@ -82,6 +118,8 @@ impl HttpStream {
                         #[cfg(feature = "tls")]
                         HttpStream::ClientTls(s) => s.read(buf).await?,
                         HttpStream::DuplexStream(s) => s.read(buf).await?,
+                        #[cfg(unix)]
+                        HttpStream::Unix(s) => s.read(buf).await?,
                         HttpStream::WithPreRead { .. } => {
                             // 不应该发生，WithPreRead不应该嵌套
                             0
@@ -102,6 +140,8 @@ impl HttpStream {
             #[cfg(feature = "tls")]
             HttpStream::ClientTls(s) => s.read_exact(buf).await?,
             HttpStream::DuplexStream(s) => s.read_exact(buf).await?,
+            #[cfg(unix)]
+            HttpStream::Unix(s) => s.read_exact(buf).await?,
             HttpStream::WithPreRead {
                 stream,
                 pre_read_data,
@@ -119,6 +159,8 @@ impl HttpStream {
                         #[cfg(feature = "tls")]
                         HttpStream::ClientTls(s) => s.read_exact(buf).await?,
                         HttpStream::DuplexStream(s) => s.read_exact(buf).await?,
+                        #[cfg(unix)]
+                        HttpStream::Unix(s) => s.read_exact(buf).await?,
                         HttpStream::WithPreRead { .. } => 0,
                         HttpStream::RateLimited(s) => {
                             // RateLimitedStream 没有 read_exact，需要自己实现
@@ -157,6 +199,8 @@ impl HttpStream {
             #[cfg(feature = "tls")]
             HttpStream::ClientTls(s) => s.write_all(buf).await?,
             HttpStream::DuplexStream(s) => s.write_all(buf).await?,
+            #[cfg(unix)]
+            HttpStream::Unix(s) => s.write_all(buf).await?,
             HttpStream::WithPreRead { stream, .. } => match stream.as_mut() {
                 HttpStream::Tcp(s) => s.write_all(buf).await?,
                 #[cfg(feature = "tls")]
@@ -164,6 +208,8 @@ impl HttpStream {
                 #[cfg(feature = "tls")]
                 HttpStream::ClientTls(s) => s.write_all(buf).await?,
                 HttpStream::DuplexStream(s) => s.write_all(buf).await?,
+                #[cfg(unix)]
+                HttpStream::Unix(s) => s.write_all(buf).await?,
                 HttpStream::WithPreRead { .. } => {}
                 HttpStream::RateLimited(s) => Box::pin(s.write_all(buf)).await?,
             },
@@ -183,6 +229,8 @@ impl HttpStream {
             #[cfg(feature = "tls")]
             HttpStream::ClientTls(s) => write_all_vectored_inner(s, bufs).await?,
             HttpStream::DuplexStream(s) => write_all_vectored_inner(s, bufs).await?,
+            #[cfg(unix)]
+            HttpStream::Unix(s) => write_all_vectored_inner(s, bufs).await?,
             HttpStream::WithPreRead { stream, .. } => match stream.as_mut() {
                 HttpStream::Tcp(s) => write_all_vectored_inner(s, bufs).await?,
                 #[cfg(feature = "tls")]
@@ -190,6 +238,8 @@ impl HttpStream {
                 #[cfg(feature = "tls")]
                 HttpStream::ClientTls(s) => write_all_vectored_inner(s, bufs).await?,
                 HttpStream::DuplexStream(s) => write_all_vectored_inner(s, bufs).await?,
+                #[cfg(unix)]
+                HttpStream::Unix(s) => write_all_vectored_inner(s, bufs).await?,
                 HttpStream::WithPreRead { .. } => {}
                 HttpStream::RateLimited(s) => {
                     for buf in bufs {
@@ -214,6 +264,8 @@ impl HttpStream {
             #[cfg(feature = "tls")]
             HttpStream::ClientTls(s) => write_all_vectored2_inner(s, a, b).await?,
             HttpStream::DuplexStream(s) => write_all_vectored2_inner(s, a, b).await?,
+            #[cfg(unix)]
+            HttpStream::Unix(s) => write_all_vectored2_inner(s, a, b).await?,
             HttpStream::WithPreRead { stream, .. } => match stream.as_mut() {
                 HttpStream::Tcp(s) => write_all_vectored2_inner(s, a, b).await?,
                 #[cfg(feature = "tls")]
@@ -221,6 +273,8 @@ impl HttpStream {
                 #[cfg(feature = "tls")]
                 HttpStream::ClientTls(s) => write_all_vectored2_inner(s, a, b).await?,
                 HttpStream::DuplexStream(s) => write_all_vectored2_inner(s, a, b).await?,
+                #[cfg(unix)]
+                HttpStream::Unix(s) => write_all_vectored2_inner(s, a, b).await?,
                 HttpStream::WithPreRead { .. } => {}
                 HttpStream::RateLimited(s) => {
                     s.write_all(a).await?;
@@ -26,6 +26,7 @@ mod acme_tests {
                 "https://acme-staging-v02.api.letsencrypt.org/directory".to_string(),
             ),
             cert_dir: Some("/tmp/test_certs".to_string()),
+            ..Default::default()
         };
         assert_eq!(opts.domains.len(), 2);
         assert_eq!(
@@ -206,6 +207,7 @@ mod acme_integration_tests {
                 "https://acme-staging-v02.api.letsencrypt.org/directory".to_string(),
             ),
             cert_dir: Some("/tmp/potato_acme_staging_test".to_string()),
+            ..Default::default()
         };
 
         let _server = HttpServer::new("127.0.0.1:8443");
@@ -414,6 +416,7 @@ mod acme_integration_tests {
                 "https://acme-staging-v02.api.letsencrypt.org/directory".to_string(),
             ),
             cert_dir: Some(temp_dir.to_str().unwrap().to_string()),
+            ..Default::default()
         };
 
         // 验证多域名配置
@@ -0,0 +1,260 @@
+/// ACME 证书存储与多实例协调测试
+#[cfg(feature = "acme")]
+mod acme_cert_store_tests {
+    use base64::Engine;
+    use potato::acme::{AcmeManager, AcmeOptions, CertStore, FileCertStore};
+    use std::collections::HashMap;
+    use std::sync::{Arc, Mutex};
+    use std::time::{Duration, Instant};
+
+    /// 模拟多实例共享的存储（如 Redis）
+    #[derive(Clone, Default)]
+    struct MemoryStore {
+        data: Arc<Mutex<HashMap<String, Vec<u8>>>>,
+        locks: Arc<Mutex<HashMap<String, (String, Instant)>>>,
+    }
+
+    #[async_trait::async_trait]
+    impl CertStore for MemoryStore {
+        async fn load(&self, key: &str) -> anyhow::Result<Option<Vec<u8>>> {
+            Ok(self.data.lock().unwrap().get(key).cloned())
+        }
+
+        async fn store(&self, key: &str, data: &[u8], _private: bool) -> anyhow::Result<()> {
+            self.data
+                .lock()
+                .unwrap()
+                .insert(key.to_string(), data.to_vec());
+            Ok(())
+        }
+
+        async fn delete(&self, key: &str) -> anyhow::Result<()> {
+            self.data.lock().unwrap().remove(key);
+            Ok(())
+        }
+
+        async fn try_lock(&self, name: &str, owner: &str, ttl: Duration) -> anyhow::Result<bool> {
+            let mut locks = self.locks.lock().unwrap();
+            match locks.get(name) {
+                Some((holder, expires)) if holder != owner && *expires > Instant::now() => {
+                    Ok(false)
+                }
+                _ => {
+                    locks.insert(name.to_string(), (owner.to_string(), Instant::now() + ttl));
+                    Ok(true)
+                }
+            }
+        }
+
+        async fn unlock(&self, name: &str, owner: &str) -> anyhow::Result<()> {
+            let mut locks = self.locks.lock().unwrap();
+            if locks.get(name).is_some_and(|(holder, _)| holder == owner) {
+                locks.remove(name);
+            }
+            Ok(())
+        }
+    }
+
+    fn temp_dir(name: &str) -> std::path::PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("potato_cert_store_{name}_{}", std::process::id()));
+        _ = std::fs::remove_dir_all(&dir);
+        dir
+    }
+
+    /// 离线可用的账户凭据（直接给出目录地址，加载时不访问 ACME 服务器）
+    fn account_json() -> String {
+        let key = rcgen::KeyPair::generate().unwrap();
+        let key = base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(key.serialize_der());
+        format!(
+            r#"{{"id":"https://acme.invalid/acct/1","key_pkcs8":"{key}","directory":null,"urls":{{"newNonce":"https://acme.invalid/nonce","newAccount":"https://acme.invalid/acct","newOrder":"https://acme.invalid/order"}}}}"#
+        )
+    }
+
+    fn test_cert() -> (String, String) {
+        let cert = rcgen::generate_simple_self_signed(vec!["potato.example".to_string()]).unwrap();
+        (cert.cert.pem(), cert.signing_key.serialize_pem())
+    }
+
+    #[tokio::test]
+    async fn test_file_store_roundtrip() -> anyhow::Result<()> {
+        let dir = temp_dir("roundtrip");
+        let store = FileCertStore::new(&dir);
+
+        assert!(store.load("cert.pem").await?.is_none());
+        store.store("cert.pem", b"cert", false).await?;
+        store.store("key.pem", b"key", true).await?;
+        store.store("http-01/token", b"auth", false).await?;
+        assert_eq!(store.load("cert.pem").await?.as_deref(), Some(&b"cert"[..]));
+        assert_eq!(
+            store.load("http-01/token").await?.as_deref(),
+            Some(&b"auth"[..])
+        );
+
+        // 覆盖写入，不残留临时文件
+        store.store("key.pem", b"key2", true).await?;
+        assert_eq!(store.load("key.pem").await?.as_deref(), Some(&b"key2"[..]));
+        assert_eq!(std::fs::read_dir(&dir)?.count(), 3);
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = |name: &str| std::fs::metadata(dir.join(name)).unwrap().permissions();
+            assert_eq!(mode("key.pem").mode() & 0o777, 0o600);
+            assert_ne!(mode("cert.pem").mode() & 0o077, 0);
+        }
+
+        store.delete("http-01/token").await?;
+        store.delete("http-01/token").await?;
+        assert!(store.load("http-01/token").await?.is_none());
+        assert!(store.load("../cert.pem").await.is_err());
+        assert!(store.store("a//b", b"x", false).await.is_err());
+
+        _ = std::fs::remove_dir_all(&dir);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_file_store_lease() -> anyhow::Result<()> {
+        let dir = temp_dir("lease");
+        let store = FileCertStore::new(&dir);
+        let ttl = Duration::from_secs(60);
+
+        assert!(store.try_lock("renew", "a", ttl).await?);
+        assert!(!store.try_lock("renew", "b", ttl).await?);
+        // 持有者可续期，非持有者无法释放
+        assert!(store.try_lock("renew", "a", ttl).await?);
+        store.unlock("renew", "b").await?;
+        assert!(!store.try_lock("renew", "b", ttl).await?);
+        store.unlock("renew", "a").await?;
+        assert!(store.try_lock("renew", "b", ttl).await?);
+        store.unlock("renew", "b").await?;
+
+        // 过期的租约可被其他实例接管
+        assert!(store.try_lock("renew", "c", Duration::ZERO).await?);
+        assert!(store.try_lock("renew", "d", ttl).await?);
+        store.unlock("renew", "d").await?;
+
+        // 并发竞争时只有一个实例获得租约
+        let mut tasks = vec![];
+        for i in 0..8 {
+            let store = store.clone();
+            tasks.push(tokio::spawn(async move {
+                store
+                    .try_lock("race", &format!("owner{i}"), Duration::from_secs(60))
+                    .await
+                    .unwrap()
+            }));
+        }
+        let mut winners = 0;
+        for task in tasks {
+            winners += task.await? as u32;
+        }
+        assert_eq!(winners, 1);
+
+        _ = std::fs::remove_dir_all(&dir);
+        Ok(())
+    }
+
+    #[test]
+    fn test_file_store_expired_lease_has_single_taker() -> anyhow::Result<()> {
+        let dir = temp_dir("expired_lease");
+        std::fs::create_dir_all(&dir)?;
+        for round in 0..20 {
+            // 多个实例（线程）同时发现同一个过期租约
+            std::fs::write(dir.join("renew.lock"), "old 0")?;
+            let barrier = Arc::new(std::sync::Barrier::new(8));
+            let threads: Vec<_> = (0..8)
+                .map(|i| {
+                    let store = FileCertStore::new(&dir);
+                    let barrier = Arc::clone(&barrier);
+                    std::thread::spawn(move || {
+                        let rt = tokio::runtime::Builder::new_current_thread()
+                            .build()
+                            .unwrap();
+                        barrier.wait();
+                        rt.block_on(store.try_lock(
+                            "renew",
+                            &format!("owner{i}"),
+                            Duration::from_secs(60),
+                        ))
+                        .unwrap()
+                    })
+                })
+                .collect();
+            let winners = threads
+                .into_iter()
+                .map(|thread| thread.join().unwrap() as u32)
+                .sum::<u32>();
+            assert_eq!(winners, 1, "round {round}");
+            // 锁文件始终包含完整的租约
+            let lease = std::fs::read_to_string(dir.join("renew.lock"))?;
+            assert!(lease.starts_with("owner"));
+        }
+
+        _ = std::fs::remove_dir_all(&dir);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_manager_uses_shared_store() -> anyhow::Result<()> {
+        let store = MemoryStore::default();
+        let (cert_pem, key_pem) = test_cert();
+        store
+            .store("account.json", account_json().as_bytes(), true)
+            .await?;
+        store.store("cert.pem", cert_pem.as_bytes(), false).await?;
+        store.store("key.pem", key_pem.as_bytes(), true).await?;
+
+        let opts = AcmeOptions::new("potato.example", "admin@potato.example")
+            .with_cert_store(store.clone());
+        let (manager, acceptor) = AcmeManager::new(opts).await?;
+        _ = acceptor.get_acceptor().await;
+
+        // HTTP-01 挑战可由发起申请的其他实例写入存储
+        assert!(manager.challenge_response("tok-1").await.is_none());
+        store.store("http-01/tok-1", b"tok-1.thumb", false).await?;
+        assert_eq!(
+            manager.challenge_response("tok-1").await.as_deref(),
+            Some("tok-1.thumb")
+        );
+        assert!(manager
+            .challenge_response("../account.json")
+            .await
+            .is_none());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_follower_waits_for_leader() -> anyhow::Result<()> {
+        let store = MemoryStore::default();
+        store
+            .store("account.json", account_json().as_bytes(), true)
+            .await?;
+        // 另一个实例正在申请证书
+        assert!(
+            store
+                .try_lock("renew", "leader", Duration::from_secs(60))
+                .await?
+        );
+
+        let leader = store.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(500)).await;
+            let (cert_pem, key_pem) = test_cert();
+            _ = leader.store("key.pem", key_pem.as_bytes(), true).await;
+            _ = leader.store("cert.pem", cert_pem.as_bytes(), false).await;
+        });
+
+        let opts = AcmeOptions::new("potato.example", "admin@potato.example")
+            .with_cert_store(store.clone());
+        let (_manager, _acceptor) =
+            tokio::time::timeout(Duration::from_secs(10), AcmeManager::new(opts)).await??;
+        assert!(
+            !store
+                .try_lock("renew", "other", Duration::from_secs(60))
+                .await?
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,191 @@
+/// ACME DNS-01 测试：RFC 2136 动态更新与 TXT 记录生效检查
+#[cfg(feature = "acme")]
+mod acme_dns01_tests {
+    use potato::acme::{
+        lookup_txt, wait_for_txt, AcmeManager, AcmeOptions, DnsProvider, Rfc2136Provider,
+    };
+    use std::collections::HashMap;
+    use std::net::SocketAddr;
+    use std::sync::atomic::{AtomicU16, Ordering};
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+    use tokio::net::UdpSocket;
+
+    static PORT_COUNTER: AtomicU16 = AtomicU16::new(38000);
+
+    fn get_test_port() -> u16 {
+        PORT_COUNTER.fetch_add(1, Ordering::Relaxed)
+    }
+
+    type Records = Arc<Mutex<HashMap<String, Vec<String>>>>;
+
+    /// 读取 DNS 名称（测试中的请求不含压缩指针）
+    fn read_name(buf: &[u8], mut pos: usize) -> (String, usize) {
+        let mut labels = vec![];
+        while buf[pos] != 0 {
+            let len = buf[pos] as usize;
+            labels.push(String::from_utf8_lossy(&buf[pos + 1..pos + 1 + len]).to_string());
+            pos += 1 + len;
+        }
+        (labels.join("."), pos + 1)
+    }
+
+    fn u16_at(buf: &[u8], pos: usize) -> u16 {
+        u16::from_be_bytes([buf[pos], buf[pos + 1]])
+    }
+
+    /// 极简 DNS 服务器：处理 TXT 查询与 RFC 2136 更新，`tsig_key` 不为空时要求更新请求携带该 TSIG 密钥
+    async fn start_dns_server(tsig_key: Option<&'static str>) -> (SocketAddr, Records) {
+        let addr: SocketAddr = format!("127.0.0.1:{}", get_test_port()).parse().unwrap();
+        let socket = UdpSocket::bind(addr).await.unwrap();
+        let records: Records = Arc::default();
+        let records2 = Arc::clone(&records);
+        tokio::spawn(async move {
+            let mut buf = [0u8; 4096];
+            loop {
+                let Ok((n, peer)) = socket.recv_from(&mut buf).await else {
+                    return;
+                };
+                let req = &buf[..n];
+                let opcode = (req[2] >> 3) & 0x0f;
+                let (qname, mut pos) = read_name(req, 12);
+                pos += 4;
+                let mut res = req[..pos].to_vec();
+                res[2] |= 0x80;
+                res[6..12].fill(0);
+                let mut rcode = 0;
+                if opcode == 5 {
+                    // 更新请求：zone 区域之后为 prereq（0 条）与 update 区域
+                    let mut updates = vec![];
+                    for _ in 0..u16_at(req, 8) {
+                        let (name, p) = read_name(req, pos);
+                        let class = u16_at(req, p + 2);
+                        let rdlen = u16_at(req, p + 8) as usize;
+                        let rdata = &req[p + 11..p + 10 + rdlen];
+                        updates.push((name, class, String::from_utf8_lossy(rdata).to_string()));
+                        pos = p + 10 + rdlen;
+                    }
+                    let tsig = match u16_at(req, 10) {
+                        1 => Some(read_name(req, pos)).filter(|(_, p)| u16_at(req, *p) == 250),
+                        _ => None,
+                    };
+                    match (tsig_key, tsig) {
+                        (Some(key), Some((name, _))) if key == name => {}
+                        (None, _) => {}
+                        _ => rcode = 9,
+                    }
+                    if rcode == 0 {
+                        let mut records = records2.lock().unwrap();
+                        for (name, class, value) in updates {
+                            let rrset = records.entry(name).or_default();
+                            match class {
+                                1 => rrset.push(value),
+                                _ => rrset.retain(|v| *v != value),
+                            }
+                        }
+                    }
+                } else {
+                    let values = records2
+                        .lock()
+                        .unwrap()
+                        .get(&qname)
+                        .cloned()
+                        .unwrap_or_default();
+                    res[7] = values.len() as u8;
+                    for value in values {
+                        // 指向问题区域名称的压缩指针
+                        res.extend([0xc0, 12, 0, 16, 0, 1, 0, 0, 0, 60]);
+                        res.extend(((value.len() + 1) as u16).to_be_bytes());
+                        res.push(value.len() as u8);
+                        res.extend(value.as_bytes());
+                    }
+                }
+                res[3] = (res[3] & 0xf0) | rcode;
+                _ = socket.send_to(&res, peer).await;
+            }
+        });
+        (addr, records)
+    }
+
+    #[tokio::test]
+    async fn test_rfc2136_create_and_delete() -> anyhow::Result<()> {
+        let (server, records) = start_dns_server(None).await;
+        let provider = Rfc2136Provider::new(server, "example.com").with_ttl(30);
+        let fqdn = "_acme-challenge.example.com";
+
+        provider.create_txt_record(fqdn, "token-a").await?;
+        provider.create_txt_record(fqdn, "token-b").await?;
+        let mut values = lookup_txt(server, fqdn).await?;
+        values.sort();
+        assert_eq!(values, vec!["token-a", "token-b"]);
+
+        // 只删除指定内容的记录，通配符与主域名可同时验证
+        provider.delete_txt_record(fqdn, "token-a").await?;
+        assert_eq!(lookup_txt(server, fqdn).await?, vec!["token-b"]);
+        provider.delete_txt_record(fqdn, "token-b").await?;
+        assert!(lookup_txt(server, fqdn).await?.is_empty());
+        assert!(records.lock().unwrap()[fqdn].is_empty());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_rfc2136_tsig() -> anyhow::Result<()> {
+        let (server, _records) = start_dns_server(Some("acme-key")).await;
+        let fqdn = "_acme-challenge.tsig.example.com";
+
+        let unsigned = Rfc2136Provider::new(server, "example.com");
+        let err = unsigned.create_txt_record(fqdn, "v").await.unwrap_err();
+        assert!(err.to_string().contains("NOTAUTH"), "{err}");
+
+        let signed = unsigned.with_tsig("acme-key", "c2VjcmV0LWtleS1mb3ItdGVzdHM=");
+        signed.create_txt_record(fqdn, "v").await?;
+        assert_eq!(lookup_txt(server, fqdn).await?, vec!["v"]);
+
+        let bad_secret = Rfc2136Provider::new(server, "example.com").with_tsig("acme-key", "!!");
+        assert!(bad_secret.create_txt_record(fqdn, "v").await.is_err());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_wait_for_txt_propagation() -> anyhow::Result<()> {
+        let (server, _records) = start_dns_server(None).await;
+        let (secondary, _records2) = start_dns_server(None).await;
+        let fqdn = "_acme-challenge.wait.example.com";
+        let provider = Rfc2136Provider::new(server, "example.com");
+        provider.create_txt_record(fqdn, "ready").await?;
+
+        wait_for_txt(&[server], fqdn, "ready", Duration::from_secs(5)).await?;
+        // 次级服务器尚未同步时超时
+        let err = wait_for_txt(&[server, secondary], fqdn, "ready", Duration::from_secs(1))
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("not propagated"), "{err}");
+
+        // 记录延迟生效时持续轮询
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(500)).await;
+            let secondary = Rfc2136Provider::new(secondary, "example.com");
+            _ = secondary.create_txt_record(fqdn, "ready").await;
+        });
+        wait_for_txt(&[server, secondary], fqdn, "ready", Duration::from_secs(10)).await?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_wildcard_requires_dns_provider() {
+        let mut opts = AcmeOptions::new("*.example.com", "admin@example.com");
+        assert!(opts.dns_provider.is_none());
+        let err = AcmeManager::new(opts).await.err().unwrap();
+        assert!(err.to_string().contains("DNS-01"), "{err}");
+
+        opts = AcmeOptions::new("*.example.com", "admin@example.com")
+            .with_dns_provider(Rfc2136Provider::new(
+                "127.0.0.1:53".parse().unwrap(),
+                "example.com",
+            ))
+            .with_dns_resolvers(vec!["127.0.0.1:53".parse().unwrap()]);
+        assert!(opts.dns_provider.is_some());
+        assert_eq!(opts.dns_resolvers.len(), 1);
+        assert_eq!(opts.dns_propagation_timeout, Duration::from_secs(120));
+    }
+}
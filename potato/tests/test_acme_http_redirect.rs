@@ -0,0 +1,204 @@
+/// ACME 明文 HTTP 监听（HTTP-01 验证与 HTTPS 跳转）及 HSTS 测试
+#[cfg(feature = "acme")]
+mod acme_http_redirect_tests {
+    use base64::Engine;
+    use potato::acme::{AcmeOptions, CertStore, FileCertStore};
+    use potato::{HttpServer, Session, TlsClientConfig};
+    use std::sync::atomic::{AtomicU16, Ordering};
+    use std::time::Duration;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpStream;
+
+    static PORT_COUNTER: AtomicU16 = AtomicU16::new(42000);
+
+    fn get_test_port() -> u16 {
+        PORT_COUNTER.fetch_add(1, Ordering::Relaxed)
+    }
+
+    /// 准备已有账户与证书的存储，启动时不访问 ACME 服务器
+    async fn prepare_store(name: &str) -> anyhow::Result<FileCertStore> {
+        let dir = std::env::temp_dir().join(format!(
+            "potato_acme_redirect_{name}_{}",
+            std::process::id()
+        ));
+        _ = std::fs::remove_dir_all(&dir);
+        let store = FileCertStore::new(dir);
+        let key = rcgen::KeyPair::generate()?;
+        let key = base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(key.serialize_der());
+        let account = format!(
+            r#"{{"id":"https://acme.invalid/acct/1","key_pkcs8":"{key}","directory":null,"urls":{{"newNonce":"https://acme.invalid/nonce","newAccount":"https://acme.invalid/acct","newOrder":"https://acme.invalid/order"}}}}"#
+        );
+        let cert = rcgen::generate_simple_self_signed(vec!["potato.example".to_string()])?;
+        store
+            .store("account.json", account.as_bytes(), true)
+            .await?;
+        store
+            .store("key.pem", cert.signing_key.serialize_pem().as_bytes(), true)
+            .await?;
+        store
+            .store("cert.pem", cert.cert.pem().as_bytes(), false)
+            .await?;
+        Ok(store)
+    }
+
+    /// 发送原始 HTTP 请求，返回（状态码，响应头与正文）
+    async fn raw_request(port: u16, request: &str) -> anyhow::Result<(u16, String)> {
+        let mut stream = TcpStream::connect(format!("127.0.0.1:{port}")).await?;
+        stream.write_all(request.as_bytes()).await?;
+        let mut buf = vec![];
+        tokio::time::timeout(Duration::from_secs(5), stream.read_to_end(&mut buf)).await??;
+        let text = String::from_utf8_lossy(&buf).to_string();
+        let status = text
+            .split(' ')
+            .nth(1)
+            .and_then(|code| code.parse().ok())
+            .unwrap_or(0);
+        Ok((status, text))
+    }
+
+    /// 等待端口开始或停止监听
+    async fn wait_listening(port: u16, listening: bool) -> bool {
+        for _ in 0..50 {
+            let connected = TcpStream::connect(format!("127.0.0.1:{port}"))
+                .await
+                .is_ok();
+            if connected == listening {
+                return true;
+            }
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+        false
+    }
+
+    fn header<'a>(response: &'a str, name: &str) -> Option<&'a str> {
+        response.lines().find_map(|line| {
+            let (key, value) = line.split_once(':')?;
+            key.eq_ignore_ascii_case(name).then(|| value.trim())
+        })
+    }
+
+    #[tokio::test]
+    async fn test_http_listener_challenge_and_redirect() -> anyhow::Result<()> {
+        let store = prepare_store("redirect").await?;
+        let dir = store.dir.clone();
+        store.store("http-01/tok-1", b"tok-1.thumb", false).await?;
+
+        let (https_port, http_port) = (get_test_port(), get_test_port());
+        let opts = AcmeOptions::new("potato.example", "admin@potato.example")
+            .with_cert_store(store)
+            .with_http_redirect(format!("127.0.0.1:{http_port}"))
+            .with_hsts(Duration::from_secs(31536000), true);
+        let mut server = HttpServer::new(format!("127.0.0.1:{https_port}"));
+        let shutdown = server.shutdown_signal()?;
+        tokio::spawn(async move { server.serve_acme_with_opts(opts).await });
+        assert!(wait_listening(http_port, true).await);
+        assert!(wait_listening(https_port, true).await);
+
+        // HTTP-01 验证经由明文监听应答
+        let (status, res) = raw_request(
+            http_port,
+            "GET /.well-known/acme-challenge/tok-1 HTTP/1.1\r\nHost: potato.example\r\nConnection: close\r\n\r\n",
+        )
+        .await?;
+        assert_eq!(status, 200);
+        assert!(res.ends_with("tok-1.thumb"));
+        let (status, _) = raw_request(
+            http_port,
+            "GET /.well-known/acme-challenge/unknown HTTP/1.1\r\nHost: potato.example\r\nConnection: close\r\n\r\n",
+        )
+        .await?;
+        assert_eq!(status, 404);
+
+        // 其余请求跳转到 HTTPS，保留路径与查询参数，替换端口
+        let (status, res) = raw_request(
+            http_port,
+            "GET /docs/page?lang=en HTTP/1.1\r\nHost: potato.example:8080\r\nConnection: close\r\n\r\n",
+        )
+        .await?;
+        assert_eq!(status, 301);
+        assert_eq!(
+            header(&res, "Location"),
+            Some(format!("https://potato.example:{https_port}/docs/page?lang=en").as_str())
+        );
+        assert!(header(&res, "Strict-Transport-Security").is_none());
+        let (status, res) = raw_request(
+            http_port,
+            "POST /api HTTP/1.1\r\nHost: [::1]\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+        )
+        .await?;
+        assert_eq!(status, 301);
+        assert_eq!(
+            header(&res, "Location"),
+            Some(format!("https://[::1]:{https_port}/api").as_str())
+        );
+
+        // 缺少或非法的 Host 头
+        let (status, _) = raw_request(
+            http_port,
+            "GET / HTTP/1.1\r\nHost: evil.example/\r\nConnection: close\r\n\r\n",
+        )
+        .await?;
+        assert_eq!(status, 400);
+
+        // HTTPS 响应附加 HSTS 头
+        let mut session =
+            Session::with_tls_config(TlsClientConfig::new().danger_accept_invalid_certs());
+        let res = session
+            .get(&format!("https://127.0.0.1:{https_port}/missing"), vec![])
+            .await?;
+        assert_eq!(
+            res.headers
+                .iter()
+                .find(|(k, _)| k.eq_ignore_ascii_case("Strict-Transport-Security"))
+                .map(|(_, v)| v.to_string()),
+            Some("max-age=31536000; includeSubDomains".to_string())
+        );
+
+        _ = shutdown.send(());
+        // 关闭后明文监听一并停止
+        assert!(wait_listening(http_port, false).await);
+        _ = std::fs::remove_dir_all(&dir);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_redirect_status_options() -> anyhow::Result<()> {
+        let opts = AcmeOptions::new("potato.example", "admin@potato.example");
+        assert!(opts.http_listen.is_none());
+        assert_eq!(opts.redirect_status, 301);
+        assert!(opts.hsts.is_none());
+
+        // 308 保留请求方法
+        let store = prepare_store("permanent").await?;
+        let dir = store.dir.clone();
+        let (https_port, http_port) = (get_test_port(), get_test_port());
+        let mut opts = AcmeOptions::new("potato.example", "admin@potato.example")
+            .with_cert_store(store)
+            .with_http_redirect(format!("127.0.0.1:{http_port}"));
+        opts.redirect_status = 308;
+        let mut server = HttpServer::new(format!("127.0.0.1:{https_port}"));
+        let shutdown = server.shutdown_signal()?;
+        tokio::spawn(async move { server.serve_acme_with_opts(opts).await });
+        assert!(wait_listening(http_port, true).await);
+        assert!(wait_listening(https_port, true).await);
+        let (status, res) = raw_request(
+            http_port,
+            "GET / HTTP/1.1\r\nHost: potato.example\r\nConnection: close\r\n\r\n",
+        )
+        .await?;
+        assert_eq!(status, 308);
+        assert_eq!(
+            header(&res, "Location"),
+            Some(format!("https://potato.example:{https_port}/").as_str())
+        );
+        _ = shutdown.send(());
+
+        // 仅支持永久跳转
+        let mut opts = AcmeOptions::new("potato.example", "admin@potato.example");
+        opts.redirect_status = 302;
+        let mut server = HttpServer::new(format!("127.0.0.1:{}", get_test_port()));
+        assert!(server.serve_acme_with_opts(opts).await.is_err());
+        _ = std::fs::remove_dir_all(&dir);
+        Ok(())
+    }
+}
@@ -0,0 +1,160 @@
+/// ACME 续期策略与生命周期事件测试
+#[cfg(feature = "acme")]
+mod acme_lifecycle_tests {
+    use base64::Engine;
+    use potato::acme::{AcmeEvent, AcmeManager, AcmeOptions, CertStore, FileCertStore};
+    use std::time::Duration;
+    use tokio::sync::mpsc;
+
+    /// 账户凭据中的 ACME 地址不可连接，续期必然失败
+    fn account_json() -> String {
+        let key = rcgen::KeyPair::generate().unwrap();
+        let key = base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(key.serialize_der());
+        format!(
+            r#"{{"id":"https://127.0.0.1:1/acct/1","key_pkcs8":"{key}","directory":null,"urls":{{"newNonce":"https://127.0.0.1:1/nonce","newAccount":"https://127.0.0.1:1/acct","newOrder":"https://127.0.0.1:1/order"}}}}"#
+        )
+    }
+
+    /// 准备存储，证书在 `valid_days` 天后过期
+    async fn prepare_store(name: &str, valid_days: i64) -> anyhow::Result<FileCertStore> {
+        let dir = std::env::temp_dir().join(format!(
+            "potato_acme_lifecycle_{name}_{}",
+            std::process::id()
+        ));
+        _ = std::fs::remove_dir_all(&dir);
+        let store = FileCertStore::new(dir);
+        let mut params = rcgen::CertificateParams::new(vec!["potato.example".to_string()])?;
+        params.not_after = time::OffsetDateTime::now_utc() + time::Duration::days(valid_days);
+        let key = rcgen::KeyPair::generate()?;
+        let cert = params.self_signed(&key)?;
+        store
+            .store("account.json", account_json().as_bytes(), true)
+            .await?;
+        store
+            .store("key.pem", key.serialize_pem().as_bytes(), true)
+            .await?;
+        store
+            .store("cert.pem", cert.pem().as_bytes(), false)
+            .await?;
+        Ok(store)
+    }
+
+    fn options(store: FileCertStore, tx: mpsc::UnboundedSender<AcmeEvent>) -> AcmeOptions {
+        AcmeOptions::new("potato.example", "admin@potato.example")
+            .with_cert_store(store)
+            .with_renewal(
+                Duration::from_millis(100),
+                Duration::from_secs(30 * 24 * 3600),
+            )
+            .with_retry_backoff(Duration::from_millis(100), Duration::from_millis(300))
+            .with_event_handler(move |event| _ = tx.send(event.clone()))
+    }
+
+    #[test]
+    fn test_renewal_options() {
+        let opts = AcmeOptions::new("potato.example", "admin@potato.example");
+        assert_eq!(opts.check_interval, Duration::from_secs(6 * 3600));
+        assert_eq!(opts.renew_before, Duration::from_secs(30 * 24 * 3600));
+        assert_eq!(opts.retry_backoff, Duration::from_secs(5 * 60));
+        assert_eq!(opts.retry_backoff_max, Duration::from_secs(6 * 3600));
+        assert!(opts.on_event.is_none());
+        assert!(!opts.ocsp_stapling);
+
+        let opts = opts
+            .with_renewal(Duration::from_secs(60), Duration::from_secs(7 * 24 * 3600))
+            .with_retry_backoff(Duration::from_secs(1), Duration::from_secs(10))
+            .with_event_handler(|_| {})
+            .with_ocsp_stapling();
+        assert_eq!(opts.check_interval, Duration::from_secs(60));
+        assert_eq!(opts.renew_before, Duration::from_secs(7 * 24 * 3600));
+        assert_eq!(opts.retry_backoff, Duration::from_secs(1));
+        assert_eq!(opts.retry_backoff_max, Duration::from_secs(10));
+        assert!(opts.on_event.is_some());
+        assert!(opts.ocsp_stapling);
+    }
+
+    #[tokio::test]
+    async fn test_expiring_cert_events_and_backoff() -> anyhow::Result<()> {
+        let store = prepare_store("expiring", 10).await?;
+        let dir = store.dir.clone();
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let (manager, acceptor) = AcmeManager::new(options(store, tx)).await?;
+        let task = tokio::spawn(manager.start_renewal_loop(acceptor));
+
+        let mut retries = vec![];
+        let mut expiring = 0;
+        while retries.len() < 4 {
+            let event = tokio::time::timeout(Duration::from_secs(10), rx.recv())
+                .await?
+                .unwrap();
+            match event {
+                AcmeEvent::ExpiringSoon { domains, not_after } => {
+                    assert_eq!(domains, vec!["potato.example".to_string()]);
+                    let remaining = not_after.duration_since(std::time::SystemTime::now())?;
+                    assert!(remaining > Duration::from_secs(9 * 24 * 3600));
+                    // 每次续期前都会报告证书即将过期
+                    assert_eq!(expiring, retries.len());
+                    expiring += 1;
+                }
+                AcmeEvent::Failed {
+                    domains,
+                    error,
+                    retry_in,
+                } => {
+                    assert_eq!(domains, vec!["potato.example".to_string()]);
+                    assert!(!error.is_empty());
+                    assert_eq!(expiring, retries.len() + 1);
+                    retries.push(retry_in);
+                }
+                event => panic!("unexpected event: {event:?}"),
+            }
+        }
+        // 指数退避并受上限约束
+        assert_eq!(
+            retries,
+            [100, 200, 300, 300].map(Duration::from_millis).to_vec()
+        );
+        task.abort();
+        _ = std::fs::remove_dir_all(&dir);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_initial_issuance_failure_event() -> anyhow::Result<()> {
+        let store = prepare_store("initial", 90).await?;
+        store.delete("cert.pem").await?;
+        let dir = store.dir.clone();
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        assert!(AcmeManager::new(options(store, tx)).await.is_err());
+
+        match rx.try_recv()? {
+            AcmeEvent::Failed {
+                domains,
+                error,
+                retry_in,
+            } => {
+                assert_eq!(domains, vec!["potato.example".to_string()]);
+                assert!(!error.is_empty());
+                assert_eq!(retry_in, Duration::ZERO);
+            }
+            event => panic!("unexpected event: {event:?}"),
+        }
+        _ = std::fs::remove_dir_all(&dir);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_valid_cert_no_events() -> anyhow::Result<()> {
+        let store = prepare_store("valid", 90).await?;
+        let dir = store.dir.clone();
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let (manager, acceptor) = AcmeManager::new(options(store, tx)).await?;
+        let task = tokio::spawn(manager.start_renewal_loop(acceptor));
+
+        tokio::time::sleep(Duration::from_millis(500)).await;
+        assert!(rx.try_recv().is_err());
+        task.abort();
+        _ = std::fs::remove_dir_all(&dir);
+        Ok(())
+    }
+}
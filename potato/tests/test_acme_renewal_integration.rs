@@ -189,6 +189,7 @@ mod acme_renewal_integration_tests {
                 "https://acme-staging-v02.api.letsencrypt.org/directory".to_string(),
             ),
             cert_dir: Some("/tmp/test_certs".to_string()),
+            ..Default::default()
         };
 
         assert_eq!(full_opts.domains.len(), 3);
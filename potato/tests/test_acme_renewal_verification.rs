@@ -148,6 +148,7 @@ mod acme_renewal_verification {
                 "https://acme-staging-v02.api.letsencrypt.org/directory".to_string(),
             ),
             cert_dir: Some("./test_certs".to_string()),
+            ..Default::default()
         };
 
         assert_eq!(opts2.domains.len(), 2);
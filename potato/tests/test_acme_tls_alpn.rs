@@ -0,0 +1,206 @@
+/// ACME TLS-ALPN-01 测试
+#[cfg(feature = "acme")]
+mod acme_tls_alpn_tests {
+    use potato::acme::{AcmeOptions, DynamicTlsAcceptor, ACME_TLS_ALPN};
+    use sha2::{Digest, Sha256};
+    use std::sync::atomic::{AtomicU16, Ordering};
+    use std::sync::Arc;
+    use std::time::Duration;
+    use tokio::net::{TcpListener, TcpStream};
+    use tokio_rustls::rustls;
+    use tokio_rustls::rustls::client::danger::{
+        HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier,
+    };
+    use tokio_rustls::rustls::pki_types::{CertificateDer, ServerName, UnixTime};
+    use tokio_rustls::rustls::{DigitallySignedStruct, SignatureScheme};
+
+    static PORT_COUNTER: AtomicU16 = AtomicU16::new(39000);
+
+    fn get_test_port() -> u16 {
+        PORT_COUNTER.fetch_add(1, Ordering::Relaxed)
+    }
+
+    /// ACME 验证方不校验验证证书的证书链，仅检查扩展内容
+    #[derive(Debug)]
+    struct AcceptAnyCert;
+
+    impl ServerCertVerifier for AcceptAnyCert {
+        fn verify_server_cert(
+            &self,
+            _end_entity: &CertificateDer<'_>,
+            _intermediates: &[CertificateDer<'_>],
+            _server_name: &ServerName<'_>,
+            _ocsp_response: &[u8],
+            _now: UnixTime,
+        ) -> Result<ServerCertVerified, rustls::Error> {
+            Ok(ServerCertVerified::assertion())
+        }
+
+        fn verify_tls12_signature(
+            &self,
+            _message: &[u8],
+            _cert: &CertificateDer<'_>,
+            _dss: &DigitallySignedStruct,
+        ) -> Result<HandshakeSignatureValid, rustls::Error> {
+            Ok(HandshakeSignatureValid::assertion())
+        }
+
+        fn verify_tls13_signature(
+            &self,
+            _message: &[u8],
+            _cert: &CertificateDer<'_>,
+            _dss: &DigitallySignedStruct,
+        ) -> Result<HandshakeSignatureValid, rustls::Error> {
+            Ok(HandshakeSignatureValid::assertion())
+        }
+
+        fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+            rustls::crypto::ring::default_provider()
+                .signature_verification_algorithms
+                .supported_schemes()
+        }
+    }
+
+    /// 握手并返回服务端证书与协商的 ALPN
+    async fn handshake(
+        port: u16,
+        server_name: &str,
+        alpn: &[u8],
+    ) -> anyhow::Result<(CertificateDer<'static>, Option<Vec<u8>>)> {
+        let mut config = rustls::ClientConfig::builder_with_provider(Arc::new(
+            rustls::crypto::ring::default_provider(),
+        ))
+        .with_safe_default_protocol_versions()?
+        .dangerous()
+        .with_custom_certificate_verifier(Arc::new(AcceptAnyCert))
+        .with_no_client_auth();
+        config.alpn_protocols = vec![alpn.to_vec()];
+        let connector = tokio_rustls::TlsConnector::from(Arc::new(config));
+        let tcp = TcpStream::connect(format!("127.0.0.1:{port}")).await?;
+        let stream = connector
+            .connect(ServerName::try_from(server_name.to_string())?, tcp)
+            .await?;
+        let conn = stream.get_ref().1;
+        let cert = conn.peer_certificates().unwrap()[0].clone().into_owned();
+        Ok((cert, conn.alpn_protocol().map(|p| p.to_vec())))
+    }
+
+    async fn start_acceptor(acceptor: DynamicTlsAcceptor) -> u16 {
+        let port = get_test_port();
+        let listener = TcpListener::bind(format!("127.0.0.1:{port}"))
+            .await
+            .unwrap();
+        tokio::spawn(async move {
+            loop {
+                let Ok((stream, _)) = listener.accept().await else {
+                    return;
+                };
+                let acceptor = acceptor.get_acceptor().await;
+                tokio::spawn(async move {
+                    if let Ok(stream) = acceptor.accept(stream).await {
+                        tokio::time::sleep(Duration::from_millis(100)).await;
+                        drop(stream);
+                    }
+                });
+            }
+        });
+        port
+    }
+
+    fn contains(haystack: &[u8], needle: &[u8]) -> bool {
+        haystack.windows(needle.len()).any(|w| w == needle)
+    }
+
+    #[tokio::test]
+    async fn test_tls_alpn_challenge_cert() -> anyhow::Result<()> {
+        let normal = rcgen::generate_simple_self_signed(vec!["potato.example".to_string()])?;
+        let acceptor =
+            DynamicTlsAcceptor::new(&normal.cert.pem(), &normal.signing_key.serialize_pem())?;
+        let port = start_acceptor(acceptor.clone()).await;
+
+        // 未设置验证证书时 acme-tls/1 握手失败
+        assert!(handshake(port, "potato.example", ACME_TLS_ALPN)
+            .await
+            .is_err());
+
+        let key_authorization = "token-123.thumbprint-456";
+        acceptor.set_tls_alpn_challenge("Potato.Example", key_authorization)?;
+        let (cert, alpn) = handshake(port, "potato.example", ACME_TLS_ALPN).await?;
+        assert_eq!(alpn.as_deref(), Some(ACME_TLS_ALPN));
+        // id-pe-acmeIdentifier（1.3.6.1.5.5.7.1.31），内容为 key authorization 的 SHA-256
+        assert!(contains(
+            cert.as_ref(),
+            &[0x06, 0x08, 0x2b, 0x06, 0x01, 0x05, 0x05, 0x07, 0x01, 0x1f]
+        ));
+        let digest = Sha256::digest(key_authorization.as_bytes());
+        assert!(contains(cert.as_ref(), &digest));
+        assert!(contains(cert.as_ref(), b"potato.example"));
+
+        // 其他域名的验证握手失败，普通握手仍返回正式证书
+        assert!(handshake(port, "other.example", ACME_TLS_ALPN)
+            .await
+            .is_err());
+        let (cert, alpn) = handshake(port, "potato.example", b"h2").await?;
+        assert_eq!(alpn.as_deref(), Some(&b"h2"[..]));
+        assert_eq!(cert.as_ref(), normal.cert.der().as_ref());
+
+        // 热重载后验证证书仍然有效，清除后失效
+        let renewed = rcgen::generate_simple_self_signed(vec!["potato.example".to_string()])?;
+        acceptor
+            .reload(&renewed.cert.pem(), &renewed.signing_key.serialize_pem())
+            .await?;
+        assert!(handshake(port, "potato.example", ACME_TLS_ALPN)
+            .await
+            .is_ok());
+        acceptor.clear_tls_alpn_challenges();
+        assert!(handshake(port, "potato.example", ACME_TLS_ALPN)
+            .await
+            .is_err());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_tls_alpn_options() {
+        let opts = AcmeOptions::new("potato.example", "admin@potato.example").with_tls_alpn01();
+        assert!(opts.tls_alpn01);
+        assert!(opts.dns_provider.is_none());
+        assert!(opts.acme_ca_file.is_none());
+    }
+
+    /// 使用本地 Pebble 测试服务器完整申请证书，需手动运行：
+    ///
+    /// ```text
+    /// pebble -config test/config/pebble-config.json   # tlsPort 为 5001
+    /// PEBBLE_CA=/path/to/pebble/test/certs/pebble.minica.pem \
+    ///     cargo test -p potato --features acme --test test_acme_tls_alpn -- --ignored
+    /// ```
+    #[tokio::test]
+    #[ignore]
+    async fn test_tls_alpn_with_pebble() -> anyhow::Result<()> {
+        let directory = std::env::var("PEBBLE_DIRECTORY")
+            .unwrap_or_else(|_| "https://127.0.0.1:14000/dir".to_string());
+        let ca_file = std::env::var("PEBBLE_CA")?;
+        let domain = std::env::var("PEBBLE_DOMAIN").unwrap_or_else(|_| "localhost".to_string());
+        let cert_dir = std::env::temp_dir().join(format!("potato_pebble_{}", std::process::id()));
+
+        let mut opts = AcmeOptions::new(domain.clone(), "admin@potato.example").with_tls_alpn01();
+        opts.acme_directory = Some(directory);
+        opts.acme_ca_file = Some(ca_file);
+        opts.cert_dir = Some(cert_dir.to_string_lossy().to_string());
+        let mut server = potato::HttpServer::new("0.0.0.0:5001");
+        tokio::spawn(async move { server.serve_acme_with_opts(opts).await });
+
+        let cert_file = cert_dir.join("cert.pem");
+        for _ in 0..60 {
+            if cert_file.exists() {
+                break;
+            }
+            tokio::time::sleep(Duration::from_secs(1)).await;
+        }
+        tokio::time::sleep(Duration::from_secs(1)).await;
+        let (cert, _) = handshake(5001, &domain, b"http/1.1").await?;
+        assert!(contains(cert.as_ref(), b"Pebble"));
+        _ = std::fs::remove_dir_all(&cert_dir);
+        Ok(())
+    }
+}
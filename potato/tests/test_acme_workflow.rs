@@ -70,6 +70,7 @@ mod acme_full_workflow_tests {
                 "https://acme-staging-v02.api.letsencrypt.org/directory".to_string(),
             ),
             cert_dir: Some("/tmp/multi_domain_certs".to_string()),
+            ..Default::default()
         };
 
         assert_eq!(opts.domains.len(), 3);
@@ -0,0 +1,149 @@
+/// 自定义声明与 require_role / require_scope 标注测试
+use potato::{HttpResponse, HttpServer, SessionCache};
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicU16, Ordering};
+use std::time::Duration;
+
+static PORT_COUNTER: AtomicU16 = AtomicU16::new(42500);
+
+fn get_test_port() -> u16 {
+    PORT_COUNTER.fetch_add(1, Ordering::Relaxed)
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+struct OrderClaims {
+    roles: Vec<String>,
+    scope: String,
+    tenant: String,
+}
+
+#[potato::http_get("/claims/me")]
+async fn me(session: &mut SessionCache) -> anyhow::Result<HttpResponse> {
+    let claims: OrderClaims = session.claims()?;
+    Ok(HttpResponse::text(format!(
+        "{}:{}",
+        session.user_id().unwrap_or_default(),
+        claims.tenant
+    )))
+}
+
+#[potato::http_get("/claims/admin")]
+#[potato::require_role("admin", "ops")]
+async fn admin() -> HttpResponse {
+    HttpResponse::text("admin")
+}
+
+#[potato::http_post("/claims/orders")]
+#[potato::require_role("clerk")]
+#[potato::require_scope("orders:write")]
+async fn create_order(session: &mut SessionCache) -> HttpResponse {
+    let tenant: String = session.claim("tenant").unwrap_or_default();
+    HttpResponse::text(format!("created for {tenant}"))
+}
+
+async fn token_for(roles: &[&str], scope: &str) -> String {
+    let claims = OrderClaims {
+        roles: roles.iter().map(|r| r.to_string()).collect(),
+        scope: scope.to_string(),
+        tenant: "acme".to_string(),
+    };
+    SessionCache::generate_token_with_claims(7, Duration::from_secs(600), &claims)
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+async fn test_custom_claims() -> anyhow::Result<()> {
+    let token = token_for(&["clerk", "ops"], "orders:read orders:write").await;
+    let session = SessionCache::from_token(&token).await?;
+    assert_eq!(session.user_id(), Some(7));
+    assert_eq!(session.roles(), vec!["clerk", "ops"]);
+    assert_eq!(session.scopes(), vec!["orders:read", "orders:write"]);
+    assert!(session.has_role("ops") && !session.has_role("admin"));
+    assert_eq!(session.claim::<String>("tenant").as_deref(), Some("acme"));
+    assert_eq!(SessionCache::parse_token(&token).await.unwrap().0, 7);
+
+    // 同一用户的不同token携带各自的声明
+    let other = SessionCache::from_token(&token_for(&["admin"], "").await).await?;
+    assert_eq!(other.roles(), vec!["admin"]);
+    assert_eq!(session.roles(), vec!["clerk", "ops"]);
+
+    // 保留字段与非对象声明
+    let reserved = serde_json::json!({ "sub": 1 });
+    assert!(
+        SessionCache::generate_token_with_claims(7, Duration::from_secs(60), &reserved)
+            .await
+            .is_err()
+    );
+    assert!(
+        SessionCache::generate_token_with_claims(7, Duration::from_secs(60), &"admin")
+            .await
+            .is_err()
+    );
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_require_role_and_scope() -> anyhow::Result<()> {
+    let server_addr = format!("127.0.0.1:{}", get_test_port());
+    let mut server = HttpServer::new(&server_addr);
+    server.configure(|ctx| {
+        #[cfg(feature = "openapi")]
+        ctx.use_openapi("/doc/");
+        ctx.use_handlers();
+    });
+    let handle = tokio::spawn(async move {
+        let _ = server.serve_http().await;
+    });
+    tokio::time::sleep(Duration::from_millis(300)).await;
+
+    let clerk = token_for(&["clerk"], "orders:read").await;
+    let writer = token_for(&["clerk"], "orders:read orders:write").await;
+    let ops = token_for(&["ops"], "").await;
+
+    let url = format!("http://{server_addr}/claims/me");
+    let mut res = potato::get!(&url, Authorization = format!("Bearer {clerk}")).await?;
+    assert_eq!(res.http_code, 200);
+    assert_eq!(res.body.data().await, b"7:acme");
+
+    // 未携带token返回 401，角色不符返回 403
+    let url = format!("http://{server_addr}/claims/admin");
+    assert_eq!(potato::get(&url, vec![]).await?.http_code, 401);
+    let res = potato::get!(&url, Authorization = format!("Bearer {clerk}")).await?;
+    assert_eq!(res.http_code, 403);
+    let res = potato::get!(&url, Authorization = format!("Bearer {ops}")).await?;
+    assert_eq!(res.http_code, 200);
+
+    // 多个标注须全部满足，scope 不足时附带 WWW-Authenticate
+    let url = format!("http://{server_addr}/claims/orders");
+    let res = potato::post!(&url, vec![], Authorization = format!("Bearer {ops}")).await?;
+    assert_eq!(res.http_code, 403);
+    let res = potato::post!(&url, vec![], Authorization = format!("Bearer {clerk}")).await?;
+    assert_eq!(res.http_code, 403);
+    assert_eq!(
+        res.get_header("WWW-Authenticate"),
+        Some("Bearer error=\"insufficient_scope\", scope=\"orders:write\"")
+    );
+    let mut res = potato::post!(&url, vec![], Authorization = format!("Bearer {writer}")).await?;
+    assert_eq!(res.http_code, 200);
+    assert_eq!(res.body.data().await, b"created for acme");
+
+    // OpenAPI 中列出所需的角色与 scope
+    #[cfg(feature = "openapi")]
+    {
+        let mut res = potato::get(&format!("http://{server_addr}/doc/index.json"), vec![]).await?;
+        let doc: serde_json::Value = serde_json::from_slice(res.body.data().await)?;
+        let orders = &doc["paths"]["/claims/orders"]["post"];
+        assert_eq!(
+            orders["security"],
+            serde_json::json!([{ "bearerAuth": ["clerk", "orders:write"] }])
+        );
+        assert!(orders["responses"].get("403").is_some());
+        let me = &doc["paths"]["/claims/me"]["get"];
+        assert_eq!(me["security"], serde_json::json!([{ "bearerAuth": [] }]));
+        assert!(me["responses"].get("403").is_none());
+    }
+
+    handle.abort();
+    Ok(())
+}
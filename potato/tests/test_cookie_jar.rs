@@ -0,0 +1,209 @@
+/// 签名与加密 Cookie、密钥轮换及无状态 Cookie 会话测试
+use potato::{CookieBuilder, CookieKeys, HttpResponse, HttpServer, ServerConfig, SessionCache};
+use std::sync::atomic::{AtomicU16, Ordering};
+use std::time::Duration;
+
+static PORT_COUNTER: AtomicU16 = AtomicU16::new(42900);
+
+fn get_test_port() -> u16 {
+    PORT_COUNTER.fetch_add(1, Ordering::Relaxed)
+}
+
+#[potato::http_get("/jar/login")]
+async fn login() -> anyhow::Result<HttpResponse> {
+    let claims = serde_json::json!({ "roles": ["admin"] });
+    let cookie = SessionCache::generate_cookie_with_claims(7, Duration::from_secs(600), &claims)?;
+    let mut res = HttpResponse::text("ok");
+    res.set_cookie(cookie.priority("High"));
+    Ok(res)
+}
+
+#[potato::http_get("/jar/visit")]
+#[potato::require_role("admin")]
+async fn visit(session: &mut SessionCache) -> anyhow::Result<HttpResponse> {
+    let visits = session.get_persistent::<u32>("visits").unwrap_or(0) + 1;
+    session.set_persistent("visits", &visits)?;
+    session.set_signed_cookie("theme", "dark");
+    session.set_cookie_with_builder(
+        CookieBuilder::new("cart", "apple,pear")
+            .encrypted()
+            .partitioned(true),
+    );
+    Ok(HttpResponse::text(format!(
+        "{}:{visits}",
+        session.user_id().unwrap_or_default()
+    )))
+}
+
+#[potato::http_get("/jar/read")]
+async fn read(session: &mut SessionCache) -> HttpResponse {
+    HttpResponse::text(format!(
+        "{:?}:{:?}:{:?}",
+        session.get_signed_cookie("theme"),
+        session.get_encrypted_cookie("cart"),
+        session.get_persistent::<u32>("visits"),
+    ))
+}
+
+#[potato::http_get("/jar/logout")]
+async fn logout(session: &mut SessionCache) -> HttpResponse {
+    session.clear_session_cookie();
+    HttpResponse::text("bye")
+}
+
+#[test]
+fn test_cookie_keys_sign_and_encrypt() -> anyhow::Result<()> {
+    let keys = CookieKeys::new("k1-0123456789abcdef0123456789abcdef");
+    let signed = keys.sign("theme", "dark")?;
+    assert!(signed.starts_with("dark."));
+    assert_eq!(keys.verify("theme", &signed).as_deref(), Some("dark"));
+    // 篡改值、挪用到其他 Cookie 或使用其他密钥均校验失败
+    assert_eq!(
+        keys.verify("theme", &signed.replacen("dark", "lite", 1)),
+        None
+    );
+    assert_eq!(keys.verify("lang", &signed), None);
+    assert_eq!(CookieKeys::generate().verify("theme", &signed), None);
+
+    let sealed = keys.encrypt("cart", "apple; pear")?;
+    assert!(!sealed.contains("apple"));
+    assert_ne!(sealed, keys.encrypt("cart", "apple; pear")?);
+    assert_eq!(
+        keys.decrypt("cart", &sealed).as_deref(),
+        Some("apple; pear")
+    );
+    assert_eq!(keys.decrypt("other", &sealed), None);
+    assert_eq!(keys.decrypt("cart", &sealed[1..]), None);
+
+    // 轮换后旧密钥仍可校验与解密，新值使用新密钥
+    let rotated = CookieKeys::new("k2-0123456789abcdef0123456789abcdef")
+        .with_previous("k1-0123456789abcdef0123456789abcdef");
+    assert_eq!(rotated.verify("theme", &signed).as_deref(), Some("dark"));
+    assert_eq!(
+        rotated.decrypt("cart", &sealed).as_deref(),
+        Some("apple; pear")
+    );
+    assert_eq!(keys.verify("theme", &rotated.sign("theme", "dark")?), None);
+    Ok(())
+}
+
+#[test]
+fn test_cookie_partitioned_and_priority() {
+    let cookie = CookieBuilder::new("embed", "1")
+        .same_site("None")
+        .partitioned(true)
+        .priority("High");
+    assert_eq!(
+        cookie.to_set_cookie_string(),
+        "embed=1; Path=/; Secure; SameSite=None; Partitioned; Priority=High"
+    );
+    assert!(cookie
+        .to_delete_cookie_string()
+        .ends_with("; Secure; Partitioned"));
+}
+
+fn set_cookies(res: &HttpResponse) -> Vec<String> {
+    res.header_lines()
+        .filter(|(k, _)| k.eq_ignore_ascii_case("Set-Cookie"))
+        .map(|(_, v)| v.to_string())
+        .collect()
+}
+
+/// 取出 `Set-Cookie` 中指定 Cookie 的值
+fn cookie_value(res: &HttpResponse, name: &str) -> Option<String> {
+    set_cookies(res).into_iter().find_map(|line| {
+        let pair = line.split(';').next()?;
+        pair.strip_prefix(&format!("{name}=")).map(str::to_string)
+    })
+}
+
+async fn get(url: &str, cookie: &str) -> anyhow::Result<HttpResponse> {
+    potato::get!(url, Cookie = cookie.to_string()).await
+}
+
+#[tokio::test]
+async fn test_stateless_cookie_session() -> anyhow::Result<()> {
+    let k1 = "session-k1-0123456789abcdef0123456789";
+    let k2 = "session-k2-0123456789abcdef0123456789";
+    ServerConfig::set_cookie_keys(CookieKeys::new(k1));
+    let addr = format!("127.0.0.1:{}", get_test_port());
+    let mut server = HttpServer::new(&addr);
+    server.configure(|ctx| ctx.use_handlers());
+    tokio::spawn(async move {
+        let _ = server.serve_http().await;
+    });
+    tokio::time::sleep(Duration::from_millis(300)).await;
+    let base = format!("http://{addr}/jar");
+
+    let res = potato::get(&format!("{base}/login"), vec![]).await?;
+    let login_line = set_cookies(&res).remove(0);
+    assert!(login_line.contains("; Max-Age="));
+    assert!(login_line.ends_with("; HttpOnly; SameSite=Lax; Priority=High"));
+    let session = cookie_value(&res, "potato_session").unwrap();
+
+    // 会话值修改后重新下发会话 Cookie，签名与加密的 Cookie 一并下发
+    let mut res = get(
+        &format!("{base}/visit"),
+        &format!("potato_session={session}"),
+    )
+    .await?;
+    assert_eq!(res.body.data().await, b"7:1");
+    let session = cookie_value(&res, "potato_session").unwrap();
+    let theme = cookie_value(&res, "theme").unwrap();
+    let cart = cookie_value(&res, "cart").unwrap();
+    assert!(theme.starts_with("dark."));
+    assert!(!cart.contains("apple"));
+    assert!(set_cookies(&res)
+        .iter()
+        .any(|line| line.starts_with("cart=") && line.ends_with("; Secure; Partitioned")));
+
+    let jar = format!("potato_session={session}; theme={theme}; cart={cart}");
+    let mut res = get(&format!("{base}/visit"), &jar).await?;
+    assert_eq!(res.body.data().await, b"7:2");
+    let session = cookie_value(&res, "potato_session").unwrap();
+
+    // 未修改会话值时不重新下发
+    let jar = format!("potato_session={session}; theme={theme}; cart={cart}");
+    let mut res = get(&format!("{base}/read"), &jar).await?;
+    assert_eq!(
+        res.body.data().await,
+        b"Some(\"dark\"):Some(\"apple,pear\"):Some(2)"
+    );
+    assert!(cookie_value(&res, "potato_session").is_none());
+
+    // 篡改的 Cookie 视为无效
+    let forged = format!("potato_session={session}; theme=lite.{}", &theme[5..]);
+    let mut res = get(&format!("{base}/read"), &forged).await?;
+    assert_eq!(res.body.data().await, b"None:None:Some(2)");
+    let res = get(
+        &format!("{base}/read"),
+        &format!("potato_session=x{session}"),
+    )
+    .await?;
+    assert_eq!(res.http_code, 401);
+    assert_eq!(
+        potato::get(&format!("{base}/read"), vec![])
+            .await?
+            .http_code,
+        401
+    );
+
+    // 密钥轮换：旧密钥加密的会话仍然有效，并改用新密钥重新下发
+    ServerConfig::set_cookie_keys(CookieKeys::new(k2).with_previous(k1));
+    let res = get(&format!("{base}/read"), &jar).await?;
+    assert_eq!(res.http_code, 200);
+    let rotated = cookie_value(&res, "potato_session").unwrap();
+    ServerConfig::set_cookie_keys(CookieKeys::new(k2));
+    assert_eq!(get(&format!("{base}/read"), &jar).await?.http_code, 401);
+    let rotated_jar = format!("potato_session={rotated}");
+    let mut res = get(&format!("{base}/read"), &rotated_jar).await?;
+    assert_eq!(res.body.data().await, b"None:None:Some(2)");
+
+    // 登出删除会话 Cookie
+    let res = get(&format!("{base}/logout"), &rotated_jar).await?;
+    assert_eq!(
+        set_cookies(&res),
+        ["potato_session=; Path=/; Expires=Thu, 01 Jan 1970 00:00:00 GMT"]
+    );
+    Ok(())
+}
@@ -0,0 +1,263 @@
+/// 全局 CORS 中间件测试
+use potato::{CorsConfig, HttpRequest, HttpResponse, HttpServer};
+use std::sync::atomic::{AtomicU16, Ordering};
+use std::time::Duration;
+use tokio::time::sleep;
+
+static PORT_COUNTER: AtomicU16 = AtomicU16::new(43200);
+
+fn get_test_port() -> u16 {
+    PORT_COUNTER.fetch_add(1, Ordering::Relaxed)
+}
+
+#[potato::http_get("/cors/items")]
+async fn list_items() -> HttpResponse {
+    let mut res = HttpResponse::json("[]");
+    res.add_header("Vary".into(), "Accept-Encoding".into());
+    res
+}
+
+#[potato::http_post("/cors/items")]
+async fn create_item() -> HttpResponse {
+    HttpResponse::text("created")
+}
+
+#[potato::http_get("/cors/annotated")]
+#[potato::cors(origin = "https://route.example")]
+async fn annotated() -> HttpResponse {
+    HttpResponse::text("annotated")
+}
+
+/// 代替认证中间件：缺少或错误的 `X-API-Key` 返回 401
+fn require_api_key(req: &mut HttpRequest) -> Option<HttpResponse> {
+    match req.get_header("X-API-Key") {
+        Some("secret") => None,
+        _ => {
+            let mut res = HttpResponse::text("unauthorized");
+            res.http_code = 401;
+            Some(res)
+        }
+    }
+}
+
+fn headers(pairs: &[(&str, &str)]) -> Vec<potato::Headers> {
+    pairs
+        .iter()
+        .map(|(k, v)| potato::Headers::Custom((k.to_string(), v.to_string())))
+        .collect()
+}
+
+async fn preflight(url: &str, origin: &str, method: &str) -> anyhow::Result<HttpResponse> {
+    potato::options(
+        url,
+        headers(&[
+            ("Origin", origin),
+            ("Access-Control-Request-Method", method),
+            ("Access-Control-Request-Headers", "content-type,x-api-key"),
+        ]),
+    )
+    .await
+}
+
+#[tokio::test]
+async fn test_cors_wildcard() -> anyhow::Result<()> {
+    let server_addr = format!("127.0.0.1:{}", get_test_port());
+    let mut server = HttpServer::new(&server_addr);
+    server.configure(|ctx| {
+        ctx.use_cors(CorsConfig::default_minimal());
+        // 预检请求在认证之前应答
+        ctx.use_custom_sync(require_api_key);
+        ctx.use_handlers();
+        ctx.use_location_route("/static", "./", false);
+    });
+    let server_handle = tokio::spawn(async move {
+        let _ = server.serve_http().await;
+    });
+    sleep(Duration::from_millis(300)).await;
+
+    let base = format!("http://{server_addr}");
+
+    let res = preflight(&format!("{base}/cors/items"), "https://a.example", "POST").await?;
+    assert_eq!(res.http_code, 204);
+    assert_eq!(res.get_header("Access-Control-Allow-Origin"), Some("*"));
+    assert_eq!(
+        res.get_header("Access-Control-Allow-Methods"),
+        Some("GET,HEAD,OPTIONS,POST")
+    );
+    assert_eq!(res.get_header("Access-Control-Allow-Headers"), Some("*"));
+    assert_eq!(res.get_header("Access-Control-Max-Age"), Some("86400"));
+    assert_eq!(res.get_header("Access-Control-Allow-Credentials"), None);
+
+    // 未注册处理函数的路由（静态文件等）同样应答预检，回显请求的方法
+    let res = preflight(
+        &format!("{base}/static/Cargo.toml"),
+        "https://a.example",
+        "PUT",
+    )
+    .await?;
+    assert_eq!(res.http_code, 204);
+    assert_eq!(res.get_header("Access-Control-Allow-Methods"), Some("PUT"));
+
+    // 非预检的 OPTIONS 仍由路由处理，经过认证
+    let res = potato::options(&format!("{base}/cors/items"), vec![]).await?;
+    assert_eq!(res.http_code, 401);
+
+    // 实际请求附带 CORS 头，允许任意来源时无需 Vary: Origin
+    let url = format!("{base}/cors/items");
+    let res = potato::get(
+        &url,
+        headers(&[("Origin", "https://a.example"), ("X-API-Key", "secret")]),
+    )
+    .await?;
+    assert_eq!(res.http_code, 200);
+    assert_eq!(res.get_header("Access-Control-Allow-Origin"), Some("*"));
+    assert_eq!(res.get_header("Vary"), Some("Accept-Encoding"));
+
+    server_handle.abort();
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_cors_allow_list_with_credentials() -> anyhow::Result<()> {
+    let server_addr = format!("127.0.0.1:{}", get_test_port());
+    let mut server = HttpServer::new(&server_addr);
+    server.configure(|ctx| {
+        ctx.use_cors(
+            CorsConfig::default_minimal()
+                .with_origin("https://app.example.com")
+                .with_origin("https://*.example.org")
+                .with_origin("http://localhost:*")
+                .with_expose_headers("X-Total-Count")
+                .with_max_age(Duration::from_secs(600))
+                .with_credentials()
+                .unwrap(),
+        );
+        // 预检请求在认证之前应答
+        ctx.use_custom_sync(require_api_key);
+        ctx.use_handlers();
+        ctx.use_location_route("/static", "./", false);
+    });
+    let server_handle = tokio::spawn(async move {
+        let _ = server.serve_http().await;
+    });
+    sleep(Duration::from_millis(300)).await;
+
+    let base = format!("http://{server_addr}");
+    let url = format!("{base}/cors/items");
+
+    for origin in [
+        "https://app.example.com",
+        "https://tenant.example.org",
+        "https://a.b.example.org",
+        "http://localhost:5173",
+    ] {
+        let res = preflight(&url, origin, "POST").await?;
+        assert_eq!(res.http_code, 204, "{origin}");
+        assert_eq!(res.get_header("Access-Control-Allow-Origin"), Some(origin));
+        assert_eq!(
+            res.get_header("Access-Control-Allow-Credentials"),
+            Some("true")
+        );
+        // 携带凭证时回显请求的头而不是通配符
+        assert_eq!(
+            res.get_header("Access-Control-Allow-Headers"),
+            Some("content-type,x-api-key")
+        );
+        assert_eq!(res.get_header("Access-Control-Max-Age"), Some("600"));
+        assert!(res.get_header("Vary").unwrap().starts_with("Origin"));
+    }
+
+    for origin in [
+        "https://evil.com",
+        "https://app.example.com.evil.com",
+        "https://example.org",
+        "https://evil.com/.example.org",
+        "http://localhost",
+        "null",
+    ] {
+        let res = preflight(&url, origin, "POST").await?;
+        assert_eq!(res.http_code, 403, "{origin}");
+        assert_eq!(res.get_header("Access-Control-Allow-Origin"), None);
+    }
+
+    let res = potato::get(
+        &url,
+        headers(&[
+            ("Origin", "https://tenant.example.org"),
+            ("X-API-Key", "secret"),
+        ]),
+    )
+    .await?;
+    assert_eq!(
+        res.get_header("Access-Control-Allow-Origin"),
+        Some("https://tenant.example.org")
+    );
+    assert_eq!(
+        res.get_header("Access-Control-Allow-Credentials"),
+        Some("true")
+    );
+    assert_eq!(
+        res.get_header("Access-Control-Expose-Headers"),
+        Some("X-Total-Count")
+    );
+    assert_eq!(res.get_header("Vary"), Some("Accept-Encoding, Origin"));
+
+    // 不被允许的来源与同源请求不附带 CORS 头，但须声明 Vary: Origin
+    for pairs in [
+        vec![("Origin", "https://evil.com"), ("X-API-Key", "secret")],
+        vec![("X-API-Key", "secret")],
+    ] {
+        let res = potato::get(&url, headers(&pairs)).await?;
+        assert_eq!(res.http_code, 200);
+        assert_eq!(res.get_header("Access-Control-Allow-Origin"), None);
+        assert_eq!(res.get_header("Vary"), Some("Accept-Encoding, Origin"));
+    }
+
+    // 认证失败等中间件产生的响应同样附带 CORS 头
+    let res = potato::get(&url, headers(&[("Origin", "https://app.example.com")])).await?;
+    assert_eq!(res.http_code, 401);
+    assert_eq!(
+        res.get_header("Access-Control-Allow-Origin"),
+        Some("https://app.example.com")
+    );
+
+    // 路由的 cors 标注优先
+    let res = potato::get(
+        &format!("{base}/cors/annotated"),
+        headers(&[
+            ("Origin", "https://app.example.com"),
+            ("X-API-Key", "secret"),
+        ]),
+    )
+    .await?;
+    assert_eq!(
+        res.get_header("Access-Control-Allow-Origin"),
+        Some("https://route.example")
+    );
+
+    server_handle.abort();
+    Ok(())
+}
+
+#[test]
+fn test_cors_credentials_require_allow_list() {
+    let err = CorsConfig::default_minimal()
+        .with_credentials()
+        .unwrap_err();
+    assert!(err.to_string().contains("explicit origin allow-list"));
+    assert!(CorsConfig::default_minimal()
+        .with_origin("https://app.example.com")
+        .with_credentials()
+        .is_ok());
+}
+
+#[test]
+fn test_cors_with_origin_replaces_wildcard() {
+    let config = CorsConfig::default_minimal()
+        .with_origin("https://app.example.com")
+        .with_origin("https://*.example.org");
+    assert_eq!(
+        config.origin.as_deref(),
+        Some("https://app.example.com,https://*.example.org")
+    );
+}
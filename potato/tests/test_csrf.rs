@@ -0,0 +1,390 @@
+/// 集成测试：验证 use_csrf 中间件与 #[csrf_exempt] 标注
+use std::sync::atomic::{AtomicU16, Ordering};
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+use tokio::time::sleep;
+
+static PORT_COUNTER: AtomicU16 = AtomicU16::new(42200);
+
+fn get_test_port() -> u16 {
+    PORT_COUNTER.fetch_add(1, Ordering::Relaxed)
+}
+
+/// 发送原始 HTTP 请求，返回（状态码，响应头与正文）
+async fn raw_request(addr: &str, request: String) -> anyhow::Result<(u16, String)> {
+    let mut stream = TcpStream::connect(addr).await?;
+    stream.write_all(request.as_bytes()).await?;
+    let mut buf = vec![];
+    tokio::time::timeout(Duration::from_secs(5), stream.read_to_end(&mut buf)).await??;
+    let text = String::from_utf8_lossy(&buf).to_string();
+    let status = text
+        .split(' ')
+        .nth(1)
+        .and_then(|code| code.parse().ok())
+        .unwrap_or(0);
+    Ok((status, text))
+}
+
+fn post(addr: &str, path: &str, headers: &[&str], body: &str) -> String {
+    let mut req = format!("POST {path} HTTP/1.1\r\nHost: {addr}\r\nConnection: close\r\n");
+    for header in headers {
+        req.push_str(header);
+        req.push_str("\r\n");
+    }
+    if !body.is_empty() {
+        req.push_str("Content-Type: application/x-www-form-urlencoded\r\n");
+    }
+    req.push_str(&format!("Content-Length: {}\r\n\r\n{body}", body.len()));
+    req
+}
+
+/// 取出响应中指定 Cookie 的值
+fn set_cookie_value(response: &str, name: &str) -> Option<String> {
+    response.lines().find_map(|line| {
+        let (key, value) = line.split_once(':')?;
+        if !key.eq_ignore_ascii_case("Set-Cookie") {
+            return None;
+        }
+        let (cookie, value) = value.trim().split(';').next()?.split_once('=')?;
+        (cookie == name).then(|| value.to_string())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use potato::{CsrfConfig, HttpRequest, HttpResponse, HttpServer};
+
+    #[potato::http_get("/csrf/form")]
+    async fn form(req: &mut HttpRequest) -> HttpResponse {
+        let token = req.csrf_token().map(|t| t.to_string()).unwrap_or_default();
+        HttpResponse::html(format!("<input name=\"csrf_token\" value=\"{token}\">"))
+    }
+
+    #[potato::http_post("/csrf/submit")]
+    async fn submit() -> HttpResponse {
+        HttpResponse::text("accepted")
+    }
+
+    #[potato::http_post("/csrf/webhook")]
+    #[potato::csrf_exempt]
+    async fn webhook() -> HttpResponse {
+        HttpResponse::text("hooked")
+    }
+
+    #[potato::http_get("/csrf/login")]
+    async fn login(session: &mut potato::SessionCache) -> HttpResponse {
+        session.set_cookie("sid", "abc");
+        HttpResponse::text("login")
+    }
+
+    async fn start(config: CsrfConfig) -> (String, tokio::task::JoinHandle<()>) {
+        let server_addr = format!("127.0.0.1:{}", get_test_port());
+        let mut server = HttpServer::new(&server_addr);
+        server.configure(move |ctx| {
+            ctx.use_csrf(config.clone());
+            ctx.use_handlers();
+        });
+        let handle = tokio::spawn(async move {
+            let _ = server.serve_http().await;
+        });
+        sleep(Duration::from_millis(300)).await;
+        (server_addr, handle)
+    }
+
+    /// 访问表单页获取令牌
+    async fn fetch_token(addr: &str) -> anyhow::Result<String> {
+        let (status, res) = raw_request(
+            addr,
+            format!("GET /csrf/form HTTP/1.1\r\nHost: {addr}\r\nConnection: close\r\n\r\n"),
+        )
+        .await?;
+        assert_eq!(status, 200);
+        let token = set_cookie_value(&res, "csrf_token").unwrap();
+        // 页面中的令牌与 Cookie 一致
+        assert!(res.contains(&format!("value=\"{token}\"")));
+        assert!(res.contains("SameSite=Lax"));
+        Ok(token)
+    }
+
+    #[tokio::test]
+    async fn test_token_header_and_form_field() -> anyhow::Result<()> {
+        let (addr, handle) = start(CsrfConfig::new()).await;
+        let token = fetch_token(&addr).await?;
+        let cookie = format!("Cookie: csrf_token={token}");
+
+        // 请求头回传
+        let header = format!("X-CSRF-Token: {token}");
+        let (status, res) =
+            raw_request(&addr, post(&addr, "/csrf/submit", &[&cookie, &header], "")).await?;
+        assert_eq!(status, 200);
+        assert!(res.ends_with("accepted"));
+        // Cookie 有效时不重复下发
+        assert!(set_cookie_value(&res, "csrf_token").is_none());
+
+        // 表单字段回传
+        let body = format!("csrf_token={token}&name=potato");
+        let (status, _) =
+            raw_request(&addr, post(&addr, "/csrf/submit", &[&cookie], &body)).await?;
+        assert_eq!(status, 200);
+
+        // 同源 Origin 与 Sec-Fetch-Site 放行
+        let origin = format!("Origin: http://{addr}");
+        let (status, _) = raw_request(
+            &addr,
+            post(
+                &addr,
+                "/csrf/submit",
+                &[&cookie, &header, &origin, "Sec-Fetch-Site: same-origin"],
+                "",
+            ),
+        )
+        .await?;
+        assert_eq!(status, 200);
+
+        handle.abort();
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_rejections() -> anyhow::Result<()> {
+        let (addr, handle) = start(CsrfConfig::new()).await;
+        let token = fetch_token(&addr).await?;
+        let cookie = format!("Cookie: csrf_token={token}");
+        let header = format!("X-CSRF-Token: {token}");
+
+        // 缺少令牌
+        let (status, res) = raw_request(&addr, post(&addr, "/csrf/submit", &[&cookie], "")).await?;
+        assert_eq!(status, 403);
+        assert!(res.ends_with("missing CSRF token"));
+
+        // 令牌不一致
+        let (status, _) = raw_request(
+            &addr,
+            post(
+                &addr,
+                "/csrf/submit",
+                &[&cookie, "X-CSRF-Token: forged"],
+                "",
+            ),
+        )
+        .await?;
+        assert_eq!(status, 403);
+
+        // 缺少 Cookie 时拒绝并下发新令牌
+        let (status, res) = raw_request(&addr, post(&addr, "/csrf/submit", &[&header], "")).await?;
+        assert_eq!(status, 403);
+        assert!(res.ends_with("missing CSRF cookie"));
+        assert!(set_cookie_value(&res, "csrf_token").is_some());
+
+        // 未签名的 Cookie 视为缺失
+        let (status, _) = raw_request(
+            &addr,
+            post(
+                &addr,
+                "/csrf/submit",
+                &["Cookie: csrf_token=abc.def", "X-CSRF-Token: abc.def"],
+                "",
+            ),
+        )
+        .await?;
+        assert_eq!(status, 403);
+
+        // 跨站请求
+        let (status, res) = raw_request(
+            &addr,
+            post(
+                &addr,
+                "/csrf/submit",
+                &[&cookie, &header, "Sec-Fetch-Site: cross-site"],
+                "",
+            ),
+        )
+        .await?;
+        assert_eq!(status, 403);
+        assert!(res.ends_with("cross-site request"));
+        let (status, _) = raw_request(
+            &addr,
+            post(
+                &addr,
+                "/csrf/submit",
+                &[&cookie, &header, "Origin: https://evil.example"],
+                "",
+            ),
+        )
+        .await?;
+        assert_eq!(status, 403);
+        let (status, _) = raw_request(
+            &addr,
+            post(
+                &addr,
+                "/csrf/submit",
+                &[&cookie, &header, "Referer: https://evil.example/page"],
+                "",
+            ),
+        )
+        .await?;
+        assert_eq!(status, 403);
+
+        handle.abort();
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_exemptions_and_trusted_origins() -> anyhow::Result<()> {
+        let (addr, handle) = start(
+            CsrfConfig::new()
+                .with_trusted_origin("https://app.example.com")
+                .with_exempt_path("/csrf/hooks/"),
+        )
+        .await;
+
+        // #[csrf_exempt] 标注的路由
+        let (status, res) = raw_request(
+            &addr,
+            post(&addr, "/csrf/webhook", &["Sec-Fetch-Site: cross-site"], ""),
+        )
+        .await?;
+        assert_eq!(status, 200);
+        assert!(res.ends_with("hooked"));
+
+        // 路径前缀豁免（未匹配路由返回 404 而非 403）
+        let (status, _) = raw_request(&addr, post(&addr, "/csrf/hooks/github", &[], "")).await?;
+        assert_eq!(status, 404);
+
+        // 可信来源
+        let token = fetch_token(&addr).await?;
+        let cookie = format!("Cookie: csrf_token={token}");
+        let header = format!("X-CSRF-Token: {token}");
+        let (status, _) = raw_request(
+            &addr,
+            post(
+                &addr,
+                "/csrf/submit",
+                &[&cookie, &header, "Origin: https://app.example.com"],
+                "",
+            ),
+        )
+        .await?;
+        assert_eq!(status, 200);
+
+        // 固定密钥签发的令牌在其他实例上同样有效
+        let (addr2, handle2) = start(CsrfConfig::new().with_secret(*b"shared-secret")).await;
+        let token = fetch_token(&addr2).await?;
+        let (addr3, handle3) = start(CsrfConfig::new().with_secret(*b"shared-secret")).await;
+        let cookie = format!("Cookie: csrf_token={token}");
+        let header = format!("X-CSRF-Token: {token}");
+        let (status, _) = raw_request(
+            &addr3,
+            post(&addr3, "/csrf/submit", &[&cookie, &header], ""),
+        )
+        .await?;
+        assert_eq!(status, 200);
+        let (status, _) =
+            raw_request(&addr, post(&addr, "/csrf/submit", &[&cookie, &header], "")).await?;
+        assert_eq!(status, 403);
+
+        handle.abort();
+        handle2.abort();
+        handle3.abort();
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_token_bound_to_session() -> anyhow::Result<()> {
+        potato::SessionCache::set_jwt_secret(b"csrf-test-secret").await;
+        let alice = potato::SessionCache::generate_token(7, Duration::from_secs(60)).await?;
+        let mallory = potato::SessionCache::generate_token(8, Duration::from_secs(60)).await?;
+        let (addr, handle) = start(CsrfConfig::new()).await;
+
+        let (status, res) = raw_request(
+            &addr,
+            format!(
+                "GET /csrf/form HTTP/1.1\r\nHost: {addr}\r\nAuthorization: Bearer {alice}\r\nConnection: close\r\n\r\n"
+            ),
+        )
+        .await?;
+        assert_eq!(status, 200);
+        let token = set_cookie_value(&res, "csrf_token").unwrap();
+        let cookie = format!("Cookie: csrf_token={token}");
+        let header = format!("X-CSRF-Token: {token}");
+
+        let bearer = format!("Authorization: Bearer {alice}");
+        let (status, _) = raw_request(
+            &addr,
+            post(&addr, "/csrf/submit", &[&cookie, &header, &bearer], ""),
+        )
+        .await?;
+        assert_eq!(status, 200);
+
+        // 其他用户或未登录时不接受该令牌
+        let bearer = format!("Authorization: Bearer {mallory}");
+        let (status, res) = raw_request(
+            &addr,
+            post(&addr, "/csrf/submit", &[&cookie, &header, &bearer], ""),
+        )
+        .await?;
+        assert_eq!(status, 403);
+        assert!(res.ends_with("missing CSRF cookie"));
+        let (status, _) =
+            raw_request(&addr, post(&addr, "/csrf/submit", &[&cookie, &header], "")).await?;
+        assert_eq!(status, 403);
+
+        // 未登录时获得的令牌不能用于已登录的会话
+        let token = fetch_token(&addr).await?;
+        let cookie = format!("Cookie: csrf_token={token}");
+        let header = format!("X-CSRF-Token: {token}");
+        let bearer = format!("Authorization: Bearer {alice}");
+        let (status, _) = raw_request(
+            &addr,
+            post(&addr, "/csrf/submit", &[&cookie, &header, &bearer], ""),
+        )
+        .await?;
+        assert_eq!(status, 403);
+
+        handle.abort();
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_coexists_with_session_cookies() -> anyhow::Result<()> {
+        potato::SessionCache::set_jwt_secret(b"csrf-test-secret").await;
+        let jwt = potato::SessionCache::generate_token(7, Duration::from_secs(60)).await?;
+        let (addr, handle) = start(CsrfConfig::new()).await;
+
+        // 处理函数设置的 Cookie 与令牌 Cookie 分别下发
+        let (status, res) = raw_request(
+            &addr,
+            format!(
+                "GET /csrf/login HTTP/1.1\r\nHost: {addr}\r\nAuthorization: Bearer {jwt}\r\nConnection: close\r\n\r\n"
+            ),
+        )
+        .await?;
+        assert_eq!(status, 200);
+        assert_eq!(set_cookie_value(&res, "sid").as_deref(), Some("abc"));
+        assert!(set_cookie_value(&res, "csrf_token").is_some());
+
+        // 客户端保留两个 Cookie
+        let res = potato::get(&format!("http://{addr}/csrf/form"), vec![]).await?;
+        assert!(res
+            .get_header("Set-Cookie")
+            .unwrap()
+            .starts_with("csrf_token="));
+        let res = potato::get(
+            &format!("http://{addr}/csrf/login"),
+            vec![potato::Headers::Authorization(format!("Bearer {jwt}"))],
+        )
+        .await?;
+        let cookies: Vec<_> = res
+            .header_lines()
+            .filter(|(k, _)| *k == "Set-Cookie")
+            .map(|(_, v)| v.split('=').next().unwrap_or_default())
+            .collect();
+        assert!(cookies.contains(&"sid"));
+        assert!(cookies.contains(&"csrf_token"));
+
+        handle.abort();
+        Ok(())
+    }
+}
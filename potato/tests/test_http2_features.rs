@@ -125,6 +125,11 @@ mod http2_tests {
         res
     }
 
+    #[potato::http_get("/h2c_version")]
+    async fn h2c_version(req: &mut potato::HttpRequest) -> potato::HttpResponse {
+        potato::HttpResponse::text(format!("v{}", req.version))
+    }
+
     #[potato::http_get("/http2_trailers")]
     async fn http2_trailers(_: &mut potato::HttpRequest) -> potato::HttpResponse {
         let mut res = potato::HttpResponse::text("with-trailer");
@@ -293,4 +298,343 @@ mod http2_tests {
         server_handle.abort();
         Ok(())
     }
+
+    #[tokio::test]
+    async fn test_http2_config_settings_are_advertised() -> anyhow::Result<()> {
+        let port = get_test_port();
+        let addr = format!("127.0.0.1:{port}");
+        let (cert_file, key_file, cert) = create_test_cert_files()?;
+
+        let mut server = potato::HttpServer::new(&addr);
+        server.set_http2_config(potato::Http2Config {
+            max_concurrent_streams: 7,
+            ..Default::default()
+        });
+        let server_handle = tokio::spawn(async move {
+            let _ = server.serve_http2(&cert_file, &key_file).await;
+        });
+        sleep(Duration::from_millis(350)).await;
+
+        let tls_stream = connect_tls_with_alpn(&addr, &cert, vec![b"h2".to_vec()]).await?;
+        let (mut sender, mut connection) = client::handshake(tls_stream).await?;
+        let request = http::Request::builder()
+            .method("GET")
+            .uri("https://localhost/http2_native")
+            .body(())?;
+        let (response_future, _) = sender.send_request(request, true)?;
+        let response = tokio::select! {
+            response = response_future => response?,
+            _ = &mut connection => anyhow::bail!("connection closed unexpectedly"),
+        };
+        assert_eq!(response.status(), http::StatusCode::OK);
+        assert_eq!(connection.max_concurrent_send_streams(), 7);
+
+        server_handle.abort();
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_http2_rapid_reset_closes_connection() -> anyhow::Result<()> {
+        let port = get_test_port();
+        let addr = format!("127.0.0.1:{port}");
+        let (cert_file, key_file, cert) = create_test_cert_files()?;
+
+        let mut server = potato::HttpServer::new(&addr);
+        server.set_http2_config(potato::Http2Config {
+            max_resets_per_window: 5,
+            // 关闭 h2 协议栈自身的限制，确认由服务端的重置计数触发
+            max_pending_accept_reset_streams: 10_000,
+            ..Default::default()
+        });
+        let server_handle = tokio::spawn(async move {
+            let _ = server.serve_http2(&cert_file, &key_file).await;
+        });
+        sleep(Duration::from_millis(350)).await;
+
+        let tls_stream = connect_tls_with_alpn(&addr, &cert, vec![b"h2".to_vec()]).await?;
+        let (mut sender, connection) = client::handshake(tls_stream).await?;
+        let conn_handle = tokio::spawn(connection);
+
+        for _ in 0..50 {
+            let request = http::Request::builder()
+                .method("POST")
+                .uri("https://localhost/http2_native")
+                .body(())?;
+            let Ok((_, mut send_stream)) = sender.send_request(request, false) else {
+                break;
+            };
+            send_stream.send_reset(h2::Reason::CANCEL);
+            sleep(Duration::from_millis(10)).await;
+        }
+
+        let result = tokio::time::timeout(Duration::from_secs(5), conn_handle).await??;
+        let err = result.expect_err("connection should be closed by GOAWAY");
+        assert_eq!(err.reason(), Some(h2::Reason::ENHANCE_YOUR_CALM));
+
+        server_handle.abort();
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_http2_graceful_shutdown_and_idle_timeout() -> anyhow::Result<()> {
+        let port = get_test_port();
+        let addr = format!("127.0.0.1:{port}");
+        let (cert_file, key_file, cert) = create_test_cert_files()?;
+
+        let mut server = potato::HttpServer::new(&addr);
+        server.set_http2_config(potato::Http2Config {
+            idle_timeout: Some(Duration::from_millis(300)),
+            keepalive_interval: Some(Duration::from_millis(100)),
+            ..Default::default()
+        });
+        let shutdown = server.shutdown_signal()?;
+        let server_handle = tokio::spawn(async move {
+            let _ = server.serve_http2(&cert_file, &key_file).await;
+        });
+        sleep(Duration::from_millis(350)).await;
+
+        // 空闲超时：无活动流后服务端发送 GOAWAY 并关闭连接
+        let tls_stream = connect_tls_with_alpn(&addr, &cert, vec![b"h2".to_vec()]).await?;
+        let (mut sender, connection) = client::handshake(tls_stream).await?;
+        let conn_handle = tokio::spawn(connection);
+        let request = http::Request::builder()
+            .method("GET")
+            .uri("https://localhost/http2_native")
+            .body(())?;
+        let (response_future, _) = sender.send_request(request, true)?;
+        assert_eq!(response_future.await?.status(), http::StatusCode::OK);
+        let result = tokio::time::timeout(Duration::from_secs(3), conn_handle).await??;
+        assert!(result.is_ok());
+
+        // 关闭信号：已建立的连接收到 GOAWAY 后关闭
+        let tls_stream = connect_tls_with_alpn(&addr, &cert, vec![b"h2".to_vec()]).await?;
+        let (_sender, connection) = client::handshake(tls_stream).await?;
+        let conn_handle = tokio::spawn(connection);
+        sleep(Duration::from_millis(50)).await;
+        _ = shutdown.send(());
+        let result = tokio::time::timeout(Duration::from_secs(3), conn_handle).await??;
+        assert!(result.is_ok());
+
+        server_handle.await?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_h2c_prior_knowledge_and_http11_share_listener() -> anyhow::Result<()> {
+        let port = get_test_port();
+        let addr = format!("127.0.0.1:{port}");
+
+        let mut server = potato::HttpServer::new(&addr);
+        server.set_http2_config(potato::Http2Config {
+            h2c: true,
+            ..Default::default()
+        });
+        let server_handle = tokio::spawn(async move {
+            let _ = server.serve_http().await;
+        });
+        sleep(Duration::from_millis(350)).await;
+
+        let url = format!("http://127.0.0.1:{port}/h2c_version");
+        let mut session = potato::client::http2::H2Session::new();
+        let mut res = session.get(&url, vec![]).await?;
+        assert_eq!(res.http_code, 200);
+        assert_eq!(res.body.data().await.to_vec(), b"v20".to_vec());
+        // 复用同一条 h2c 连接
+        let mut res = session.get(&url, vec![]).await?;
+        assert_eq!(res.body.data().await.to_vec(), b"v20".to_vec());
+
+        let mut res = potato::get!(url.as_str()).await?;
+        assert_eq!(res.body.data().await.to_vec(), b"v11".to_vec());
+
+        server_handle.abort();
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_h2c_upgrade_serves_request_on_stream_1() -> anyhow::Result<()> {
+        use tokio::io::AsyncReadExt;
+
+        let port = get_test_port();
+        let addr = format!("127.0.0.1:{port}");
+
+        let mut server = potato::HttpServer::new(&addr);
+        server.set_http2_config(potato::Http2Config {
+            h2c: true,
+            ..Default::default()
+        });
+        let server_handle = tokio::spawn(async move {
+            let _ = server.serve_http().await;
+        });
+        sleep(Duration::from_millis(350)).await;
+
+        let mut stream = tokio::net::TcpStream::connect(&addr).await?;
+        stream
+            .write_all(
+                b"GET /h2c_version HTTP/1.1\r\nHost: 127.0.0.1\r\n\
+                  Connection: Upgrade, HTTP2-Settings\r\nUpgrade: h2c\r\n\
+                  HTTP2-Settings: AAMAAABkAAQAAP__\r\n\r\n",
+            )
+            .await?;
+
+        let mut head = Vec::new();
+        while !head.ends_with(b"\r\n\r\n") {
+            head.push(stream.read_u8().await?);
+        }
+        let head = String::from_utf8_lossy(&head).to_string();
+        assert!(head.starts_with("HTTP/1.1 101"));
+        assert!(head.to_ascii_lowercase().contains("upgrade: h2c"));
+
+        // 连接前言 + 空 SETTINGS 帧
+        stream
+            .write_all(b"PRI * HTTP/2.0\r\n\r\nSM\r\n\r\n\x00\x00\x00\x04\x00\x00\x00\x00\x00")
+            .await?;
+
+        let mut body = Vec::new();
+        let read_frames = async {
+            loop {
+                let mut frame_head = [0u8; 9];
+                stream.read_exact(&mut frame_head).await?;
+                let len = u32::from_be_bytes([0, frame_head[0], frame_head[1], frame_head[2]]);
+                let stream_id = u32::from_be_bytes([
+                    frame_head[5],
+                    frame_head[6],
+                    frame_head[7],
+                    frame_head[8],
+                ]) & 0x7fff_ffff;
+                let mut payload = vec![0u8; len as usize];
+                stream.read_exact(&mut payload).await?;
+                // DATA 帧
+                if frame_head[3] == 0x0 && stream_id == 1 {
+                    body.extend_from_slice(&payload);
+                    if frame_head[4] & 0x1 != 0 {
+                        break;
+                    }
+                }
+            }
+            anyhow::Ok(())
+        };
+        tokio::time::timeout(Duration::from_secs(5), read_frames).await??;
+        assert_eq!(body, b"v20".to_vec());
+
+        server_handle.abort();
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_h2c_is_off_by_default() -> anyhow::Result<()> {
+        use tokio::io::AsyncReadExt;
+
+        let port = get_test_port();
+        let addr = format!("127.0.0.1:{port}");
+
+        let mut server = potato::HttpServer::new(&addr);
+        let server_handle = tokio::spawn(async move {
+            let _ = server.serve_http().await;
+        });
+        sleep(Duration::from_millis(350)).await;
+
+        let mut stream = tokio::net::TcpStream::connect(&addr).await?;
+        stream
+            .write_all(
+                b"GET /h2c_version HTTP/1.1\r\nHost: 127.0.0.1\r\n\
+                  Connection: Upgrade, HTTP2-Settings, close\r\nUpgrade: h2c\r\n\
+                  HTTP2-Settings: AAMAAABkAAQAAP__\r\n\r\n",
+            )
+            .await?;
+        let mut res = Vec::new();
+        tokio::time::timeout(Duration::from_secs(5), stream.read_to_end(&mut res)).await??;
+        let res = String::from_utf8_lossy(&res).to_string();
+        assert!(res.starts_with("HTTP/1.1 200"));
+        assert!(res.ends_with("v11"));
+
+        // 先验知识的 HTTP/2 连接同样不被接受
+        let url = format!("http://127.0.0.1:{port}/h2c_version");
+        let mut session = potato::client::http2::H2Session::new();
+        assert!(session.get(&url, vec![]).await.is_err());
+
+        server_handle.abort();
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_h2c_upgrade_applies_http2_settings_header() -> anyhow::Result<()> {
+        use tokio::io::AsyncReadExt;
+
+        let port = get_test_port();
+        let addr = format!("127.0.0.1:{port}");
+
+        let mut server = potato::HttpServer::new(&addr);
+        server.set_http2_config(potato::Http2Config {
+            h2c: true,
+            ..Default::default()
+        });
+        let server_handle = tokio::spawn(async move {
+            let _ = server.serve_http().await;
+        });
+        sleep(Duration::from_millis(350)).await;
+
+        // HTTP2-Settings 将 SETTINGS_INITIAL_WINDOW_SIZE 设为 0
+        let mut stream = tokio::net::TcpStream::connect(&addr).await?;
+        stream
+            .write_all(
+                b"GET /h2c_version HTTP/1.1\r\nHost: 127.0.0.1\r\n\
+                  Connection: Upgrade, HTTP2-Settings\r\nUpgrade: h2c\r\n\
+                  HTTP2-Settings: AAQAAAAA\r\n\r\n",
+            )
+            .await?;
+        let mut head = Vec::new();
+        while !head.ends_with(b"\r\n\r\n") {
+            head.push(stream.read_u8().await?);
+        }
+        assert!(head.starts_with(b"HTTP/1.1 101"));
+        stream
+            .write_all(b"PRI * HTTP/2.0\r\n\r\nSM\r\n\r\n\x00\x00\x00\x04\x00\x00\x00\x00\x00")
+            .await?;
+
+        let mut body = Vec::new();
+        let mut window_opened = false;
+        let read_frames = async {
+            loop {
+                let mut frame_head = [0u8; 9];
+                let read = tokio::time::timeout(
+                    Duration::from_millis(500),
+                    stream.read_exact(&mut frame_head),
+                )
+                .await;
+                let Ok(read) = read else {
+                    // 窗口为 0 时服务端不能发送 DATA，打开流 1 的窗口后才会收到响应体
+                    assert!(body.is_empty());
+                    assert!(!window_opened);
+                    window_opened = true;
+                    stream
+                        .write_all(b"\x00\x00\x04\x08\x00\x00\x00\x00\x01\x00\x00\x00\x64")
+                        .await?;
+                    continue;
+                };
+                read?;
+                let len = u32::from_be_bytes([0, frame_head[0], frame_head[1], frame_head[2]]);
+                let stream_id = u32::from_be_bytes([
+                    frame_head[5],
+                    frame_head[6],
+                    frame_head[7],
+                    frame_head[8],
+                ]) & 0x7fff_ffff;
+                let mut payload = vec![0u8; len as usize];
+                stream.read_exact(&mut payload).await?;
+                if frame_head[3] == 0x0 && stream_id == 1 {
+                    assert!(window_opened);
+                    body.extend_from_slice(&payload);
+                    if frame_head[4] & 0x1 != 0 {
+                        break;
+                    }
+                }
+            }
+            anyhow::Ok(())
+        };
+        tokio::time::timeout(Duration::from_secs(5), read_frames).await??;
+        assert_eq!(body, b"v20".to_vec());
+
+        server_handle.abort();
+        Ok(())
+    }
 }
@@ -0,0 +1,316 @@
+#![cfg(feature = "auth")]
+
+/// Basic、Digest、API Key 认证中间件与 auth_scheme 标注测试
+use base64::Engine;
+use md5::{Digest, Md5};
+use potato::{
+    ApiKeyAuth, BasicAuth, DigestAuth, HttpRequest, HttpResponse, HttpServer, ServerConfig,
+};
+use std::sync::atomic::{AtomicU16, Ordering};
+use std::time::Duration;
+use tokio::time::sleep;
+
+static PORT_COUNTER: AtomicU16 = AtomicU16::new(42800);
+
+fn get_test_port() -> u16 {
+    PORT_COUNTER.fetch_add(1, Ordering::Relaxed)
+}
+
+const HTPASSWD: &str = "
+# htpasswd -B / argon2 / {SHA}
+alice:$2y$04$RXbL7lXMjw6RgpgJncGY2.r170nlXGEujD0W2yp8oM8JHr.2xT902
+bob:$argon2id$v=19$m=1024,t=1,p=1$c29tZXNhbHRzb21lc2FsdA$KQJ2wt6rwp1vsKXw9UY5VhLOVQYX8wErRupcUg/w1Z4
+carol:{SHA}hsAaMBat8aKiQgxhKhAqvbXOPbw=
+";
+
+#[potato::http_get("/auth/keys")]
+#[potato::auth_scheme("test-keys", "test-ops")]
+async fn keys_or_ops(req: &mut HttpRequest) -> HttpResponse {
+    let user = req.auth_user().unwrap();
+    HttpResponse::text(format!("{}:{}", user.scheme, user.name))
+}
+
+#[potato::http_get("/auth/missing")]
+#[potato::auth_scheme("test-unregistered")]
+async fn missing_scheme() -> HttpResponse {
+    HttpResponse::text("unreachable")
+}
+
+fn whoami(req: &mut HttpRequest) -> Option<HttpResponse> {
+    let user = req.auth_user()?;
+    Some(HttpResponse::text(format!("{}:{}", user.scheme, user.name)))
+}
+
+fn basic(user: &str, password: &str) -> String {
+    let cred = base64::engine::general_purpose::STANDARD.encode(format!("{user}:{password}"));
+    format!("Basic {cred}")
+}
+
+#[tokio::test]
+async fn test_basic_auth_htpasswd() -> anyhow::Result<()> {
+    assert!(BasicAuth::new("ops")
+        .with_htpasswd("dave:$apr1$abc$def")
+        .is_err());
+    let auth = BasicAuth::new("ops").with_htpasswd(HTPASSWD)?;
+
+    let server_addr = format!("127.0.0.1:{}", get_test_port());
+    let mut server = HttpServer::new(&server_addr);
+    server.configure(move |ctx| {
+        ctx.use_custom_sync(|req| match &req.url_path[..] {
+            "/public" => Some(HttpResponse::text("public")),
+            _ => None,
+        });
+        ctx.use_auth(auth.clone());
+        ctx.use_custom_sync(whoami);
+    });
+    let server_handle = tokio::spawn(async move {
+        let _ = server.serve_http().await;
+    });
+    sleep(Duration::from_millis(300)).await;
+
+    // use_auth 之前的路由不受影响
+    let mut res = potato::get(&format!("http://{server_addr}/public"), vec![]).await?;
+    assert_eq!(res.body.data().await, b"public");
+
+    let url = format!("http://{server_addr}/me");
+    for (user, password) in [
+        ("alice", "alice-pw"),
+        ("bob", "bob-pw"),
+        ("carol", "carol-pw"),
+    ] {
+        let mut res = potato::get!(&url, Authorization = basic(user, password)).await?;
+        assert_eq!(res.http_code, 200);
+        assert_eq!(res.body.data().await, format!("Basic:{user}").as_bytes());
+    }
+    let res = potato::get(&url, vec![]).await?;
+    assert_eq!(res.http_code, 401);
+    for auth in [
+        basic("alice", "bob-pw"),
+        basic("mallory", "x"),
+        "Basic !!!".to_string(),
+    ] {
+        let res = potato::get!(&url, Authorization = auth).await?;
+        assert_eq!(res.http_code, 401);
+        assert_eq!(
+            res.get_header("WWW-Authenticate"),
+            Some("Basic realm=\"ops\", charset=\"UTF-8\"")
+        );
+    }
+
+    server_handle.abort();
+    Ok(())
+}
+
+/// 按 RFC 7616 计算客户端的 Digest 响应
+fn digest(user: &str, password: &str, challenge: &str, uri: &str) -> String {
+    let hex = |s: String| format!("{:x}", Md5::digest(s.as_bytes()));
+    let param = |key: &str| {
+        let start = challenge.find(&format!("{key}=\"")).unwrap() + key.len() + 2;
+        challenge[start..].split('"').next().unwrap().to_string()
+    };
+    let (realm, nonce) = (param("realm"), param("nonce"));
+    let ha1 = hex(format!("{user}:{realm}:{password}"));
+    let ha2 = hex(format!("GET:{uri}"));
+    let response = hex(format!("{ha1}:{nonce}:00000001:abc123:auth:{ha2}"));
+    format!(
+        "Digest username=\"{user}\", realm=\"{realm}\", nonce=\"{nonce}\", uri=\"{uri}\", \
+         qop=auth, nc=00000001, cnonce=\"abc123\", response=\"{response}\", algorithm=MD5"
+    )
+}
+
+#[tokio::test]
+async fn test_digest_auth() -> anyhow::Result<()> {
+    let server_addr = format!("127.0.0.1:{}", get_test_port());
+    let mut server = HttpServer::new(&server_addr);
+    server.configure(|ctx| {
+        ctx.use_auth(DigestAuth::new("devices").with_user("cam", "cam-pw"));
+        ctx.use_custom_sync(whoami);
+    });
+    let server_handle = tokio::spawn(async move {
+        let _ = server.serve_http().await;
+    });
+    sleep(Duration::from_millis(300)).await;
+
+    let url = format!("http://{server_addr}/me?id=1");
+    let res = potato::get(&url, vec![]).await?;
+    assert_eq!(res.http_code, 401);
+    let challenge = res.get_header("WWW-Authenticate").unwrap_or_default();
+    assert!(challenge.starts_with("Digest realm=\"devices\", qop=\"auth\", algorithm=MD5"));
+
+    let auth = digest("cam", "cam-pw", challenge, "/me?id=1");
+    let mut res = potato::get!(&url, Authorization = auth.clone()).await?;
+    assert_eq!(res.body.data().await, b"Digest:cam");
+    // 口令错误或 uri 与请求不符
+    let res = potato::get!(
+        &format!("http://{server_addr}/me"),
+        Authorization = digest("cam", "x", challenge, "/me")
+    )
+    .await?;
+    assert_eq!(res.http_code, 401);
+    assert!(!res
+        .get_header("WWW-Authenticate")
+        .unwrap()
+        .contains("stale"));
+    let res = potato::get!(&format!("http://{server_addr}/other"), Authorization = auth).await?;
+    assert_eq!(res.http_code, 401);
+    server_handle.abort();
+
+    // nonce 过期时提示 stale，客户端使用新 nonce 重试
+    let server_addr = format!("127.0.0.1:{}", get_test_port());
+    let mut server = HttpServer::new(&server_addr);
+    server.configure(|ctx| {
+        ctx.use_auth(
+            DigestAuth::new("devices")
+                .with_user("cam", "cam-pw")
+                .with_nonce_ttl(Duration::ZERO),
+        );
+        ctx.use_custom_sync(whoami);
+    });
+    let server_handle = tokio::spawn(async move {
+        let _ = server.serve_http().await;
+    });
+    sleep(Duration::from_millis(300)).await;
+
+    let url = format!("http://{server_addr}/me");
+    let res = potato::get(&url, vec![]).await?;
+    let challenge = res.get_header("WWW-Authenticate").unwrap_or_default();
+    sleep(Duration::from_millis(1100)).await;
+    let res = potato::get!(
+        &url,
+        Authorization = digest("cam", "cam-pw", challenge, "/me")
+    )
+    .await?;
+    assert_eq!(res.http_code, 401);
+    assert!(res
+        .get_header("WWW-Authenticate")
+        .unwrap()
+        .ends_with(", stale=true"));
+
+    server_handle.abort();
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_api_key_and_auth_scheme_annotation() -> anyhow::Result<()> {
+    ServerConfig::set_auth_scheme(
+        "test-keys",
+        ApiKeyAuth::new("X-API-Key")
+            .with_key("ci", "plain-key")
+            .with_key_hash("deploy", ApiKeyAuth::hash_key("hashed-key")),
+    );
+    ServerConfig::set_auth_scheme("test-ops", BasicAuth::new("ops").with_htpasswd(HTPASSWD)?);
+    let server_addr = format!("127.0.0.1:{}", get_test_port());
+    let mut server = HttpServer::new(&server_addr);
+    server.configure(|ctx| {
+        #[cfg(feature = "openapi")]
+        ctx.use_openapi("/doc/");
+        ctx.use_handlers();
+    });
+    let server_handle = tokio::spawn(async move {
+        let _ = server.serve_http().await;
+    });
+    sleep(Duration::from_millis(300)).await;
+
+    let url = format!("http://{server_addr}/auth/keys");
+    for (key, name) in [("plain-key", "ci"), ("hashed-key", "deploy")] {
+        let mut res = potato::get!(&url, "X-API-Key" = key).await?;
+        assert_eq!(res.body.data().await, format!("ApiKey:{name}").as_bytes());
+    }
+    let mut res = potato::get!(&url, Authorization = basic("bob", "bob-pw")).await?;
+    assert_eq!(res.http_code, 200);
+    assert_eq!(res.body.data().await, b"Basic:bob");
+
+    // 两种方式都未满足时列出全部质询
+    let res = potato::get!(&url, "X-API-Key" = "wrong").await?;
+    assert_eq!(res.http_code, 401);
+    let challenges: Vec<_> = res
+        .header_lines()
+        .filter(|(k, _)| k.eq_ignore_ascii_case("WWW-Authenticate"))
+        .map(|(_, v)| v)
+        .collect();
+    assert_eq!(
+        challenges,
+        [
+            "ApiKey header=\"X-API-Key\"",
+            "Basic realm=\"ops\", charset=\"UTF-8\""
+        ]
+    );
+
+    // 未注册的认证方式
+    let res = potato::get(&format!("http://{server_addr}/auth/missing"), vec![]).await?;
+    assert_eq!(res.http_code, 500);
+
+    #[cfg(feature = "openapi")]
+    {
+        let mut res = potato::get(&format!("http://{server_addr}/doc/index.json"), vec![]).await?;
+        let doc: serde_json::Value = serde_json::from_slice(res.body.data().await)?;
+        let op = &doc["paths"]["/auth/keys"]["get"];
+        assert_eq!(
+            op["security"],
+            serde_json::json!([{ "test-keys": [] }, { "test-ops": [] }])
+        );
+        assert!(op["responses"].get("401").is_some());
+        let schemes = &doc["components"]["securitySchemes"];
+        assert_eq!(
+            schemes["test-keys"],
+            serde_json::json!({ "type": "apiKey", "in": "header", "name": "X-API-Key" })
+        );
+        assert_eq!(
+            schemes["test-ops"],
+            serde_json::json!({ "type": "http", "scheme": "basic" })
+        );
+    }
+
+    server_handle.abort();
+    Ok(())
+}
+
+#[cfg(feature = "openapi")]
+#[tokio::test]
+async fn test_global_auth_in_openapi() -> anyhow::Result<()> {
+    let server_addr = format!("127.0.0.1:{}", get_test_port());
+    let mut server = HttpServer::new(&server_addr);
+    server.configure(|ctx| {
+        // 文档本身不要求认证，之后添加的全局认证仍写入文档
+        ctx.use_openapi("/doc/");
+        ctx.use_auth_any(vec![
+            BasicAuth::new("ops").into(),
+            ApiKeyAuth::new("X-API-Key").into(),
+        ]);
+        ctx.use_handlers();
+    });
+    let server_handle = tokio::spawn(async move {
+        let _ = server.serve_http().await;
+    });
+    sleep(Duration::from_millis(300)).await;
+
+    let mut res = potato::get(&format!("http://{server_addr}/doc/index.json"), vec![]).await?;
+    assert_eq!(res.http_code, 200);
+    let doc: serde_json::Value = serde_json::from_slice(res.body.data().await)?;
+    assert_eq!(
+        doc["security"],
+        serde_json::json!([{ "basicAuth": [] }, { "apiKeyAuth_X-API-Key": [] }])
+    );
+    let schemes = &doc["components"]["securitySchemes"];
+    assert_eq!(
+        schemes["basicAuth"],
+        serde_json::json!({ "type": "http", "scheme": "basic" })
+    );
+    assert_eq!(
+        schemes["apiKeyAuth_X-API-Key"],
+        serde_json::json!({ "type": "apiKey", "in": "header", "name": "X-API-Key" })
+    );
+    // 路由自身的认证要求与全局认证组合
+    let op = &doc["paths"]["/auth/keys"]["get"];
+    assert_eq!(op["security"].as_array().map(Vec::len), Some(4));
+    assert_eq!(
+        op["security"][0],
+        serde_json::json!({ "basicAuth": [], "test-keys": [] })
+    );
+    assert!(doc["paths"]["/auth/missing"]["get"]["responses"]
+        .get("401")
+        .is_some());
+
+    server_handle.abort();
+    Ok(())
+}
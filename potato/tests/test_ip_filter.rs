@@ -0,0 +1,257 @@
+/// IP 过滤中间件与 ip_filter 标注测试
+use potato::{HttpResponse, HttpServer, IpFilter};
+use std::sync::atomic::{AtomicU16, Ordering};
+use std::time::Duration;
+use tokio::time::sleep;
+
+static PORT_COUNTER: AtomicU16 = AtomicU16::new(43100);
+
+fn get_test_port() -> u16 {
+    PORT_COUNTER.fetch_add(1, Ordering::Relaxed)
+}
+
+#[potato::http_get("/ipf/public")]
+#[potato::ip_filter(allow = ["0.0.0.0/0", "::/0"])]
+async fn public_route() -> HttpResponse {
+    HttpResponse::text("public")
+}
+
+#[potato::http_get("/ipf/admin")]
+#[potato::ip_filter(allow = ["10.0.0.0/8"], deny = ["10.9.9.9"])]
+async fn admin_route() -> HttpResponse {
+    HttpResponse::text("admin")
+}
+
+#[potato::http_get("/ipf/plain")]
+async fn plain_route() -> HttpResponse {
+    HttpResponse::text("plain")
+}
+
+#[tokio::test]
+async fn test_ip_filter_allow_and_deny() -> anyhow::Result<()> {
+    let filter = IpFilter::new()
+        .with_allow(["10.0.0.0/8", "2001:db8::/32"])?
+        .with_deny(["10.0.0.9", "2001:db8:bad::/48"])?;
+    let server_addr = format!("127.0.0.1:{}", get_test_port());
+    let mut server = HttpServer::new(&server_addr);
+    server.configure(move |ctx| {
+        // 测试连接均来自本机，通过可信代理转发的地址模拟不同客户端
+        ctx.use_trusted_proxies(["127.0.0.1"]).unwrap();
+        ctx.use_ip_filter(filter.clone());
+        ctx.use_custom_sync(|_| Some(HttpResponse::text("ok")));
+    });
+    let server_handle = tokio::spawn(async move {
+        let _ = server.serve_http().await;
+    });
+    sleep(Duration::from_millis(300)).await;
+
+    let url = format!("http://{server_addr}/");
+    for client in ["10.1.2.3", "2001:db8::1", "::ffff:10.1.2.3"] {
+        let res = potato::get!(&url, "X-Forwarded-For" = client).await?;
+        assert_eq!(res.http_code, 200);
+    }
+    // 拒绝列表优先于允许列表
+    for client in ["10.0.0.9", "2001:db8:bad::1"] {
+        let res = potato::get!(&url, "X-Forwarded-For" = client).await?;
+        assert_eq!(res.http_code, 403);
+    }
+    // 允许列表非空时未命中即拒绝
+    let res = potato::get!(&url, "X-Forwarded-For" = "192.168.1.1").await?;
+    assert_eq!(res.http_code, 403);
+    let res = potato::get(&url, vec![]).await?;
+    assert_eq!(res.http_code, 403);
+    server_handle.abort();
+
+    // 仅有拒绝列表时放行其余地址
+    let server_addr = format!("127.0.0.1:{}", get_test_port());
+    let mut server = HttpServer::new(&server_addr);
+    server.configure(|ctx| {
+        ctx.use_trusted_proxies(["127.0.0.1"]).unwrap();
+        ctx.use_ip_filter(IpFilter::new().with_deny(["203.0.113.0/24"]).unwrap());
+        ctx.use_custom_sync(|_| Some(HttpResponse::text("ok")));
+    });
+    let server_handle = tokio::spawn(async move {
+        let _ = server.serve_http().await;
+    });
+    sleep(Duration::from_millis(300)).await;
+
+    let url = format!("http://{server_addr}/");
+    let res = potato::get!(&url, "X-Forwarded-For" = "203.0.113.7").await?;
+    assert_eq!(res.http_code, 403);
+    let res = potato::get!(&url, "X-Forwarded-For" = "198.51.100.1").await?;
+    assert_eq!(res.http_code, 200);
+    let res = potato::get(&url, vec![]).await?;
+    assert_eq!(res.http_code, 200);
+
+    assert!(IpFilter::new().with_allow(["10.0.0.0/33"]).is_err());
+    server_handle.abort();
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_ip_filter_reload() -> anyhow::Result<()> {
+    let filter = IpFilter::new().with_allow(["10.0.0.0/8"])?;
+    let handle = filter.clone();
+    let server_addr = format!("127.0.0.1:{}", get_test_port());
+    let mut server = HttpServer::new(&server_addr);
+    server.configure(move |ctx| {
+        ctx.use_trusted_proxies(["127.0.0.1"]).unwrap();
+        ctx.use_ip_filter(filter.clone());
+        ctx.use_custom_sync(|_| Some(HttpResponse::text("ok")));
+    });
+    let server_handle = tokio::spawn(async move {
+        let _ = server.serve_http().await;
+    });
+    sleep(Duration::from_millis(300)).await;
+
+    let url = format!("http://{server_addr}/");
+    let res = potato::get!(&url, "X-Forwarded-For" = "172.16.0.1").await?;
+    assert_eq!(res.http_code, 403);
+    handle.set_allow(["172.16.0.0/12"])?;
+    let res = potato::get!(&url, "X-Forwarded-For" = "172.16.0.1").await?;
+    assert_eq!(res.http_code, 200);
+    let res = potato::get!(&url, "X-Forwarded-For" = "10.0.0.1").await?;
+    assert_eq!(res.http_code, 403);
+    // 解析失败时保留原规则
+    assert!(handle.set_allow(["not-an-ip"]).is_err());
+    let res = potato::get!(&url, "X-Forwarded-For" = "172.16.0.1").await?;
+    assert_eq!(res.http_code, 200);
+    server_handle.abort();
+
+    let path = std::env::temp_dir().join(format!("potato_ip_filter_{}.txt", std::process::id()));
+    std::fs::write(
+        &path,
+        "# 办公网\nallow 10.0.0.0/8\ndeny 10.0.0.9 # 离职设备\n::1\n",
+    )?;
+    let filter = IpFilter::from_file(&path)?;
+    assert!(filter.is_allowed(&"10.1.1.1".parse()?));
+    assert!(filter.is_allowed(&"::1".parse()?));
+    assert!(!filter.is_allowed(&"10.0.0.9".parse()?));
+    assert!(!filter.is_allowed(&"192.168.1.1".parse()?));
+    std::fs::write(&path, "deny 10.0.0.0/8\n")?;
+    filter.reload()?;
+    assert!(!filter.is_allowed(&"10.1.1.1".parse()?));
+    assert!(filter.is_allowed(&"192.168.1.1".parse()?));
+    std::fs::write(&path, "block 10.0.0.0/8\n")?;
+    assert!(filter.reload().is_err());
+    assert!(filter.is_allowed(&"192.168.1.1".parse()?));
+    std::fs::remove_file(&path)?;
+    assert!(IpFilter::new().reload().is_err());
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_ip_filter_annotation() -> anyhow::Result<()> {
+    let server_addr = format!("127.0.0.1:{}", get_test_port());
+    let mut server = HttpServer::new(&server_addr);
+    server.configure(|ctx| {
+        ctx.use_trusted_proxies(["127.0.0.1"]).unwrap();
+        ctx.use_ip_filter(IpFilter::new().with_allow(["192.168.0.0/16"]).unwrap());
+        ctx.use_handlers();
+        ctx.use_custom_sync(|_| Some(HttpResponse::text("ok")));
+    });
+    let server_handle = tokio::spawn(async move {
+        let _ = server.serve_http().await;
+    });
+    sleep(Duration::from_millis(300)).await;
+
+    // 标注的路由使用自身规则，不受 use_ip_filter 影响
+    let url = format!("http://{server_addr}/ipf/public");
+    let res = potato::get!(&url, "X-Forwarded-For" = "203.0.113.7").await?;
+    assert_eq!(res.http_code, 200);
+    let url = format!("http://{server_addr}/ipf/admin");
+    let res = potato::get!(&url, "X-Forwarded-For" = "10.1.1.1").await?;
+    assert_eq!(res.http_code, 200);
+    let res = potato::get!(&url, "X-Forwarded-For" = "192.168.1.1").await?;
+    assert_eq!(res.http_code, 403);
+    let res = potato::get!(&url, "X-Forwarded-For" = "10.9.9.9").await?;
+    assert_eq!(res.http_code, 403);
+    let url = format!("http://{server_addr}/ipf/plain");
+    let res = potato::get!(&url, "X-Forwarded-For" = "10.1.1.1").await?;
+    assert_eq!(res.http_code, 403);
+    let res = potato::get!(&url, "X-Forwarded-For" = "192.168.1.1").await?;
+    assert_eq!(res.http_code, 200);
+    server_handle.abort();
+
+    // 同一路径由其他路由响应时，标注的规则不能放行全局规则拒绝的地址，但拒绝时立即生效
+    let server_addr = format!("127.0.0.1:{}", get_test_port());
+    let mut server = HttpServer::new(&server_addr);
+    server.configure(|ctx| {
+        ctx.use_trusted_proxies(["127.0.0.1"]).unwrap();
+        ctx.use_ip_filter(IpFilter::new().with_allow(["192.168.0.0/16"]).unwrap());
+        ctx.use_custom_sync(|req| {
+            matches!(&req.url_path[..], "/ipf/public" | "/ipf/admin")
+                .then(|| HttpResponse::text("shadowed"))
+        });
+        ctx.use_handlers();
+    });
+    let server_handle = tokio::spawn(async move {
+        let _ = server.serve_http().await;
+    });
+    sleep(Duration::from_millis(300)).await;
+
+    let url = format!("http://{server_addr}/ipf/public");
+    let res = potato::get!(&url, "X-Forwarded-For" = "203.0.113.7").await?;
+    assert_eq!(res.http_code, 403);
+    let mut res = potato::get!(&url, "X-Forwarded-For" = "192.168.1.1").await?;
+    assert_eq!(res.http_code, 200);
+    assert_eq!(res.body.data().await, b"shadowed");
+    let url = format!("http://{server_addr}/ipf/admin");
+    let res = potato::get!(&url, "X-Forwarded-For" = "10.1.1.1").await?;
+    assert_eq!(res.http_code, 403);
+    let res = potato::get!(&url, "X-Forwarded-For" = "192.168.1.1").await?;
+    assert_eq!(res.http_code, 403);
+    server_handle.abort();
+
+    // 未配置 use_ip_filter 时标注同样生效；放在 use_handlers 之后的过滤只作用于之后的路由
+    let server_addr = format!("127.0.0.1:{}", get_test_port());
+    let mut server = HttpServer::new(&server_addr);
+    server.configure(|ctx| {
+        ctx.use_trusted_proxies(["127.0.0.1"]).unwrap();
+        ctx.use_handlers();
+        ctx.use_ip_filter(IpFilter::new().with_allow(["10.0.0.0/8"]).unwrap());
+        ctx.use_custom_sync(|_| Some(HttpResponse::text("ok")));
+    });
+    let server_handle = tokio::spawn(async move {
+        let _ = server.serve_http().await;
+    });
+    sleep(Duration::from_millis(300)).await;
+
+    let res = potato::get(&format!("http://{server_addr}/ipf/admin"), vec![]).await?;
+    assert_eq!(res.http_code, 403);
+    let res = potato::get(&format!("http://{server_addr}/ipf/plain"), vec![]).await?;
+    assert_eq!(res.http_code, 200);
+    let url = format!("http://{server_addr}/other");
+    let res = potato::get(&url, vec![]).await?;
+    assert_eq!(res.http_code, 403);
+    let res = potato::get!(&url, "X-Forwarded-For" = "10.1.1.1").await?;
+    assert_eq!(res.http_code, 200);
+
+    server_handle.abort();
+    Ok(())
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn test_ip_filter_unix_peer() -> anyhow::Result<()> {
+    let dir = std::env::temp_dir().join(format!("potato_ip_filter_test_{}", std::process::id()));
+    std::fs::create_dir_all(&dir)?;
+    let path = dir.join("app.sock");
+    let mut server = HttpServer::new(format!("unix:{}", path.display()));
+    server.configure(|ctx| {
+        ctx.use_ip_filter(IpFilter::new().with_allow(["127.0.0.1"]).unwrap());
+        ctx.use_custom_sync(|_| Some(HttpResponse::text("ok")));
+    });
+    let shutdown = server.shutdown_signal()?;
+    let server_handle = tokio::spawn(async move { server.serve_http().await });
+    sleep(Duration::from_millis(300)).await;
+
+    // Unix 域套接字连接的占位地址 127.0.0.1:0 不匹配允许列表
+    let mut session = potato::Session::new_unix(&path);
+    let res = session.get("http://localhost/", vec![]).await?;
+    assert_eq!(res.http_code, 403);
+
+    _ = shutdown.send(());
+    server_handle.await??;
+    Ok(())
+}
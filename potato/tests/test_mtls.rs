@@ -0,0 +1,264 @@
+/// 双向 TLS（mTLS）测试
+#[cfg(feature = "tls")]
+#[cfg(test)]
+mod mtls_tests {
+    use potato::{ClientAuth, HttpRequest, HttpResponse, HttpServer};
+    use rcgen::{
+        BasicConstraints, CertificateParams, CertificateRevocationListParams, DnType, IsCa, Issuer,
+        KeyIdMethod, KeyPair, KeyUsagePurpose, RevocationReason, RevokedCertParams, SanType,
+        SerialNumber,
+    };
+    use std::path::PathBuf;
+    use std::sync::atomic::{AtomicU16, Ordering};
+    use std::sync::Arc;
+    use std::time::Duration;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::time::sleep;
+    use tokio_rustls::rustls;
+    use tokio_rustls::rustls::pki_types::{CertificateDer, PrivateKeyDer, ServerName};
+
+    static PORT_COUNTER: AtomicU16 = AtomicU16::new(36000);
+
+    fn get_test_port() -> u16 {
+        PORT_COUNTER.fetch_add(1, Ordering::Relaxed)
+    }
+
+    #[potato::http_get("/mtls_whoami")]
+    async fn mtls_whoami(req: &mut HttpRequest) -> HttpResponse {
+        match req.client_cert() {
+            Some(cert) => HttpResponse::text(format!(
+                "cn={} dns={} uri={} serial={}",
+                cert.common_name.clone().unwrap_or_default(),
+                cert.dns_names.join(","),
+                cert.uris.join(","),
+                cert.serial,
+            )),
+            None => HttpResponse::text("anonymous"),
+        }
+    }
+
+    /// 测试用 CA，签发服务端与客户端证书
+    struct TestPki {
+        dir: PathBuf,
+        ca_params: CertificateParams,
+        ca_key: KeyPair,
+        ca_der: CertificateDer<'static>,
+    }
+
+    type ClientIdentity = (Vec<CertificateDer<'static>>, PrivateKeyDer<'static>);
+
+    impl TestPki {
+        fn new() -> anyhow::Result<Self> {
+            let dir = std::env::temp_dir().join(format!(
+                "potato_mtls_test_{}_{}",
+                std::process::id(),
+                get_test_port()
+            ));
+            std::fs::create_dir_all(&dir)?;
+            let mut ca_params = CertificateParams::new(vec![])?;
+            ca_params
+                .distinguished_name
+                .push(DnType::CommonName, "potato test ca");
+            ca_params.is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
+            ca_params.key_usages = vec![
+                KeyUsagePurpose::KeyCertSign,
+                KeyUsagePurpose::CrlSign,
+                KeyUsagePurpose::DigitalSignature,
+            ];
+            let ca_key = KeyPair::generate()?;
+            let ca_cert = ca_params.self_signed(&ca_key)?;
+            std::fs::write(dir.join("ca.pem"), ca_cert.pem())?;
+            Ok(Self {
+                dir,
+                ca_params,
+                ca_key,
+                ca_der: ca_cert.der().clone(),
+            })
+        }
+
+        fn path(&self, name: &str) -> String {
+            self.dir.join(name).to_string_lossy().to_string()
+        }
+
+        fn issue(
+            &self,
+            cn: &str,
+            sans: Vec<SanType>,
+            serial: u64,
+        ) -> anyhow::Result<(rcgen::Certificate, KeyPair)> {
+            let mut params = CertificateParams::default();
+            params.distinguished_name.push(DnType::CommonName, cn);
+            params.subject_alt_names = sans;
+            params.serial_number = Some(SerialNumber::from(serial));
+            let key = KeyPair::generate()?;
+            let issuer = Issuer::from_params(&self.ca_params, &self.ca_key);
+            Ok((params.signed_by(&key, &issuer)?, key))
+        }
+
+        fn write_server_cert(&self) -> anyhow::Result<(String, String)> {
+            let (cert, key) = self.issue(
+                "localhost",
+                vec![SanType::DnsName("localhost".try_into()?)],
+                1,
+            )?;
+            std::fs::write(self.dir.join("server.pem"), cert.pem())?;
+            std::fs::write(self.dir.join("server.key"), key.serialize_pem())?;
+            Ok((self.path("server.pem"), self.path("server.key")))
+        }
+
+        fn client_identity(&self, cn: &str, serial: u64) -> anyhow::Result<ClientIdentity> {
+            let sans = vec![
+                SanType::DnsName(format!("{cn}.internal").try_into()?),
+                SanType::URI(format!("spiffe://example.org/{cn}").try_into()?),
+            ];
+            let (cert, key) = self.issue(cn, sans, serial)?;
+            Ok((
+                vec![cert.der().clone()],
+                PrivateKeyDer::try_from(key.serialize_der()).map_err(anyhow::Error::msg)?,
+            ))
+        }
+
+        fn write_crl(&self, revoked_serial: u64) -> anyhow::Result<String> {
+            let now = time::OffsetDateTime::now_utc();
+            let crl = CertificateRevocationListParams {
+                this_update: now - time::Duration::hours(1),
+                next_update: now + time::Duration::days(1),
+                crl_number: SerialNumber::from(1u64),
+                issuing_distribution_point: None,
+                revoked_certs: vec![RevokedCertParams {
+                    serial_number: SerialNumber::from(revoked_serial),
+                    revocation_time: now - time::Duration::hours(1),
+                    reason_code: Some(RevocationReason::KeyCompromise),
+                    invalidity_date: None,
+                }],
+                key_identifier_method: KeyIdMethod::Sha256,
+            }
+            .signed_by(&Issuer::from_params(&self.ca_params, &self.ca_key))?;
+            std::fs::write(self.dir.join("ca.crl"), crl.pem()?)?;
+            Ok(self.path("ca.crl"))
+        }
+    }
+
+    async fn start_server(
+        pki: &TestPki,
+        client_auth: ClientAuth,
+        http2: bool,
+    ) -> anyhow::Result<(u16, tokio::sync::oneshot::Sender<()>)> {
+        let (cert_file, key_file) = pki.write_server_cert()?;
+        let port = get_test_port();
+        let mut server = HttpServer::new(format!("127.0.0.1:{port}"));
+        server.set_client_auth(client_auth);
+        server.configure(|ctx| ctx.use_handlers());
+        let shutdown = server.shutdown_signal()?;
+        tokio::spawn(async move {
+            #[cfg(feature = "http2")]
+            if http2 {
+                return server.serve_http2(&cert_file, &key_file).await;
+            }
+            _ = http2;
+            server.serve_https(&cert_file, &key_file).await
+        });
+        sleep(Duration::from_millis(200)).await;
+        Ok((port, shutdown))
+    }
+
+    /// 发送一次请求，握手或请求失败时返回错误
+    async fn whoami(
+        pki: &TestPki,
+        port: u16,
+        identity: Option<ClientIdentity>,
+    ) -> anyhow::Result<String> {
+        let _ = rustls::crypto::CryptoProvider::install_default(
+            rustls::crypto::ring::default_provider(),
+        );
+        let mut roots = rustls::RootCertStore::empty();
+        roots.add(pki.ca_der.clone())?;
+        let builder = rustls::ClientConfig::builder().with_root_certificates(roots);
+        let mut config = match identity {
+            Some((chain, key)) => builder.with_client_auth_cert(chain, key)?,
+            None => builder.with_no_client_auth(),
+        };
+        config.alpn_protocols = vec![b"http/1.1".to_vec()];
+        let connector = tokio_rustls::TlsConnector::from(Arc::new(config));
+        let tcp = tokio::net::TcpStream::connect(format!("127.0.0.1:{port}")).await?;
+        let mut stream = connector
+            .connect(ServerName::try_from("localhost")?, tcp)
+            .await?;
+        stream
+            .write_all(b"GET /mtls_whoami HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n")
+            .await?;
+        let mut out = Vec::new();
+        _ = stream.read_to_end(&mut out).await;
+        let res = String::from_utf8_lossy(&out).to_string();
+        if !res.starts_with("HTTP/1.1 200") {
+            anyhow::bail!("unexpected response: {res}");
+        }
+        Ok(res)
+    }
+
+    #[tokio::test]
+    async fn test_mtls_required() -> anyhow::Result<()> {
+        let pki = TestPki::new()?;
+        let (port, shutdown) =
+            start_server(&pki, ClientAuth::required(pki.path("ca.pem")), true).await?;
+
+        let res = whoami(&pki, port, Some(pki.client_identity("svc-a", 0x1234)?)).await?;
+        assert!(res.contains("cn=svc-a"), "{res}");
+        assert!(res.contains("dns=svc-a.internal"), "{res}");
+        assert!(res.contains("uri=spiffe://example.org/svc-a"), "{res}");
+        assert!(res.contains("serial=1234"), "{res}");
+
+        // 未出示证书或证书不是由该 CA 签发时握手失败
+        assert!(whoami(&pki, port, None).await.is_err());
+        let other = TestPki::new()?;
+        assert!(whoami(&pki, port, Some(other.client_identity("svc-x", 2)?))
+            .await
+            .is_err());
+
+        _ = shutdown.send(());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_mtls_optional() -> anyhow::Result<()> {
+        let pki = TestPki::new()?;
+        let (port, shutdown) =
+            start_server(&pki, ClientAuth::optional(pki.path("ca.pem")), false).await?;
+
+        let res = whoami(&pki, port, None).await?;
+        assert!(res.contains("anonymous"), "{res}");
+        let res = whoami(&pki, port, Some(pki.client_identity("svc-b", 3)?)).await?;
+        assert!(res.contains("cn=svc-b"), "{res}");
+
+        _ = shutdown.send(());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_mtls_crl() -> anyhow::Result<()> {
+        let pki = TestPki::new()?;
+        let crl_file = pki.write_crl(66)?;
+        let client_auth = ClientAuth::required(pki.path("ca.pem")).with_crl(crl_file);
+        let (port, shutdown) = start_server(&pki, client_auth, false).await?;
+
+        assert!(whoami(&pki, port, Some(pki.client_identity("svc-c", 65)?))
+            .await
+            .is_ok());
+        assert!(whoami(&pki, port, Some(pki.client_identity("svc-d", 66)?))
+            .await
+            .is_err());
+
+        _ = shutdown.send(());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_client_auth_bad_ca() -> anyhow::Result<()> {
+        let pki = TestPki::new()?;
+        let (cert_file, key_file) = pki.write_server_cert()?;
+        let mut server = HttpServer::new(format!("127.0.0.1:{}", get_test_port()));
+        server.set_client_auth(ClientAuth::required(pki.path("missing-ca.pem")));
+        assert!(server.serve_https(&cert_file, &key_file).await.is_err());
+        Ok(())
+    }
+}
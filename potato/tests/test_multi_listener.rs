@@ -0,0 +1,164 @@
+/// 多监听（listen_* + serve）功能测试
+#[cfg(feature = "tls")]
+#[cfg(test)]
+mod multi_listener_tests {
+    use potato::{HttpRequest, HttpResponse, HttpServer};
+    use std::sync::atomic::{AtomicU16, Ordering};
+    use std::time::Duration;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::time::sleep;
+    use tokio_rustls::rustls;
+    use tokio_rustls::rustls::pki_types::{CertificateDer, ServerName};
+
+    static PORT_COUNTER: AtomicU16 = AtomicU16::new(32000);
+
+    fn get_test_port() -> u16 {
+        PORT_COUNTER.fetch_add(1, Ordering::Relaxed)
+    }
+
+    fn create_test_cert_files() -> anyhow::Result<(String, String, CertificateDer<'static>)> {
+        let cert = rcgen::generate_simple_self_signed(vec!["localhost".to_string()])?;
+        let temp_dir =
+            std::env::temp_dir().join(format!("potato_multi_listener_test_{}", get_test_port()));
+        std::fs::create_dir_all(&temp_dir)?;
+
+        let cert_path = temp_dir.join("cert.pem");
+        let key_path = temp_dir.join("key.pem");
+        std::fs::write(&cert_path, cert.cert.pem())?;
+        std::fs::write(&key_path, cert.signing_key.serialize_pem())?;
+
+        Ok((
+            cert_path.to_string_lossy().to_string(),
+            key_path.to_string_lossy().to_string(),
+            cert.cert.der().clone(),
+        ))
+    }
+
+    async fn https_get(
+        port: u16,
+        path: &str,
+        cert_der: &CertificateDer<'static>,
+    ) -> anyhow::Result<String> {
+        let _ = rustls::crypto::CryptoProvider::install_default(
+            rustls::crypto::ring::default_provider(),
+        );
+        let mut roots = rustls::RootCertStore::empty();
+        roots.add(cert_der.clone())?;
+        let mut config = rustls::ClientConfig::builder()
+            .with_root_certificates(roots)
+            .with_no_client_auth();
+        config.alpn_protocols = vec![b"http/1.1".to_vec()];
+        let connector = tokio_rustls::TlsConnector::from(std::sync::Arc::new(config));
+        let stream = tokio::net::TcpStream::connect(format!("127.0.0.1:{port}")).await?;
+        let mut stream = connector
+            .connect(ServerName::try_from("localhost")?.to_owned(), stream)
+            .await?;
+        let request =
+            format!("GET {path} HTTP/1.1\r\nHost: localhost:{port}\r\nConnection: close\r\n\r\n");
+        stream.write_all(request.as_bytes()).await?;
+
+        let mut out = Vec::new();
+        let mut buf = [0_u8; 4096];
+        loop {
+            match stream.read(&mut buf).await {
+                Ok(0) => break,
+                Ok(n) => out.extend_from_slice(&buf[..n]),
+                Err(err) if err.to_string().contains("close_notify") => break,
+                Err(err) => return Err(err.into()),
+            }
+        }
+        Ok(String::from_utf8_lossy(&out).to_string())
+    }
+
+    async fn http_get(port: u16, path: &str) -> anyhow::Result<String> {
+        let mut stream = tokio::net::TcpStream::connect(format!("127.0.0.1:{port}")).await?;
+        let request =
+            format!("GET {path} HTTP/1.1\r\nHost: 127.0.0.1:{port}\r\nConnection: close\r\n\r\n");
+        stream.write_all(request.as_bytes()).await?;
+        let mut out = Vec::new();
+        stream.read_to_end(&mut out).await?;
+        Ok(String::from_utf8_lossy(&out).to_string())
+    }
+
+    #[potato::http_get("/multi_listener_hello")]
+    async fn multi_listener_hello(req: &mut HttpRequest) -> HttpResponse {
+        HttpResponse::text(format!("hello v{}", req.version))
+    }
+
+    #[tokio::test]
+    async fn test_http_and_https_listeners_share_pipe_context() -> anyhow::Result<()> {
+        let (cert_file, key_file, cert_der) = create_test_cert_files()?;
+        let http_port = get_test_port();
+        let https_port = get_test_port();
+
+        let mut server = HttpServer::new("");
+        server
+            .listen_http(format!("127.0.0.1:{http_port}"))
+            .listen_https(format!("127.0.0.1:{https_port}"), cert_file, key_file);
+        server.configure(|ctx| {
+            ctx.use_handlers();
+            ctx.use_custom_sync(|_req| Some(HttpResponse::text("fallback")));
+        });
+        let shutdown = server.shutdown_signal()?;
+        let server_handle = tokio::spawn(async move { server.serve().await });
+        sleep(Duration::from_millis(300)).await;
+
+        let res = http_get(http_port, "/multi_listener_hello").await?;
+        assert!(res.contains("200 OK"));
+        assert!(res.contains("hello v11"));
+        assert!(!res.to_ascii_lowercase().contains("alt-svc"));
+
+        let res = https_get(https_port, "/multi_listener_hello", &cert_der).await?;
+        assert!(res.contains("200 OK"));
+        assert!(res.contains("hello v11"));
+        assert!(!res.to_ascii_lowercase().contains("alt-svc"));
+
+        // 两个监听共享同一条中间件管线
+        let res = https_get(https_port, "/multi_listener_missing", &cert_der).await?;
+        assert!(res.contains("fallback"));
+
+        _ = shutdown.send(());
+        server_handle.await??;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_serve_without_listener_fails() {
+        let mut server = HttpServer::new("127.0.0.1:0");
+        assert!(server.serve().await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_serve_reports_bind_error() -> anyhow::Result<()> {
+        let port = get_test_port();
+        let _occupied = std::net::TcpListener::bind(format!("127.0.0.1:{port}"))?;
+        let mut server = HttpServer::new("");
+        server.listen_http(format!("127.0.0.1:{port}"));
+        assert!(server.serve().await.is_err());
+        Ok(())
+    }
+
+    #[cfg(feature = "http3")]
+    #[tokio::test]
+    async fn test_https_listener_advertises_h3() -> anyhow::Result<()> {
+        let (cert_file, key_file, cert_der) = create_test_cert_files()?;
+        let port = get_test_port();
+
+        let mut server = HttpServer::new("");
+        server
+            .listen_https(format!("127.0.0.1:{port}"), &cert_file, &key_file)
+            .listen_h3(format!("127.0.0.1:{port}"), &cert_file, &key_file);
+        server.configure(|ctx| ctx.use_handlers());
+        let shutdown = server.shutdown_signal()?;
+        let server_handle = tokio::spawn(async move { server.serve().await });
+        sleep(Duration::from_millis(300)).await;
+
+        let res = https_get(port, "/multi_listener_hello", &cert_der).await?;
+        assert!(res.contains("hello v11"));
+        assert!(res.contains(&format!("Alt-Svc: h3=\":{port}\"; ma=86400")));
+
+        _ = shutdown.send(());
+        server_handle.await??;
+        Ok(())
+    }
+}
@@ -0,0 +1,241 @@
+/// OAuth2 客户端测试，授权服务器与受保护接口由 potato 自身模拟
+use base64::Engine;
+use potato::{HttpRequest, HttpResponse, HttpServer, OAuth2Client, OAuth2Config, OAuth2Error};
+use sha2::{Digest, Sha256};
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicU16, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+static PORT_COUNTER: AtomicU16 = AtomicU16::new(42700);
+
+fn get_test_port() -> u16 {
+    PORT_COUNTER.fetch_add(1, Ordering::Relaxed)
+}
+
+/// 模拟授权服务器的状态
+#[derive(Default)]
+struct Idp {
+    /// 有效的访问令牌
+    valid: HashSet<String>,
+    /// code -> code_challenge
+    codes: HashMap<String, String>,
+    device_polls: usize,
+    issued: usize,
+}
+
+impl Idp {
+    fn issue(&mut self, prefix: &str, expires_in: u64, refresh: bool) -> HttpResponse {
+        self.issued += 1;
+        let token = format!("{prefix}-{}", self.issued);
+        self.valid.insert(token.clone());
+        let mut body = serde_json::json!({
+            "access_token": token, "token_type": "Bearer", "expires_in": expires_in,
+        });
+        if refresh {
+            body["refresh_token"] = "rt-1".into();
+        }
+        HttpResponse::json(body.to_string())
+    }
+}
+
+fn oauth_error(code: u16, error: &str) -> HttpResponse {
+    let mut res = HttpResponse::json(serde_json::json!({ "error": error }).to_string());
+    res.http_code = code;
+    res
+}
+
+fn pair(req: &HttpRequest, key: &str) -> String {
+    req.body_pairs
+        .get(key)
+        .map(|v| v.to_string())
+        .unwrap_or_default()
+}
+
+fn handle(idp: &Mutex<Idp>, req: &mut HttpRequest) -> Option<HttpResponse> {
+    let mut idp = idp.lock().unwrap();
+    match &req.url_path[..] {
+        "/token" => {
+            let basic = format!(
+                "Basic {}",
+                base64::engine::general_purpose::STANDARD.encode("app:s%3Acret")
+            );
+            let confidential = req.get_header("Authorization") == Some(basic.as_str());
+            Some(match &pair(req, "grant_type")[..] {
+                "client_credentials" if !confidential => oauth_error(401, "invalid_client"),
+                "client_credentials" => idp.issue("cc", 3600, false),
+                "authorization_code" => {
+                    let challenge = idp.codes.remove(&pair(req, "code"))?;
+                    let verifier = pair(req, "code_verifier");
+                    let expected = base64::engine::general_purpose::URL_SAFE_NO_PAD
+                        .encode(Sha256::digest(verifier.as_bytes()));
+                    match challenge == expected && pair(req, "client_id") == "cli" {
+                        true => idp.issue("ac", 1, true),
+                        false => oauth_error(400, "invalid_grant"),
+                    }
+                }
+                "refresh_token" if pair(req, "refresh_token") == "rt-1" => {
+                    idp.issue("rf", 3600, false)
+                }
+                "urn:ietf:params:oauth:grant-type:device_code" => {
+                    idp.device_polls += 1;
+                    match idp.device_polls {
+                        1 => oauth_error(400, "authorization_pending"),
+                        _ => idp.issue("dc", 3600, true),
+                    }
+                }
+                _ => oauth_error(400, "invalid_grant"),
+            })
+        }
+        "/device" => Some(HttpResponse::json(
+            serde_json::json!({
+                "device_code": "dev-1", "user_code": "WDJB-MJHT",
+                "verification_uri": "https://example.com/device",
+                "expires_in": 60, "interval": 0,
+            })
+            .to_string(),
+        )),
+        "/api" => {
+            let token = req
+                .get_header("Authorization")
+                .and_then(|v| v.strip_prefix("Bearer "))
+                .unwrap_or_default();
+            Some(match idp.valid.contains(token) {
+                true => HttpResponse::text(format!("ok:{token}:{}", req.body.len())),
+                false => {
+                    let mut res = HttpResponse::text("unauthorized");
+                    res.http_code = 401;
+                    res
+                }
+            })
+        }
+        _ => None,
+    }
+}
+
+async fn start_idp() -> (String, Arc<Mutex<Idp>>) {
+    let addr = format!("127.0.0.1:{}", get_test_port());
+    let idp = Arc::new(Mutex::new(Idp::default()));
+    let mut server = HttpServer::new(&addr);
+    let state = idp.clone();
+    server.configure(move |ctx| {
+        let state = state.clone();
+        ctx.use_custom_sync(move |req| handle(&state, req));
+    });
+    tokio::spawn(async move {
+        let _ = server.serve_http().await;
+    });
+    tokio::time::sleep(Duration::from_millis(300)).await;
+    (format!("http://{addr}"), idp)
+}
+
+async fn body(res: &mut HttpResponse) -> String {
+    String::from_utf8_lossy(res.body.data().await).to_string()
+}
+
+#[tokio::test]
+async fn test_oauth2_client_credentials() -> anyhow::Result<()> {
+    let (base, idp) = start_idp().await;
+    let config = OAuth2Config::new("app", format!("{base}/token")).with_scope("orders:read");
+
+    // 客户端认证失败时返回授权服务器的错误码
+    let mut client = OAuth2Client::new(config.clone().with_client_secret("wrong"));
+    let err = client.client_credentials().await.unwrap_err();
+    assert_eq!(
+        err.downcast_ref::<OAuth2Error>().unwrap().error,
+        "invalid_client"
+    );
+
+    // 未获取令牌时不发送请求
+    let mut client = OAuth2Client::new(config.with_client_secret("s:cret"));
+    assert!(client.get(&format!("{base}/api"), vec![]).await.is_err());
+
+    client.client_credentials().await?;
+    let mut res = client.get(&format!("{base}/api"), vec![]).await?;
+    assert_eq!(body(&mut res).await, "ok:cc-1:0");
+
+    // 令牌被吊销后收到401，重新获取令牌并重试
+    idp.lock().unwrap().valid.clear();
+    let mut res = client
+        .post(&format!("{base}/api"), b"hello".to_vec(), vec![])
+        .await?;
+    assert_eq!(body(&mut res).await, "ok:cc-2:5");
+    assert_eq!(client.token().unwrap().access_token, "cc-2");
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_oauth2_authorization_code_pkce() -> anyhow::Result<()> {
+    let (base, idp) = start_idp().await;
+    let mut client = OAuth2Client::new(
+        OAuth2Config::new("cli", format!("{base}/token"))
+            .with_auth_url("https://idp.example.com/authorize?prompt=login")
+            .with_redirect_uri("http://127.0.0.1:8080/callback")
+            .with_scope("openid")
+            .with_scope("profile"),
+    );
+    let auth = client.authorize_url()?;
+    assert!(auth
+        .url
+        .starts_with("https://idp.example.com/authorize?prompt=login&response_type=code"));
+    assert!(auth.url.contains("&code_challenge_method=S256"));
+    assert!(auth.url.contains("&scope=openid+profile"));
+    assert!(auth
+        .url
+        .contains("&redirect_uri=http%3A%2F%2F127.0.0.1%3A8080%2Fcallback"));
+    assert!(auth.url.contains(&format!("&state={}", auth.state)));
+
+    // 授权服务器记录 code_challenge，回调时凭 code_verifier 换取令牌
+    let challenge = auth
+        .url
+        .split('&')
+        .find_map(|kv| kv.strip_prefix("code_challenge="))
+        .unwrap()
+        .to_string();
+    idp.lock()
+        .unwrap()
+        .codes
+        .insert("code-1".to_string(), challenge);
+    assert!(client
+        .exchange_code(&auth, "code-1", "forged-state")
+        .await
+        .is_err());
+    let token = client.exchange_code(&auth, "code-1", &auth.state).await?;
+    assert_eq!(token.access_token, "ac-1");
+    assert!(token.is_expired(Duration::from_secs(30)));
+
+    // 令牌即将过期，请求前使用 refresh_token 刷新，并保留原 refresh_token
+    let mut res = client.get(&format!("{base}/api"), vec![]).await?;
+    assert_eq!(body(&mut res).await, "ok:rf-2:0");
+    let token = client.token().unwrap();
+    assert_eq!(token.refresh_token.as_deref(), Some("rt-1"));
+    assert!(!token.is_expired(Duration::from_secs(30)));
+
+    // 令牌可序列化后恢复
+    let saved = serde_json::to_string(token)?;
+    let mut restored = OAuth2Client::new(OAuth2Config::new("cli", format!("{base}/token")));
+    restored.set_token(serde_json::from_str(&saved)?);
+    let mut res = restored.get(&format!("{base}/api"), vec![]).await?;
+    assert_eq!(body(&mut res).await, "ok:rf-2:0");
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_oauth2_device_code() -> anyhow::Result<()> {
+    let (base, idp) = start_idp().await;
+    let mut client = OAuth2Client::new(
+        OAuth2Config::new("tv", format!("{base}/token"))
+            .with_device_auth_url(format!("{base}/device")),
+    );
+    let device = client.start_device_authorization().await?;
+    assert_eq!(device.user_code, "WDJB-MJHT");
+    let token = client.poll_device_token(&device).await?;
+    assert_eq!(token.access_token, "dc-1");
+    assert_eq!(idp.lock().unwrap().device_polls, 2);
+
+    // 无客户端凭据且令牌失效时刷新后重试
+    idp.lock().unwrap().valid.clear();
+    let mut res = client.get(&format!("{base}/api"), vec![]).await?;
+    assert_eq!(body(&mut res).await, "ok:rf-2:0");
+    Ok(())
+}
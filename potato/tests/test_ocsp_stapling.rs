@@ -0,0 +1,366 @@
+/// OCSP 装订测试
+#[cfg(feature = "tls")]
+#[cfg(test)]
+mod ocsp_stapling_tests {
+    use potato::HttpServer;
+    use rcgen::{BasicConstraints, CertificateParams, CustomExtension, IsCa, Issuer, KeyPair};
+    use std::path::PathBuf;
+    use std::sync::atomic::{AtomicU16, Ordering};
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::{TcpListener, TcpStream};
+    use tokio_rustls::rustls;
+    use tokio_rustls::rustls::pki_types::{CertificateDer, ServerName, UnixTime};
+
+    static PORT_COUNTER: AtomicU16 = AtomicU16::new(41000);
+
+    fn get_test_port() -> u16 {
+        PORT_COUNTER.fetch_add(1, Ordering::Relaxed)
+    }
+
+    fn der(tag: u8, content: &[u8]) -> Vec<u8> {
+        let mut out = vec![tag];
+        match content.len() {
+            len if len < 0x80 => out.push(len as u8),
+            len if len < 0x100 => out.extend([0x81, len as u8]),
+            len => out.extend([0x82, (len >> 8) as u8, len as u8]),
+        }
+        out.extend_from_slice(content);
+        out
+    }
+
+    fn generalized_time(time: time::OffsetDateTime) -> Vec<u8> {
+        let text = format!(
+            "{:04}{:02}{:02}{:02}{:02}{:02}Z",
+            time.year(),
+            time.month() as u8,
+            time.day(),
+            time.hour(),
+            time.minute(),
+            time.second()
+        );
+        der(0x18, text.as_bytes())
+    }
+
+    /// 构造状态为 good、7 天后过期的 OCSP 响应（签名不参与服务端校验）
+    fn ocsp_response() -> Vec<u8> {
+        let now = time::OffsetDateTime::now_utc();
+        let single = [
+            der(0x30, &[]),
+            vec![0x80, 0x00],
+            generalized_time(now),
+            der(0xa0, &generalized_time(now + time::Duration::days(7))),
+        ]
+        .concat();
+        let tbs = [
+            der(0xa2, &der(0x04, &[0u8; 20])),
+            generalized_time(now),
+            der(0x30, &der(0x30, &single)),
+        ]
+        .concat();
+        let basic = [
+            der(0x30, &tbs),
+            der(0x30, &[0x06, 0x03, 0x2a, 0x03, 0x04]),
+            der(0x03, &[0x00, 0x01]),
+        ]
+        .concat();
+        let basic_oid = [
+            0x06, 0x09, 0x2b, 0x06, 0x01, 0x05, 0x05, 0x07, 0x30, 0x01, 0x01,
+        ];
+        let response_bytes = [basic_oid.to_vec(), der(0x04, &der(0x30, &basic))].concat();
+        let response = [
+            vec![0x0a, 0x01, 0x00],
+            der(0xa0, &der(0x30, &response_bytes)),
+        ]
+        .concat();
+        der(0x30, &response)
+    }
+
+    /// 模拟 OCSP 服务器，记录收到的请求体
+    async fn start_responder(response: Vec<u8>) -> (u16, Arc<Mutex<Vec<Vec<u8>>>>) {
+        let port = get_test_port();
+        let listener = TcpListener::bind(format!("127.0.0.1:{port}"))
+            .await
+            .unwrap();
+        let requests = Arc::new(Mutex::new(vec![]));
+        let requests2 = Arc::clone(&requests);
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                let mut buf = vec![];
+                let mut chunk = [0u8; 4096];
+                let body = loop {
+                    let Ok(n) = stream.read(&mut chunk).await else {
+                        break None;
+                    };
+                    if n == 0 {
+                        break None;
+                    }
+                    buf.extend_from_slice(&chunk[..n]);
+                    let Some(pos) = buf.windows(4).position(|w| w == b"\r\n\r\n") else {
+                        continue;
+                    };
+                    let head = String::from_utf8_lossy(&buf[..pos]).to_ascii_lowercase();
+                    let len = head
+                        .lines()
+                        .find_map(|line| line.strip_prefix("content-length:"))
+                        .and_then(|len| len.trim().parse::<usize>().ok())
+                        .unwrap_or(0);
+                    if buf.len() >= pos + 4 + len {
+                        break Some(buf[pos + 4..pos + 4 + len].to_vec());
+                    }
+                };
+                let Some(body) = body else {
+                    continue;
+                };
+                requests2.lock().unwrap().push(body);
+                let head = format!(
+                    "HTTP/1.1 200 OK\r\nContent-Type: application/ocsp-response\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                    response.len()
+                );
+                _ = stream.write_all(head.as_bytes()).await;
+                _ = stream.write_all(&response).await;
+            }
+        });
+        (port, requests)
+    }
+
+    struct TestPki {
+        dir: PathBuf,
+        cert_file: String,
+        key_file: String,
+        serial: Vec<u8>,
+    }
+
+    /// 生成 CA 与带有 AIA OCSP 地址的服务端证书，证书文件包含完整证书链
+    fn issue_cert(ocsp_port: u16) -> anyhow::Result<TestPki> {
+        let dir = std::env::temp_dir().join(format!(
+            "potato_ocsp_test_{}_{ocsp_port}",
+            std::process::id()
+        ));
+        std::fs::create_dir_all(&dir)?;
+
+        let ca_key = KeyPair::generate()?;
+        let mut ca_params = CertificateParams::new(vec![])?;
+        ca_params.is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
+        let ca_cert = ca_params.self_signed(&ca_key)?;
+
+        // AuthorityInfoAccess { AccessDescription { id-ad-ocsp, uniformResourceIdentifier } }
+        let url = format!("http://127.0.0.1:{ocsp_port}/ocsp");
+        let ocsp_oid = [0x06, 0x08, 0x2b, 0x06, 0x01, 0x05, 0x05, 0x07, 0x30, 0x01];
+        let aia = der(
+            0x30,
+            &der(
+                0x30,
+                &[ocsp_oid.to_vec(), der(0x86, url.as_bytes())].concat(),
+            ),
+        );
+        let serial = vec![0x01, 0x23, 0x45, 0x67];
+        let mut params = CertificateParams::new(vec!["ocsp.test".to_string()])?;
+        params.serial_number = Some(rcgen::SerialNumber::from_slice(&serial));
+        params.custom_extensions = vec![CustomExtension::from_oid_content(
+            &[1, 3, 6, 1, 5, 5, 7, 1, 1],
+            aia,
+        )];
+        let key = KeyPair::generate()?;
+        let cert = params.signed_by(&key, &Issuer::from_params(&ca_params, &ca_key))?;
+
+        let cert_file = dir.join("cert.pem");
+        let key_file = dir.join("key.pem");
+        std::fs::write(&cert_file, format!("{}{}", cert.pem(), ca_cert.pem()))?;
+        std::fs::write(&key_file, key.serialize_pem())?;
+        Ok(TestPki {
+            cert_file: cert_file.to_string_lossy().to_string(),
+            key_file: key_file.to_string_lossy().to_string(),
+            dir,
+            serial,
+        })
+    }
+
+    /// 不校验证书，记录服务端装订的 OCSP 响应
+    #[derive(Debug)]
+    struct RecordOcsp(Arc<Mutex<Vec<u8>>>);
+
+    impl rustls::client::danger::ServerCertVerifier for RecordOcsp {
+        fn verify_server_cert(
+            &self,
+            _: &CertificateDer<'_>,
+            _: &[CertificateDer<'_>],
+            _: &ServerName<'_>,
+            ocsp_response: &[u8],
+            _: UnixTime,
+        ) -> Result<rustls::client::danger::ServerCertVerified, rustls::Error> {
+            *self.0.lock().unwrap() = ocsp_response.to_vec();
+            Ok(rustls::client::danger::ServerCertVerified::assertion())
+        }
+
+        fn verify_tls12_signature(
+            &self,
+            _: &[u8],
+            _: &CertificateDer<'_>,
+            _: &rustls::DigitallySignedStruct,
+        ) -> Result<rustls::client::danger::HandshakeSignatureValid, rustls::Error> {
+            Ok(rustls::client::danger::HandshakeSignatureValid::assertion())
+        }
+
+        fn verify_tls13_signature(
+            &self,
+            _: &[u8],
+            _: &CertificateDer<'_>,
+            _: &rustls::DigitallySignedStruct,
+        ) -> Result<rustls::client::danger::HandshakeSignatureValid, rustls::Error> {
+            Ok(rustls::client::danger::HandshakeSignatureValid::assertion())
+        }
+
+        fn supported_verify_schemes(&self) -> Vec<rustls::SignatureScheme> {
+            rustls::crypto::ring::default_provider()
+                .signature_verification_algorithms
+                .supported_schemes()
+        }
+    }
+
+    /// 握手并返回服务端装订的 OCSP 响应
+    async fn stapled_response(port: u16) -> anyhow::Result<Vec<u8>> {
+        let recorded = Arc::new(Mutex::new(vec![]));
+        let config = rustls::ClientConfig::builder_with_provider(Arc::new(
+            rustls::crypto::ring::default_provider(),
+        ))
+        .with_safe_default_protocol_versions()?
+        .dangerous()
+        .with_custom_certificate_verifier(Arc::new(RecordOcsp(Arc::clone(&recorded))))
+        .with_no_client_auth();
+        let connector = tokio_rustls::TlsConnector::from(Arc::new(config));
+        let stream = TcpStream::connect(format!("127.0.0.1:{port}")).await?;
+        connector
+            .connect(ServerName::try_from("ocsp.test")?, stream)
+            .await?;
+        let response = recorded.lock().unwrap().clone();
+        Ok(response)
+    }
+
+    /// 首次握手触发后台获取，之后的握手附带响应
+    async fn wait_for_staple(port: u16) -> anyhow::Result<Vec<u8>> {
+        for _ in 0..50 {
+            let response = stapled_response(port).await?;
+            if !response.is_empty() {
+                return Ok(response);
+            }
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+        anyhow::bail!("no OCSP response stapled")
+    }
+
+    #[tokio::test]
+    async fn test_ocsp_stapling_static_cert() -> anyhow::Result<()> {
+        let response = ocsp_response();
+        let (ocsp_port, requests) = start_responder(response.clone()).await;
+        let pki = issue_cert(ocsp_port)?;
+
+        let port = get_test_port();
+        let mut server = HttpServer::new(format!("127.0.0.1:{port}"));
+        server.set_ocsp_stapling(true);
+        let shutdown = server.shutdown_signal()?;
+        let (cert_file, key_file) = (pki.cert_file.clone(), pki.key_file.clone());
+        tokio::spawn(async move { server.serve_https(&cert_file, &key_file).await });
+        tokio::time::sleep(Duration::from_millis(200)).await;
+
+        assert_eq!(wait_for_staple(port).await?, response);
+        // 请求中的 CertID 包含证书序列号，且只请求一次
+        let requests = requests.lock().unwrap().clone();
+        assert_eq!(requests.len(), 1);
+        assert!(requests[0]
+            .windows(pki.serial.len())
+            .any(|w| w == pki.serial.as_slice()));
+
+        _ = shutdown.send(());
+        _ = std::fs::remove_dir_all(&pki.dir);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_ocsp_stapling_disabled_or_unavailable() -> anyhow::Result<()> {
+        // 未启用装订时不附带响应
+        let (ocsp_port, requests) = start_responder(ocsp_response()).await;
+        let pki = issue_cert(ocsp_port)?;
+        let port = get_test_port();
+        let mut server = HttpServer::new(format!("127.0.0.1:{port}"));
+        let shutdown = server.shutdown_signal()?;
+        let (cert_file, key_file) = (pki.cert_file.clone(), pki.key_file.clone());
+        tokio::spawn(async move { server.serve_https(&cert_file, &key_file).await });
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        assert!(stapled_response(port).await?.is_empty());
+        tokio::time::sleep(Duration::from_millis(300)).await;
+        assert!(stapled_response(port).await?.is_empty());
+        assert!(requests.lock().unwrap().is_empty());
+        _ = shutdown.send(());
+
+        // OCSP 服务器返回错误状态时不装订
+        let (bad_port, bad_requests) = start_responder(vec![0x30, 0x03, 0x0a, 0x01, 0x06]).await;
+        let bad_pki = issue_cert(bad_port)?;
+        let port = get_test_port();
+        let mut server = HttpServer::new(format!("127.0.0.1:{port}"));
+        server.set_ocsp_stapling(true);
+        let shutdown = server.shutdown_signal()?;
+        let (cert_file, key_file) = (bad_pki.cert_file.clone(), bad_pki.key_file.clone());
+        tokio::spawn(async move { server.serve_https(&cert_file, &key_file).await });
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        assert!(stapled_response(port).await?.is_empty());
+        tokio::time::sleep(Duration::from_millis(500)).await;
+        assert_eq!(bad_requests.lock().unwrap().len(), 1);
+        assert!(stapled_response(port).await?.is_empty());
+        _ = shutdown.send(());
+
+        _ = std::fs::remove_dir_all(&pki.dir);
+        _ = std::fs::remove_dir_all(&bad_pki.dir);
+        Ok(())
+    }
+
+    #[cfg(feature = "acme")]
+    #[tokio::test]
+    async fn test_ocsp_stapling_acme_cert() -> anyhow::Result<()> {
+        use base64::Engine;
+        use potato::acme::{AcmeManager, AcmeOptions, CertStore, FileCertStore};
+
+        let response = ocsp_response();
+        let (ocsp_port, _) = start_responder(response.clone()).await;
+        let pki = issue_cert(ocsp_port)?;
+
+        // 存储中已有账户与证书，创建管理器时不访问 ACME 服务器
+        let store = FileCertStore::new(pki.dir.join("acme"));
+        let key = KeyPair::generate()?;
+        let key = base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(key.serialize_der());
+        let account = format!(
+            r#"{{"id":"https://acme.invalid/acct/1","key_pkcs8":"{key}","directory":null,"urls":{{"newNonce":"https://acme.invalid/nonce","newAccount":"https://acme.invalid/acct","newOrder":"https://acme.invalid/order"}}}}"#
+        );
+        store
+            .store("account.json", account.as_bytes(), true)
+            .await?;
+        let key_pem = std::fs::read(&pki.key_file)?;
+        let cert_pem = std::fs::read(&pki.cert_file)?;
+        store.store("key.pem", &key_pem, true).await?;
+        store.store("cert.pem", &cert_pem, false).await?;
+
+        let opts = AcmeOptions::new("ocsp.test", "admin@ocsp.test")
+            .with_cert_store(store)
+            .with_ocsp_stapling();
+        let (_manager, acceptor) = AcmeManager::new(opts).await?;
+
+        let port = get_test_port();
+        let listener = TcpListener::bind(format!("127.0.0.1:{port}")).await?;
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                let acceptor = acceptor.get_acceptor().await;
+                tokio::spawn(async move {
+                    if let Ok(stream) = acceptor.accept(stream).await {
+                        tokio::time::sleep(Duration::from_millis(100)).await;
+                        drop(stream);
+                    }
+                });
+            }
+        });
+
+        assert_eq!(wait_for_staple(port).await?, response);
+        _ = std::fs::remove_dir_all(&pki.dir);
+        Ok(())
+    }
+}
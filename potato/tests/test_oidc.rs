@@ -0,0 +1,287 @@
+/// OIDC 资源服务器模式测试，身份提供方由 potato 自身模拟
+use potato::jsonwebtoken::jwk::JwkSet;
+use potato::jsonwebtoken::{encode, Algorithm, EncodingKey, Header};
+use potato::{HttpRequest, HttpResponse, HttpServer, JwtKey, OidcConfig};
+use std::sync::atomic::{AtomicU16, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+static PORT_COUNTER: AtomicU16 = AtomicU16::new(42600);
+
+fn get_test_port() -> u16 {
+    PORT_COUNTER.fetch_add(1, Ordering::Relaxed)
+}
+
+#[potato::http_get("/oidc/me")]
+async fn me(req: &mut HttpRequest) -> HttpResponse {
+    match req.oidc_claims() {
+        Some(claims) => {
+            let tenant: String = claims.claim("tenant").unwrap_or_default();
+            HttpResponse::text(format!(
+                "{}:{tenant}:{}",
+                claims.sub,
+                claims.has_role("admin")
+            ))
+        }
+        None => HttpResponse::text("anonymous"),
+    }
+}
+
+#[potato::http_get("/oidc/admin")]
+#[potato::require_role("admin")]
+#[potato::require_scope("orders:read")]
+async fn admin(req: &mut HttpRequest) -> HttpResponse {
+    let sub = req.oidc_claims().map(|claims| claims.sub.clone());
+    HttpResponse::text(sub.unwrap_or_default())
+}
+
+/// 模拟的身份提供方签名密钥
+struct IdpKey {
+    kid: &'static str,
+    pem: String,
+}
+
+impl IdpKey {
+    fn new(kid: &'static str) -> Self {
+        let pem = rcgen::KeyPair::generate_for(&rcgen::PKCS_ECDSA_P256_SHA256)
+            .unwrap()
+            .serialize_pem();
+        Self { kid, pem }
+    }
+
+    fn jwk(&self) -> potato::jsonwebtoken::jwk::Jwk {
+        let mut jwk = JwtKey::es256(self.kid, &self.pem)
+            .unwrap()
+            .to_jwk()
+            .unwrap()
+            .clone();
+        // 部分身份提供方的 JWKS 不含 alg
+        jwk.common.key_algorithm = None;
+        jwk
+    }
+
+    fn sign(&self, claims: serde_json::Value) -> String {
+        let mut header = Header::new(Algorithm::ES256);
+        header.kid = Some(self.kid.to_string());
+        let key = EncodingKey::from_ec_pem(self.pem.as_bytes()).unwrap();
+        encode(&header, &claims, &key).unwrap()
+    }
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs()
+}
+
+/// 启动模拟身份提供方，返回（issuer，JWKS 请求计数）
+async fn start_idp(jwks: Arc<Mutex<JwkSet>>) -> (String, Arc<AtomicUsize>) {
+    let issuer = format!("http://127.0.0.1:{}", get_test_port());
+    let fetches = Arc::new(AtomicUsize::new(0));
+    let mut server = HttpServer::new(issuer.trim_start_matches("http://"));
+    let (doc_issuer, counter) = (issuer.clone(), fetches.clone());
+    server.configure(move |ctx| {
+        let (doc_issuer, counter, jwks) = (doc_issuer.clone(), counter.clone(), jwks.clone());
+        ctx.use_custom_sync(move |req| match &req.url_path[..] {
+            "/.well-known/openid-configuration" => Some(HttpResponse::json(
+                serde_json::json!({
+                    "issuer": doc_issuer,
+                    "jwks_uri": format!("{doc_issuer}/keys"),
+                })
+                .to_string(),
+            )),
+            "/keys" => {
+                counter.fetch_add(1, Ordering::Relaxed);
+                let jwks = jwks.lock().unwrap();
+                Some(HttpResponse::json(serde_json::to_string(&*jwks).unwrap()))
+            }
+            _ => None,
+        });
+    });
+    tokio::spawn(async move {
+        let _ = server.serve_http().await;
+    });
+    (issuer, fetches)
+}
+
+async fn call(addr: &str, path: &str, token: Option<&str>) -> (u16, String, Option<String>) {
+    let url = format!("http://{addr}{path}");
+    let mut res = match token {
+        Some(token) => potato::get!(&url, Authorization = format!("Bearer {token}")).await,
+        None => potato::get(&url, vec![]).await,
+    }
+    .unwrap();
+    let www = res.get_header("WWW-Authenticate").map(str::to_string);
+    let body = String::from_utf8_lossy(res.body.data().await).to_string();
+    (res.http_code, body, www)
+}
+
+#[tokio::test]
+async fn test_oidc_resource_server() -> anyhow::Result<()> {
+    let key1 = IdpKey::new("idp-1");
+    let jwks = Arc::new(Mutex::new(JwkSet {
+        keys: vec![key1.jwk()],
+    }));
+    let (issuer, fetches) = start_idp(jwks.clone()).await;
+    let config = OidcConfig::new(&issuer)
+        .with_audience("orders")
+        .with_leeway(Duration::ZERO)
+        .with_jwks_refresh(Duration::from_millis(500));
+    let api = format!("127.0.0.1:{}", get_test_port());
+    let mut server = HttpServer::new(&api);
+    server.configure(move |ctx| {
+        ctx.use_oidc(config.clone());
+        ctx.use_handlers();
+        ctx.use_custom_sync(|_req| Some(HttpResponse::text("fallback")));
+    });
+    let server_handle = tokio::spawn(async move {
+        let _ = server.serve_http().await;
+    });
+    tokio::time::sleep(Duration::from_millis(300)).await;
+
+    let claims = |iss: &str, aud: &str, exp: u64| {
+        serde_json::json!({
+            "iss": iss, "aud": aud, "sub": "user-42", "exp": exp,
+            "roles": ["admin"], "tenant": "acme",
+        })
+    };
+    let valid = key1.sign(claims(&issuer, "orders", now() + 600));
+    assert_eq!(
+        call(&api, "/oidc/me", Some(&valid)).await.1,
+        "user-42:acme:true"
+    );
+    // 公钥已缓存
+    assert_eq!(call(&api, "/oidc/me", Some(&valid)).await.0, 200);
+    assert_eq!(fetches.load(Ordering::Relaxed), 1);
+
+    // 未携带令牌时交由处理函数决定
+    assert_eq!(call(&api, "/oidc/me", None).await.1, "anonymous");
+
+    // aud、iss、exp、签名不符时返回 401
+    for token in [
+        key1.sign(claims(&issuer, "billing", now() + 600)),
+        key1.sign(claims("http://evil.example", "orders", now() + 600)),
+        key1.sign(claims(&issuer, "orders", now() - 10)),
+        IdpKey {
+            kid: "idp-1",
+            pem: IdpKey::new("forged").pem,
+        }
+        .sign(claims(&issuer, "orders", now() + 600)),
+        "not-a-jwt".to_string(),
+    ] {
+        let (code, _, www) = call(&api, "/oidc/me", Some(&token)).await;
+        assert_eq!(code, 401);
+        assert!(www.unwrap().starts_with("Bearer error=\"invalid_token\""));
+    }
+
+    // 身份提供方轮换密钥后按新的 kid 重新拉取
+    let key2 = IdpKey::new("idp-2");
+    jwks.lock().unwrap().keys.push(key2.jwk());
+    tokio::time::sleep(Duration::from_millis(600)).await;
+    let rotated = key2.sign(claims(&issuer, "orders", now() + 600));
+    assert_eq!(call(&api, "/oidc/me", Some(&rotated)).await.0, 200);
+    assert!(fetches.load(Ordering::Relaxed) >= 2);
+
+    server_handle.abort();
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_oidc_require_token_and_unavailable() -> anyhow::Result<()> {
+    let key = IdpKey::new("idp-1");
+    let jwks = Arc::new(Mutex::new(JwkSet {
+        keys: vec![key.jwk()],
+    }));
+    let (issuer, _) = start_idp(jwks).await;
+    let config = OidcConfig::new(&issuer)
+        .require_token()
+        .with_exempt_path("/health");
+    let api = format!("127.0.0.1:{}", get_test_port());
+    let mut server = HttpServer::new(&api);
+    server.configure(move |ctx| {
+        ctx.use_oidc(config.clone());
+        ctx.use_handlers();
+        ctx.use_custom_sync(|_req| Some(HttpResponse::text("fallback")));
+    });
+    let server_handle = tokio::spawn(async move {
+        let _ = server.serve_http().await;
+    });
+
+    // 身份提供方不可达
+    let closed = format!("http://127.0.0.1:{}", get_test_port());
+    let config = OidcConfig::new(&closed);
+    let down = format!("127.0.0.1:{}", get_test_port());
+    let mut server = HttpServer::new(&down);
+    server.configure(move |ctx| {
+        ctx.use_oidc(config.clone());
+        ctx.use_handlers();
+        ctx.use_custom_sync(|_req| Some(HttpResponse::text("fallback")));
+    });
+    let down_handle = tokio::spawn(async move {
+        let _ = server.serve_http().await;
+    });
+    tokio::time::sleep(Duration::from_millis(300)).await;
+
+    let (code, _, www) = call(&api, "/oidc/me", None).await;
+    assert_eq!((code, www.as_deref()), (401, Some("Bearer")));
+    assert_eq!(call(&api, "/health", None).await.1, "fallback");
+    // 未配置 audience 时不校验 aud
+    let token = key.sign(serde_json::json!({
+        "iss": issuer, "aud": "anything", "sub": "7", "exp": now() + 600,
+    }));
+    assert_eq!(call(&api, "/oidc/me", Some(&token)).await.1, "7::false");
+
+    assert_eq!(call(&down, "/oidc/me", Some(&token)).await.0, 503);
+    assert_eq!(call(&down, "/oidc/me", None).await.1, "anonymous");
+
+    server_handle.abort();
+    down_handle.abort();
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_oidc_with_access_requirements() -> anyhow::Result<()> {
+    let key = IdpKey::new("idp-1");
+    let jwks = Arc::new(Mutex::new(JwkSet {
+        keys: vec![key.jwk()],
+    }));
+    let (issuer, _) = start_idp(jwks).await;
+    let config = OidcConfig::new(&issuer);
+    let api = format!("127.0.0.1:{}", get_test_port());
+    let mut server = HttpServer::new(&api);
+    server.configure(move |ctx| {
+        ctx.use_oidc(config.clone());
+        ctx.use_handlers();
+        ctx.use_custom_sync(|_req| Some(HttpResponse::text("fallback")));
+    });
+    let server_handle = tokio::spawn(async move {
+        let _ = server.serve_http().await;
+    });
+    tokio::time::sleep(Duration::from_millis(300)).await;
+
+    let token = |roles: &[&str], scope: &str| {
+        key.sign(serde_json::json!({
+            "iss": issuer, "sub": "user-42", "exp": now() + 600,
+            "roles": roles, "scope": scope,
+        }))
+    };
+    // 角色与 scope 取自 use_oidc 校验通过的令牌
+    let (code, body, _) = call(&api, "/oidc/admin", Some(&token(&["admin"], "orders:read"))).await;
+    assert_eq!((code, &body[..]), (200, "user-42"));
+    let (code, body, _) = call(
+        &api,
+        "/oidc/admin",
+        Some(&token(&["viewer"], "orders:read")),
+    )
+    .await;
+    assert_eq!((code, &body[..]), (403, "Forbidden: requires role admin"));
+    let (code, _, www) = call(&api, "/oidc/admin", Some(&token(&["admin"], "profile"))).await;
+    assert_eq!(code, 403);
+    assert!(www.unwrap().contains("insufficient_scope"));
+    // 未携带令牌时仍要求认证
+    assert_eq!(call(&api, "/oidc/admin", None).await.0, 401);
+
+    server_handle.abort();
+    Ok(())
+}
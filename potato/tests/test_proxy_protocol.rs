@@ -0,0 +1,213 @@
+/// PROXY 协议与可信代理测试
+#[cfg(test)]
+mod proxy_protocol_tests {
+    use potato::utils::cidr::IpCidr;
+    use potato::{HttpRequest, HttpResponse, HttpServer};
+    use std::sync::atomic::{AtomicU16, Ordering};
+    use std::time::Duration;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::time::sleep;
+
+    static PORT_COUNTER: AtomicU16 = AtomicU16::new(34000);
+
+    fn get_test_port() -> u16 {
+        PORT_COUNTER.fetch_add(1, Ordering::Relaxed)
+    }
+
+    #[potato::http_get("/proxy_protocol_addr")]
+    async fn proxy_protocol_addr(req: &mut HttpRequest) -> HttpResponse {
+        let raw = req
+            .client_addr
+            .map(|addr| addr.to_string())
+            .unwrap_or_default();
+        let resolved = req.get_client_addr().await.map(|addr| addr.to_string());
+        HttpResponse::text(format!(
+            "raw={raw} resolved={}",
+            resolved.unwrap_or_default()
+        ))
+    }
+
+    async fn start_server(
+        port: u16,
+        proxy_protocol: bool,
+        trusted: &'static [&'static str],
+    ) -> anyhow::Result<tokio::sync::oneshot::Sender<()>> {
+        let mut server = HttpServer::new(format!("127.0.0.1:{port}"));
+        server.set_proxy_protocol(proxy_protocol);
+        server.configure(move |ctx| {
+            if !trusted.is_empty() {
+                ctx.use_trusted_proxies(trusted.iter()).unwrap();
+            }
+            ctx.use_handlers();
+        });
+        let shutdown = server.shutdown_signal()?;
+        tokio::spawn(async move { server.serve_http().await });
+        sleep(Duration::from_millis(200)).await;
+        Ok(shutdown)
+    }
+
+    async fn send_raw(port: u16, prefix: &[u8], headers: &str) -> anyhow::Result<String> {
+        let mut stream = tokio::net::TcpStream::connect(format!("127.0.0.1:{port}")).await?;
+        let mut data = prefix.to_vec();
+        data.extend_from_slice(
+            format!(
+                "GET /proxy_protocol_addr HTTP/1.1\r\nHost: localhost\r\n{headers}Connection: close\r\n\r\n"
+            )
+            .as_bytes(),
+        );
+        stream.write_all(&data).await?;
+        let mut out = Vec::new();
+        _ = stream.read_to_end(&mut out).await;
+        Ok(String::from_utf8_lossy(&out).to_string())
+    }
+
+    fn v2_header(command: u8, family: u8, addr: &[u8]) -> Vec<u8> {
+        let mut header = b"\r\n\r\n\0\r\nQUIT\n".to_vec();
+        header.push(0x20 | command);
+        header.push(family);
+        header.extend_from_slice(&(addr.len() as u16).to_be_bytes());
+        header.extend_from_slice(addr);
+        header
+    }
+
+    #[tokio::test]
+    async fn test_proxy_protocol_v1_and_v2() -> anyhow::Result<()> {
+        let port = get_test_port();
+        let shutdown = start_server(port, true, &[]).await?;
+
+        let res = send_raw(port, b"PROXY TCP4 203.0.113.7 10.0.0.1 51234 80\r\n", "").await?;
+        assert!(res.contains("raw=203.0.113.7:51234"), "{res}");
+
+        let res = send_raw(port, b"PROXY UNKNOWN\r\n", "").await?;
+        assert!(res.contains("raw=127.0.0.1:"), "{res}");
+
+        let mut addr = vec![0x20, 0x01, 0x0d, 0xb8];
+        addr.extend_from_slice(&[0; 11]);
+        addr.push(0x01);
+        addr.extend_from_slice(&[0; 16]);
+        addr.extend_from_slice(&4711u16.to_be_bytes());
+        addr.extend_from_slice(&443u16.to_be_bytes());
+        let res = send_raw(port, &v2_header(0x1, 0x21, &addr), "").await?;
+        assert!(res.contains("raw=[2001:db8::1]:4711"), "{res}");
+
+        // LOCAL 命令（代理自身的健康检查）保留连接地址
+        let res = send_raw(port, &v2_header(0x0, 0x00, &[]), "").await?;
+        assert!(res.contains("raw=127.0.0.1:"), "{res}");
+
+        // 缺少协议头时直接断开
+        let res = send_raw(port, b"", "").await?;
+        assert!(res.is_empty(), "{res}");
+
+        _ = shutdown.send(());
+        Ok(())
+    }
+
+    #[cfg(feature = "tls")]
+    #[tokio::test]
+    async fn test_proxy_protocol_before_tls() -> anyhow::Result<()> {
+        use tokio_rustls::rustls;
+        use tokio_rustls::rustls::pki_types::ServerName;
+
+        let cert = rcgen::generate_simple_self_signed(vec!["localhost".to_string()])?;
+        let dir = std::env::temp_dir().join(format!("potato_proxy_protocol_{}", get_test_port()));
+        std::fs::create_dir_all(&dir)?;
+        let (cert_file, key_file) = (dir.join("cert.pem"), dir.join("key.pem"));
+        std::fs::write(&cert_file, cert.cert.pem())?;
+        std::fs::write(&key_file, cert.signing_key.serialize_pem())?;
+
+        let port = get_test_port();
+        let mut server = HttpServer::new("");
+        server.set_proxy_protocol(true).listen_https(
+            format!("127.0.0.1:{port}"),
+            cert_file.to_string_lossy(),
+            key_file.to_string_lossy(),
+        );
+        server.configure(|ctx| ctx.use_handlers());
+        let shutdown = server.shutdown_signal()?;
+        tokio::spawn(async move { server.serve().await });
+        sleep(Duration::from_millis(200)).await;
+
+        let _ = rustls::crypto::CryptoProvider::install_default(
+            rustls::crypto::ring::default_provider(),
+        );
+        let mut roots = rustls::RootCertStore::empty();
+        roots.add(cert.cert.der().clone())?;
+        let mut config = rustls::ClientConfig::builder()
+            .with_root_certificates(roots)
+            .with_no_client_auth();
+        config.alpn_protocols = vec![b"http/1.1".to_vec()];
+        let connector = tokio_rustls::TlsConnector::from(std::sync::Arc::new(config));
+        let mut tcp = tokio::net::TcpStream::connect(format!("127.0.0.1:{port}")).await?;
+        let mut addr = vec![198, 51, 100, 9, 10, 0, 0, 1];
+        addr.extend_from_slice(&40000u16.to_be_bytes());
+        addr.extend_from_slice(&443u16.to_be_bytes());
+        tcp.write_all(&v2_header(0x1, 0x11, &addr)).await?;
+        let mut stream = connector
+            .connect(ServerName::try_from("localhost")?.to_owned(), tcp)
+            .await?;
+        stream
+            .write_all(b"GET /proxy_protocol_addr HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n")
+            .await?;
+        let mut out = Vec::new();
+        _ = stream.read_to_end(&mut out).await;
+        let res = String::from_utf8_lossy(&out);
+        assert!(res.contains("raw=198.51.100.9:40000"), "{res}");
+
+        _ = shutdown.send(());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_trusted_proxies_forwarded_headers() -> anyhow::Result<()> {
+        let port = get_test_port();
+        let shutdown = start_server(port, false, &["127.0.0.1", "10.0.0.0/8"]).await?;
+
+        let res = send_raw(port, b"", "X-Forwarded-For: 198.51.100.1, 10.1.1.1\r\n").await?;
+        assert!(res.contains("resolved=198.51.100.1:0"), "{res}");
+        assert!(res.contains("raw=127.0.0.1:"), "{res}");
+
+        let res = send_raw(
+            port,
+            b"",
+            "X-Forwarded-For: 198.51.100.1, 203.0.113.5, 10.1.1.1\r\n",
+        )
+        .await?;
+        assert!(res.contains("resolved=203.0.113.5:0"), "{res}");
+
+        let res = send_raw(
+            port,
+            b"",
+            "Forwarded: for=192.0.2.60;proto=https, for=\"[2001:db8::7]:4711\"\r\n",
+        )
+        .await?;
+        assert!(res.contains("resolved=[2001:db8::7]:4711"), "{res}");
+
+        _ = shutdown.send(());
+
+        // 连接不来自可信代理时忽略转发头
+        let port = get_test_port();
+        let shutdown = start_server(port, false, &["10.0.0.0/8"]).await?;
+        let res = send_raw(port, b"", "X-Forwarded-For: 198.51.100.1\r\n").await?;
+        assert!(res.contains("resolved=127.0.0.1:"), "{res}");
+        _ = shutdown.send(());
+        Ok(())
+    }
+
+    #[test]
+    fn test_ip_cidr_contains() -> anyhow::Result<()> {
+        let cidr: IpCidr = "10.0.0.0/8".parse()?;
+        assert!(cidr.contains(&"10.255.0.1".parse()?));
+        assert!(!cidr.contains(&"11.0.0.1".parse()?));
+        assert!(cidr.contains(&"::ffff:10.1.2.3".parse()?));
+
+        let cidr: IpCidr = "2001:db8::/32".parse()?;
+        assert!(cidr.contains(&"2001:db8:1::1".parse()?));
+        assert!(!cidr.contains(&"2001:db9::1".parse()?));
+
+        let cidr: IpCidr = "192.0.2.1".parse()?;
+        assert_eq!(cidr.to_string(), "192.0.2.1/32");
+        assert!("10.0.0.0/33".parse::<IpCidr>().is_err());
+        assert!("not-an-ip".parse::<IpCidr>().is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,338 @@
+/// 请求速率限制中间件与 rate_limit 标注测试
+#[cfg(feature = "auth")]
+use potato::ApiKeyAuth;
+use potato::{
+    HttpRequest, HttpResponse, HttpServer, MemoryRateLimitStore, RateLimit, RateLimitState,
+    RateLimitStore,
+};
+use std::sync::atomic::{AtomicU16, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::time::sleep;
+
+static PORT_COUNTER: AtomicU16 = AtomicU16::new(43000);
+
+fn get_test_port() -> u16 {
+    PORT_COUNTER.fetch_add(1, Ordering::Relaxed)
+}
+
+fn tenant_key(req: &HttpRequest) -> Option<String> {
+    req.get_header("X-Tenant").map(str::to_string)
+}
+
+fn fallback(_req: &mut HttpRequest) -> Option<HttpResponse> {
+    Some(HttpResponse::text("ok"))
+}
+
+#[potato::http_get("/limited/route")]
+#[potato::rate_limit(2, per = "1m")]
+async fn limited_route() -> HttpResponse {
+    HttpResponse::text("route")
+}
+
+#[potato::http_get("/limited/tenant")]
+#[potato::rate_limit(1, per = "1h", key = tenant_key)]
+async fn limited_tenant() -> HttpResponse {
+    HttpResponse::text("tenant")
+}
+
+#[tokio::test]
+async fn test_rate_limit_per_ip() -> anyhow::Result<()> {
+    let server_addr = format!("127.0.0.1:{}", get_test_port());
+    let mut server = HttpServer::new(&server_addr);
+    server.configure(|ctx| {
+        ctx.use_rate_limit(RateLimit::new(3, Duration::from_secs(60)));
+        ctx.use_custom_sync(fallback);
+    });
+    let server_handle = tokio::spawn(async move {
+        let _ = server.serve_http().await;
+    });
+    sleep(Duration::from_millis(300)).await;
+
+    let url = format!("http://{server_addr}/");
+    for left in ["2", "1", "0"] {
+        let res = potato::get(&url, vec![]).await?;
+        assert_eq!(res.http_code, 200);
+        assert_eq!(res.get_header("RateLimit-Remaining"), Some(left));
+        assert_eq!(res.get_header("Retry-After"), None);
+    }
+    let res = potato::get(&url, vec![]).await?;
+    assert_eq!(res.http_code, 429);
+    assert_eq!(res.get_header("Retry-After"), Some("20"));
+    assert_eq!(res.get_header("RateLimit-Limit"), Some("3"));
+    assert_eq!(res.get_header("RateLimit-Remaining"), Some("0"));
+    assert_eq!(res.get_header("RateLimit-Reset"), Some("60"));
+    assert_eq!(res.get_header("RateLimit-Policy"), Some("3;w=60"));
+    server_handle.abort();
+
+    // 令牌按速率补充
+    let server_addr = format!("127.0.0.1:{}", get_test_port());
+    let mut server = HttpServer::new(&server_addr);
+    server.configure(|ctx| {
+        ctx.use_rate_limit(RateLimit::new(2, Duration::from_secs(1)));
+        ctx.use_custom_sync(fallback);
+    });
+    let server_handle = tokio::spawn(async move {
+        let _ = server.serve_http().await;
+    });
+    sleep(Duration::from_millis(300)).await;
+
+    let url = format!("http://{server_addr}/");
+    assert_eq!(potato::get(&url, vec![]).await?.http_code, 200);
+    assert_eq!(potato::get(&url, vec![]).await?.http_code, 200);
+    let res = potato::get(&url, vec![]).await?;
+    assert_eq!(res.http_code, 429);
+    assert_eq!(res.get_header("RateLimit-Remaining"), Some("0"));
+    assert_eq!(res.get_header("Retry-After"), Some("1"));
+    sleep(Duration::from_millis(600)).await;
+    let res = potato::get(&url, vec![]).await?;
+    assert_eq!(res.http_code, 200);
+    assert_eq!(res.get_header("RateLimit-Remaining"), Some("0"));
+    assert_eq!(res.get_header("Retry-After"), None);
+
+    server_handle.abort();
+    Ok(())
+}
+
+#[cfg(feature = "auth")]
+#[tokio::test]
+async fn test_rate_limit_per_subject() -> anyhow::Result<()> {
+    let server_addr = format!("127.0.0.1:{}", get_test_port());
+    let mut server = HttpServer::new(&server_addr);
+    server.configure(|ctx| {
+        ctx.use_auth(
+            ApiKeyAuth::new("X-API-Key")
+                .with_key("alice", "key-a")
+                .with_key("bob", "key-b"),
+        );
+        ctx.use_rate_limit(RateLimit::new(1, Duration::from_secs(60)).per_subject());
+        ctx.use_custom_sync(fallback);
+    });
+    let server_handle = tokio::spawn(async move {
+        let _ = server.serve_http().await;
+    });
+    sleep(Duration::from_millis(300)).await;
+
+    let url = format!("http://{server_addr}/");
+    let res = potato::get!(&url, "X-API-Key" = "key-a").await?;
+    assert_eq!(res.http_code, 200);
+    let res = potato::get!(&url, "X-API-Key" = "key-a").await?;
+    assert_eq!(res.http_code, 429);
+    // 不同调用方使用各自的令牌桶
+    let res = potato::get!(&url, "X-API-Key" = "key-b").await?;
+    assert_eq!(res.http_code, 200);
+
+    server_handle.abort();
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_rate_limit_custom_key() -> anyhow::Result<()> {
+    let server_addr = format!("127.0.0.1:{}", get_test_port());
+    let mut server = HttpServer::new(&server_addr);
+    server.configure(|ctx| {
+        ctx.use_rate_limit(RateLimit::new(1, Duration::from_secs(60)).with_key(tenant_key));
+        ctx.use_custom_sync(fallback);
+    });
+    let server_handle = tokio::spawn(async move {
+        let _ = server.serve_http().await;
+    });
+    sleep(Duration::from_millis(300)).await;
+
+    let url = format!("http://{server_addr}/");
+    let res = potato::get!(&url, "X-Tenant" = "acme").await?;
+    assert_eq!(res.http_code, 200);
+    let res = potato::get!(&url, "X-Tenant" = "acme").await?;
+    assert_eq!(res.http_code, 429);
+    let res = potato::get!(&url, "X-Tenant" = "globex").await?;
+    assert_eq!(res.http_code, 200);
+    // 自定义键返回 None 的请求不受限制
+    for _ in 0..3 {
+        let res = potato::get(&url, vec![]).await?;
+        assert_eq!(res.http_code, 200);
+        assert_eq!(res.get_header("RateLimit-Remaining"), None);
+        assert_eq!(res.get_header("Retry-After"), None);
+    }
+
+    server_handle.abort();
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_rate_limit_annotation() -> anyhow::Result<()> {
+    let server_addr = format!("127.0.0.1:{}", get_test_port());
+    let mut server = HttpServer::new(&server_addr);
+    server.configure(|ctx| {
+        ctx.use_rate_limit(RateLimit::new(10, Duration::from_secs(60)));
+        ctx.use_handlers();
+        ctx.use_custom_sync(fallback);
+    });
+    let server_handle = tokio::spawn(async move {
+        let _ = server.serve_http().await;
+    });
+    sleep(Duration::from_millis(300)).await;
+
+    // 全局与路由限流同时生效时，响应头取剩余额度较少的一项
+    let url = format!("http://{server_addr}/limited/route");
+    let res = potato::get(&url, vec![]).await?;
+    assert_eq!(res.http_code, 200);
+    assert_eq!(res.get_header("RateLimit-Remaining"), Some("1"));
+    let res = potato::get(&format!("http://{server_addr}/other"), vec![]).await?;
+    assert_eq!(res.get_header("RateLimit-Remaining"), Some("8"));
+    let res = potato::get(&url, vec![]).await?;
+    assert_eq!(res.http_code, 200);
+    assert_eq!(res.get_header("RateLimit-Remaining"), Some("0"));
+    let res = potato::get(&url, vec![]).await?;
+    assert_eq!(res.http_code, 429);
+    assert_eq!(res.get_header("RateLimit-Policy"), Some("2;w=60"));
+    assert_eq!(res.get_header("Retry-After"), Some("30"));
+
+    let url = format!("http://{server_addr}/limited/tenant");
+    let res = potato::get!(&url, "X-Tenant" = "acme").await?;
+    assert_eq!(res.http_code, 200);
+    let res = potato::get!(&url, "X-Tenant" = "acme").await?;
+    assert_eq!(res.http_code, 429);
+    let res = potato::get!(&url, "X-Tenant" = "globex").await?;
+    assert_eq!(res.http_code, 200);
+
+    server_handle.abort();
+    Ok(())
+}
+
+/// 统计调用次数的存储，`fail` 为真时模拟存储故障
+struct CountingStore {
+    inner: MemoryRateLimitStore,
+    calls: Arc<AtomicUsize>,
+    fail: bool,
+}
+
+#[async_trait::async_trait]
+impl RateLimitStore for CountingStore {
+    async fn take(&self, key: &str, limit: u32, per: Duration) -> anyhow::Result<RateLimitState> {
+        self.calls.fetch_add(1, Ordering::Relaxed);
+        if self.fail {
+            anyhow::bail!("store unavailable");
+        }
+        assert!(key.starts_with("shared:ip:127.0.0.1"));
+        self.inner.take(key, limit, per).await
+    }
+}
+
+#[tokio::test]
+async fn test_rate_limit_custom_store() -> anyhow::Result<()> {
+    let calls = Arc::new(AtomicUsize::new(0));
+    let store_calls = calls.clone();
+    let server_addr = format!("127.0.0.1:{}", get_test_port());
+    let mut server = HttpServer::new(&server_addr);
+    server.configure(move |ctx| {
+        ctx.use_rate_limit(
+            RateLimit::new(1, Duration::from_secs(60))
+                .with_name("shared")
+                .with_store(CountingStore {
+                    inner: MemoryRateLimitStore::new(),
+                    calls: store_calls.clone(),
+                    fail: false,
+                }),
+        );
+        ctx.use_custom_sync(fallback);
+    });
+    let server_handle = tokio::spawn(async move {
+        let _ = server.serve_http().await;
+    });
+    sleep(Duration::from_millis(300)).await;
+
+    let url = format!("http://{server_addr}/");
+    assert_eq!(potato::get(&url, vec![]).await?.http_code, 200);
+    assert_eq!(potato::get(&url, vec![]).await?.http_code, 429);
+    assert_eq!(calls.load(Ordering::Relaxed), 2);
+    server_handle.abort();
+
+    // 存储故障时放行
+    let calls = Arc::new(AtomicUsize::new(0));
+    let store_calls = calls.clone();
+    let server_addr = format!("127.0.0.1:{}", get_test_port());
+    let mut server = HttpServer::new(&server_addr);
+    server.configure(move |ctx| {
+        ctx.use_rate_limit(
+            RateLimit::new(1, Duration::from_secs(60)).with_store(CountingStore {
+                inner: MemoryRateLimitStore::new(),
+                calls: store_calls.clone(),
+                fail: true,
+            }),
+        );
+        ctx.use_custom_sync(fallback);
+    });
+    let server_handle = tokio::spawn(async move {
+        let _ = server.serve_http().await;
+    });
+    sleep(Duration::from_millis(300)).await;
+
+    let url = format!("http://{server_addr}/");
+    for _ in 0..3 {
+        let res = potato::get(&url, vec![]).await?;
+        assert_eq!(res.http_code, 200);
+        assert_eq!(res.get_header("RateLimit-Remaining"), None);
+        assert_eq!(res.get_header("Retry-After"), None);
+    }
+    assert_eq!(calls.load(Ordering::Relaxed), 3);
+
+    server_handle.abort();
+    Ok(())
+}
+
+/// 记录收到的限流键
+#[cfg(unix)]
+struct KeyStore {
+    inner: MemoryRateLimitStore,
+    keys: Arc<std::sync::Mutex<Vec<String>>>,
+}
+
+#[cfg(unix)]
+#[async_trait::async_trait]
+impl RateLimitStore for KeyStore {
+    async fn take(&self, key: &str, limit: u32, per: Duration) -> anyhow::Result<RateLimitState> {
+        self.keys.lock().unwrap().push(key.to_string());
+        self.inner.take(key, limit, per).await
+    }
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn test_rate_limit_unix_peer() -> anyhow::Result<()> {
+    let dir = std::env::temp_dir().join(format!("potato_rate_limit_test_{}", std::process::id()));
+    std::fs::create_dir_all(&dir)?;
+    let path = dir.join("app.sock");
+    let keys = Arc::new(std::sync::Mutex::new(vec![]));
+    let store_keys = keys.clone();
+    let mut server = HttpServer::new(format!("unix:{}", path.display()));
+    server.configure(move |ctx| {
+        ctx.use_rate_limit(
+            RateLimit::new(1, Duration::from_secs(60)).with_store(KeyStore {
+                inner: MemoryRateLimitStore::new(),
+                keys: store_keys.clone(),
+            }),
+        );
+        ctx.use_custom_sync(fallback);
+    });
+    let shutdown = server.shutdown_signal()?;
+    let server_handle = tokio::spawn(async move { server.serve_http().await });
+    sleep(Duration::from_millis(300)).await;
+
+    // Unix 域套接字连接不与 127.0.0.1 的客户端共用令牌桶
+    let mut session = potato::Session::new_unix(&path);
+    assert_eq!(
+        session.get("http://localhost/", vec![]).await?.http_code,
+        200
+    );
+    assert_eq!(
+        session.get("http://localhost/", vec![]).await?.http_code,
+        429
+    );
+    let keys = keys.lock().unwrap().clone();
+    assert_eq!(keys.len(), 2);
+    assert!(keys.iter().all(|key| key.ends_with(":unix")));
+
+    _ = shutdown.send(());
+    server_handle.await??;
+    Ok(())
+}
@@ -0,0 +1,84 @@
+/// 可重复响应头与 SessionCache cookies 下发测试
+use potato::{HttpResponse, HttpServer, SessionCache};
+use std::time::Duration;
+use tokio::time::sleep;
+
+#[test]
+fn test_append_header_lines() {
+    let mut res = HttpResponse::text("ok");
+    res.append_header("Set-Cookie".into(), "a=1".into());
+    res.append_header("set-cookie".into(), "b=2".into());
+    res.add_header("X-Single".into(), "1".into());
+    // 首个值保留在 headers 中，之后的值不会合并进去
+    assert_eq!(
+        res.headers.get("Set-Cookie").map(|v| v.as_ref()),
+        Some("a=1")
+    );
+    let mut cookies: Vec<&str> = res
+        .header_lines()
+        .filter(|(k, _)| k.eq_ignore_ascii_case("Set-Cookie"))
+        .map(|(_, v)| v)
+        .collect();
+    cookies.sort();
+    assert_eq!(cookies, ["a=1", "b=2"]);
+    assert_eq!(
+        res.header_lines().filter(|(k, _)| *k == "X-Single").count(),
+        1
+    );
+}
+
+fn set_cookies(res: &HttpResponse) -> Vec<String> {
+    let mut cookies: Vec<String> = res
+        .header_lines()
+        .filter(|(k, _)| k.eq_ignore_ascii_case("Set-Cookie"))
+        .map(|(_, v)| v.split(';').next().unwrap_or_default().to_string())
+        .collect();
+    cookies.sort();
+    cookies
+}
+
+#[tokio::test]
+async fn test_session_cookies_applied_by_all_handler_kinds() -> anyhow::Result<()> {
+    #[potato::http_get("/headers/async")]
+    async fn async_handler(cache: &mut SessionCache) -> HttpResponse {
+        cache.set_cookie("a", "1");
+        cache.set_cookie("b", "2");
+        HttpResponse::text("async")
+    }
+
+    #[potato::http_get("/headers/limited")]
+    #[potato::max_concurrency(2)]
+    async fn limited_handler(cache: &mut SessionCache) -> HttpResponse {
+        cache.set_cookie("c", "3");
+        HttpResponse::text("limited")
+    }
+
+    SessionCache::set_jwt_secret(b"test-secret-key").await;
+    // 会话缓存跨请求保留，每个处理函数使用各自的用户会话
+    let tokens = [
+        SessionCache::generate_token(30001, Duration::from_secs(3600)).await?,
+        SessionCache::generate_token(30002, Duration::from_secs(3600)).await?,
+    ];
+
+    let server_addr = "127.0.0.1:18300";
+    let mut server = HttpServer::new(server_addr);
+    let server_handle = tokio::spawn(async move {
+        let _ = server.serve_http().await;
+    });
+    sleep(Duration::from_millis(300)).await;
+
+    let get = |path: &str, token: &str| {
+        let url = format!("http://{server_addr}{path}");
+        let auth = format!("Bearer {token}");
+        async move { potato::get!(&url, Authorization = auth).await }
+    };
+
+    // 多个 cookie 各占一行 Set-Cookie
+    let res = get("/headers/async", &tokens[0]).await?;
+    assert_eq!(set_cookies(&res), ["a=1", "b=2"]);
+    let res = get("/headers/limited", &tokens[1]).await?;
+    assert_eq!(set_cookies(&res), ["c=3"]);
+
+    server_handle.abort();
+    Ok(())
+}
@@ -0,0 +1,225 @@
+/// 集成测试：验证 use_security_headers 中间件、CSP nonce 与 #[security_headers(...)] 标注
+use std::sync::atomic::{AtomicU16, Ordering};
+use std::time::Duration;
+use tokio::time::sleep;
+
+static PORT_COUNTER: AtomicU16 = AtomicU16::new(42100);
+
+fn get_test_port() -> u16 {
+    PORT_COUNTER.fetch_add(1, Ordering::Relaxed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use potato::{HttpRequest, HttpResponse, HttpServer, SecurityHeaders};
+
+    #[potato::http_get("/sec/page")]
+    async fn page(req: &mut HttpRequest) -> HttpResponse {
+        let nonce = req.csp_nonce().map(|n| n.to_string()).unwrap_or_default();
+        HttpResponse::html(format!("<script nonce=\"{nonce}\">run()</script>"))
+    }
+
+    #[potato::http_get("/sec/own-header")]
+    async fn own_header() -> HttpResponse {
+        let mut res = HttpResponse::text("ok");
+        res.add_header("X-Frame-Options".into(), "SAMEORIGIN".into());
+        res
+    }
+
+    #[potato::http_get("/sec/off")]
+    #[potato::security_headers(off)]
+    async fn off() -> HttpResponse {
+        HttpResponse::text("off")
+    }
+
+    #[potato::http_get("/sec/embed")]
+    #[potato::security_headers(frame_options = "", csp = "frame-ancestors https://partner.example")]
+    async fn embed() -> HttpResponse {
+        HttpResponse::text("embed")
+    }
+
+    #[potato::http_get("/sec/basic")]
+    #[security_headers(basic, csp_report_only = true, csp = "default-src 'self'")]
+    async fn basic() -> HttpResponse {
+        HttpResponse::text("basic")
+    }
+
+    async fn start(policy: SecurityHeaders) -> (String, tokio::task::JoinHandle<()>) {
+        let server_addr = format!("127.0.0.1:{}", get_test_port());
+        let mut server = HttpServer::new(&server_addr);
+        server.configure(move |ctx| {
+            ctx.use_security_headers(policy.clone());
+            ctx.use_handlers();
+        });
+        let handle = tokio::spawn(async move {
+            let _ = server.serve_http().await;
+        });
+        sleep(Duration::from_millis(300)).await;
+        (server_addr, handle)
+    }
+
+    #[test]
+    fn test_presets() {
+        assert_eq!(SecurityHeaders::none(), SecurityHeaders::default());
+        let strict = SecurityHeaders::strict();
+        assert!(strict.csp.as_deref().unwrap().contains("'nonce-{nonce}'"));
+        assert_eq!(strict.frame_options.as_deref(), Some("DENY"));
+        let basic = SecurityHeaders::basic();
+        assert!(basic.csp.is_none());
+        assert_eq!(basic.content_type_options.as_deref(), Some("nosniff"));
+
+        let mut policy = SecurityHeaders::none()
+            .with_hsts(Duration::from_secs(600), true, true)
+            .with_csp_report_only("default-src 'self'");
+        assert_eq!(
+            policy.hsts.as_deref(),
+            Some("max-age=600; includeSubDomains; preload")
+        );
+        assert!(policy.csp_report_only);
+        assert!(policy.set("referrer_policy", Some("no-referrer".into())));
+        assert!(!policy.set("unknown", None));
+        assert_eq!(policy.referrer_policy.as_deref(), Some("no-referrer"));
+    }
+
+    #[tokio::test]
+    async fn test_strict_headers_and_nonce() -> anyhow::Result<()> {
+        let (addr, handle) = start(SecurityHeaders::strict()).await;
+
+        let mut res = potato::get(&format!("http://{addr}/sec/page"), vec![]).await?;
+        assert_eq!(res.http_code, 200);
+        let strict = SecurityHeaders::strict();
+        assert_eq!(
+            res.headers
+                .get("Strict-Transport-Security")
+                .map(|v| v.to_string()),
+            strict.hsts
+        );
+        assert_eq!(
+            res.headers.get("X-Content-Type-Options").map(|v| &v[..]),
+            Some("nosniff")
+        );
+        assert_eq!(
+            res.headers.get("X-Frame-Options").map(|v| &v[..]),
+            Some("DENY")
+        );
+        assert_eq!(
+            res.headers
+                .get("Cross-Origin-Embedder-Policy")
+                .map(|v| &v[..]),
+            Some("require-corp")
+        );
+
+        // 处理函数读取的 nonce 与 CSP 中的一致
+        let body = String::from_utf8(res.body.data().await.to_vec())?;
+        let nonce = body
+            .strip_prefix("<script nonce=\"")
+            .and_then(|rest| rest.split('"').next())
+            .unwrap()
+            .to_string();
+        assert_eq!(nonce.len(), 24);
+        let csp = res.headers.get("Content-Security-Policy").unwrap();
+        assert!(csp.contains(&format!("'nonce-{nonce}'")));
+        assert!(!csp.contains("{nonce}"));
+
+        // 每个请求生成新的 nonce
+        let mut res = potato::get(&format!("http://{addr}/sec/page"), vec![]).await?;
+        let body = String::from_utf8(res.body.data().await.to_vec())?;
+        assert!(!body.contains(&nonce));
+
+        // 未匹配路由的响应同样附加
+        let res = potato::get(&format!("http://{addr}/sec/missing"), vec![]).await?;
+        assert_eq!(res.http_code, 404);
+        assert_eq!(
+            res.headers.get("X-Frame-Options").map(|v| &v[..]),
+            Some("DENY")
+        );
+
+        // 处理函数自行设置的头不被覆盖
+        let res = potato::get(&format!("http://{addr}/sec/own-header"), vec![]).await?;
+        assert_eq!(
+            res.headers.get("X-Frame-Options").map(|v| &v[..]),
+            Some("SAMEORIGIN")
+        );
+
+        handle.abort();
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_route_overrides() -> anyhow::Result<()> {
+        let (addr, handle) = start(SecurityHeaders::strict()).await;
+
+        let res = potato::get(&format!("http://{addr}/sec/off"), vec![]).await?;
+        assert!(res.headers.get("X-Content-Type-Options").is_none());
+        assert!(res.headers.get("Content-Security-Policy").is_none());
+
+        // 仅修改指定字段，其余沿用全局策略
+        let res = potato::get(&format!("http://{addr}/sec/embed"), vec![]).await?;
+        assert!(res.headers.get("X-Frame-Options").is_none());
+        assert_eq!(
+            res.headers.get("Content-Security-Policy").map(|v| &v[..]),
+            Some("frame-ancestors https://partner.example")
+        );
+        assert_eq!(
+            res.headers.get("Referrer-Policy").map(|v| &v[..]),
+            Some("no-referrer")
+        );
+
+        // 换用其他预设
+        let res = potato::get(&format!("http://{addr}/sec/basic"), vec![]).await?;
+        assert_eq!(
+            res.headers.get("X-Frame-Options").map(|v| &v[..]),
+            Some("SAMEORIGIN")
+        );
+        assert!(res.headers.get("Content-Security-Policy").is_none());
+        assert_eq!(
+            res.headers
+                .get("Content-Security-Policy-Report-Only")
+                .map(|v| &v[..]),
+            Some("default-src 'self'")
+        );
+
+        handle.abort();
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_without_middleware() -> anyhow::Result<()> {
+        let server_addr = format!("127.0.0.1:{}", get_test_port());
+        let mut server = HttpServer::new(&server_addr);
+        let handle = tokio::spawn(async move {
+            let _ = server.serve_http().await;
+        });
+        sleep(Duration::from_millis(300)).await;
+
+        // 未启用中间件时路由标注在空策略上生效
+        let res = potato::get(&format!("http://{server_addr}/sec/embed"), vec![]).await?;
+        assert_eq!(
+            res.headers.get("Content-Security-Policy").map(|v| &v[..]),
+            Some("frame-ancestors https://partner.example")
+        );
+        assert!(res.headers.get("X-Frame-Options").is_none());
+        assert!(res.headers.get("Referrer-Policy").is_none());
+        let res = potato::get(&format!("http://{server_addr}/sec/basic"), vec![]).await?;
+        assert_eq!(
+            res.headers.get("X-Frame-Options").map(|v| &v[..]),
+            Some("SAMEORIGIN")
+        );
+        assert_eq!(
+            res.headers
+                .get("Content-Security-Policy-Report-Only")
+                .map(|v| &v[..]),
+            Some("default-src 'self'")
+        );
+
+        // 未标注的路由不附加安全头，也不生成 nonce
+        let mut res = potato::get(&format!("http://{server_addr}/sec/page"), vec![]).await?;
+        assert!(res.headers.get("X-Frame-Options").is_none());
+        let body = String::from_utf8(res.body.data().await.to_vec())?;
+        assert_eq!(body, "<script nonce=\"\">run()</script>");
+
+        handle.abort();
+        Ok(())
+    }
+}
@@ -0,0 +1,179 @@
+/// SessionStore 持久化存储测试
+use potato::{FileSessionStore, MemorySessionStore, SessionCache, SessionRecord, SessionStore};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU16, Ordering};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+static PORT_COUNTER: AtomicU16 = AtomicU16::new(42300);
+
+fn get_test_port() -> u16 {
+    PORT_COUNTER.fetch_add(1, Ordering::Relaxed)
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs()
+}
+
+fn temp_dir(name: &str) -> std::path::PathBuf {
+    let dir = std::env::temp_dir().join(format!(
+        "potato_session_store_{name}_{}",
+        std::process::id()
+    ));
+    _ = std::fs::remove_dir_all(&dir);
+    dir
+}
+
+async fn check_store(store: &dyn SessionStore) -> anyhow::Result<()> {
+    let mut values = HashMap::new();
+    values.insert("name".to_string(), serde_json::json!("potato"));
+    let record = SessionRecord {
+        values,
+        expires_at: unix_now() + 60,
+    };
+    assert!(store.get(1).await?.is_none());
+    store.set(1, record.clone()).await?;
+    assert_eq!(store.get(1).await?, Some(record.clone()));
+
+    let expires_at = unix_now() + 120;
+    store.touch(1, expires_at).await?;
+    assert_eq!(store.get(1).await?.unwrap().expires_at, expires_at);
+    // 不存在的会话不报错
+    store.touch(2, unix_now() + 120).await?;
+    store.remove(2).await?;
+
+    // 已过期的会话不再返回，并由 expire 清理
+    store
+        .set(
+            3,
+            SessionRecord {
+                expires_at: unix_now() - 1,
+                ..record.clone()
+            },
+        )
+        .await?;
+    assert!(store.get(3).await?.is_none());
+    store.expire().await?;
+    assert!(store.get(1).await?.is_some());
+
+    store.remove(1).await?;
+    assert!(store.get(1).await?.is_none());
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_memory_and_file_store() -> anyhow::Result<()> {
+    check_store(&MemorySessionStore::new()).await?;
+
+    let dir = temp_dir("basic");
+    let store = FileSessionStore::new(&dir);
+    check_store(&store).await?;
+
+    // 过期与损坏的文件由 expire 清理
+    store
+        .set(
+            5,
+            SessionRecord {
+                values: HashMap::new(),
+                expires_at: unix_now() - 1,
+            },
+        )
+        .await?;
+    std::fs::write(dir.join("6.json"), b"not json")?;
+    store.expire().await?;
+    assert!(!dir.join("5.json").exists());
+    assert!(!dir.join("6.json").exists());
+    _ = std::fs::remove_dir_all(&dir);
+    Ok(())
+}
+
+#[potato::http_get("/session_store/visit")]
+async fn visit(session: &mut SessionCache) -> potato::HttpResponse {
+    let visits = session.get_persistent::<u32>("visits").unwrap_or(0) + 1;
+    _ = session.set_persistent("visits", &visits);
+    potato::HttpResponse::text(visits.to_string())
+}
+
+#[tokio::test]
+async fn test_session_cache_persistence() -> anyhow::Result<()> {
+    #[derive(Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+    struct Profile {
+        name: String,
+        visits: u32,
+    }
+
+    let dir = temp_dir("cache");
+    SessionCache::set_store(FileSessionStore::new(&dir));
+    SessionCache::set_jwt_secret(b"session-store-secret").await;
+    let token = SessionCache::generate_token(4242, Duration::from_secs(3600)).await?;
+
+    let cache = SessionCache::from_token(&token).await?;
+    let profile = Profile {
+        name: "potato".to_string(),
+        visits: 1,
+    };
+    cache.set_persistent("profile", &profile)?;
+    assert_eq!(cache.get_persistent::<Profile>("profile"), Some(profile));
+    // 类型不匹配时返回 None
+    assert!(cache.get_persistent::<u32>("profile").is_none());
+    cache.save().await?;
+
+    // 其他实例读取同一存储
+    let store = FileSessionStore::new(&dir);
+    let mut record = store.get(4242).await?.unwrap();
+    assert_eq!(record.values["profile"]["visits"], 1);
+    assert!(record.expires_at > unix_now() + 3500);
+
+    // 其他实例写入后，本实例的下一个请求读取到最新数据
+    record.values.insert(
+        "profile".to_string(),
+        serde_json::json!({"name": "potato", "visits": 2}),
+    );
+    store.set(4242, record).await?;
+    let cache = SessionCache::from_token(&token).await?;
+    assert_eq!(
+        cache.get_persistent::<Profile>("profile").map(|p| p.visits),
+        Some(2)
+    );
+
+    // 任意类型的值不写入存储
+    cache.set("local_only", 1u8);
+    assert_eq!(
+        cache
+            .remove_persistent::<Profile>("profile")
+            .unwrap()
+            .visits,
+        2
+    );
+    cache.save().await?;
+    assert!(store.get(4242).await?.unwrap().values.is_empty());
+
+    SessionCache::invalidate_async(4242).await?;
+    assert!(store.get(4242).await?.is_none());
+    let cache = SessionCache::from_token(&token).await?;
+    assert!(cache.get::<u8>("local_only").is_none());
+
+    // 处理函数结束后自动保存
+    let token = SessionCache::generate_token(4343, Duration::from_secs(3600)).await?;
+    let server_addr = format!("127.0.0.1:{}", get_test_port());
+    let mut server = potato::HttpServer::new(&server_addr);
+    let handle = tokio::spawn(async move {
+        let _ = server.serve_http().await;
+    });
+    tokio::time::sleep(Duration::from_millis(300)).await;
+    for expected in ["1", "2"] {
+        let mut res = potato::get(
+            &format!("http://{server_addr}/session_store/visit"),
+            vec![potato::Headers::Authorization(format!("Bearer {token}"))],
+        )
+        .await?;
+        assert_eq!(res.body.data().await.to_vec(), expected.as_bytes());
+    }
+    assert_eq!(store.get(4343).await?.unwrap().values["visits"], 2);
+
+    handle.abort();
+    _ = std::fs::remove_dir_all(&dir);
+    Ok(())
+}
@@ -0,0 +1,219 @@
+/// SNI 多证书与热加载测试
+#[cfg(feature = "tls")]
+#[cfg(test)]
+mod sni_cert_tests {
+    use potato::{HttpServer, SniCertResolver};
+    use std::path::{Path, PathBuf};
+    use std::sync::atomic::{AtomicU16, Ordering};
+    use std::sync::Arc;
+    use std::time::Duration;
+    use tokio::time::sleep;
+    use tokio_rustls::rustls;
+    use tokio_rustls::rustls::pki_types::{CertificateDer, ServerName};
+
+    static PORT_COUNTER: AtomicU16 = AtomicU16::new(35000);
+
+    fn get_test_port() -> u16 {
+        PORT_COUNTER.fetch_add(1, Ordering::Relaxed)
+    }
+
+    fn temp_dir() -> PathBuf {
+        let dir = std::env::temp_dir().join(format!(
+            "potato_sni_test_{}_{}",
+            std::process::id(),
+            get_test_port()
+        ));
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    /// 生成自签名证书并写入 `<name>.pem` / `<name>.key`
+    fn write_cert(
+        dir: &Path,
+        file_name: &str,
+        names: &[&str],
+    ) -> anyhow::Result<(PathBuf, PathBuf, CertificateDer<'static>)> {
+        let cert = rcgen::generate_simple_self_signed(
+            names.iter().map(|n| n.to_string()).collect::<Vec<_>>(),
+        )?;
+        let cert_file = dir.join(format!("{file_name}.pem"));
+        let key_file = dir.join(format!("{file_name}.key"));
+        std::fs::write(&cert_file, cert.cert.pem())?;
+        std::fs::write(&key_file, cert.signing_key.serialize_pem())?;
+        Ok((cert_file, key_file, cert.cert.der().clone()))
+    }
+
+    /// 握手并返回服务端出示的证书（不校验证书）
+    async fn peer_cert(port: u16, sni: &str) -> anyhow::Result<CertificateDer<'static>> {
+        #[derive(Debug)]
+        struct AcceptAny(Arc<rustls::crypto::CryptoProvider>);
+        impl rustls::client::danger::ServerCertVerifier for AcceptAny {
+            fn verify_server_cert(
+                &self,
+                _: &CertificateDer<'_>,
+                _: &[CertificateDer<'_>],
+                _: &ServerName<'_>,
+                _: &[u8],
+                _: rustls::pki_types::UnixTime,
+            ) -> Result<rustls::client::danger::ServerCertVerified, rustls::Error> {
+                Ok(rustls::client::danger::ServerCertVerified::assertion())
+            }
+            fn verify_tls12_signature(
+                &self,
+                message: &[u8],
+                cert: &CertificateDer<'_>,
+                dss: &rustls::DigitallySignedStruct,
+            ) -> Result<rustls::client::danger::HandshakeSignatureValid, rustls::Error>
+            {
+                rustls::crypto::verify_tls12_signature(
+                    message,
+                    cert,
+                    dss,
+                    &self.0.signature_verification_algorithms,
+                )
+            }
+            fn verify_tls13_signature(
+                &self,
+                message: &[u8],
+                cert: &CertificateDer<'_>,
+                dss: &rustls::DigitallySignedStruct,
+            ) -> Result<rustls::client::danger::HandshakeSignatureValid, rustls::Error>
+            {
+                rustls::crypto::verify_tls13_signature(
+                    message,
+                    cert,
+                    dss,
+                    &self.0.signature_verification_algorithms,
+                )
+            }
+            fn supported_verify_schemes(&self) -> Vec<rustls::SignatureScheme> {
+                self.0.signature_verification_algorithms.supported_schemes()
+            }
+        }
+
+        let provider = Arc::new(rustls::crypto::ring::default_provider());
+        let mut config = rustls::ClientConfig::builder_with_provider(Arc::clone(&provider))
+            .with_safe_default_protocol_versions()?
+            .dangerous()
+            .with_custom_certificate_verifier(Arc::new(AcceptAny(provider)))
+            .with_no_client_auth();
+        config.alpn_protocols = vec![b"h2".to_vec(), b"http/1.1".to_vec()];
+        let connector = tokio_rustls::TlsConnector::from(Arc::new(config));
+        let stream = tokio::net::TcpStream::connect(format!("127.0.0.1:{port}")).await?;
+        let stream = connector
+            .connect(ServerName::try_from(sni.to_string())?, stream)
+            .await?;
+        let certs = stream
+            .get_ref()
+            .1
+            .peer_certificates()
+            .ok_or_else(|| anyhow::anyhow!("no peer certificate"))?;
+        Ok(certs[0].clone().into_owned())
+    }
+
+    #[tokio::test]
+    async fn test_sni_selects_certificate() -> anyhow::Result<()> {
+        let dir = temp_dir();
+        let (a_cert, a_key, a_der) = write_cert(&dir, "a", &["a.test"])?;
+        let (w_cert, w_key, w_der) = write_cert(&dir, "wild", &["*.wild.test"])?;
+        let (d_cert, d_key, d_der) = write_cert(&dir, "default", &["default.test"])?;
+
+        let certs = SniCertResolver::new();
+        certs.add(["a.test", "www.a.test"], &a_cert, &a_key)?;
+        certs.add(["*.wild.test"], &w_cert, &w_key)?;
+        certs.set_default(&d_cert, &d_key)?;
+        assert!(certs.add(["a.*.test"], &a_cert, &a_key).is_err());
+
+        let port = get_test_port();
+        let mut server = HttpServer::new(format!("127.0.0.1:{port}"));
+        server.set_cert_resolver(certs);
+        let shutdown = server.shutdown_signal()?;
+        tokio::spawn(async move { server.serve_https("", "").await });
+        sleep(Duration::from_millis(200)).await;
+
+        assert_eq!(peer_cert(port, "a.test").await?, a_der);
+        assert_eq!(peer_cert(port, "WWW.A.TEST").await?, a_der);
+        assert_eq!(peer_cert(port, "x.wild.test").await?, w_der);
+        // 通配符只匹配一级子域名
+        assert_eq!(peer_cert(port, "y.x.wild.test").await?, d_der);
+        assert_eq!(peer_cert(port, "wild.test").await?, d_der);
+        assert_eq!(peer_cert(port, "other.test").await?, d_der);
+
+        _ = shutdown.send(());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_listener_cert_is_fallback() -> anyhow::Result<()> {
+        let dir = temp_dir();
+        let (a_cert, a_key, a_der) = write_cert(&dir, "a", &["a.test"])?;
+        let (f_cert, f_key, f_der) = write_cert(&dir, "fallback", &["fallback.test"])?;
+
+        let certs = SniCertResolver::new();
+        certs.add(["a.test"], &a_cert, &a_key)?;
+
+        let port = get_test_port();
+        let mut server = HttpServer::new("");
+        server.set_cert_resolver(certs).listen_https(
+            format!("127.0.0.1:{port}"),
+            f_cert.to_string_lossy(),
+            f_key.to_string_lossy(),
+        );
+        let shutdown = server.shutdown_signal()?;
+        tokio::spawn(async move { server.serve().await });
+        sleep(Duration::from_millis(200)).await;
+
+        assert_eq!(peer_cert(port, "a.test").await?, a_der);
+        assert_eq!(peer_cert(port, "b.test").await?, f_der);
+
+        _ = shutdown.send(());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_reload_and_watch() -> anyhow::Result<()> {
+        let dir = temp_dir();
+        let (a_cert, a_key, a_der) = write_cert(&dir, "a", &["a.test"])?;
+
+        let certs = SniCertResolver::new();
+        certs.add(["a.test"], &a_cert, &a_key)?;
+
+        let port = get_test_port();
+        let mut server = HttpServer::new(format!("127.0.0.1:{port}"));
+        server.set_cert_resolver(certs.clone());
+        let shutdown = server.shutdown_signal()?;
+        #[cfg(feature = "http2")]
+        tokio::spawn(async move { server.serve_http2("", "").await });
+        #[cfg(not(feature = "http2"))]
+        tokio::spawn(async move { server.serve_https("", "").await });
+        sleep(Duration::from_millis(200)).await;
+        assert_eq!(peer_cert(port, "a.test").await?, a_der);
+
+        // 手动 reload
+        let (_, _, a2_der) = write_cert(&dir, "a", &["a.test"])?;
+        assert_eq!(peer_cert(port, "a.test").await?, a_der);
+        certs.reload()?;
+        assert_eq!(peer_cert(port, "a.test").await?, a2_der);
+
+        // 加载失败时保留旧证书
+        std::fs::write(&a_cert, "broken")?;
+        assert!(certs.reload().is_err());
+        assert_eq!(peer_cert(port, "a.test").await?, a2_der);
+
+        // 监听文件变化自动 reload
+        certs.watch(Duration::from_millis(50));
+        let (_, _, a3_der) = write_cert(&dir, "a", &["a.test"])?;
+        let mut reloaded = false;
+        for _ in 0..40 {
+            sleep(Duration::from_millis(50)).await;
+            if peer_cert(port, "a.test").await? == a3_der {
+                reloaded = true;
+                break;
+            }
+        }
+        assert!(reloaded);
+
+        _ = shutdown.send(());
+        Ok(())
+    }
+}
@@ -0,0 +1,239 @@
+/// 客户端 TLS 配置测试：私有 CA、客户端证书、证书固定与跳过校验
+#[cfg(feature = "tls")]
+#[cfg(test)]
+mod tls_client_config_tests {
+    use potato::{
+        ClientAuth, HttpRequest, HttpServer, Session, TlsClientConfig, Websocket, WsFrame,
+    };
+    use rcgen::{
+        BasicConstraints, CertificateParams, DnType, IsCa, Issuer, KeyPair, KeyUsagePurpose,
+        SanType,
+    };
+    use std::path::PathBuf;
+    use std::sync::atomic::{AtomicU16, Ordering};
+    use std::time::Duration;
+    use tokio::time::sleep;
+
+    static PORT_COUNTER: AtomicU16 = AtomicU16::new(37000);
+
+    fn get_test_port() -> u16 {
+        PORT_COUNTER.fetch_add(1, Ordering::Relaxed)
+    }
+
+    #[potato::http_get("/tls_client_hello")]
+    async fn tls_client_hello(req: &mut HttpRequest) -> String {
+        match req.client_cert() {
+            Some(cert) => format!("hello {}", cert.common_name.clone().unwrap_or_default()),
+            None => "hello".to_string(),
+        }
+    }
+
+    #[potato::http_get("/tls_client_ws")]
+    async fn tls_client_ws(req: &mut HttpRequest) -> anyhow::Result<()> {
+        let mut ws = req.upgrade_websocket().await?;
+        loop {
+            if let WsFrame::Text(text) = ws.recv().await? {
+                ws.send_text(&text).await?;
+            }
+        }
+    }
+
+    /// 私有 CA 及其签发的服务端、客户端证书
+    struct TestPki {
+        dir: PathBuf,
+        server_der: Vec<u8>,
+    }
+
+    impl TestPki {
+        fn new() -> anyhow::Result<Self> {
+            let dir = std::env::temp_dir().join(format!(
+                "potato_tls_client_test_{}_{}",
+                std::process::id(),
+                get_test_port()
+            ));
+            std::fs::create_dir_all(&dir)?;
+
+            let mut ca_params = CertificateParams::new(vec![])?;
+            ca_params
+                .distinguished_name
+                .push(DnType::CommonName, "potato private ca");
+            ca_params.is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
+            ca_params.key_usages = vec![KeyUsagePurpose::KeyCertSign];
+            let ca_key = KeyPair::generate()?;
+            let ca_cert = ca_params.self_signed(&ca_key)?;
+            std::fs::write(dir.join("ca.pem"), ca_cert.pem())?;
+            let issuer = Issuer::from_params(&ca_params, &ca_key);
+
+            let mut server_der = vec![];
+            for (name, cn) in [("server", "localhost"), ("client", "svc-a")] {
+                let mut params = CertificateParams::default();
+                params.distinguished_name.push(DnType::CommonName, cn);
+                params.subject_alt_names = vec![SanType::DnsName(cn.try_into()?)];
+                let key = KeyPair::generate()?;
+                let cert = params.signed_by(&key, &issuer)?;
+                std::fs::write(dir.join(format!("{name}.pem")), cert.pem())?;
+                std::fs::write(dir.join(format!("{name}.key")), key.serialize_pem())?;
+                if name == "server" {
+                    server_der = cert.der().to_vec();
+                }
+            }
+            Ok(Self { dir, server_der })
+        }
+
+        fn path(&self, name: &str) -> String {
+            self.dir.join(name).to_string_lossy().to_string()
+        }
+    }
+
+    async fn start_server(
+        pki: &TestPki,
+        client_auth: Option<ClientAuth>,
+    ) -> anyhow::Result<(u16, tokio::sync::oneshot::Sender<()>)> {
+        let port = get_test_port();
+        let mut server = HttpServer::new(format!("127.0.0.1:{port}"));
+        if let Some(client_auth) = client_auth {
+            server.set_client_auth(client_auth);
+        }
+        server.configure(|ctx| ctx.use_handlers());
+        let shutdown = server.shutdown_signal()?;
+        let (cert_file, key_file) = (pki.path("server.pem"), pki.path("server.key"));
+        tokio::spawn(async move {
+            #[cfg(feature = "http2")]
+            return server.serve_http2(&cert_file, &key_file).await;
+            #[cfg(not(feature = "http2"))]
+            server.serve_https(&cert_file, &key_file).await
+        });
+        sleep(Duration::from_millis(200)).await;
+        Ok((port, shutdown))
+    }
+
+    async fn get_body(tls_config: TlsClientConfig, port: u16) -> anyhow::Result<String> {
+        let mut session = Session::with_tls_config(tls_config);
+        let mut res = session
+            .get(
+                &format!("https://localhost:{port}/tls_client_hello"),
+                vec![],
+            )
+            .await?;
+        assert_eq!(res.http_code, 200);
+        Ok(String::from_utf8_lossy(res.body.data().await).to_string())
+    }
+
+    #[tokio::test]
+    async fn test_private_ca_and_danger_mode() -> anyhow::Result<()> {
+        let pki = TestPki::new()?;
+        let (port, shutdown) = start_server(&pki, None).await?;
+        let url = format!("https://localhost:{port}/tls_client_hello");
+
+        // 默认只信任公共根证书
+        assert!(Session::new().get(&url, vec![]).await.is_err());
+
+        let tls = TlsClientConfig::new().add_root_pem(pki.path("ca.pem"));
+        assert_eq!(get_body(tls.clone(), port).await?, "hello");
+        let only_private = tls.without_webpki_roots();
+        assert_eq!(get_body(only_private, port).await?, "hello");
+
+        let danger = TlsClientConfig::new().danger_accept_invalid_certs();
+        assert_eq!(get_body(danger, port).await?, "hello");
+
+        let missing_root = TlsClientConfig::new().add_root_pem(pki.path("missing.pem"));
+        assert!(get_body(missing_root, port).await.is_err());
+
+        _ = shutdown.send(());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_spki_pinning() -> anyhow::Result<()> {
+        let pki = TestPki::new()?;
+        let (port, shutdown) = start_server(&pki, None).await?;
+        let pin = TlsClientConfig::spki_sha256(&pki.server_der)?;
+
+        let pinned = TlsClientConfig::new()
+            .add_root_pem(pki.path("ca.pem"))
+            .pin_spki_sha256(&pin);
+        assert_eq!(get_body(pinned, port).await?, "hello");
+
+        // 证书固定与跳过证书链校验可同时使用，适合固定自签名证书
+        let pinned_self_signed = TlsClientConfig::new()
+            .danger_accept_invalid_certs()
+            .pin_spki_sha256(&pin);
+        assert_eq!(get_body(pinned_self_signed, port).await?, "hello");
+
+        let wrong_pin = TlsClientConfig::new()
+            .add_root_pem(pki.path("ca.pem"))
+            .pin_spki_sha256("AAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA=");
+        assert!(get_body(wrong_pin, port).await.is_err());
+
+        _ = shutdown.send(());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_client_certificate() -> anyhow::Result<()> {
+        let pki = TestPki::new()?;
+        let (port, shutdown) =
+            start_server(&pki, Some(ClientAuth::required(pki.path("ca.pem")))).await?;
+
+        let tls = TlsClientConfig::new().add_root_pem(pki.path("ca.pem"));
+        assert!(get_body(tls.clone(), port).await.is_err());
+        let tls = tls.with_client_cert(pki.path("client.pem"), pki.path("client.key"));
+        assert_eq!(get_body(tls.clone(), port).await?, "hello svc-a");
+
+        // wss 同样使用该配置
+        let mut ws = Websocket::connect_with_tls(
+            &format!("wss://localhost:{port}/tls_client_ws"),
+            vec![],
+            tls.clone(),
+        )
+        .await?;
+        ws.send_text("ping").await?;
+        match ws.recv().await? {
+            WsFrame::Text(text) => assert_eq!(text, "ping"),
+            _ => panic!("unexpected frame"),
+        }
+
+        #[cfg(feature = "http2")]
+        {
+            let mut session = potato::client::http2::H2Session::with_tls_config(tls);
+            let mut res = session
+                .get(
+                    &format!("https://localhost:{port}/tls_client_hello"),
+                    vec![],
+                )
+                .await?;
+            assert_eq!(res.http_code, 200);
+            assert_eq!(res.body.data().await, b"hello svc-a");
+        }
+
+        _ = shutdown.send(());
+        Ok(())
+    }
+
+    #[cfg(feature = "http3")]
+    #[tokio::test]
+    async fn test_h3_session_private_ca() -> anyhow::Result<()> {
+        let pki = TestPki::new()?;
+        let port = get_test_port();
+        let mut server = HttpServer::new(format!("127.0.0.1:{port}"));
+        server.configure(|ctx| ctx.use_handlers());
+        let shutdown = server.shutdown_signal()?;
+        let (cert_file, key_file) = (pki.path("server.pem"), pki.path("server.key"));
+        tokio::spawn(async move { server.serve_http3(&cert_file, &key_file).await });
+        sleep(Duration::from_millis(200)).await;
+
+        let tls = TlsClientConfig::new().add_root_pem(pki.path("ca.pem"));
+        let mut session = potato::client::http3::H3Session::with_tls_config(tls);
+        let mut res = session
+            .get(
+                &format!("https://localhost:{port}/tls_client_hello"),
+                vec![],
+            )
+            .await?;
+        assert_eq!(res.http_code, 200);
+        assert_eq!(res.body.data().await, b"hello");
+
+        _ = shutdown.send(());
+        Ok(())
+    }
+}
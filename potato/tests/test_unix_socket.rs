@@ -0,0 +1,149 @@
+/// Unix 域套接字与已打开套接字监听测试
+#[cfg(unix)]
+#[cfg(test)]
+mod unix_socket_tests {
+    use potato::{HttpRequest, HttpResponse, HttpServer, Session};
+    use std::os::unix::fs::PermissionsExt;
+    use std::path::PathBuf;
+    use std::sync::atomic::{AtomicU16, Ordering};
+    use std::time::Duration;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::time::sleep;
+
+    static PORT_COUNTER: AtomicU16 = AtomicU16::new(33000);
+
+    fn get_test_port() -> u16 {
+        PORT_COUNTER.fetch_add(1, Ordering::Relaxed)
+    }
+
+    fn temp_socket_path() -> PathBuf {
+        let dir = std::env::temp_dir().join(format!(
+            "potato_unix_socket_test_{}_{}",
+            std::process::id(),
+            get_test_port()
+        ));
+        std::fs::create_dir_all(&dir).unwrap();
+        dir.join("app.sock")
+    }
+
+    #[potato::http_get("/unix_socket_peer")]
+    async fn unix_socket_peer(req: &mut HttpRequest) -> HttpResponse {
+        let addr = req
+            .get_client_addr()
+            .await
+            .map(|addr| addr.ip().to_string());
+        HttpResponse::text(format!("peer {}", addr.unwrap_or_default()))
+    }
+
+    #[tokio::test]
+    async fn test_unix_socket_server_and_session() -> anyhow::Result<()> {
+        let path = temp_socket_path();
+        // 遗留的套接字文件应被清理
+        drop(std::os::unix::net::UnixListener::bind(&path)?);
+        assert!(path.exists());
+
+        let mut server = HttpServer::new(format!("unix:{}", path.display()));
+        server.set_unix_socket_mode(0o600);
+        server.configure(|ctx| ctx.use_handlers());
+        let shutdown = server.shutdown_signal()?;
+        let server_handle = tokio::spawn(async move { server.serve_http().await });
+        sleep(Duration::from_millis(200)).await;
+
+        let mode = std::fs::metadata(&path)?.permissions().mode() & 0o777;
+        assert_eq!(mode, 0o600);
+
+        let mut session = Session::new_unix(&path);
+        for _ in 0..2 {
+            let mut res = session
+                .get("http://localhost/unix_socket_peer", vec![])
+                .await?;
+            assert_eq!(res.http_code, 200);
+            assert_eq!(res.body.data().await, b"peer 127.0.0.1".to_vec());
+        }
+
+        _ = shutdown.send(());
+        server_handle.await??;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_unix_socket_in_use_is_rejected() -> anyhow::Result<()> {
+        let path = temp_socket_path();
+        let _occupied = std::os::unix::net::UnixListener::bind(&path)?;
+        let mut server = HttpServer::new(format!("unix:{}", path.display()));
+        assert!(server.serve_http().await.is_err());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_listen_unix_and_inherited_fd() -> anyhow::Result<()> {
+        let path = temp_socket_path();
+        let port = get_test_port();
+        let inherited = std::net::TcpListener::bind(format!("127.0.0.1:{port}"))?;
+
+        let mut server = HttpServer::new("");
+        server
+            .listen_http(format!("unix:{}", path.display()))
+            .listen_fd(inherited.into());
+        server.configure(|ctx| ctx.use_handlers());
+        let shutdown = server.shutdown_signal()?;
+        let server_handle = tokio::spawn(async move { server.serve().await });
+        sleep(Duration::from_millis(200)).await;
+
+        let mut stream = tokio::net::TcpStream::connect(format!("127.0.0.1:{port}")).await?;
+        stream
+            .write_all(
+                b"GET /unix_socket_peer HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n",
+            )
+            .await?;
+        let mut out = Vec::new();
+        stream.read_to_end(&mut out).await?;
+        let out = String::from_utf8_lossy(&out);
+        assert!(out.contains("200 OK"));
+        assert!(out.contains("peer 127.0.0.1"));
+
+        let mut session = Session::new_unix(&path);
+        let res = session
+            .get("http://localhost/unix_socket_peer", vec![])
+            .await?;
+        assert_eq!(res.http_code, 200);
+
+        _ = shutdown.send(());
+        server_handle.await??;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_listen_systemd_requires_socket_activation() {
+        if std::env::var_os("LISTEN_FDS").is_some() {
+            return;
+        }
+        let mut server = HttpServer::new("");
+        assert!(server.listen_systemd().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_unix_peer_is_not_trusted_proxy() -> anyhow::Result<()> {
+        let path = temp_socket_path();
+        let mut server = HttpServer::new(format!("unix:{}", path.display()));
+        server.configure(|ctx| {
+            ctx.use_trusted_proxies(["127.0.0.1"]).unwrap();
+            ctx.use_handlers();
+        });
+        let shutdown = server.shutdown_signal()?;
+        let server_handle = tokio::spawn(async move { server.serve_http().await });
+        sleep(Duration::from_millis(200)).await;
+
+        // Unix 域套接字的占位地址不被当作可信代理，转发头不被采信
+        let mut session = Session::new_unix(&path);
+        let forwarded = potato::Headers::Custom(("X-Forwarded-For".into(), "203.0.113.9".into()));
+        let mut res = session
+            .get("http://localhost/unix_socket_peer", vec![forwarded])
+            .await?;
+        assert_eq!(res.body.data().await, b"peer 127.0.0.1".to_vec());
+
+        _ = shutdown.send(());
+        server_handle.await??;
+        Ok(())
+    }
+}